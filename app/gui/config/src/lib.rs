@@ -60,6 +60,19 @@ ensogl::read_args! {
         authentication_enabled : bool,
         email                  : String,
         application_config_url : String,
+        /// The contents of the user keymap file. See the `ensogl::application::shortcut::keymap`
+        /// module docs to learn more.
+        keymap                 : String,
+        /// The format of the user keymap: `toml` or `json` (the default).
+        keymap_format          : String,
+        /// The contents of the theme file loaded at startup. See the
+        /// `ensogl::display::style::theme::file` module docs to learn more.
+        theme_file             : String,
+        /// The format of the theme file: `json` or `toml` (the default).
+        theme_file_format      : String,
+        /// Upload the dropped files even if a file with the same content is already present in
+        /// the project's data directory, instead of reusing that file.
+        keep_duplicate_uploads : bool,
         /// When profiling the application (e.g. with the `./run profile` command), this argument
         /// chooses what is profiled.
         test_workflow          : String,
@@ -2,6 +2,7 @@
 
 use crate::prelude::*;
 
+use crate::alias_analysis::analyze_ast;
 use crate::alias_analysis::analyze_crumbable;
 use crate::definition::DefinitionInfo;
 use crate::definition::ScopeKind;
@@ -45,6 +46,11 @@ impl Endpoint {
         let node = is_non_def.and_option_from(|| MainLine::from_ast(line_ast))?.id();
         Some(Endpoint { node, crumbs })
     }
+
+    /// The endpoint denoting the whole node, e.g. an input or output pseudo-node.
+    pub fn new_node_root(node: Id) -> Endpoint {
+        Endpoint { node, crumbs: default() }
+    }
 }
 
 /// Connection source, i.e. the port generating the data / identifier introducer.
@@ -67,17 +73,29 @@ pub struct Connection {
     pub destination: Destination,
 }
 
+/// Names of the definition's parameters, mapped to the endpoints introducing them, i.e. the input
+/// pseudo-nodes.
+pub type Parameters = HashMap<NormalizedName, Endpoint>;
+
 /// Lists all the connection in the graph for the given code block.
 pub fn list_block(block: &ast::Block<Ast>) -> Vec<Connection> {
+    list_block_with_parameters(block, &default())
+}
+
+/// Lists all the connection in the graph for the given code block, including the connections from
+/// the given parameters. The names introduced in the block shadow the parameters.
+pub fn list_block_with_parameters(
+    block: &ast::Block<Ast>,
+    parameters: &Parameters,
+) -> Vec<Connection> {
     let identifiers = analyze_crumbable(block);
     let introduced_iter = identifiers.introduced.into_iter();
-    type NameMap = HashMap<NormalizedName, Endpoint>;
-    let introduced_names = introduced_iter
-        .flat_map(|name| {
-            let endpoint = Endpoint::new_in_block(block, name.crumbs)?;
-            Some((name.item, endpoint))
-        })
-        .collect::<NameMap>();
+    let introduced_in_block = introduced_iter.flat_map(|name| {
+        let endpoint = Endpoint::new_in_block(block, name.crumbs)?;
+        Some((name.item, endpoint))
+    });
+    let mut introduced_names = parameters.clone();
+    introduced_names.extend(introduced_in_block);
     identifiers
         .used
         .into_iter()
@@ -91,18 +109,33 @@ pub fn list_block(block: &ast::Block<Ast>) -> Vec<Connection> {
         .collect()
 }
 
-/// Lists all the connection in the single-expression definition body.
-pub fn list_expression(_ast: &Ast) -> Vec<Connection> {
-    // At this points single-expression graphs do not have any connection.
-    // This will change when there will be input/output pseudo-nodes.
-    vec![]
+/// Lists all the connection in the single-expression definition body. As there is only one node,
+/// these may be only the connections from the given parameters.
+pub fn list_expression(ast: &Ast, parameters: &Parameters) -> Vec<Connection> {
+    let node = match MainLine::from_ast(ast) {
+        Some(node) => node.id(),
+        None => return vec![],
+    };
+    let used = analyze_ast(ast).used.into_iter();
+    used.filter_map(|name| {
+        let source = parameters.get(&name.item).cloned()?;
+        let destination = Endpoint { node, crumbs: name.crumbs };
+        Some(Connection { source, destination })
+    })
+    .collect()
 }
 
-/// Lists connections in the given definition body. For now it only makes sense for block shape.
+/// Lists connections between the nodes in the given definition body.
 pub fn list(body: &Ast) -> Vec<Connection> {
+    list_with_parameters(body, &default())
+}
+
+/// Lists connections in the given definition body, including the connections from the
+/// definition's parameters.
+pub fn list_with_parameters(body: &Ast, parameters: &Parameters) -> Vec<Connection> {
     match body.shape() {
-        ast::Shape::Block(block) => list_block(block),
-        _ => list_expression(body),
+        ast::Shape::Block(block) => list_block_with_parameters(block, parameters),
+        _ => list_expression(body, parameters),
     }
 }
 
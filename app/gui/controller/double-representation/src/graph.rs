@@ -4,11 +4,14 @@ use crate::prelude::*;
 
 use crate::connection;
 use crate::connection::Connection;
+use crate::connection::Endpoint;
 use crate::definition;
 use crate::definition::DefinitionInfo;
 use crate::definition::DefinitionProvider;
+use crate::identifier::NormalizedName;
 use crate::node;
 use crate::node::LocatedNode;
+use crate::node::MainLine;
 use crate::node::NodeInfo;
 
 use ast::known;
@@ -22,6 +25,17 @@ pub type Id = definition::Id;
 
 
 
+// ==============
+// === Errors ===
+// ==============
+
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Fail)]
+#[fail(display = "The node {} has no single variable which could be returned.", _0)]
+pub struct NodeWithoutVariable(node::Id);
+
+
+
 // ====================
 // === LocationHint ===
 // ====================
@@ -41,6 +55,51 @@ pub enum LocationHint {
 
 
 
+// ==================
+// === PseudoNode ===
+// ==================
+
+/// The kind of [`PseudoNode`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PseudoNodeKind {
+    /// The definition's parameter. Its output is the parameter's value.
+    Input {
+        /// The index of the parameter in the definition's parameter list.
+        index: usize,
+        #[allow(missing_docs)]
+        name:  String,
+    },
+    /// The definition's result. Its only input is the value of the last node.
+    Output,
+}
+
+/// A synthetic node of the graph, not corresponding to any line of the definition's body. The
+/// pseudo-nodes allow representing the definition's parameters and its returned value as
+/// connection endpoints.
+///
+/// The input pseudo-nodes are identified by the ids of the parameters' ASTs, and the output
+/// pseudo-node by the id of the whole definition's AST.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub struct PseudoNode {
+    pub id:   node::Id,
+    pub kind: PseudoNodeKind,
+}
+
+impl PseudoNode {
+    /// Check if this is an input pseudo-node.
+    pub fn is_input(&self) -> bool {
+        matches!(self.kind, PseudoNodeKind::Input { .. })
+    }
+
+    /// Check if this is the output pseudo-node.
+    pub fn is_output(&self) -> bool {
+        self.kind == PseudoNodeKind::Output
+    }
+}
+
+
+
 // =================
 // === GraphInfo ===
 // =================
@@ -95,6 +154,81 @@ impl GraphInfo {
         connection::list(&self.source.ast.rarg)
     }
 
+    /// Gets the input pseudo-nodes, one for each parameter being a plain variable.
+    pub fn input_nodes(&self) -> Vec<PseudoNode> {
+        let args = self.source.args.iter().enumerate();
+        args.filter_map(|(index, arg)| {
+            let name = ast::identifier::as_var(&arg.item)?.to_owned();
+            let id = arg.item.id()?;
+            Some(PseudoNode { id, kind: PseudoNodeKind::Input { index, name } })
+        })
+        .collect()
+    }
+
+    /// Gets the output pseudo-node, representing the value returned by the definition.
+    pub fn output_node(&self) -> Option<PseudoNode> {
+        let id = self.source.ast.id()?;
+        Some(PseudoNode { id, kind: PseudoNodeKind::Output })
+    }
+
+    /// Gets all input and output pseudo-nodes.
+    pub fn pseudo_nodes(&self) -> Vec<PseudoNode> {
+        let mut nodes = self.input_nodes();
+        nodes.extend(self.output_node());
+        nodes
+    }
+
+    /// Gets the list of connections in this graph including the connections from the input
+    /// pseudo-nodes and the connection from the last node to the output pseudo-node. The last
+    /// node is connected to the output only if it is not a binding, as only then its value is
+    /// returned.
+    pub fn connections_with_pseudo_nodes(&self) -> Vec<Connection> {
+        let parameters = self.input_nodes().into_iter().filter_map(|node| match node.kind {
+            PseudoNodeKind::Input { name, .. } =>
+                Some((NormalizedName::new(name), Endpoint::new_node_root(node.id))),
+            PseudoNodeKind::Output => None,
+        });
+        let parameters = parameters.collect();
+        let mut connections = connection::list_with_parameters(&self.source.ast.rarg, &parameters);
+        let returned_node = self.nodes().pop().filter(|last| last.pattern().is_none());
+        let output_connection = returned_node.zip(self.output_node()).map(|(last, output)| {
+            let source = Endpoint::new_node_root(last.id());
+            let destination = Endpoint::new_node_root(output.id);
+            Connection { source, destination }
+        });
+        connections.extend(output_connection);
+        connections
+    }
+
+    /// Make the graph return the value of the given node, i.e. connect it to the output
+    /// pseudo-node. The node must be the last one not being a binding, or have a pattern with a
+    /// single variable.
+    ///
+    /// If the last line just returns a variable of another node, it is replaced; otherwise a new
+    /// line returning the node's variable is appended.
+    pub fn set_output(&mut self, node_id: node::Id) -> FallibleResult {
+        let nodes = self.nodes();
+        let last = nodes.last().ok_or_else(|| node::IdNotFound { id: node_id })?;
+        let node = self.locate_node(node_id)?.node;
+        let is_last = last.id() == node_id;
+        if is_last && node.pattern().is_none() {
+            return Ok(());
+        }
+        let pattern = node.pattern().ok_or(NodeWithoutVariable(node_id))?;
+        let variable = ast::identifier::as_var(pattern).ok_or(NodeWithoutVariable(node_id))?;
+        let returned = Ast::var(variable);
+        let last_is_return_line = !is_last
+            && last.pattern().is_none()
+            && ast::identifier::as_var(last.expression()).is_some();
+        if last_is_return_line {
+            self.edit_node(last.id(), returned)
+        } else {
+            let main_line = MainLine::from_ast(&returned).ok_or(NodeWithoutVariable(node_id))?;
+            let node = NodeInfo { documentation: None, main_line };
+            self.add_node(&node, LocationHint::End)
+        }
+    }
+
     /// Adds a new node to this graph.
     pub fn add_node(&mut self, node: &NodeInfo, location_hint: LocationHint) -> FallibleResult {
         let mut lines = self.source.block_lines();
@@ -493,4 +627,85 @@ main =
         assert!(graph.edit_node(uuid::Uuid::new_v4(), Ast::var("foo")).is_err());
         graph.expect_code(expected_code);
     }
+
+    #[wasm_bindgen_test]
+    fn pseudo_nodes_of_graph() {
+        let parser = parser::Parser::new_or_panic();
+        let program = r"
+main a b =
+    foo = a + 2
+    bar = foo + b
+    bar + 1";
+        let mut graph = main_graph(&parser, program);
+        let inputs = graph.input_nodes();
+        let names = inputs.iter().map(|node| match &node.kind {
+            PseudoNodeKind::Input { name, .. } => name.as_str(),
+            PseudoNodeKind::Output => "",
+        });
+        assert_eq!(names.collect_vec(), vec!["a", "b"]);
+        let output = graph.output_node().unwrap();
+        assert!(output.is_output());
+
+        let nodes = graph.nodes();
+        let connections = graph.connections_with_pseudo_nodes();
+        let source_and_target = |c: &Connection| (c.source.node, c.destination.node);
+        let mut pairs = connections.iter().map(source_and_target).collect_vec();
+        let mut expected = vec![
+            (inputs[0].id, nodes[0].id()),
+            (nodes[0].id(), nodes[1].id()),
+            (inputs[1].id, nodes[1].id()),
+            (nodes[1].id(), nodes[2].id()),
+            (nodes[2].id(), output.id),
+        ];
+        pairs.sort();
+        expected.sort();
+        assert_eq!(pairs, expected);
+
+        graph.set_output(nodes[0].id()).unwrap();
+        graph.expect_code(
+            r"main a b =
+    foo = a + 2
+    bar = foo + b
+    bar + 1
+    foo",
+        );
+        graph.set_output(nodes[1].id()).unwrap();
+        graph.expect_code(
+            r"main a b =
+    foo = a + 2
+    bar = foo + b
+    bar + 1
+    bar",
+        );
+        let nodes = graph.nodes();
+        graph.set_output(nodes[3].id()).unwrap();
+        graph.expect_code(
+            r"main a b =
+    foo = a + 2
+    bar = foo + b
+    bar + 1
+    bar",
+        );
+
+        let program = r"
+main =
+    foo = 2
+    bar = foo + 1";
+        let mut graph = main_graph(&parser, program);
+        let nodes = graph.nodes();
+        let connections = graph.connections_with_pseudo_nodes();
+        let output = graph.output_node().unwrap();
+        assert!(!connections.iter().any(|c| c.destination.node == output.id));
+        graph.set_output(nodes[1].id()).unwrap();
+        graph.expect_code(
+            r"main =
+    foo = 2
+    bar = foo + 1
+    bar",
+        );
+        let connections = graph.connections_with_pseudo_nodes();
+        let last = graph.nodes().last().unwrap().id();
+        let returned = connections.iter().find(|c| c.destination.node == output.id);
+        assert_eq!(returned.map(|c| c.source.node), Some(last));
+    }
 }
@@ -40,6 +40,7 @@ pub mod connection;
 pub mod definition;
 pub mod graph;
 pub mod identifier;
+pub mod lint;
 pub mod module;
 pub mod node;
 pub mod project;
@@ -48,6 +49,7 @@ pub mod refactorings;
 pub mod test_utils;
 pub mod text;
 pub mod tp;
+pub mod usages;
 
 
 
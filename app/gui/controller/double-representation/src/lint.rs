@@ -0,0 +1,280 @@
+//! Lints finding code which can be removed from a module: unused imports, methods unreachable from
+//! the module's entry points and nodes whose results are never consumed.
+//!
+//! Each [`Finding`] comes with a quick-fix removing the code, see [`Finding::apply_fix`]. Not all
+//! of them are safe, see [`Finding::is_fix_safe`].
+
+use crate::prelude::*;
+
+use crate::alias_analysis;
+use crate::definition;
+use crate::definition::DefinitionName;
+use crate::definition::DefinitionProvider;
+use crate::graph::GraphInfo;
+use crate::identifier::NormalizedName;
+use crate::module;
+use crate::module::ImportInfo;
+use crate::module::QualifiedName;
+use crate::node;
+use crate::usages;
+use crate::usages::Callee;
+
+use ast::known;
+
+
+
+// ===============
+// === Finding ===
+// ===============
+
+/// The name of the method being the entry point of every module.
+pub const MAIN_DEFINITION_NAME: &str = "main";
+
+/// A piece of code which can be removed without changing the module's behavior.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Finding {
+    /// The import's module is never referred in the module's code. The import may still bring
+    /// extension methods into scope, which cannot be checked without type information, so the
+    /// quick-fix of this finding is unsafe.
+    UnusedImport(ImportInfo),
+    /// The top-level method is not called, directly or indirectly, from any entry point.
+    UnreachableDefinition(DefinitionName),
+    /// The node's result is not used by any other node and it has no visualization attached.
+    UnusedNode {
+        #[allow(missing_docs)]
+        definition: definition::Id,
+        #[allow(missing_docs)]
+        node:       node::Id,
+    },
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnusedImport(import) => write!(f, "Import `{}` may be unused.", import),
+            Self::UnreachableDefinition(name) => write!(f, "Method `{}` is never used.", name),
+            Self::UnusedNode { definition, node } =>
+                write!(f, "Result of node {} in `{}` is never used.", node, definition),
+        }
+    }
+}
+
+impl Finding {
+    /// Check if applying the quick-fix surely does not change the module's behavior. The fixes
+    /// which are not safe should be applied only when confirmed by the user.
+    pub fn is_fix_safe(&self) -> bool {
+        !matches!(self, Self::UnusedImport(_))
+    }
+
+    /// Remove the code reported by this finding.
+    pub fn apply_fix(&self, module: &mut module::Info) -> FallibleResult {
+        match self {
+            Self::UnusedImport(import) => module.remove_import(import),
+            Self::UnreachableDefinition(name) => {
+                let crumb = module::locate_line_with(&module.ast, name)?;
+                module.remove_line(crumb.line_index)?;
+                remove_redundant_empty_line(module, crumb.line_index)
+            }
+            Self::UnusedNode { definition, node } =>
+                module.update_definition(definition, |definition| {
+                    let mut graph = GraphInfo::from_definition(definition);
+                    graph.remove_node(*node)?;
+                    Ok(graph.source)
+                }),
+        }
+    }
+}
+
+/// Apply the quick-fixes of all findings. The findings identify the code by names and ids, not
+/// by positions, so the fixes may be applied in any order.
+pub fn apply_fixes<'a>(
+    module: &mut module::Info,
+    findings: impl IntoIterator<Item = &'a Finding>,
+) -> FallibleResult {
+    for finding in findings {
+        finding.apply_fix(module)?;
+    }
+    Ok(())
+}
+
+/// After removing the line at `index`, remove the empty line which took its place, if it was
+/// separating the removed line from the previous one. This way no double empty lines are left.
+fn remove_redundant_empty_line(module: &mut module::Info, index: usize) -> FallibleResult {
+    let lines = &module.ast.lines;
+    let is_empty = |index: usize| lines.get(index).map_or(false, |line| line.elem.is_none());
+    let previous_is_empty = index == 0 || is_empty(index - 1);
+    if is_empty(index) && previous_is_empty {
+        module.remove_line(index)?;
+    }
+    Ok(())
+}
+
+
+
+// ============
+// === Lint ===
+// ============
+
+/// Find all removable code in the module of the given name.
+///
+/// The entry points are the [`MAIN_DEFINITION_NAME`] method, the methods defined on types (as they
+/// may be called on values anywhere) and the methods listed in `exported`. The
+/// `has_visualization` function tells if the node has a visualization attached, so its result
+/// is consumed even if no other node uses it.
+pub fn lint(
+    name: &QualifiedName,
+    ast: &known::Module,
+    exported: &[String],
+    has_visualization: impl Fn(node::Id) -> bool,
+) -> Vec<Finding> {
+    let summary = usages::Summary::new(name, ast);
+    let unused_imports = unused_imports(ast, &summary.imports);
+    let unreachable = unreachable_definitions(&summary, exported);
+    let unused_nodes = ast
+        .def_iter()
+        .filter(|child| !unreachable.contains(&child.name.item))
+        .flat_map(|child| {
+            let id = definition::Id::new_single_crumb(child.name.item.clone());
+            let graph = GraphInfo::from_definition(child.item);
+            unused_nodes(&graph, &has_visualization)
+                .into_iter()
+                .map(move |node| Finding::UnusedNode { definition: id.clone(), node })
+        })
+        .collect_vec();
+    let unused_imports = unused_imports.into_iter().map(Finding::UnusedImport);
+    let unreachable = unreachable.into_iter().map(Finding::UnreachableDefinition);
+    unused_imports.chain(unreachable).chain(unused_nodes).collect()
+}
+
+/// The imports whose module name is not used in any non-import line. Imports used only for the
+/// extension methods they bring into scope are reported as well, as they cannot be told apart
+/// without type information. That's why the quick-fix of [`Finding::UnusedImport`] is unsafe.
+fn unused_imports(ast: &known::Module, imports: &[ImportInfo]) -> Vec<ImportInfo> {
+    let lines = ast.lines.iter().filter_map(|line| line.elem.as_ref());
+    let code_lines = lines.filter(|line| ImportInfo::from_ast(line).is_none());
+    let used_names: HashSet<NormalizedName> = code_lines
+        .flat_map(|line| alias_analysis::analyze_ast(line).used)
+        .map(|name| name.item)
+        .collect();
+    let is_used = |import: &&ImportInfo| {
+        let module_name = import.target.last().map(NormalizedName::new);
+        module_name.map_or(true, |name| used_names.contains(&name))
+    };
+    imports.iter().filter(|import| !is_used(import)).cloned().collect()
+}
+
+/// The top-level methods not reachable from the entry points. A call by name only (like
+/// `value.foo`) is assumed to reach every method of this name.
+fn unreachable_definitions(summary: &usages::Summary, exported: &[String]) -> Vec<DefinitionName> {
+    let is_entry_point = |definition: &usages::Definition| {
+        let method = &definition.method;
+        method.name == MAIN_DEFINITION_NAME
+            || method.defined_on_type != method.module
+            || exported.contains(&method.name)
+    };
+    let mut reachable: HashSet<&definition::Id> = default();
+    let mut to_visit = summary
+        .definitions
+        .iter()
+        .filter(|definition| is_entry_point(definition))
+        .map(|definition| &definition.id)
+        .collect_vec();
+    while let Some(id) = to_visit.pop() {
+        if reachable.insert(id) {
+            let calls = summary.calls.iter().filter(|call| &call.definition == id);
+            for call in calls {
+                let called = summary.definitions.iter().filter(|definition| match &call.callee {
+                    Callee::Method(method) => usages::same_method(method, &definition.method),
+                    Callee::Name(name) => name == &definition.method.name,
+                });
+                to_visit.extend(called.map(|definition| &definition.id));
+            }
+        }
+    }
+    let unreachable = summary.definitions.iter().filter(|def| !reachable.contains(&def.id));
+    unreachable.filter_map(|definition| definition.id.crumbs.last().cloned()).collect()
+}
+
+/// The nodes of the graph whose results are not consumed. The last node is the graph's output,
+/// so it is always consumed.
+fn unused_nodes(graph: &GraphInfo, has_visualization: impl Fn(node::Id) -> bool) -> Vec<node::Id> {
+    let nodes = graph.nodes();
+    let connections = graph.connections();
+    let consumed: HashSet<node::Id> = connections.iter().map(|c| c.source.node).collect();
+    let (_output, nodes) = match nodes.split_last() {
+        Some(split) => split,
+        None => return vec![],
+    };
+    let is_unused = |id: &node::Id| !consumed.contains(id) && !has_visualization(*id);
+    nodes.iter().map(|node| node.id()).filter(is_unused).collect()
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn finding_and_fixing_removable_code() {
+        let parser = parser::Parser::new_or_panic();
+        let code = r#"import Standard.Table
+import Standard.Visualization
+
+helper x = x + 1
+
+unused = helper 2
+
+Text.shout = this + "!"
+
+main =
+    table = Table.read "data.csv"
+    debug = 10
+    shown = 20
+    result = helper table
+    result"#;
+        let ast = parser.parse_module(code, default()).unwrap();
+        let name = QualifiedName::from_text("local.Project.Main").unwrap();
+        let mut module = module::Info { ast: ast.clone_ref() };
+        let main = definition::Id::new_plain_name("main");
+        let main_nodes = GraphInfo::from_definition(module::get_definition(&ast, &main).unwrap());
+        let main_nodes = main_nodes.nodes().iter().map(|node| node.id()).collect_vec();
+        let shown = main_nodes[2];
+
+        let findings = lint(&name, &ast, &[], |node| node == shown);
+        let expected = vec![
+            Finding::UnusedImport(ImportInfo::from_target_str("Standard.Visualization")),
+            Finding::UnreachableDefinition(DefinitionName::new_plain("unused")),
+            Finding::UnusedNode { definition: main.clone(), node: main_nodes[1] },
+        ];
+        assert_eq!(findings, expected);
+
+        let exported = vec!["unused".to_owned()];
+        let findings = lint(&name, &ast, &exported, |node| node == shown);
+        assert_eq!(findings.len(), expected.len() - 1);
+        assert!(!findings.contains(&expected[1]));
+
+        assert!(!expected[0].is_fix_safe());
+        assert!(expected[1..].iter().all(Finding::is_fix_safe));
+        apply_fixes(&mut module, &expected).unwrap();
+        module.expect_code(
+            r#"import Standard.Table
+
+helper x = x + 1
+
+Text.shout = this + "!"
+
+main =
+    table = Table.read "data.csv"
+    shown = 20
+    result = helper table
+    result"#,
+        );
+    }
+}
@@ -34,8 +34,10 @@ use std::collections::BTreeSet;
 /// method definition. In place of them a new node shall be placed that invokes the method.
 ///
 /// Any connections incoming into the extracted nodes shall be translated into the method arguments.
-/// Any connections leaving the extracted nodes shall be treated as function outputs. Currently only
-/// one output is supported, so an extracted function can return at most one unique identifier.
+/// Any connections leaving the extracted nodes shall be treated as function outputs. If there is
+/// more than one output identifier, the extracted function returns them packed into nested pairs
+/// (see [`PAIR_CONSTRUCTOR`]) and the new node destructures them back into the original names, so
+/// the connections to the nodes using the outputs are kept.
 pub fn collapse(
     graph: &GraphInfo,
     selected_nodes: impl IntoIterator<Item = node::Id>,
@@ -46,6 +48,13 @@ pub fn collapse(
 }
 
 
+// === Constants ===
+
+/// The constructor used to pack multiple outputs of the extracted function. The outputs `a`, `b`
+/// and `c` are returned as `Pair a (Pair b c)`.
+pub const PAIR_CONSTRUCTOR: &str = "Pair";
+
+
 // === Collapsed ===
 
 /// Result of running node collapse algorithm. Describes update to the refactored definition.
@@ -67,6 +76,14 @@ pub struct Collapsed {
 #[fail(display = "At least one node must be selected for collapsing refactoring.")]
 pub struct NoNodesSelected;
 
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Fail)]
+#[fail(
+    display = "The selected nodes cannot be collapsed, as they use a value computed from their own \
+    results by a node which is not selected."
+)]
+pub struct CannotPlaceCollapsedNode;
+
 #[allow(missing_docs)]
 #[derive(Clone, Copy, Debug, Fail)]
 #[fail(
@@ -85,15 +102,6 @@ pub struct CannotConstructCollapsedNode;
 #[fail(display = "Internal refactoring error: Cannot resolve identifier for the endpoint {:?}", _0)]
 pub struct EndpointIdentifierCannotBeResolved(Endpoint);
 
-#[allow(missing_docs)]
-#[derive(Clone, Debug, Fail)]
-#[fail(
-    display = "Currently collapsing nodes is supported only when there would be at most one \
-output from the collapsed function. Found more than one output: `{}` and `{}`.",
-    _0, _1
-)]
-pub struct MultipleOutputIdentifiers(String, String);
-
 
 
 // ===================
@@ -159,7 +167,7 @@ impl GraphHelper {
 // === Output ===
 // =================
 
-/// Describes the output of the extracted function.
+/// Describes an output of the extracted function.
 #[derive(Clone, Debug)]
 pub struct Output {
     /// The node that introduces output variable.
@@ -179,9 +187,8 @@ pub struct Output {
 pub struct Extracted {
     /// Identifiers used in the collapsed nodes from the outside scope.
     inputs:          Vec<Identifier>,
-    /// Information on node that will act as extracted function output.
-    /// Currently we allow at most one output, to be revisited in the future.
-    output:          Option<Output>,
+    /// Identifiers returned by the extracted function, in the order of nodes introducing them.
+    outputs:         Vec<Output>,
     /// Nodes that are being collapsed and extracted into a separate method.
     extracted_nodes: Vec<NodeInfo>,
 }
@@ -203,7 +210,7 @@ impl Extracted {
         // Leaf is an extracted node that has no outgoing connections.
         let mut leaves = extracted_nodes_set.clone();
         let mut inputs = Vec::new();
-        let mut output = None;
+        let mut outputs: Vec<Output> = Vec::new();
         for connection in graph.info.connections() {
             let starts_inside = extracted_nodes_set.contains(&connection.source.node);
             let ends_inside = extracted_nodes_set.contains(&connection.destination.node);
@@ -213,34 +220,36 @@ impl Extracted {
             if !starts_inside && ends_inside {
                 inputs.push(identifier)
             } else if starts_inside && !ends_inside {
-                match output {
-                    Some(Output { identifier: previous_identifier, .. })
-                        if identifier != previous_identifier =>
-                    {
-                        let ident1 = identifier.to_string();
-                        let ident2 = previous_identifier.to_string();
-                        return Err(MultipleOutputIdentifiers(ident1, ident2).into());
-                    }
-                    Some(_) => {} // Ignore duplicate usage of the same identifier.
-                    None => {
-                        let node = connection.source.node;
-                        output = Some(Output { node, identifier });
-                    }
+                // Ignore duplicate usage of the same identifier.
+                if !outputs.iter().any(|output| output.identifier == identifier) {
+                    let node = connection.source.node;
+                    outputs.push(Output { node, identifier });
                 }
             }
         }
+        let node_index = |id: node::Id| extracted_nodes.iter().position(|node| node.id() == id);
+        outputs.sort_by_key(|output| node_index(output.node));
 
         // If there is no output found so far, it means that none of our nodes is used outside
         // the extracted function. In such we will return value from arbitrarily chosen leaf.
-        output = output.or_else(|| {
-            let output_leaf_id = leaves.into_iter().next()?;
-            let output_node = extracted_nodes.iter().find(|node| node.id() == output_leaf_id)?;
-            let identifier = Identifier::new(output_node.pattern()?.clone_ref())?;
-            let node = output_node.id();
-            Some(Output { node, identifier })
-        });
-
-        Ok(Self { inputs, output, extracted_nodes })
+        if outputs.is_empty() {
+            let leaf_output = || {
+                let output_leaf_id = leaves.into_iter().next()?;
+                let output_node =
+                    extracted_nodes.iter().find(|node| node.id() == output_leaf_id)?;
+                let identifier = Identifier::new(output_node.pattern()?.clone_ref())?;
+                let node = output_node.id();
+                Some(Output { node, identifier })
+            };
+            outputs.extend(leaf_output());
+        }
+
+        Ok(Self { inputs, outputs, extracted_nodes })
+    }
+
+    /// Check if the node is extracted.
+    pub fn is_extracted(&self, id: node::Id) -> bool {
+        self.extracted_nodes.iter().any(|node| node.id() == id)
     }
 
     /// Check if the given line belongs to the selection (i.e. is extracted into a new method).
@@ -248,23 +257,43 @@ impl Extracted {
         self.extracted_nodes.iter().any(|extracted_node| extracted_node.contains_line(line_ast))
     }
 
+    /// Generate AST packing all the outputs. It is used both as the returned expression of the
+    /// extracted function and as the pattern of the collapsed node. None if there are no outputs.
+    ///
+    /// Each call generates AST with new IDs.
+    pub fn outputs_ast(&self, parser: &Parser) -> FallibleResult<Option<Ast>> {
+        match self.outputs.as_slice() {
+            [] => Ok(None),
+            // A single value is returned by just uttering its identifier, with a new ID.
+            [output] => Ok(Some(output.identifier.with_new_id().into())),
+            outputs => {
+                let names = outputs.iter().map(|output| output.identifier.name()).collect_vec();
+                let (last, init) = names.split_last().ok_or(CannotConstructCollapsedNode)?;
+                let packed = init.iter().rev().fold(last.to_string(), |packed, name| {
+                    let packed = if packed.contains(' ') { iformat!("({packed})") } else { packed };
+                    iformat!("{PAIR_CONSTRUCTOR} {name} {packed}")
+                });
+                Ok(Some(parser.parse_line_ast(packed)?))
+            }
+        }
+    }
+
     /// Generate AST of a line that needs to be appended to the extracted nodes' Asts.
     /// None if there is no such need.
-    pub fn return_line(&self) -> Option<Ast> {
-        // To return value we just utter its identifier. But the expression needs a new ID.
-        self.output.as_ref().map(|out| out.identifier.with_new_id().into())
+    pub fn return_line(&self, parser: &Parser) -> FallibleResult<Option<Ast>> {
+        self.outputs_ast(parser)
     }
 
     /// Generate the description for the new method's definition with the extracted nodes.
-    pub fn generate(&self, name: Identifier) -> definition::ToAdd {
+    pub fn generate(&self, name: Identifier, parser: &Parser) -> FallibleResult<definition::ToAdd> {
         let name = definition::DefinitionName::new_plain(name);
         let inputs = self.inputs.iter().collect::<BTreeSet<_>>();
-        let return_line = self.return_line();
+        let return_line = self.return_line(parser)?;
         let mut selected_nodes_iter = self.extracted_nodes.iter().map(|node| node.ast().clone());
         let body_head = selected_nodes_iter.next().unwrap();
         let body_tail = selected_nodes_iter.chain(return_line).map(Some).collect();
         let explicit_parameter_names = inputs.iter().map(|input| input.name().into()).collect();
-        definition::ToAdd { name, explicit_parameter_names, body_head, body_tail }
+        Ok(definition::ToAdd { name, explicit_parameter_names, body_head, body_tail })
     }
 }
 
@@ -292,7 +321,9 @@ pub struct Collapser {
     /// Information about nodes that are extracted into a separate definition.
     extracted:      Extracted,
     /// Which node from the refactored graph should be replaced with a call to a extracted method.
-    /// This only exists because we care about this node line's position (not its state).
+    /// This only exists because we care about this node line's position (not its state). It is
+    /// the last extracted node placed after all nodes computing the inputs and before all not
+    /// extracted nodes using the outputs.
     replaced_node:  node::Id,
     parser:         Parser,
     /// Identifier of the node to be introduced as a result of collapsing.
@@ -309,12 +340,40 @@ impl Collapser {
     ) -> FallibleResult<Self> {
         let graph = GraphHelper::new(graph);
         let extracted = Extracted::new(&graph, selected_nodes)?;
-        let last_selected = extracted.extracted_nodes.iter().last().ok_or(NoNodesSelected)?.id();
-        let replaced_node = extracted.output.as_ref().map(|out| out.node).unwrap_or(last_selected);
+        if extracted.extracted_nodes.is_empty() {
+            return Err(NoNodesSelected.into());
+        }
+        let replaced_node = Self::replaced_node(&graph, &extracted)?;
         let collapsed_node = node::Id::new_v4();
         Ok(Collapser { graph, extracted, replaced_node, parser, collapsed_node })
     }
 
+    /// Choose the extracted node whose line will be replaced with the collapsed node. The
+    /// collapsed node must be placed after the nodes computing its inputs and before the nodes
+    /// using its outputs. This may be impossible if the selection is interleaved with the nodes
+    /// both using an output and computing an input.
+    fn replaced_node(graph: &GraphHelper, extracted: &Extracted) -> FallibleResult<node::Id> {
+        let position = |id: node::Id| graph.nodes.iter().position(|node| node.id() == id);
+        let connections = graph.info.connections();
+        let crossing = |from_inside: bool| {
+            connections.iter().filter(move |connection| {
+                extracted.is_extracted(connection.source.node) == from_inside
+                    && extracted.is_extracted(connection.destination.node) != from_inside
+            })
+        };
+        let last_input_source =
+            crossing(false).filter_map(|connection| position(connection.source.node)).max();
+        let first_consumer =
+            crossing(true).filter_map(|connection| position(connection.destination.node)).min();
+        let fits = |index: usize| {
+            last_input_source.map_or(true, |source| index > source)
+                && first_consumer.map_or(true, |consumer| index < consumer)
+        };
+        let mut candidates = extracted.extracted_nodes.iter().rev().map(|node| node.id());
+        let replaced = candidates.find(|id| position(*id).map_or(false, fits));
+        replaced.ok_or_else(|| CannotPlaceCollapsedNode.into())
+    }
+
     /// Generate the expression that calls the extracted method definition.
     ///
     /// Does not include any pattern for assigning the resulting value.
@@ -353,8 +412,8 @@ impl Collapser {
             let expression = MainLine::from_ast(&expression_ast).ok_or(no_node_err)?;
             let mut new_node = NodeInfo { documentation: None, main_line: expression };
             new_node.set_id(self.collapsed_node);
-            if let Some(Output { identifier, .. }) = &self.extracted.output {
-                new_node.set_pattern(identifier.with_new_id().into())
+            if let Some(pattern) = self.extracted.outputs_ast(&self.parser)? {
+                new_node.set_pattern(pattern)
             }
             Ok(LineDisposition::Replace(new_node.ast().clone_ref()))
         } else {
@@ -364,7 +423,7 @@ impl Collapser {
 
     /// Run the collapsing refactoring on this input.
     pub fn collapse(&self, name: Identifier) -> FallibleResult<Collapsed> {
-        let new_method = self.extracted.generate(name);
+        let new_method = self.extracted.generate(name, &self.parser)?;
         let updated_definition =
             self.graph.rewrite_definition(|line| self.rewrite_line(line, &new_method))?;
         let collapsed_node = self.collapsed_node;
@@ -539,5 +598,69 @@ mod tests {
     number2 = 2
     vector = here.custom_new number1 number2";
         case.run(&parser);
+
+        // Check that extracted nodes with several outputs return them packed in pairs, which are
+        // unpacked by the collapsed node into the original names.
+        case.initial_method_code = r"custom_old =
+    a = 1
+    b = a + 1
+    c = b + 2
+    d = c + 3
+    b + c + d";
+        case.extracted_lines = 1..4;
+        case.expected_generated = r"custom_new a =
+    b = a + 1
+    c = b + 2
+    d = c + 3
+    Pair b (Pair c d)";
+        case.expected_refactored = r"custom_old =
+    a = 1
+    Pair b (Pair c d) = here.custom_new a
+    b + c + d";
+        case.run(&parser);
+    }
+
+    #[wasm_bindgen_test]
+    fn collapsing_interleaved_nodes() {
+        let parser = Parser::new_or_panic();
+        let name = Identifier::try_from("custom_new").unwrap();
+        let collapse_lines = |code: &str, lines: &[usize]| {
+            let ast = parser.parse_module(code, default()).unwrap();
+            let refactored_name = DefinitionName::new_plain("custom_old");
+            let main = module::locate_child(&ast, &refactored_name).unwrap();
+            let graph = graph::GraphInfo::from_definition(main.item);
+            let nodes = graph.nodes();
+            let selection = lines.iter().map(|line| nodes[*line].id());
+            collapse(&graph, selection, name.clone(), &parser)
+        };
+
+        // The collapsed node is placed before the first not extracted node using its outputs.
+        let code = r"custom_old =
+    a = 1
+    b = a + 1
+    c = b + 2
+    d = a + 3
+    b + c + d";
+        let collapsed = collapse_lines(code, &[1, 3]).unwrap();
+        let expected_generated = r"custom_new a =
+    b = a + 1
+    d = a + 3
+    Pair b d";
+        let expected_refactored = r"custom_old =
+    a = 1
+    Pair b d = here.custom_new a
+    c = b + 2
+    b + c + d";
+        assert_eq!(collapsed.new_method.ast(0, &parser).unwrap().repr(), expected_generated);
+        assert_eq!(collapsed.updated_definition.ast.repr(), expected_refactored);
+
+        // The node between the extracted ones both uses an output and computes an input.
+        let code = r"custom_old =
+    a = 1
+    b = a + 1
+    c = b + 2
+    d = c + 3
+    d";
+        assert!(collapse_lines(code, &[1, 3]).is_err());
     }
 }
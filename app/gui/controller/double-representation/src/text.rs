@@ -3,7 +3,7 @@
 use crate::prelude::*;
 use enso_text::unit::*;
 
-use ast::IdMap;
+use parser::Parser;
 
 
 
@@ -11,166 +11,123 @@ use ast::IdMap;
 // === Text API ===
 // ================
 
-/// Update IdMap to reflect the recent code change.
-pub fn apply_code_change_to_id_map(
-    id_map: &mut IdMap,
+/// The maximum number of compared pairs of children for which their longest common subsequence is
+/// computed. For larger lists of children, only their common prefix and suffix are aligned by text.
+const MAX_ALIGNED_PAIRS: usize = 100_000;
+
+/// Apply the text change to the module's code and parse it, keeping the ids of the module's AST.
+///
+/// The changed code is parsed once and its AST is matched against the module's AST, so the ids of
+/// the nodes present in both are kept, even if their text or position changed. The nodes are
+/// matched as follows:
+/// * subtrees of identical text are matched along with all their descendants;
+/// * the children of matched nodes are aligned by their text (the longest common subsequence);
+/// * if both matched nodes have the same shape, the children left between the aligned ones are
+///   matched by their position, e.g. the edited argument of a prefix application.
+///
+/// The nodes without a match in the module keep the new ids given by the parser.
+pub fn apply_change_preserving_ids(
+    parser: &Parser,
+    module: &ast::known::Module,
     change: &enso_text::text::Change<Bytes, String>,
-    code: &str,
-) {
-    // TODO [mwu]
-    //   The initial provisional algorithm received some changes to better behave in our typical
-    //   editor use-cases, i.e. to keep node ids when editing its expression. However, this came
-    //   at price of not properly keeping other sub-ids on parts of the node line.
-    //   In future, better and cleaner algorithm will need to be provided, likely with a different
-    //   API. Because of such expected rewrite and deeper restructuring, we don't really want to
-    //   spend much time on refactoring this function right now, even if it could be made nicer.
-
-    let removed = &change.range.clone();
-    let inserted = change.text.as_str();
-    let new_code = change.applied(code).unwrap_or_else(|_| code.to_owned());
-    let non_white = |c: char| !c.is_whitespace();
-    let logger = enso_logger::DefaultWarningLogger::new("apply_code_change_to_id_map");
-    let vector = &mut id_map.vec;
-    let inserted_size: Bytes = inserted.len().into();
-
-    info!(logger, "Old code:\n```\n{code}\n```");
-    info!(logger, "New code:\n```\n{new_code}\n```");
-    info!(logger, "Updating the ID map with the following text edit: {change:?}.");
-
-    // Remove all entries fully covered by the removed span.
-    vector.drain_filter(|(range, _)| removed.contains_range(range));
-
-    // If the edited section ends up being the trailing part of AST node, how many bytes should be
-    // trimmed from the id. Precalculated, as is constant in the loop below.
-    let to_trim_back: Bytes = {
-        let last_non_white = inserted.rfind(non_white);
-        let inserted_len = || inserted.len();
-        let length_to_last_non_white = |index| inserted.len() - index - 1;
-        last_non_white.map_or_else(inserted_len, length_to_last_non_white).into()
-    };
-    // As above but for the front side.
-    let to_trim_front: Bytes = {
-        let first_non_white = inserted.find(non_white);
-        first_non_white.unwrap_or(inserted.len()).into()
-    };
+) -> FallibleResult<ast::known::Module> {
+    let old_code = module.repr();
+    let mut code: enso_text::Text = old_code.as_str().into();
+    code.apply_change(change.as_ref());
+    let new_code = String::from(code);
+    let new_ast = parser.parse(new_code.clone(), default())?;
+    let new_ast = preserve_ids(module.ast(), &new_ast, &old_code, &new_code)?;
+    Ok(new_ast.try_into()?)
+}
 
-    let inserted_non_white = inserted.chars().any(non_white);
-
-    // In case of collisions (when, after resizing spans, multiple ids for the same span are
-    // present), the mappings from this map will be preferred over other ones.
-    //
-    // This is needed for edits like: `foo f` => `foo` — the earlier `foo` in `foo f` also has a
-    // id map entry, however we want it to be consistently shadowed by the id from the whole App
-    // expression.
-    let mut preferred: HashMap<enso_text::Range<Bytes>, ast::Id> = default();
-
-    for (range, id) in vector.iter_mut() {
-        let mut trim_front = false;
-        let mut trim_back = false;
-        let initial_range = *range;
-        info!(logger, "Processing @{range}: `{&code[*range]}`.");
-        if range.start > removed.end {
-            debug!(logger, "Node after the edited region.");
-            // AST node starts after edited region — it will be simply shifted.
-            let between_range: enso_text::Range<_> = (removed.end..range.start).into();
-            let code_between = &code[between_range];
-            *range = range.moved_left(removed.size()).moved_right(inserted_size);
-
-            // If there are only spaces between current AST symbol and insertion, extend the symbol.
-            // This is for cases like line with `foo ` being changed into `foo j`.
-            debug!(logger, "Between: `{code_between}`.");
-            if all_spaces(code_between) && inserted_non_white {
-                debug!(logger, "Will extend the node leftwards.");
-                range.start -= inserted_size + between_range.size();
-                trim_front = true;
-            }
-        } else if range.start >= removed.start {
-            // AST node starts inside the edited region. It does not have to end inside it.
-            debug!(logger, "Node overlapping with the end of the edited region.");
-            let removed_before = range.start - removed.start;
-            *range = range.moved_left(removed_before);
-            range.end -= removed.size() - removed_before;
-            range.end += inserted_size;
-            trim_front = true;
-        } else if range.end >= removed.start {
-            // AST node starts before the edited region and reaches (or possibly goes past) its end.
-            debug!(logger, "Node overlapping with the beginning of the edited region.");
-            if range.end <= removed.end {
-                trim_back = true;
-            }
-            let removed_chars = (range.end - removed.start).min(removed.size());
-            range.end -= removed_chars;
-            range.end += inserted_size;
-        } else {
-            debug!(logger, "Node before the edited region.");
-            // If there are only spaces between current AST symbol and insertion, extend the symbol.
-            // This is for cases like line with `foo ` being changed into `foo j`.
-            let between_range: enso_text::Range<_> = (range.end..removed.start).into();
-            let between = &code[between_range];
-            if all_spaces(between) && inserted_non_white {
-                debug!(logger, "Will extend ");
-                range.end += between_range.size() + inserted_size;
-                trim_back = true;
-            }
-        }
+/// Get the `new` subtree with the ids of the matching nodes of the `old` subtree. The `old_repr`
+/// and `new_repr` are the subtrees' code, computed once by the caller.
+fn preserve_ids(old: &Ast, new: &Ast, old_repr: &str, new_repr: &str) -> FallibleResult<Ast> {
+    if old_repr == new_repr {
+        return Ok(old.clone_ref());
+    }
+    let old_children = old.enumerate().collect_vec();
+    let new_children = new.enumerate().collect_vec();
+    let same_shape = mem::discriminant(old.shape()) == mem::discriminant(new.shape());
+    let old_reprs = old_children.iter().map(|(_, child)| child.repr()).collect_vec();
+    let new_reprs = new_children.iter().map(|(_, child)| child.repr()).collect_vec();
+    let mut updated = new.clone_ref();
+    for (old_index, new_index) in align(&old_reprs, &new_reprs, same_shape) {
+        let (crumb, new_child) = &new_children[new_index];
+        let old_child = old_children[old_index].1;
+        let (old_repr, new_repr) = (&old_reprs[old_index], &new_reprs[new_index]);
+        let child = preserve_ids(old_child, new_child, old_repr, new_repr)?;
+        updated = updated.set(crumb, child)?;
+    }
+    Ok(match old.id {
+        Some(id) => updated.with_id(id),
+        None => updated,
+    })
+}
 
-        if trim_front && to_trim_front > 0.bytes() {
-            range.start += to_trim_front;
-            debug!(logger, "Trimming front {to_trim_front.as_usize()} chars.");
-        }
+/// Pair the indices of old and new elements: first the elements of identical text, keeping their
+/// order. Then, if `positional` is set, the elements left between the same pairs are paired by
+/// their position.
+///
+/// The common prefix and suffix are paired directly, so the typical edit of a single element costs
+/// linear time.
+fn align(old: &[String], new: &[String], positional: bool) -> Vec<(usize, usize)> {
+    let same = |(old, new): &(&String, &String)| old == new;
+    let prefix = old.iter().zip(new).take_while(same).count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest.iter().rev().zip(new_rest.iter().rev()).take_while(same).count();
+    let old_middle = &old_rest[..old_rest.len() - suffix];
+    let new_middle = &new_rest[..new_rest.len() - suffix];
+    let middle = if old_middle.len() * new_middle.len() <= MAX_ALIGNED_PAIRS {
+        align_middle(old_middle, new_middle, positional)
+    } else if positional {
+        (0..old_middle.len()).zip(0..new_middle.len()).collect()
+    } else {
+        vec![]
+    };
+    let prefix_pairs = (0..prefix).map(|index| (index, index));
+    let middle_pairs = middle.into_iter().map(|(i, j)| (prefix + i, prefix + j));
+    let suffix_pairs = (1..=suffix).rev().map(|k| (old.len() - k, new.len() - k));
+    prefix_pairs.chain(middle_pairs).chain(suffix_pairs).collect()
+}
 
-        if trim_back {
-            if to_trim_back > 0.bytes() {
-                range.end += -to_trim_back;
-                debug!(logger, "Trimming back {to_trim_back.as_usize()} chars.");
+/// Pair the elements as described in [`align`], using their longest common subsequence.
+fn align_middle(old: &[String], new: &[String], positional: bool) -> Vec<(usize, usize)> {
+    // The longest common subsequence: `lengths[i][j]` is its length for `old[i..]` and `new[j..]`.
+    let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut pairs = vec![];
+    let (mut i, mut j) = (0, 0);
+    let mut gap_start = (0, 0);
+    loop {
+        let at_end = i == old.len() || j == new.len();
+        if at_end || old[i] == new[j] {
+            let gap_end = if at_end { (old.len(), new.len()) } else { (i, j) };
+            if positional {
+                pairs.extend((gap_start.0..gap_end.0).zip(gap_start.1..gap_end.1));
             }
-            let new_repr = &new_code[*range];
-            // Trim trailing spaces
-            let space_count = spaces_size(new_repr.chars().rev());
-            let spaces_len: Bytes = (space_count.as_usize() * ' '.len_utf8()).into();
-            if spaces_len > 0.bytes() {
-                debug!(logger, "Additionally trimming {space_count.as_usize()} trailing spaces.");
-                debug!(logger, "The would-be code: `{new_repr}`.");
-                range.end -= spaces_len;
+            if at_end {
+                break;
             }
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+            gap_start = (i, j);
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
         }
-
-        // If we edited front or end of an AST node, its extended (or shrunk) span will be
-        // preferred.
-        if trim_front || trim_back {
-            preferred.insert(*range, *id);
-        }
-
-        info!(logger, || {
-            let old_fragment = &code[initial_range];
-            let new_fragment = &new_code[*range];
-            iformat!(
-                "Processing for id {id}: {initial_range} ->\t{range}.\n
-                Code: `{old_fragment}` => `{new_fragment}`"
-            )
-        });
     }
-
-    // If non-preferred entry collides with the preferred one, remove the former.
-    vector.drain_filter(|(range, id)| {
-        preferred.get(range).map(|preferred_id| id != preferred_id).unwrap_or(false)
-    });
-}
-
-
-
-// ===============
-// === Helpers ===
-// ===============
-
-/// Returns the chars count of leading space characters sequence.
-fn spaces_size(itr: impl Iterator<Item = char>) -> Chars {
-    itr.take_while(|c| *c == ' ').fold(0, |acc, _| acc + 1).into()
-}
-
-/// Checks if the given string slice contains only space charactesr.
-fn all_spaces(text: &str) -> bool {
-    text.chars().all(|c| c == ' ')
+    pairs
 }
 
 
@@ -183,11 +140,13 @@ fn all_spaces(text: &str) -> bool {
 mod test {
     use super::*;
 
+    use crate::definition;
+    use crate::graph::GraphInfo;
     use crate::module;
+    use crate::node::NodeInfo;
 
     use ast::HasIdMap;
     use enso_prelude::default;
-    use parser::Parser;
     use uuid::Uuid;
 
     /// A sample text edit used to test "text api" properties.
@@ -245,12 +204,8 @@ mod test {
         /// See `assert_same_node_ids` for details.
         fn assert_edit_keeps_main_node_ids(&self, parser: &Parser) {
             let ast1 = parser.parse_module(&self.code, default()).unwrap();
-            let mut id_map = ast1.id_map();
-
-            apply_code_change_to_id_map(&mut id_map, &self.change, &self.code);
-            let code2 = self.resulting_code();
-
-            let ast2 = parser.parse_module(&code2, id_map.clone()).unwrap();
+            let ast2 = apply_change_preserving_ids(parser, &ast1, &self.change).unwrap();
+            assert_eq!(ast2.repr(), self.resulting_code());
             self.assert_same_node_ids(&ast1, &ast2);
         }
 
@@ -277,15 +232,184 @@ mod test {
 
     /// Returns the IDs of nodes in the `main` function in their order of line appearance.
     fn main_nodes(module: &ast::known::Module) -> Vec<Uuid> {
-        use crate::definition::*;
-        use crate::graph::GraphInfo;
-        let id = Id::new_plain_name("main");
-        let definition = module::get_definition(module, &id).unwrap();
-        let graph = GraphInfo::from_definition(definition);
-        let nodes = graph.nodes();
+        let nodes = main_graph_nodes(module).unwrap();
         nodes.into_iter().map(|node| node.id()).collect()
     }
 
+    /// Returns the nodes of the `main` function, if the module defines it.
+    fn main_graph_nodes(module: &ast::known::Module) -> Option<Vec<NodeInfo>> {
+        let id = definition::Id::new_plain_name("main");
+        let definition = module::get_definition(module, &id).ok()?;
+        Some(GraphInfo::from_definition(definition).nodes())
+    }
+
+    /// The ids of all nodes in the AST.
+    fn all_ids(ast: &Ast) -> HashSet<Uuid> {
+        ast.iter_recursive().filter_map(|ast| ast.id).collect()
+    }
+
+    /// Apply the change to the module's code and parse it, preserving the ids.
+    fn apply_change(
+        parser: &Parser,
+        module: &ast::known::Module,
+        change: &enso_text::Change<Bytes, String>,
+    ) -> ast::known::Module {
+        let new_module = apply_change_preserving_ids(parser, module, change).unwrap();
+        assert_eq!(new_module.repr(), change.applied(&module.repr()).unwrap());
+        new_module
+    }
+
+    /// The program being edited in the property tests below.
+    const PROGRAM: &str = r#"main =
+    data = Table.read file_name
+    filtered = data.filter column_name value
+    sum = foo filtered bar
+    sum"#;
+
+    #[wasm_bindgen_test]
+    fn typing_inside_identifier_keeps_all_ids() {
+        let parser = Parser::new_or_panic();
+        let module = parser.parse_module(PROGRAM, default()).unwrap();
+        let spans: HashMap<Uuid, enso_text::Range<Bytes>> =
+            module.ast().id_map().vec.into_iter().map(|(span, id)| (id, span)).collect();
+        let identifiers = module
+            .ast()
+            .iter_recursive()
+            .filter(|ast| ast::identifier::as_var(ast).map_or(false, |name| name != "main"));
+        for identifier in identifiers {
+            let span = spans[&identifier.id.unwrap()];
+            for position in span.start.as_usize() + 1..=span.end.as_usize() {
+                let change = enso_text::Change::inserted(position.into(), "x".to_string());
+                let new_module = apply_change(&parser, &module, &change);
+                let message = iformat!("Typing at {position} in `{identifier.repr()}`.");
+                assert_eq!(all_ids(new_module.ast()), all_ids(module.ast()), "{}", message);
+                assert_eq!(main_nodes(&new_module), main_nodes(&module), "{}", message);
+            }
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn adding_node_keeps_all_ids() {
+        let parser = Parser::new_or_panic();
+        let module = parser.parse_module(PROGRAM, default()).unwrap();
+        let old_ids = all_ids(module.ast());
+        let old_nodes = main_nodes(&module);
+        let line_ends = PROGRAM.match_indices('\n').map(|(index, _)| index);
+        for position in line_ends.chain(iter::once(PROGRAM.len())) {
+            let change = enso_text::Change::inserted(position.into(), "\n    added = 5".into());
+            let new_module = apply_change(&parser, &module, &change);
+            let message = iformat!("Adding node at {position}.");
+            assert!(old_ids.is_subset(&all_ids(new_module.ast())), "{}", message);
+            let new_nodes = main_nodes(&new_module);
+            assert_eq!(new_nodes.len(), old_nodes.len() + 1, "{}", message);
+            let kept_nodes = new_nodes.iter().filter(|id| old_nodes.contains(id));
+            assert!(kept_nodes.eq(old_nodes.iter()), "{}", message);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn deleting_line_keeps_ids_of_other_lines() {
+        let parser = Parser::new_or_panic();
+        let module = parser.parse_module(PROGRAM, default()).unwrap();
+        let old_nodes = main_nodes(&module);
+        let line_starts = PROGRAM.match_indices('\n').map(|(index, _)| index).collect_vec();
+        for (index, start) in line_starts.iter().copied().enumerate() {
+            let end = line_starts.get(index + 1).copied().unwrap_or(PROGRAM.len());
+            let range = enso_text::Range::new(start.into(), end.into());
+            let change = enso_text::Change { range, text: default() };
+            let new_module = apply_change(&parser, &module, &change);
+            let message = iformat!("Deleting line `{&PROGRAM[start..end]}`.");
+
+            let old_ids = module.ast().id_map().vec.into_iter();
+            let (removed, kept): (Vec<_>, Vec<_>) =
+                old_ids.partition(|(span, _)| range.contains_range(span));
+            let new_ids = all_ids(new_module.ast());
+            assert!(removed.iter().all(|(_, id)| !new_ids.contains(id)), "{}", message);
+            assert!(kept.iter().all(|(_, id)| new_ids.contains(id)), "{}", message);
+
+            let mut expected_nodes = old_nodes.clone();
+            expected_nodes.remove(index);
+            assert_eq!(main_nodes(&new_module), expected_nodes, "{}", message);
+        }
+    }
+
+    /// A minimal pseudo-random number generator (xorshift), so the randomized tests are
+    /// reproducible.
+    struct Random(u64);
+
+    impl Random {
+        /// Get a number from the `0..bound` range.
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    /// A random insertion or deletion within a single line of the `main` function, after the
+    /// line's indentation. None if there are no lines to edit.
+    fn random_line_edit(
+        random: &mut Random,
+        code: &str,
+    ) -> Option<enso_text::Change<Bytes, String>> {
+        const INDENT: usize = 4;
+        const INSERTED: &[&str] = &["x", "1", " ", "+ ", ".foo", "(", ")", "\"", " = ", "_"];
+        let line_starts = code.match_indices('\n').map(|(index, _)| index + 1);
+        let line_bodies = line_starts.map(|start| {
+            let end = code[start..].find('\n').map_or(code.len(), |length| start + length);
+            (start + INDENT, end)
+        });
+        let line_bodies = line_bodies.filter(|(start, end)| start <= end).collect_vec();
+        let (start, end) = *line_bodies.get(random.below(line_bodies.len().max(1)))?;
+        let position = start + random.below(end - start + 1);
+        let change = if position < end && random.below(2) == 0 {
+            let removed_end = (position + 1 + random.below(3)).min(end);
+            let range = enso_text::Range::new(position.into(), removed_end.into());
+            enso_text::Change { range, text: default() }
+        } else {
+            let inserted = INSERTED[random.below(INSERTED.len())];
+            enso_text::Change::inserted(position.into(), inserted.to_string())
+        };
+        Some(change)
+    }
+
+    #[wasm_bindgen_test]
+    fn random_edit_sequences_keep_ids() {
+        let parser = Parser::new_or_panic();
+        let distinct = |nodes: &[NodeInfo]| {
+            let lines: HashSet<String> = nodes.iter().map(|node| node.ast().repr()).collect();
+            lines.len() == nodes.len()
+        };
+        let ids = |nodes: &[NodeInfo]| nodes.iter().map(|node| node.id()).collect_vec();
+        for seed in 1..=20 {
+            let mut random = Random(seed);
+            let mut module = parser.parse_module(PROGRAM, default()).unwrap();
+            for _ in 0..50 {
+                let code = module.repr();
+                let change = random_line_edit(&mut random, &code).unwrap();
+                let new_module = match apply_change_preserving_ids(&parser, &module, &change) {
+                    Ok(new_module) => new_module,
+                    // The edit made the code unparseable, so try another one.
+                    Err(_) => continue,
+                };
+                let message = iformat!("Seed {seed}, applying {change:?} to:\n{code}");
+                assert_eq!(new_module.repr(), change.applied(&code).unwrap(), "{}", message);
+                ast::test_utils::assert_unique_ids(new_module.ast());
+
+                // When only a single line changed and the lines can be told apart by their code,
+                // all nodes keep their ids.
+                let old_nodes = main_graph_nodes(&module).unwrap_or_default();
+                let new_nodes = main_graph_nodes(&new_module).unwrap_or_default();
+                let same_count = old_nodes.len() == new_nodes.len();
+                if same_count && distinct(&old_nodes) && distinct(&new_nodes) {
+                    assert_eq!(ids(&new_nodes), ids(&old_nodes), "{}", message);
+                }
+                module = new_module;
+            }
+        }
+    }
+
     #[test]
     fn test_case_markdown() {
         let case = Case::from_markdown("foo«aa⎀bb»c");
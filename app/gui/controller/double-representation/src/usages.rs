@@ -0,0 +1,331 @@
+//! Code for extracting method definitions, method calls and imports of a single module. The
+//! extracted [`Summary`] is the building block of the project-wide index of usages.
+//!
+//! The calls are resolved syntactically: a call to a method defined in the same module or to a
+//! method accessed through a module name (like `Table.read` after `import Standard.Table`) is
+//! resolved to its [`MethodPointer`]. Other calls, like `value.method`, are recorded by name only
+//! and should be resolved further by the caller, e.g. using the suggestion database.
+
+use crate::prelude::*;
+
+use crate::definition;
+use crate::definition::DefinitionInfo;
+use crate::definition::DefinitionProvider;
+use crate::module;
+use crate::module::ImportInfo;
+use crate::module::QualifiedName;
+
+use ast::constants::keywords::HERE;
+use ast::identifier;
+use ast::known;
+use ast::opr;
+use engine_protocol::language_server::MethodPointer;
+
+
+
+// ==============
+// === Callee ===
+// ==============
+
+/// The method called at a call site, as far as it can be resolved from the code.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Callee {
+    /// The call was resolved to the method.
+    Method(MethodPointer),
+    /// Only the name of the called method is known, e.g. in `value.method` calls.
+    Name(String),
+}
+
+impl Callee {
+    /// The name of the called method.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Method(method) => &method.name,
+            Self::Name(name) => name,
+        }
+    }
+}
+
+
+
+// ================
+// === CallSite ===
+// ================
+
+/// A method call in the body of a module-level definition.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CallSite {
+    /// The definition containing the call.
+    pub definition: definition::Id,
+    /// The line of the definition body containing the call. For graph definitions it is the
+    /// node's expression.
+    pub node:       Option<ast::Id>,
+    /// The call expression: the method name or the access expression.
+    pub expression: Option<ast::Id>,
+    #[allow(missing_docs)]
+    pub callee:     Callee,
+}
+
+
+
+// ==================
+// === Definition ===
+// ==================
+
+/// A method defined at the module level.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Definition {
+    #[allow(missing_docs)]
+    pub id:     definition::Id,
+    #[allow(missing_docs)]
+    pub method: MethodPointer,
+}
+
+
+
+// ===============
+// === Summary ===
+// ===============
+
+/// Method definitions, method calls and imports of a module.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub struct Summary {
+    pub definitions: Vec<Definition>,
+    pub calls:       Vec<CallSite>,
+    pub imports:     Vec<ImportInfo>,
+}
+
+impl Summary {
+    /// Summarize the module of the given name.
+    pub fn new(name: &QualifiedName, ast: &known::Module) -> Self {
+        let imports = module::Info { ast: ast.clone_ref() }.iter_imports().collect_vec();
+        let resolver = Resolver::new(name, &imports);
+        let definitions = ast
+            .def_iter()
+            .map(|child| {
+                let id = definition::Id::new_single_crumb(child.name.item.clone());
+                let method = resolver.method_pointer(&child.name.item);
+                Definition { id, method }
+            })
+            .collect_vec();
+        let module_methods = definitions
+            .iter()
+            .filter(|definition| definition.method.defined_on_type == definition.method.module)
+            .map(|definition| definition.method.name.clone())
+            .collect();
+        let resolver = Resolver { module_methods, ..resolver };
+        let calls = ast
+            .def_iter()
+            .flat_map(|child| {
+                let id = definition::Id::new_single_crumb(child.name.item.clone());
+                resolver.calls(id, &child.item)
+            })
+            .collect();
+        Self { definitions, calls, imports }
+    }
+
+    /// Check if the module imports the given target.
+    pub fn imports(&self, target: &ImportInfo) -> bool {
+        self.imports.contains(target)
+    }
+}
+
+/// Check if both pointers identify the same method. The main module of a project may be referred
+/// both with and without the `Main` segment.
+pub fn same_method(a: &MethodPointer, b: &MethodPointer) -> bool {
+    let normalized = |name: &str| match QualifiedName::from_text(name) {
+        Ok(mut name) => {
+            name.remove_main_module_segment();
+            name.to_string()
+        }
+        Err(_) => name.to_owned(),
+    };
+    a.name == b.name
+        && normalized(&a.module) == normalized(&b.module)
+        && normalized(&a.defined_on_type) == normalized(&b.defined_on_type)
+}
+
+
+
+// ================
+// === Resolver ===
+// ================
+
+/// Resolves names used in a module to methods.
+#[derive(Clone, Debug)]
+struct Resolver {
+    module:         QualifiedName,
+    /// The methods defined on the module itself, which may be called by their bare names.
+    module_methods: HashSet<String>,
+    /// The modules referred by their names, i.e. the imported modules and the module itself.
+    modules:        HashMap<String, QualifiedName>,
+}
+
+impl Resolver {
+    fn new(module: &QualifiedName, imports: &[ImportInfo]) -> Self {
+        let imported = imports.iter().filter_map(|import| import.qualified_name().ok());
+        let mut modules: HashMap<String, QualifiedName> =
+            imported.map(|name| (name.name().to_string(), name)).collect();
+        modules.insert(module.name().to_string(), module.clone());
+        modules.insert(HERE.to_owned(), module.clone());
+        Self { module: module.clone(), module_methods: default(), modules }
+    }
+
+    fn method_pointer(&self, name: &definition::DefinitionName) -> MethodPointer {
+        let module = self.module.to_string();
+        let defined_on_type = match name.extended_target.as_slice() {
+            [] => module.clone(),
+            [target] if self.modules.get(&target.item) == Some(&self.module) => module.clone(),
+            [target] => iformat!("{module}.{target.item}"),
+            targets => targets.iter().map(|segment| segment.item.as_str()).join("."),
+        };
+        MethodPointer { module, defined_on_type, name: name.name.item.clone() }
+    }
+
+    fn module_method(&self, module: &QualifiedName, name: &str) -> MethodPointer {
+        let module = module.to_string();
+        MethodPointer {
+            module:          module.clone(),
+            defined_on_type: module,
+            name:            name.into(),
+        }
+    }
+
+    /// The module referred by the expression, like `Main` or `Standard.Table`.
+    fn referred_module(&self, ast: &Ast) -> Option<QualifiedName> {
+        let text = ast.repr();
+        let text = text.trim();
+        let last_segment = text.rsplit(opr::predefined::ACCESS).next()?;
+        let module = self.modules.get(last_segment)?;
+        let matches = text == last_segment || module.to_string().ends_with(text);
+        matches.then(|| module.clone())
+    }
+
+    fn calls(&self, definition: definition::Id, info: &DefinitionInfo) -> Vec<CallSite> {
+        let mut locals = HashSet::new();
+        for arg in &info.args {
+            collect_variables(&arg.item, &mut locals);
+        }
+        for ast in info.body().iter_recursive() {
+            if let Some(infix) = opr::to_assignment(ast).or_else(|| opr::to_arrow(ast)) {
+                collect_variables(&infix.larg, &mut locals);
+            }
+        }
+        let mut calls = Vec::new();
+        for line in info.block_lines() {
+            if let Some(ast) = &line.elem {
+                let mut collector =
+                    CallCollector { resolver: self, locals: &locals, calls: vec![] };
+                collector.visit(ast);
+                calls.extend(collector.calls.into_iter().map(|(expression, callee)| CallSite {
+                    definition: definition.clone(),
+                    node: ast.id(),
+                    expression,
+                    callee,
+                }));
+            }
+        }
+        calls
+    }
+}
+
+fn collect_variables(ast: &Ast, variables: &mut HashSet<String>) {
+    let names =
+        ast.iter_recursive().filter_map(|ast| identifier::as_var(ast).map(ToOwned::to_owned));
+    variables.extend(names);
+}
+
+struct CallCollector<'a> {
+    resolver: &'a Resolver,
+    locals:   &'a HashSet<String>,
+    calls:    Vec<(Option<ast::Id>, Callee)>,
+}
+
+impl<'a> CallCollector<'a> {
+    fn visit(&mut self, ast: &Ast) {
+        if let Some(infix) = opr::to_assignment(ast).or_else(|| opr::to_arrow(ast)) {
+            self.visit(&infix.rarg);
+        } else if let Some(infix) = opr::to_access(ast) {
+            if let Some(name) = identifier::as_var(&infix.rarg) {
+                let callee = match self.resolver.referred_module(&infix.larg) {
+                    Some(module) => Callee::Method(self.resolver.module_method(&module, name)),
+                    None => Callee::Name(name.into()),
+                };
+                self.calls.push((ast.id(), callee));
+            }
+            self.visit(&infix.larg);
+        } else if let Some(name) = identifier::as_var(ast) {
+            if !self.locals.contains(name) {
+                let callee = if self.resolver.module_methods.contains(name) {
+                    let module = &self.resolver.module;
+                    Callee::Method(self.resolver.module_method(module, name))
+                } else {
+                    Callee::Name(name.into())
+                };
+                self.calls.push((ast.id(), callee));
+            }
+        } else {
+            for child in ast {
+                self.visit(child);
+            }
+        }
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn summarizing_module() {
+        let parser = parser::Parser::new_or_panic();
+        let code = r#"import Standard.Table
+
+process x = x + 1
+
+main =
+    table = Table.read "data.csv"
+    result = process table
+    total = result.sum
+    Main.process total"#;
+        let ast = parser.parse_module(code, default()).unwrap();
+        let name = QualifiedName::from_text("local.Project.Main").unwrap();
+        let summary = Summary::new(&name, &ast);
+
+        assert_eq!(summary.imports, vec![ImportInfo::from_target_str("Standard.Table")]);
+        let methods = summary.definitions.iter().map(|d| d.method.name.as_str()).collect_vec();
+        assert_eq!(methods, vec!["process", "main"]);
+
+        let process = summary.definitions[0].method.clone();
+        let callees = summary.calls.iter().map(|call| &call.callee).collect_vec();
+        let read = Callee::Method(MethodPointer {
+            module:          "Standard.Table".into(),
+            defined_on_type: "Standard.Table".into(),
+            name:            "read".into(),
+        });
+        assert_eq!(callees, vec![
+            &read,
+            &Callee::Method(process.clone()),
+            &Callee::Name("sum".into()),
+            &Callee::Method(process.clone()),
+        ]);
+        let main_id = definition::Id::new_plain_name("main");
+        assert!(summary.calls.iter().all(|call| call.definition == main_id));
+
+        let process_with_main_segment = MethodPointer {
+            module:          "local.Project".into(),
+            defined_on_type: "local.Project.Main".into(),
+            name:            "process".into(),
+        };
+        assert!(same_method(&process, &process_with_main_segment));
+    }
+}
@@ -34,6 +34,7 @@ pub mod builder;
 pub mod generate;
 pub mod iter;
 pub mod node;
+pub mod type_compatibility;
 
 pub use node::Crumb;
 pub use node::Crumbs;
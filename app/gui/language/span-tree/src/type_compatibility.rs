@@ -0,0 +1,202 @@
+//! Rules telling whether a value of some type may be passed as an argument of another type.
+//!
+//! The types are known only by the names reported by the Engine and the suggestion database,
+//! which may be either qualified (like `Standard.Base.Data.Text.Text`) or not (like `Text`), and
+//! may have parameters (like `Vector Number`). The parameters are ignored both when validating
+//! connections and when coloring ports, see [`base_name`].
+
+use crate::prelude::*;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// The name of the type accepting values of all types.
+pub const ANY_TYPE_NAME: &str = "Any";
+
+/// Pairs of types where values of the first type are also values of the second one.
+const SUBTYPES: &[(&str, &str)] = &[("Integer", "Number"), ("Decimal", "Number")];
+
+/// Pairs of types where values of the first type are converted to the second one when needed.
+const CONVERSIONS: &[(&str, &str)] = &[("Integer", "Decimal"), ("Array", "Vector")];
+
+
+
+// =====================
+// === Compatibility ===
+// =====================
+
+/// The reason of passing a value to an argument being correct.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Reason {
+    /// The type of the value or of the argument is not known yet, so it cannot be checked.
+    UnknownType,
+    /// The argument accepts values of any type.
+    AcceptsAny,
+    /// The value has exactly the argument's type.
+    SameType,
+    /// The value's type is a subtype of the argument's type, like `Integer` of `Number`.
+    Subtype,
+}
+
+/// The classification of passing a value of the `source` type to an argument of the `target`
+/// type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub enum Compatibility {
+    /// The value may be passed as is.
+    Compatible(Reason),
+    /// The value will be converted to the argument's type, like `Integer` to `Decimal`.
+    Convertible { source: String, target: String },
+    /// The value does not match the argument's type.
+    Incompatible { source: String, target: String },
+}
+
+impl Compatibility {
+    /// Classify passing a value of the `source` type to an argument of the `target` type. `None`
+    /// means that the type is not known.
+    pub fn check(source: Option<&str>, target: Option<&str>) -> Self {
+        let (source, target) = match (source, target) {
+            (Some(source), Some(target)) => (source, target),
+            _ => return Self::Compatible(Reason::UnknownType),
+        };
+        if short_name(target) == ANY_TYPE_NAME {
+            Self::Compatible(Reason::AcceptsAny)
+        } else if same_type(source, target) {
+            Self::Compatible(Reason::SameType)
+        } else if is_listed(SUBTYPES, source, target) {
+            Self::Compatible(Reason::Subtype)
+        } else if is_listed(CONVERSIONS, source, target) {
+            Self::Convertible { source: source.into(), target: target.into() }
+        } else {
+            Self::Incompatible { source: source.into(), target: target.into() }
+        }
+    }
+
+    /// Check if the value may be passed to the argument, with or without a conversion.
+    pub fn is_allowed(&self) -> bool {
+        !matches!(self, Self::Incompatible { .. })
+    }
+}
+
+impl Display for Compatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compatible(Reason::UnknownType) => write!(f, "The types are not known yet."),
+            Self::Compatible(Reason::AcceptsAny) => write!(f, "The argument accepts any value."),
+            Self::Compatible(Reason::SameType) => write!(f, "The types are the same."),
+            Self::Compatible(Reason::Subtype) =>
+                write!(f, "The value's type is a subtype of the argument's type."),
+            Self::Convertible { source, target } =>
+                write!(f, "The value of type `{}` will be converted to `{}`.", source, target),
+            Self::Incompatible { source, target } =>
+                write!(f, "The value of type `{}` does not match `{}`.", source, target),
+        }
+    }
+}
+
+
+
+// ==================
+// === Type Names ===
+// ==================
+
+/// The type name without parameters, e.g. `Standard.Base.Data.Vector.Vector` for
+/// `Standard.Base.Data.Vector.Vector Number`. Type names with the same base name are given the
+/// same colors.
+pub fn base_name(tp: &str) -> &str {
+    let tp = tp.trim().trim_start_matches('(');
+    let base = tp.split_whitespace().next().unwrap_or_default();
+    base.trim_end_matches(')')
+}
+
+/// The type name without parameters and module path, e.g. `Vector` for
+/// `Standard.Base.Data.Vector.Vector Number`.
+pub fn short_name(tp: &str) -> &str {
+    let base = base_name(tp);
+    base.rsplit('.').next().unwrap_or(base)
+}
+
+/// Check if the pair of types is present in the list, comparing their short names.
+fn is_listed(pairs: &[(&str, &str)], source: &str, target: &str) -> bool {
+    let pair = (short_name(source), short_name(target));
+    pairs.iter().any(|(from, to)| pair == (*from, *to))
+}
+
+/// Check if both names refer the same type. The parameters are ignored. The names are compared
+/// with their module paths only if both are qualified.
+pub fn same_type(first: &str, second: &str) -> bool {
+    let (first, second) = (base_name(first), base_name(second));
+    let both_qualified = first.contains('.') && second.contains('.');
+    if both_qualified {
+        first == second
+    } else {
+        short_name(first) == short_name(second)
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasm_bindgen_test::wasm_bindgen_test;
+    use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn classifying_types() {
+        let text = "Standard.Base.Data.Text.Text";
+        let integer = "Standard.Base.Data.Numbers.Integer";
+        let table = "Standard.Table.Data.Table.Table";
+        let check = |source, target| Compatibility::check(source, target);
+        let incompatible = |source: &str, target: &str| Compatibility::Incompatible {
+            source: source.into(),
+            target: target.into(),
+        };
+
+        assert_eq!(check(None, Some(text)), Compatibility::Compatible(Reason::UnknownType));
+        assert_eq!(check(Some(text), None), Compatibility::Compatible(Reason::UnknownType));
+        assert_eq!(check(Some(text), Some("Any")), Compatibility::Compatible(Reason::AcceptsAny));
+        assert_eq!(check(Some(text), Some("Text")), Compatibility::Compatible(Reason::SameType));
+        let vector = "Standard.Base.Data.Vector.Vector Number";
+        assert_eq!(
+            check(Some(vector), Some("Vector")),
+            Compatibility::Compatible(Reason::SameType)
+        );
+        assert_eq!(
+            check(Some(integer), Some("Number")),
+            Compatibility::Compatible(Reason::Subtype)
+        );
+        let converted =
+            Compatibility::Convertible { source: integer.into(), target: "Decimal".into() };
+        assert_eq!(check(Some(integer), Some("Decimal")), converted);
+        assert_eq!(check(Some(text), Some(integer)), incompatible(text, integer));
+        let other_table = "local.Project.Table.Table";
+        assert_eq!(check(Some(table), Some(other_table)), incompatible(table, other_table));
+        assert!(check(Some(integer), Some("Decimal")).is_allowed());
+        assert!(!check(Some(table), Some(text)).is_allowed());
+    }
+
+    #[wasm_bindgen_test]
+    fn normalizing_type_names() {
+        assert_eq!(
+            base_name("Standard.Base.Data.Vector.Vector Number"),
+            "Standard.Base.Data.Vector.Vector"
+        );
+        assert_eq!(base_name("(Vector Text)"), "Vector");
+        assert_eq!(base_name("Standard.Base.Data.Text.Text"), "Standard.Base.Data.Text.Text");
+        assert_eq!(short_name("Standard.Base.Data.Text.Text"), "Text");
+        assert_eq!(short_name("Integer"), "Integer");
+        assert!(same_type("Standard.Base.Data.Text.Text", "Text"));
+        assert!(!same_type("Standard.Table.Data.Table.Table", "local.Project.Table"));
+    }
+}
@@ -14,6 +14,8 @@ use double_representation::connection;
 use double_representation::definition;
 use double_representation::definition::DefinitionProvider;
 use double_representation::graph::GraphInfo;
+use double_representation::graph::PseudoNode;
+use double_representation::graph::PseudoNodeKind;
 use double_representation::identifier::generate_name;
 use double_representation::identifier::LocatedName;
 use double_representation::identifier::NormalizedName;
@@ -36,6 +38,7 @@ use span_tree::SpanTree;
 // ==============
 
 pub mod executed;
+pub mod layout;
 
 pub use double_representation::graph::Id;
 pub use double_representation::graph::LocationHint;
@@ -68,6 +71,16 @@ pub struct NoPatternOnNode {
     pub node: node::Id,
 }
 
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Fail)]
+#[fail(display = "The output pseudo-node {} cannot be a connection source.", _0)]
+pub struct OutputNodeIsNotSource(node::Id);
+
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Fail)]
+#[fail(display = "The returned value cannot be disconnected, only replaced with another node.")]
+pub struct CannotDisconnectOutput;
+
 
 
 // ====================
@@ -261,9 +274,13 @@ impl NodeTrees {
 #[derive(Clone, Debug, Default)]
 pub struct Connections {
     /// Span trees for all nodes that have connections.
-    pub trees:       HashMap<node::Id, NodeTrees>,
+    pub trees:              HashMap<node::Id, NodeTrees>,
     /// The connections between nodes in the graph.
-    pub connections: Vec<Connection>,
+    pub connections:        Vec<Connection>,
+    /// The input and output pseudo-nodes of the graph. Their endpoints have always empty ports.
+    pub pseudo_nodes:       Vec<PseudoNode>,
+    /// The connections from the input pseudo-nodes and to the output pseudo-node.
+    pub pseudo_connections: Vec<Connection>,
 }
 
 impl Connections {
@@ -274,19 +291,33 @@ impl Connections {
             .iter()
             .filter_map(|node| Some((node.id(), NodeTrees::new(node, context)?)))
             .collect();
+        let pseudo_nodes = graph.pseudo_nodes();
 
-        let mut ret = Connections { trees, connections: default() };
-        let connections =
-            graph.connections().into_iter().filter_map(|c| ret.convert_connection(&c)).collect();
+        let mut ret = Connections { trees, pseudo_nodes, ..default() };
+        let connections = graph.connections_with_pseudo_nodes();
+        let connections = connections.into_iter().filter_map(|c| ret.convert_connection(&c));
+        let is_pseudo = |c: &Connection| {
+            ret.is_pseudo_node(c.source.node) || ret.is_pseudo_node(c.destination.node)
+        };
+        let (pseudo_connections, connections) = connections.partition(is_pseudo);
         ret.connections = connections;
+        ret.pseudo_connections = pseudo_connections;
         ret
     }
 
+    /// Check if the node is one of the graph's pseudo-nodes.
+    pub fn is_pseudo_node(&self, id: node::Id) -> bool {
+        self.pseudo_nodes.iter().any(|pseudo_node| pseudo_node.id == id)
+    }
+
     /// Converts Endpoint from double representation to the span tree crumbs.
     pub fn convert_endpoint(
         &self,
         endpoint: &double_representation::connection::Endpoint,
     ) -> Option<Endpoint> {
+        if self.is_pseudo_node(endpoint.node) {
+            return Some(Endpoint::new(endpoint.node, span_tree::Crumbs::default()));
+        }
         let tree = self.trees.get(&endpoint.node)?;
         let span_tree_node = tree.get_span_tree_node(&endpoint.crumbs)?;
         Some(Endpoint {
@@ -698,29 +729,70 @@ impl Handle {
         Ok(())
     }
 
+    /// Get the input pseudo-nodes (one for each parameter) and the output pseudo-node of the
+    /// graph. See [`PseudoNode`].
+    pub fn pseudo_nodes(&self) -> FallibleResult<Vec<PseudoNode>> {
+        Ok(self.graph_info()?.pseudo_nodes())
+    }
+
+    /// Get the pseudo-node with the given id, if there is one.
+    fn pseudo_node(&self, id: node::Id) -> FallibleResult<Option<PseudoNode>> {
+        Ok(self.pseudo_nodes()?.into_iter().find(|node| node.id == id))
+    }
+
     /// Create connection in graph.
+    ///
+    /// The connection may start in an input pseudo-node, what makes the destination port use the
+    /// parameter, or end in the output pseudo-node, what makes the graph return the source node's
+    /// value.
     pub fn connect(
         &self,
         connection: &Connection,
         context: &impl SpanTreeContext,
     ) -> FallibleResult {
         let _transaction_guard = self.get_or_open_transaction("Connect");
-        if connection.source.port.is_empty() {
-            // If we create connection from node's expression root, we are able to introduce missing
-            // pattern with a new variable.
-            self.introduce_pattern_if_missing(connection.source.node)?;
+        let source_node = connection.source.node;
+        let destination_node = connection.destination.node;
+        if self.pseudo_node(destination_node)?.contains_if(|node| node.is_output()) {
+            return self.connect_to_output(source_node);
         }
+        let source_pseudo_node = self.pseudo_node(source_node)?;
+        let source_identifier = match source_pseudo_node.as_ref().map(|node| &node.kind) {
+            Some(PseudoNodeKind::Input { name, .. }) => Ast::var(name.clone()),
+            Some(PseudoNodeKind::Output) => return Err(OutputNodeIsNotSource(source_node).into()),
+            None => {
+                if connection.source.port.is_empty() {
+                    // If we create connection from node's expression root, we are able to
+                    // introduce missing pattern with a new variable.
+                    self.introduce_pattern_if_missing(source_node)?;
+                }
+                self.source_info(connection, context)?.target_ast()?.clone()
+            }
+        };
 
-        let source_info = self.source_info(connection, context)?;
         let destination_info = self.destination_info(connection, context)?;
-        let source_identifier = source_info.target_ast()?.clone();
         let updated_target_node_expr = destination_info.set(source_identifier.with_new_id())?;
-        self.set_expression_ast(connection.destination.node, updated_target_node_expr)?;
+        self.set_expression_ast(destination_node, updated_target_node_expr)?;
 
-        // Reorder node lines, so the connection target is after connection source.
-        let source_node = connection.source.node;
-        let destination_node = connection.destination.node;
-        self.place_node_and_dependencies_lines_after(source_node, destination_node)
+        if source_pseudo_node.is_none() {
+            // Reorder node lines, so the connection target is after connection source.
+            self.place_node_and_dependencies_lines_after(source_node, destination_node)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Make the graph return the value of the given node.
+    fn connect_to_output(&self, source_node: node::Id) -> FallibleResult {
+        let is_last_node = self.all_node_infos()?.last().contains_if(|n| n.id() == source_node);
+        if !is_last_node {
+            self.introduce_pattern_if_missing(source_node)?;
+        }
+        self.update_definition_ast(|definition| {
+            let mut graph = GraphInfo::from_definition(definition);
+            graph.set_output(source_node)?;
+            Ok(graph.source)
+        })
     }
 
     /// Remove the connections from the graph.
@@ -730,6 +802,9 @@ impl Handle {
         context: &impl SpanTreeContext,
     ) -> FallibleResult {
         let _transaction_guard = self.get_or_open_transaction("Disconnect");
+        if self.pseudo_node(connection.destination.node)?.is_some() {
+            return Err(CannotDisconnectOutput.into());
+        }
         let info = self.destination_info(connection, context)?;
 
         let updated_expression = if connection.destination.var_crumbs.is_empty() {
@@ -867,6 +942,41 @@ impl Handle {
         )
     }
 
+    /// Arrange nodes using the automatic layered layout, see the [`layout`] module.
+    ///
+    /// The `extents` describe the sizes of the node views; nodes missing in the map are assumed
+    /// to have the default size. If `selection` is given, only the selected nodes are arranged
+    /// (and only connections between them are considered), otherwise all the nodes are. All
+    /// position changes are recorded as a single undoable transaction.
+    pub fn auto_layout(
+        &self,
+        extents: &HashMap<node::Id, layout::Extent>,
+        selection: Option<&[node::Id]>,
+    ) -> FallibleResult {
+        let _transaction_guard = self.get_or_open_transaction("Auto layout");
+        let is_arranged = |id: &node::Id| selection.map_or(true, |ids| ids.contains(id));
+        let nodes = self.nodes()?.into_iter().filter(|node| is_arranged(&node.id()));
+        let nodes = nodes
+            .map(|node| {
+                let id = node.id();
+                let extent = extents.get(&id).copied().unwrap_or_default();
+                let position = node.position().map(|position| position.vector);
+                layout::Node { id, extent, position }
+            })
+            .collect_vec();
+        let connections = self.graph_info()?.connections().into_iter();
+        let connections = connections
+            .map(|connection| (connection.source.node, connection.destination.node))
+            .filter(|(source, target)| is_arranged(source) && is_arranged(target))
+            .collect_vec();
+        let layout = layout::layout(&nodes, &connections, &default());
+        info!(self.logger, "Auto layout of {nodes.len()} nodes, {layout.crossings} crossings.");
+        for (id, position) in layout.positions {
+            self.set_node_position(id, position)?;
+        }
+        Ok(())
+    }
+
     /// Collapses the selected nodes.
     ///
     /// Lines corresponding to the selection will be extracted to a new method definition.
@@ -1102,6 +1212,27 @@ pub mod tests {
         })
     }
 
+    #[wasm_bindgen_test]
+    fn arranging_nodes_automatically() {
+        let mut test = Fixture::set_up();
+        test.data.code = "main =\n    a = 10\n    b = a + 1\n    c = b + a\n    d = 20".into();
+        test.run(|graph| async move {
+            let (a, b, c, d) = graph.nodes().unwrap().expect_tuple();
+            let position = |node: &Node| graph.node(node.id()).unwrap().position().unwrap().vector;
+
+            graph.auto_layout(&default(), None).unwrap();
+            assert!(position(&a).y > position(&b).y);
+            assert!(position(&b).y > position(&c).y);
+            assert_eq!(position(&a).y, position(&d).y);
+            assert_ne!(position(&a).x, position(&d).x);
+
+            let d_position = position(&d);
+            graph.auto_layout(&default(), Some(&[a.id(), c.id()])).unwrap();
+            assert!(position(&a).y > position(&c).y);
+            assert_eq!(position(&d), d_position);
+        })
+    }
+
     #[wasm_bindgen_test]
     fn graph_controller_notification_relay() {
         Fixture::set_up().run(|graph| async move {
@@ -6,15 +6,21 @@
 
 use crate::prelude::*;
 
+use crate::model::execution_context::replay::Recording;
 use crate::model::execution_context::ComputedValueInfoRegistry;
 use crate::model::execution_context::LocalCall;
+use crate::model::execution_context::ValueChange;
 use crate::model::execution_context::Visualization;
 use crate::model::execution_context::VisualizationId;
 use crate::model::execution_context::VisualizationUpdateData;
 
+use double_representation::graph::GraphInfo;
 use engine_protocol::language_server::MethodPointer;
 use span_tree::generate::context::CalledMethodInfo;
 use span_tree::generate::context::Context;
+use span_tree::type_compatibility::Compatibility;
+use std::cmp::Reverse;
+use std::time::Duration;
 
 
 // ==============
@@ -64,6 +70,26 @@ pub enum Notification {
 
 
 
+// ==================
+// === NodeTiming ===
+// ==================
+
+/// The execution time of a node in the graph.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NodeTiming {
+    #[allow(missing_docs)]
+    pub node:  double_representation::node::Id,
+    /// The time measured by the engine for the node's expression, if any.
+    pub own:   Option<Duration>,
+    /// The time spent in the node: its own time if measured. Otherwise, for nodes calling methods
+    /// defined in the same module, like the ones created by collapsing nodes, the total time of
+    /// the method's nodes computed by the same execution, as the call may have been taken from
+    /// the cache.
+    pub total: Duration,
+}
+
+
+
 // ==============
 // === Handle ===
 // ==============
@@ -94,10 +120,37 @@ impl Handle {
         method: MethodPointer,
     ) -> FallibleResult<Self> {
         let graph = controller::Graph::new_method(parent, &project, &method).await?;
-        let execution = project.create_execution_context(method.clone()).await?;
+        let execution = match project.create_execution_context(method.clone()).await {
+            Ok(execution) => execution,
+            Err(error) => Self::replay_context(&graph.logger, &project, method, error).await?,
+        };
         Ok(Self::new_internal(graph, project, execution))
     }
 
+    /// Create a context replaying the results recorded in the project's recording file, to be used
+    /// when the code cannot be executed. Fails with the given error if there are no recorded
+    /// results.
+    async fn replay_context(
+        logger: &Logger,
+        project: &model::Project,
+        method: MethodPointer,
+        error: failure::Error,
+    ) -> FallibleResult<model::ExecutionContext> {
+        let path = Recording::path(project.project_content_root_id());
+        let contents = project.json_rpc().read_file(&path).await.map(|read| read.contents);
+        let recording = contents.ok().and_then(|contents| serde_json::from_str(&contents).ok());
+        match recording {
+            Some(recording) => {
+                warning!(logger, "Cannot execute the code: {error}. Showing recorded results.");
+                let logger = Logger::new_sub(logger, "Replay");
+                let context = model::execution_context::Replay::new(logger, method, recording);
+                let context: model::ExecutionContext = Rc::new(context);
+                Ok(context)
+            }
+            _ => Err(error),
+        }
+    }
+
     /// Create handle for given graph and execution context.
     ///
     /// The given graph and execution context must be for the same method. Prefer using `new`,
@@ -221,6 +274,73 @@ impl Handle {
         registry.clone_ref().get_type(id)
     }
 
+    /// Record the results of the execution in the project's recording file, so they can be shown
+    /// when the project is opened while the code cannot be executed. See [`Recording::path`].
+    pub async fn save_recording(&self) -> FallibleResult {
+        let contents = serde_json::to_string(&self.execution_ctx.recording())?;
+        let path = Recording::path(self.project.project_content_root_id());
+        self.project.json_rpc().write_file(&path, &contents).await?;
+        Ok(())
+    }
+
+    /// Compare two named snapshots of the computed value registry, keeping only the changes of
+    /// this graph's nodes. See [`ComputedValueInfoRegistry::take_snapshot`].
+    pub fn node_value_changes(&self, old: &str, new: &str) -> FallibleResult<Vec<ValueChange>> {
+        let nodes = self.graph().nodes()?;
+        let nodes: HashSet<_> = nodes.iter().map(|node| node.info.id()).collect();
+        let changes = self.computed_value_info_registry().diff_snapshots(old, new)?;
+        Ok(changes.into_iter().filter(|change| nodes.contains(&change.expression)).collect())
+    }
+
+    /// Get the execution times of all nodes in the graph, in the order of nodes.
+    pub fn node_timings(&self) -> FallibleResult<Vec<NodeTiming>> {
+        let registry = self.computed_value_info_registry();
+        let nodes = self.graph().nodes()?;
+        let timings = nodes.iter().map(|node| {
+            let node = node.info.id();
+            let own = registry.execution_time(&node);
+            let total = own.or_else(|| self.called_method_time(node)).unwrap_or_default();
+            NodeTiming { node, own, total }
+        });
+        Ok(timings.collect())
+    }
+
+    /// Get the execution times of at most `count` slowest nodes in the graph, starting from the
+    /// slowest one. The nodes whose time was not measured are skipped.
+    pub fn slowest_nodes(&self, count: usize) -> FallibleResult<Vec<NodeTiming>> {
+        let timings = self.node_timings()?.into_iter().filter(|timing| !timing.total.is_zero());
+        let slowest_first = timings.sorted_by_key(|timing| Reverse(timing.total));
+        Ok(slowest_first.take(count).collect())
+    }
+
+    /// Get the execution times of the calls on the call stack, starting from the outermost one.
+    /// Each call's time is the total time spent in its frame.
+    pub fn call_stack_timings(&self) -> Vec<(LocalCall, Option<Duration>)> {
+        let registry = self.computed_value_info_registry();
+        let with_times = self.call_stack().into_iter().map(|frame| {
+            let time = registry.execution_time(&frame.call);
+            (frame, time)
+        });
+        with_times.collect()
+    }
+
+    /// The total execution time of the nodes of the method called by the node, computed by the same
+    /// execution as the node's value. `None` if the method is not defined in the graph's module or
+    /// none of such nodes' times was measured.
+    fn called_method_time(&self, node: double_representation::node::Id) -> Option<Duration> {
+        let method = self.node_method_pointer(node).ok()?;
+        let module = self.graph().module;
+        let definition = module.lookup_method(self.project.qualified_name(), &method).ok()?;
+        let definition = double_representation::module::get_definition(&module.ast(), &definition);
+        let nodes = GraphInfo::from_definition(definition.ok()?).nodes();
+        let registry = self.computed_value_info_registry();
+        let execution = registry.get(&node)?.execution;
+        let infos = nodes.iter().filter_map(|node| registry.get(&node.id()));
+        let same_execution = infos.filter(|info| info.execution == execution);
+        let times = same_execution.filter_map(|info| info.execution_time);
+        times.reduce(|total, time| total + time)
+    }
+
     /// Enter node by given node ID and method pointer.
     ///
     /// This will cause pushing a new stack frame to the execution context and changing the graph
@@ -309,6 +429,28 @@ impl Handle {
         self.graph.borrow().connect(connection, self)
     }
 
+    /// Check if the type of the connection's source matches the type of the destination argument.
+    ///
+    /// The source type is taken from the computed value registry, so it is unknown until the
+    /// source is evaluated. The argument type is taken from the called method's signature.
+    pub fn connection_compatibility(
+        &self,
+        connection: &Connection,
+    ) -> FallibleResult<Compatibility> {
+        let graph = self.graph.borrow();
+        let source = graph.source_info(connection, self)?;
+        let destination = graph.destination_info(connection, self)?;
+        let source_id = if connection.source.port.is_empty() {
+            Some(connection.source.node)
+        } else {
+            source.target_ast()?.id
+        };
+        let registry = self.computed_value_info_registry();
+        let source_type = source_id.and_then(|id| registry.get(&id)?.typename.clone());
+        let target_type = destination.port()?.tp().cloned();
+        Ok(Compatibility::check(source_type.as_deref(), target_type.as_deref()))
+    }
+
     /// Remove the connections from the graph.
     pub fn disconnect(&self, connection: &Connection) -> FallibleResult {
         self.graph.borrow().disconnect(connection, self)
@@ -354,6 +496,7 @@ pub mod tests {
 
     use engine_protocol::language_server::types::test::value_update_with_method_ptr;
     use engine_protocol::language_server::types::test::value_update_with_type;
+    use json_rpc::expect_call;
     use wasm_bindgen_test::wasm_bindgen_test;
     use wasm_bindgen_test::wasm_bindgen_test_configure;
 
@@ -473,4 +616,121 @@ pub mod tests {
         let info = get_invocation_info().unwrap();
         assert_call_info(info, &entry2);
     }
+
+    #[wasm_bindgen_test]
+    fn checking_connection_compatibility() {
+        use crate::test::mock;
+        use span_tree::type_compatibility::Reason;
+
+        let mut data = mock::Unified::new();
+        data.set_code("main =\n    text = \"x\"\n    number = 5\n    foo text number");
+        let mock::Fixture { graph, executed_graph, .. } = &data.fixture();
+        let nodes = graph.nodes().unwrap();
+        let (text, number, call) = (nodes[0].info.id(), nodes[1].info.id(), nodes[2].info.id());
+        let registry = executed_graph.computed_value_info_registry();
+        registry.apply_updates(vec![
+            value_update_with_type(text, "Standard.Base.Data.Text.Text"),
+            value_update_with_type(number, "Standard.Base.Data.Numbers.Integer"),
+            value_update_with_method_ptr(call, 1),
+        ]);
+
+        let connections = executed_graph.connections().unwrap().connections;
+        let from = |node| connections.iter().find(|c| c.source.node == node).unwrap();
+        let check = |node| executed_graph.connection_compatibility(from(node)).unwrap();
+        let incompatible = Compatibility::Incompatible {
+            source: "Standard.Base.Data.Text.Text".into(),
+            target: "Base".into(),
+        };
+        assert_eq!(check(text), incompatible);
+        assert_eq!(check(number), Compatibility::Compatible(Reason::Subtype));
+    }
+
+    #[wasm_bindgen_test]
+    fn measuring_node_execution_times() {
+        use crate::model::suggestion_database::entry::Kind;
+        use crate::model::suggestion_database::entry::Scope;
+        use crate::model::suggestion_database::Entry;
+        use crate::test::mock;
+        use engine_protocol::language_server::ProfilingInfo;
+
+        let mut data = mock::Unified::new();
+        data.set_code(
+            "main =\n    a = 5\n    b = func1 a\n    b\n\nfunc1 x =\n    y = x + 1\n    y",
+        );
+        let module_name = data.module_qualified_name();
+        data.suggestions.insert(3, Entry {
+            kind:               Kind::Method,
+            module:             module_name.clone(),
+            name:               "func1".into(),
+            arguments:          vec![],
+            return_type:        "Any".into(),
+            documentation_html: None,
+            self_type:          Some(module_name.to_string().try_into().unwrap()),
+            scope:              Scope::Everywhere,
+        });
+        let mock::Fixture { graph, executed_graph, execution, module, .. } = &data.fixture();
+        let nodes = graph.nodes().unwrap();
+        let (a, b) = (nodes[0].info.id(), nodes[1].info.id());
+        let func1 = double_representation::definition::Id::new_plain_name("func1");
+        let func1 = double_representation::module::get_definition(&module.ast(), &func1).unwrap();
+        let y = GraphInfo::from_definition(func1).nodes()[0].id();
+
+        let update_with_time = |id, millis: u64| {
+            let mut update = value_update_with_type(id, "Standard.Base.Data.Numbers.Integer");
+            let nano_time = millis * 1_000_000;
+            update.profiling_info = vec![ProfilingInfo::ExecutionTime { nano_time }];
+            update
+        };
+        let mut call = update_with_time(b, 1);
+        call.method_pointer = Some(3);
+        let updates = vec![update_with_time(a, 2), call, update_with_time(y, 4)];
+        let registry = executed_graph.computed_value_info_registry();
+        registry.apply_updates(updates);
+
+        let millis = Duration::from_millis;
+        let timings = executed_graph.node_timings().unwrap();
+        assert_eq!(timings[0], NodeTiming { node: a, own: Some(millis(2)), total: millis(2) });
+        assert_eq!(timings[1], NodeTiming { node: b, own: Some(millis(1)), total: millis(1) });
+        assert_eq!(timings[2].own, None);
+        let slowest = executed_graph.slowest_nodes(5).unwrap();
+        assert_eq!(slowest.iter().map(|timing| timing.node).collect_vec(), vec![a, b]);
+        assert_eq!(executed_graph.slowest_nodes(1).unwrap().len(), 1);
+
+        let definition = executed_graph.node_method_pointer(b).unwrap();
+        let frame = LocalCall { call: b, definition: (*definition).clone() };
+        execution.push(frame.clone());
+        assert_eq!(executed_graph.call_stack_timings(), vec![(frame, Some(millis(1)))]);
+
+        // The call taken from the cache has no time of its own, so the times of the called
+        // method's nodes are used, but only if they were computed by the same execution.
+        let mut cached_call = value_update_with_type(b, "Standard.Base.Data.Numbers.Integer");
+        cached_call.method_pointer = Some(3);
+        registry.apply_updates(vec![cached_call, update_with_time(y, 4)]);
+        let total_of_b = || executed_graph.node_timings().unwrap()[1].total;
+        assert_eq!(total_of_b(), millis(4));
+        registry.execution_completed();
+        registry.apply_updates(vec![update_with_time(y, 6)]);
+        assert_eq!(total_of_b(), Duration::ZERO);
+    }
+
+    #[wasm_bindgen_test]
+    fn saving_recording_of_results() {
+        use crate::test::mock::Fixture;
+        let id = ExpressionId::new_v4();
+        let update = value_update_with_type(id, crate::test::mock::data::TYPE_NAME);
+        let registry = ComputedValueInfoRegistry::default();
+        registry.apply_updates(vec![update.clone()]);
+        let recording = Recording::new(&registry, vec![]);
+        let contents = serde_json::to_string(&recording).unwrap();
+        let path = Recording::path(crate::test::mock::data::ROOT_ID);
+        let mut fixture = crate::test::mock::Unified::new().fixture_customize(|_, json, _| {
+            expect_call!(json.write_file(path=path,contents=contents) => Ok(()));
+        });
+        let Fixture { executed_graph, module, executor, .. } = &mut fixture;
+        let content = module.serialized_content().unwrap().content;
+        executed_graph.computed_value_info_registry().apply_updates(vec![update]);
+
+        executor.expect_completion(executed_graph.save_recording()).unwrap();
+        assert_eq!(module.serialized_content().unwrap().content, content);
+    }
 }
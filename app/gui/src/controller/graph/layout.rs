@@ -0,0 +1,575 @@
+//! Automatic layered layout of graph nodes.
+//!
+//! The layout follows the Sugiyama framework:
+//! 1. Cycles are broken by reversing some of the connections.
+//! 2. Nodes are assigned to layers following the data flow from the top to the bottom.
+//! 3. Connections spanning several layers are split by virtual nodes, so every connection links
+//!    two adjacent layers.
+//! 4. Nodes in each layer are ordered to minimize edge crossings, using the barycenter heuristic.
+//! 5. Nodes are moved horizontally towards their neighbours, without overlapping each other.
+//!
+//! The module is purely geometrical: the node sizes and connections are provided by the caller,
+//! and the computed positions are returned rather than applied.
+
+use crate::prelude::*;
+
+use double_representation::node;
+use std::collections::VecDeque;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// The width assumed for nodes of unknown extent.
+pub const DEFAULT_NODE_WIDTH: f32 = 200.0;
+/// The height assumed for nodes of unknown extent.
+pub const DEFAULT_NODE_HEIGHT: f32 = 28.0;
+
+
+
+// ==============
+// === Extent ===
+// ==============
+
+/// The space taken by a node in the scene.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Extent {
+    /// The size of the node's bounding box.
+    pub size:   Vector2,
+    /// The offset of the node's position from the top-left corner of its bounding box.
+    pub origin: Vector2,
+}
+
+impl Extent {
+    /// Constructor.
+    pub fn new(size: Vector2, origin: Vector2) -> Self {
+        Self { size, origin }
+    }
+}
+
+/// The extent of a node without a visualization: its position is at the left edge, vertically
+/// centered.
+impl Default for Extent {
+    fn default() -> Self {
+        let size = Vector2(DEFAULT_NODE_WIDTH, DEFAULT_NODE_HEIGHT);
+        let origin = Vector2(0.0, -DEFAULT_NODE_HEIGHT / 2.0);
+        Self { size, origin }
+    }
+}
+
+
+
+// ============
+// === Node ===
+// ============
+
+/// A node to be laid out.
+#[derive(Clone, Copy, Debug)]
+pub struct Node {
+    /// The node identifier.
+    pub id:       node::Id,
+    /// The space taken by the node.
+    pub extent:   Extent,
+    /// The current position of the node, if any. The existing positions decide the initial order
+    /// of nodes and the place of the whole layout.
+    pub position: Option<Vector2>,
+}
+
+
+
+// ===============
+// === Options ===
+// ===============
+
+/// Parameters of the layout.
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    /// The minimum horizontal space between nodes in the same layer.
+    pub horizontal_gap:   f32,
+    /// The vertical space between consecutive layers.
+    pub vertical_gap:     f32,
+    /// The maximum number of passes reordering nodes to minimize edge crossings.
+    pub ordering_sweeps:  usize,
+    /// The number of passes moving nodes towards their neighbours.
+    pub alignment_passes: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            horizontal_gap:   40.0,
+            vertical_gap:     40.0,
+            ordering_sweeps:  24,
+            alignment_passes: 8,
+        }
+    }
+}
+
+
+
+// ==============
+// === Layout ===
+// ==============
+
+/// The result of the layout.
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+    /// New positions of all the nodes, in the order of the input nodes.
+    pub positions: Vec<(node::Id, Vector2)>,
+    /// The number of edge crossings remaining in the layout.
+    pub crossings: usize,
+}
+
+/// Compute the layered layout of the given nodes. Connections are given as pairs of source and
+/// destination node ids. Connections between unknown nodes are ignored.
+///
+/// The top-left corner of the layout is placed at the top-left corner of the current bounding box
+/// of nodes having positions, or at the origin if no node has a position.
+pub fn layout(nodes: &[Node], connections: &[(node::Id, node::Id)], options: &Options) -> Layout {
+    if nodes.is_empty() {
+        return default();
+    }
+    let index_of: HashMap<node::Id, usize> =
+        nodes.iter().enumerate().map(|(index, node)| (node.id, index)).collect();
+    let edges = connections.iter().filter_map(|(source, target)| {
+        let source = *index_of.get(source)?;
+        let target = *index_of.get(target)?;
+        (source != target).then(|| (source, target))
+    });
+    let edges = acyclic_edges(nodes.len(), edges.unique().collect());
+    let layers = assign_layers(nodes.len(), &edges);
+    let mut graph = LayeredGraph::new(nodes, &edges, &layers);
+    let crossings = graph.minimize_crossings(options.ordering_sweeps);
+    let x = graph.horizontal_positions(options);
+    let layer_tops = graph.layer_tops(nodes, options);
+
+    let anchor = nodes
+        .iter()
+        .filter_map(|node| node.position.map(|position| position - node.extent.origin))
+        .reduce(|a, b| Vector2(a.x.min(b.x), a.y.max(b.y)))
+        .unwrap_or_default();
+    let positions = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| {
+            let top_left = Vector2(x[index], layer_tops[layers[index]]);
+            (node.id, anchor + top_left + node.extent.origin)
+        })
+        .collect();
+    Layout { positions, crossings }
+}
+
+
+
+// ========================
+// === Layer Assignment ===
+// ========================
+
+/// Reverse the connections closing cycles, found by a depth-first search. The result contains no
+/// duplicates.
+fn acyclic_edges(node_count: usize, edges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    const UNVISITED: u8 = 0;
+    const ON_STACK: u8 = 1;
+    const DONE: u8 = 2;
+    let mut outgoing = vec![vec![]; node_count];
+    for (index, (source, _)) in edges.iter().enumerate() {
+        outgoing[*source].push(index);
+    }
+    let mut reversed = vec![false; edges.len()];
+    let mut state = vec![UNVISITED; node_count];
+    for root in 0..node_count {
+        if state[root] != UNVISITED {
+            continue;
+        }
+        state[root] = ON_STACK;
+        let mut stack = vec![(root, 0)];
+        while let Some(&(node, next)) = stack.last() {
+            match outgoing[node].get(next) {
+                Some(&edge) => {
+                    if let Some(top) = stack.last_mut() {
+                        top.1 += 1;
+                    }
+                    let target = edges[edge].1;
+                    match state[target] {
+                        UNVISITED => {
+                            state[target] = ON_STACK;
+                            stack.push((target, 0));
+                        }
+                        ON_STACK => reversed[edge] = true,
+                        _ => {}
+                    }
+                }
+                None => {
+                    state[node] = DONE;
+                    stack.pop();
+                }
+            }
+        }
+    }
+    let oriented = edges.into_iter().zip(reversed).map(|((source, target), reversed)| {
+        if reversed {
+            (target, source)
+        } else {
+            (source, target)
+        }
+    });
+    oriented.unique().collect()
+}
+
+/// Assign layers to the nodes of an acyclic graph. Every node is placed below all its
+/// predecessors. Nodes without predecessors are placed right above their highest successor, so
+/// e.g. a literal used deep in the graph does not stay at the very top.
+fn assign_layers(node_count: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut predecessors = vec![vec![]; node_count];
+    let mut successors = vec![vec![]; node_count];
+    for &(source, target) in edges {
+        predecessors[target].push(source);
+        successors[source].push(target);
+    }
+    let mut pending = predecessors.iter().map(|p| p.len()).collect_vec();
+    let mut queue: VecDeque<usize> = (0..node_count).filter(|&n| pending[n] == 0).collect();
+    let mut order = Vec::with_capacity(node_count);
+    while let Some(node) = queue.pop_front() {
+        order.push(node);
+        for &successor in &successors[node] {
+            pending[successor] -= 1;
+            if pending[successor] == 0 {
+                queue.push_back(successor);
+            }
+        }
+    }
+    let mut layers = vec![0; node_count];
+    for &node in &order {
+        layers[node] = predecessors[node].iter().map(|&p| layers[p] + 1).max().unwrap_or(0);
+    }
+    for &node in order.iter().rev() {
+        if predecessors[node].is_empty() {
+            if let Some(highest) = successors[node].iter().map(|&s| layers[s]).min() {
+                layers[node] = highest - 1;
+            }
+        }
+    }
+    layers
+}
+
+
+
+// ====================
+// === LayeredGraph ===
+// ====================
+
+/// A graph with every connection linking adjacent layers. The first vertices are the laid out
+/// nodes (with the same indices), the rest are virtual vertices splitting long connections.
+#[derive(Clone, Debug)]
+struct LayeredGraph {
+    widths:  Vec<f32>,
+    is_real: Vec<bool>,
+    up:      Vec<Vec<usize>>,
+    down:    Vec<Vec<usize>>,
+    layers:  Vec<Vec<usize>>,
+}
+
+impl LayeredGraph {
+    fn new(nodes: &[Node], edges: &[(usize, usize)], node_layers: &[usize]) -> Self {
+        let mut widths = nodes.iter().map(|node| node.extent.size.x).collect_vec();
+        let mut vertex_layers = node_layers.to_vec();
+        let mut up = vec![vec![]; nodes.len()];
+        let mut down = vec![vec![]; nodes.len()];
+        for &(source, target) in edges {
+            let mut previous = source;
+            for layer in node_layers[source] + 1..node_layers[target] {
+                let virtual_vertex = widths.len();
+                widths.push(0.0);
+                vertex_layers.push(layer);
+                up.push(vec![previous]);
+                down.push(vec![]);
+                down[previous].push(virtual_vertex);
+                previous = virtual_vertex;
+            }
+            down[previous].push(target);
+            up[target].push(previous);
+        }
+        let is_real = (0..widths.len()).map(|vertex| vertex < nodes.len()).collect_vec();
+        let layer_count = node_layers.iter().max().map_or(0, |max| max + 1);
+        let mut layers = vec![vec![]; layer_count];
+        let initial_order = (0..widths.len()).sorted_by(|&a, &b| {
+            let key = |vertex: usize| {
+                let position = nodes.get(vertex).and_then(|node| node.position);
+                (position.is_none(), position.map_or(0.0, |p| p.x), vertex)
+            };
+            let (a_missing, a_x, a) = key(a);
+            let (b_missing, b_x, b) = key(b);
+            let by_x = a_x.partial_cmp(&b_x).unwrap_or(std::cmp::Ordering::Equal);
+            a_missing.cmp(&b_missing).then(by_x).then(a.cmp(&b))
+        });
+        for vertex in initial_order {
+            layers[vertex_layers[vertex]].push(vertex);
+        }
+        Self { widths, is_real, up, down, layers }
+    }
+
+    fn vertex_count(&self) -> usize {
+        self.widths.len()
+    }
+
+    /// Indices of vertices in their layers.
+    fn indices_in_layers(&self) -> Vec<usize> {
+        let mut indices = vec![0; self.vertex_count()];
+        for layer in &self.layers {
+            for (index, &vertex) in layer.iter().enumerate() {
+                indices[vertex] = index;
+            }
+        }
+        indices
+    }
+
+    /// The number of crossings of connections between all pairs of adjacent layers.
+    fn crossings(&self) -> usize {
+        let indices = self.indices_in_layers();
+        let mut crossings = 0;
+        for layer in &self.layers {
+            let segments = layer
+                .iter()
+                .flat_map(|&source| self.down[source].iter().map(move |&target| (source, target)))
+                .map(|(source, target)| (indices[source], indices[target]))
+                .collect_vec();
+            for (i, (source_1, target_1)) in segments.iter().enumerate() {
+                for (source_2, target_2) in &segments[i + 1..] {
+                    let crossing = (source_1 < source_2 && target_1 > target_2)
+                        || (source_1 > source_2 && target_1 < target_2);
+                    crossings += crossing as usize;
+                }
+            }
+        }
+        crossings
+    }
+
+    /// Reorder the vertices of the layer by the mean index of their neighbours in the adjacent
+    /// layer. Vertices without neighbours keep their index.
+    fn sort_by_barycenter(&mut self, layer: usize, downwards: bool) {
+        let indices = self.indices_in_layers();
+        let neighbours = if downwards { &self.up } else { &self.down };
+        let barycenter = |index: usize, vertex: usize| {
+            let neighbours = &neighbours[vertex];
+            if neighbours.is_empty() {
+                index as f32
+            } else {
+                neighbours.iter().map(|&n| indices[n] as f32).sum::<f32>() / neighbours.len() as f32
+            }
+        };
+        let mut keyed = self.layers[layer]
+            .iter()
+            .enumerate()
+            .map(|(index, &vertex)| (barycenter(index, vertex), index, vertex))
+            .collect_vec();
+        keyed.sort_by(|(a, a_index, _), (b, b_index, _)| {
+            a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal).then(a_index.cmp(b_index))
+        });
+        self.layers[layer] = keyed.into_iter().map(|(_, _, vertex)| vertex).collect();
+    }
+
+    /// Reorder vertices in layers with alternating downward and upward sweeps, keeping the order
+    /// with the fewest crossings. Returns the number of remaining crossings.
+    fn minimize_crossings(&mut self, sweeps: usize) -> usize {
+        let mut best_layers = self.layers.clone();
+        let mut best_crossings = self.crossings();
+        let layer_count = self.layers.len();
+        for sweep in 0..sweeps {
+            if best_crossings == 0 {
+                break;
+            }
+            let downwards = sweep % 2 == 0;
+            if downwards {
+                for layer in 1..layer_count {
+                    self.sort_by_barycenter(layer, true);
+                }
+            } else {
+                for layer in (0..layer_count.saturating_sub(1)).rev() {
+                    self.sort_by_barycenter(layer, false);
+                }
+            }
+            let crossings = self.crossings();
+            if crossings < best_crossings {
+                best_crossings = crossings;
+                best_layers = self.layers.clone();
+            }
+        }
+        self.layers = best_layers;
+        best_crossings
+    }
+
+    /// The minimum distance between the left edges of two adjacent vertices.
+    fn separation(&self, left: usize, right: usize, options: &Options) -> f32 {
+        let gap = if self.is_real[left] && self.is_real[right] {
+            options.horizontal_gap
+        } else {
+            options.horizontal_gap / 2.0
+        };
+        self.widths[left] + gap
+    }
+
+    /// Compute the x coordinates of the left edges of all vertices. Vertices are moved towards the
+    /// mean center of their neighbours, alternately in the upper and the lower layer, keeping
+    /// their order and separation. The leftmost node is placed at zero.
+    fn horizontal_positions(&self, options: &Options) -> Vec<f32> {
+        let mut x = vec![0.0; self.vertex_count()];
+        for layer in &self.layers {
+            let mut next = 0.0;
+            for (index, &vertex) in layer.iter().enumerate() {
+                x[vertex] = next;
+                if let Some(&right) = layer.get(index + 1) {
+                    next += self.separation(vertex, right, options);
+                }
+            }
+        }
+        let layer_count = self.layers.len();
+        for pass in 0..options.alignment_passes {
+            let downwards = pass % 2 == 0;
+            let (order, neighbours): (Vec<usize>, _) = if downwards {
+                ((1..layer_count).collect(), &self.up)
+            } else {
+                ((0..layer_count.saturating_sub(1)).rev().collect(), &self.down)
+            };
+            for layer in order {
+                let layer = &self.layers[layer];
+                let center = |vertex: usize, x: &[f32]| x[vertex] + self.widths[vertex] / 2.0;
+                let desired = layer
+                    .iter()
+                    .map(|&vertex| {
+                        let neighbours = &neighbours[vertex];
+                        if neighbours.is_empty() {
+                            x[vertex]
+                        } else {
+                            let sum = neighbours.iter().map(|&n| center(n, &x)).sum::<f32>();
+                            sum / neighbours.len() as f32 - self.widths[vertex] / 2.0
+                        }
+                    })
+                    .collect_vec();
+                let mut from_left = desired.clone();
+                for index in 1..layer.len() {
+                    let separation = self.separation(layer[index - 1], layer[index], options);
+                    from_left[index] = from_left[index].max(from_left[index - 1] + separation);
+                }
+                let mut from_right = desired;
+                for index in (0..layer.len().saturating_sub(1)).rev() {
+                    let separation = self.separation(layer[index], layer[index + 1], options);
+                    from_right[index] = from_right[index].min(from_right[index + 1] - separation);
+                }
+                for (index, &vertex) in layer.iter().enumerate() {
+                    x[vertex] = (from_left[index] + from_right[index]) / 2.0;
+                }
+            }
+        }
+        let real_vertices = (0..self.vertex_count()).filter(|&vertex| self.is_real[vertex]);
+        let min_x = real_vertices.map(|vertex| x[vertex]).reduce(f32::min).unwrap_or_default();
+        x.iter().map(|x| x - min_x).collect()
+    }
+
+    /// Compute the y coordinates of the top edges of all layers. The first layer's top is at zero.
+    fn layer_tops(&self, nodes: &[Node], options: &Options) -> Vec<f32> {
+        let mut tops = Vec::with_capacity(self.layers.len());
+        let mut top = 0.0;
+        for layer in &self.layers {
+            tops.push(top);
+            let heights = layer.iter().filter_map(|&vertex| nodes.get(vertex));
+            let height = heights.map(|node| node.extent.size.y).reduce(f32::max);
+            top -= height.unwrap_or_default() + options.vertical_gap;
+        }
+        tops
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes(count: usize) -> Vec<Node> {
+        let node = |_| Node { id: node::Id::new_v4(), extent: default(), position: None };
+        (0..count).map(node).collect()
+    }
+
+    fn positions(layout: &Layout) -> Vec<Vector2> {
+        layout.positions.iter().map(|(_, position)| *position).collect()
+    }
+
+    fn assert_no_overlaps(nodes: &[Node], layout: &Layout) {
+        let boxes = nodes.iter().zip(positions(layout)).map(|(node, position)| {
+            let top_left = position - node.extent.origin;
+            (top_left, top_left + Vector2(node.extent.size.x, -node.extent.size.y))
+        });
+        let boxes = boxes.collect_vec();
+        for (i, (a_min, a_max)) in boxes.iter().enumerate() {
+            for (b_min, b_max) in &boxes[i + 1..] {
+                let separate_x = a_max.x <= b_min.x || b_max.x <= a_min.x;
+                let separate_y = a_max.y >= b_min.y || b_max.y >= a_min.y;
+                assert!(separate_x || separate_y, "Nodes overlap: {:?}", boxes);
+            }
+        }
+    }
+
+    #[test]
+    fn layering_follows_data_flow() {
+        // a -> b -> c, d -> c: `d` is placed right above `c`.
+        let nodes = nodes(4);
+        let [a, b, c, d] = [nodes[0].id, nodes[1].id, nodes[2].id, nodes[3].id];
+        let layout = layout(&nodes, &[(a, b), (b, c), (d, c)], &default());
+        let p = positions(&layout);
+        assert!(p[0].y > p[1].y);
+        assert!(p[1].y > p[2].y);
+        assert_eq!(p[3].y, p[1].y);
+        assert_eq!(layout.crossings, 0);
+        assert_no_overlaps(&nodes, &layout);
+    }
+
+    #[test]
+    fn minimizing_crossings() {
+        // Two sources connected to two targets in the reversed order, and a long connection
+        // spanning over the middle layer.
+        let nodes = nodes(5);
+        let ids = nodes.iter().map(|node| node.id).collect_vec();
+        let connections = [(ids[0], ids[3]), (ids[1], ids[2]), (ids[2], ids[4]), (ids[0], ids[4])];
+        let layout = layout(&nodes, &connections, &default());
+        assert_eq!(layout.crossings, 0);
+        assert_no_overlaps(&nodes, &layout);
+        let p = positions(&layout);
+        assert_eq!(p[2].y, p[3].y);
+        assert!(p[2].y > p[4].y);
+    }
+
+    #[test]
+    fn breaking_cycles() {
+        let nodes = nodes(3);
+        let ids = nodes.iter().map(|node| node.id).collect_vec();
+        let connections = [(ids[0], ids[1]), (ids[1], ids[2]), (ids[2], ids[0]), (ids[1], ids[1])];
+        let layout = layout(&nodes, &connections, &default());
+        assert_eq!(layout.positions.len(), 3);
+        let p = positions(&layout);
+        assert!(p[0].y > p[1].y && p[1].y > p[2].y);
+    }
+
+    #[test]
+    fn anchoring_at_current_position() {
+        let mut nodes = nodes(3);
+        nodes[0].position = Some(Vector2(100.0, 50.0));
+        nodes[1].position = Some(Vector2(-300.0, 20.0));
+        let connections = [(nodes[0].id, nodes[2].id), (nodes[1].id, nodes[2].id)];
+        let layout = layout(&nodes, &connections, &default());
+        let p = positions(&layout);
+        // The existing positions decide the order: the second node stays on the left.
+        assert!(p[1].x < p[0].x);
+        let origin = Extent::default().origin;
+        let left = p.iter().map(|p| p.x - origin.x).reduce(f32::min).unwrap();
+        let top = p.iter().map(|p| p.y - origin.y).reduce(f32::max).unwrap();
+        assert_eq!(left, -300.0);
+        assert_eq!(top, 50.0 - origin.y);
+        assert_no_overlaps(&nodes, &layout);
+    }
+}
@@ -6,10 +6,10 @@ use crate::model::module::Path;
 use crate::model::module::TextChange;
 
 use ast;
-use ast::HasIdMap;
+use double_representation::lint;
 use double_representation::module;
 use double_representation::project;
-use double_representation::text::apply_code_change_to_id_map;
+use double_representation::text::apply_change_preserving_ids;
 use engine_protocol::language_server;
 use engine_protocol::types::Sha3_224;
 use parser::Parser;
@@ -74,9 +74,8 @@ impl Handle {
     /// May return Error when new code causes parsing errors, or when parsed code does not produce
     /// Module ast.
     pub fn apply_code_change(&self, change: TextChange) -> FallibleResult {
-        let mut id_map = self.model.ast().id_map();
-        apply_code_change_to_id_map(&mut id_map, &change, &self.model.ast().repr());
-        self.model.apply_code_change(change, &self.parser, id_map)
+        let new_ast = apply_change_preserving_ids(&self.parser, &self.model.ast(), &change)?;
+        self.model.apply_parsed_code_change(change, new_ast)
     }
 
     /// Read module code.
@@ -174,6 +173,27 @@ impl Handle {
         module.iter_imports().collect()
     }
 
+    /// Find the code which can be removed from the module: unused imports, unreachable methods and
+    /// nodes whose results are not consumed. See [`lint::lint`] for details.
+    pub fn lint(
+        &self,
+        project_name: project::QualifiedName,
+        exported: &[String],
+        has_visualization: impl Fn(ast::Id) -> bool,
+    ) -> Vec<lint::Finding> {
+        let name = self.qualified_name(project_name);
+        lint::lint(&name, &self.model.ast(), exported, has_visualization)
+    }
+
+    /// Apply the quick-fixes of the given lint findings as a single undoable operation. If any of
+    /// them fails, the module is not modified.
+    pub fn apply_lint_fixes(&self, findings: &[lint::Finding]) -> FallibleResult {
+        let _transaction = self.model.get_or_open_transaction("Remove unused code");
+        let mut module = self.module_info();
+        lint::apply_fixes(&mut module, findings)?;
+        self.model.update_ast(module.ast)
+    }
+
     /// Creates a mocked module controller.
     pub fn new_mock(
         path: Path,
@@ -212,6 +232,7 @@ mod test {
     use ast;
     use ast::Ast;
     use ast::BlockLine;
+    use double_representation::definition::DefinitionName;
     use enso_text::traits::*;
     use parser::Parser;
     use uuid::Uuid;
@@ -264,4 +285,23 @@ mod test {
             assert_eq!(expected_ast, controller.model.ast().into());
         });
     }
+
+    #[wasm_bindgen_test]
+    fn applying_lint_fixes_atomically() {
+        let ls = language_server::Connection::new_mock_rc(default());
+        let parser = Parser::new().unwrap();
+        let location = Path::from_mock_module_name("Test");
+        let code = "unused = 1\n\nmain =\n    2";
+        let controller =
+            Handle::new_mock(location, code, default(), ls, parser, default()).unwrap();
+        let unreachable = DefinitionName::new_plain("unused");
+        let unreachable = lint::Finding::UnreachableDefinition(unreachable);
+        let definition = double_representation::definition::Id::new_plain_name("main");
+        let missing_node = lint::Finding::UnusedNode { definition, node: Uuid::new_v4() };
+
+        controller.apply_lint_fixes(&[unreachable.clone(), missing_node]).unwrap_err();
+        assert_eq!(controller.model.ast().repr(), code);
+        controller.apply_lint_fixes(&[unreachable]).unwrap();
+        assert_eq!(controller.model.ast().repr(), "main =\n    2");
+    }
 }
@@ -33,6 +33,10 @@ pub const INITIAL_MODULE_NAME: &str = "Main";
 /// This is the definition whose graph will be opened on IDE start.
 pub const MAIN_DEFINITION_NAME: &str = "main";
 
+/// The names of the files in the project root which may define the project keymap, in the order of
+/// precedence. See the `ensogl::application::shortcut::keymap` module docs to learn more.
+pub const PROJECT_KEYMAP_FILES: [&str; 2] = ["keymap.toml", "keymap.json"];
+
 /// The code with definition of the default `main` method.
 pub fn default_main_method_code() -> String {
     format!(r#"{} = "Hello, World!""#, MAIN_DEFINITION_NAME)
@@ -94,6 +98,14 @@ pub struct InitializationResult {
     pub main_graph:        controller::ExecutedGraph,
 }
 
+/// The keymap file of the project.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub struct ProjectKeymap {
+    pub file_name: String,
+    pub contents:  String,
+}
+
 /// Project Controller Handle.
 ///
 /// This controller supports IDE-related operations on a specific project.
@@ -151,9 +163,26 @@ impl Project {
         self.init_call_stack_from_metadata(&main_module_model, &main_graph).await;
         self.notify_about_compiling_process(&main_graph);
         self.display_warning_on_unsupported_engine_version();
+        self.index_source_directory();
 
         Ok(InitializationResult { main_module_text, main_module_model, main_graph })
     }
+
+    /// Read the project keymap from the first existing file of [`PROJECT_KEYMAP_FILES`]. Returns
+    /// `None` if the project does not define a keymap.
+    pub async fn read_keymap(&self) -> FallibleResult<Option<ProjectKeymap>> {
+        let rpc = self.model.json_rpc();
+        let root_id = self.model.project_content_root_id();
+        for file_name in PROJECT_KEYMAP_FILES {
+            let path = Path::new(root_id, &[file_name]);
+            if rpc.file_exists(&path).await?.exists {
+                let contents = rpc.read_file(&path).await?.contents;
+                let file_name = file_name.to_owned();
+                return Ok(Some(ProjectKeymap { file_name, contents }));
+            }
+        }
+        Ok(None)
+    }
 }
 
 
@@ -229,6 +258,18 @@ impl Project {
         });
     }
 
+    /// Index the project's modules in the background. The opened modules are indexed by the
+    /// project model.
+    fn index_source_directory(&self) {
+        let project = self.model.clone_ref();
+        let logger = self.logger.clone_ref();
+        executor::global::spawn(async move {
+            if let Err(err) = project.usages().index_source_directory(&project).await {
+                warning!(logger, "Failed to index the project's modules: {err}");
+            }
+        });
+    }
+
     fn display_warning_on_unsupported_engine_version(&self) {
         let requirement = enso_config::engine_version_requirement();
         let version = self.model.engine_version();
@@ -256,6 +297,10 @@ mod tests {
 
     use crate::executor::test_utils::TestWithLocalPoolExecutor;
 
+    use engine_protocol::language_server;
+    use engine_protocol::language_server::response;
+    use json_rpc::expect_call;
+
     #[test]
     fn parse_supported_engine_version() {
         // Should not panic.
@@ -305,4 +350,31 @@ mod tests {
         expect_intact("here.main = 5");
         expect_intact(&format!("{}.main = 5", module_name));
     }
+
+    #[wasm_bindgen_test]
+    fn reading_project_keymap() {
+        let mut test = TestWithLocalPoolExecutor::set_up();
+        let root_id = Uuid::new_v4();
+        let toml_path = Path::new(root_id, &[PROJECT_KEYMAP_FILES[0]]);
+        let json_path = Path::new(root_id, &[PROJECT_KEYMAP_FILES[1]]);
+        let contents = r#"{"bindings": []}"#.to_owned();
+        let read_result = response::Read { contents: contents.clone() };
+        let json_client = language_server::MockClient::default();
+        let missing = response::FileExists { exists: false };
+        let existing = response::FileExists { exists: true };
+        expect_call!(json_client.file_exists(path=toml_path) => Ok(missing));
+        expect_call!(json_client.file_exists(path=json_path.clone()) => Ok(existing));
+        expect_call!(json_client.read_file(path=json_path) => Ok(read_result));
+        let json_rpc = language_server::Connection::new_mock_rc(json_client);
+        let mut project = model::project::MockAPI::new();
+        model::project::test::expect_root_id(&mut project, root_id);
+        model::project::test::expect_json_rpc(&mut project, json_rpc);
+        let controller = Project::new(Rc::new(project), default());
+
+        test.run_task(async move {
+            let keymap = controller.read_keymap().await.unwrap();
+            let file_name = PROJECT_KEYMAP_FILES[1].to_owned();
+            assert_eq!(keymap, Some(ProjectKeymap { file_name, contents }));
+        });
+    }
 }
@@ -9,6 +9,7 @@ use crate::model::module::Position;
 use crate::model::module::UploadingFile;
 use crate::model::undo_redo;
 use crate::model::undo_redo::Repository;
+use crate::notification;
 
 use engine_protocol::binary;
 use engine_protocol::common::error::code;
@@ -16,8 +17,12 @@ use engine_protocol::language_server;
 use engine_protocol::language_server::FileSystemObject;
 use engine_protocol::language_server::Path;
 use engine_protocol::types::Sha3_224;
+use flo_stream::Subscriber;
+use futures::future;
+use futures::stream::FuturesUnordered;
 use json_rpc::error::RpcError;
 use sha3::Digest;
+use std::collections::VecDeque;
 
 
 
@@ -33,6 +38,35 @@ pub struct ChecksumMismatch {
     pub local:  Sha3_224,
 }
 
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Fail)]
+#[fail(
+    display = "Wrong checksum of chunk written at offset {}: {}, local checksum is {}.",
+    offset, remote, local
+)]
+pub struct ChunkChecksumMismatch {
+    pub offset: u64,
+    pub remote: Sha3_224,
+    pub local:  Sha3_224,
+}
+
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Fail)]
+#[fail(display = "The data provider cannot read the data from the beginning again.")]
+pub struct RewindNotSupported;
+
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Fail)]
+#[fail(
+    display = "Cannot resume uploading: the Language Server has only {} bytes of the file, while \
+    data is available since byte {}.",
+    bytes_on_server, first_available_byte
+)]
+pub struct CannotResumeUploading {
+    pub bytes_on_server:      u64,
+    pub first_available_byte: u64,
+}
+
 
 
 // =================
@@ -41,6 +75,12 @@ pub struct ChecksumMismatch {
 
 const DATA_DIR_NAME: &str = "data";
 
+/// The default maximum number of chunks being written to the Language Server at the same time.
+pub const DEFAULT_MAX_CHUNKS_IN_FLIGHT: usize = 4;
+
+/// The default number of consecutive failed writes after which the uploading is given up.
+pub const DEFAULT_MAX_RETRIES: usize = 3;
+
 
 
 // ====================
@@ -52,9 +92,20 @@ pub trait DataProvider {
     /// Return a future providing the next chunk of file data.
     ///
     /// Returns [`None`] if the whole file's content has been read. The upload handlers defined in
-    /// this module ([`NodeFromDroppedFileHandler`] and [`FileUploadProcess`]) will not call this
-    /// method before fully uploading and freeing the previously read chunk.
+    /// this module ([`NodeFromDroppedFileHandler`] and [`FileUploadProcess`]) keep at most
+    /// [`UploadConfig::max_chunks_in_flight`] read chunks which are not confirmed by the Language
+    /// Server yet; no more chunks are read until some of them are confirmed and freed.
     fn next_chunk(&mut self) -> BoxFuture<FallibleResult<Option<Vec<u8>>>>;
+
+    /// Start reading the data from the beginning: the next [`Self::next_chunk`] call will return
+    /// the first chunk again.
+    ///
+    /// The data is read twice when looking for an already uploaded copy of the file (see
+    /// [`DuplicatePolicy`]): once to compute the checksum, and once to upload it. The files of
+    /// providers not supporting rewinding are always uploaded.
+    fn rewind(&mut self) -> FallibleResult {
+        Err(RewindNotSupported.into())
+    }
 }
 
 
@@ -72,17 +123,80 @@ pub struct FileToUpload<DataProvider> {
     pub data: DataProvider,
 }
 
+/// The configuration of [`FileUploadProcess`].
+#[derive(Clone, CloneRef, Copy, Debug, Eq, PartialEq)]
+pub struct UploadConfig {
+    /// The maximum number of chunks being written to the Language Server at the same time.
+    pub max_chunks_in_flight: usize,
+    /// The number of consecutive failed writes (because of lost connection or a wrong chunk
+    /// checksum) after which the uploading is given up.
+    pub max_retries:          usize,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            max_chunks_in_flight: DEFAULT_MAX_CHUNKS_IN_FLIGHT,
+            max_retries:          DEFAULT_MAX_RETRIES,
+        }
+    }
+}
+
+/// The uploading progress, published by [`FileUploadProcess`] every time the Language Server
+/// confirms a written chunk.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Progress {
+    /// The number of bytes from the file beginning confirmed by the Language Server.
+    pub bytes_uploaded: u64,
+    pub size:           u64,
+}
+
+/// A chunk of file read from the [`DataProvider`] and not yet confirmed by the Language Server.
+#[derive(Clone, Debug)]
+struct Chunk {
+    offset:    u64,
+    data:      Vec<u8>,
+    checksum:  Sha3_224,
+    confirmed: bool,
+}
+
+impl Chunk {
+    fn end(&self) -> u64 {
+        self.offset + self.data.len() as u64
+    }
+}
+
+/// The result of a single chunk write: the chunk's offset and the checksum returned by the
+/// Language Server.
+type ChunkWrite = StaticBoxFuture<(u64, FallibleResult<Sha3_224>)>;
+
 /// The handler of uploading a given file to the specific location using the Language Server's file
 /// API.
-#[derive(Clone, Debug)]
+///
+/// Several chunks are written at the same time (see [`UploadConfig`]), and each one is verified by
+/// its checksum. When a write fails, e.g. because of a lost connection, the uploading is resumed
+/// from the last byte confirmed by the Language Server (as reported by `file/info`) instead of
+/// starting over.
+#[derive(Debug)]
 pub struct FileUploadProcess<DataProvider> {
     logger:          Logger,
     bin_connection:  Rc<binary::Connection>,
     json_connection: Rc<language_server::Connection>,
     file:            FileToUpload<DataProvider>,
     remote_path:     Path,
+    config:          UploadConfig,
+    /// The number of bytes read from the data provider.
+    bytes_read:      u64,
+    /// The number of bytes from the file beginning confirmed by the Language Server.
     bytes_uploaded:  u64,
+    /// The chunks read and not freed yet, ordered by their offset. The first chunk always starts
+    /// at `bytes_uploaded`.
+    chunks:          VecDeque<Chunk>,
+    writes:          FuturesUnordered<ChunkWrite>,
+    failed_writes:   usize,
     checksum:        sha3::Sha3_224,
+    progress:        notification::Publisher<Progress>,
 }
 
 /// The information if the uploading is finished or not, returned from
@@ -102,63 +216,188 @@ impl<DP: DataProvider> FileUploadProcess<DP> {
         bin_connection: Rc<binary::Connection>,
         json_connection: Rc<language_server::Connection>,
         remote_path: Path,
+        config: UploadConfig,
     ) -> Self {
         let logger = Logger::new_sub(parent, "FileUploadProcess");
-        let bytes_uploaded = 0;
-        let checksum = sha3::Sha3_224::new();
         Self {
             logger,
             bin_connection,
             json_connection,
             file,
             remote_path,
-            bytes_uploaded,
-            checksum,
+            config,
+            bytes_read: 0,
+            bytes_uploaded: 0,
+            chunks: default(),
+            writes: default(),
+            failed_writes: 0,
+            checksum: sha3::Sha3_224::new(),
+            progress: default(),
         }
     }
 
-    /// Upload next chunk. Returns information if all data has been uploaded.
+    /// Subscribe to the uploading progress.
+    pub fn subscribe(&self) -> Subscriber<Progress> {
+        self.progress.subscribe()
+    }
+
+    /// The number of bytes from the file beginning confirmed by the Language Server.
+    pub fn bytes_uploaded(&self) -> u64 {
+        self.bytes_uploaded
+    }
+
+    /// Upload the whole file.
+    pub async fn upload(&mut self) -> FallibleResult {
+        while self.upload_chunk().await? == UploadingState::NotFinished {}
+        Ok(())
+    }
+
+    /// Read the next chunk and start writing it. Returns information if all data has been
+    /// uploaded.
     ///
-    /// After uploading, the checksum of the uploaded file is compared with the file content digest,
-    /// and an error is returned if they do not match.
+    /// If the maximum number of chunks is being written, this function waits until one of the
+    /// writes finishes. Once all data is read, it waits for all writes and compares the checksum
+    /// of the uploaded file with the file content digest, returning an error if they do not match.
     ///
     /// The outcome of this function when uploading is finished (the `upload_chunk` have returned
     /// [`UploadingState::Finished`] before) is undefined.
     pub async fn upload_chunk(&mut self) -> FallibleResult<UploadingState> {
-        match self.file.data.next_chunk().await {
-            Ok(Some(data)) => {
-                debug!(
+        if let Some(data) = self.file.data.next_chunk().await? {
+            debug!(
+                self.logger,
+                "Received chunk of {self.file.name} of size {data.len()} \
+                uploading to {self.remote_path:?}: {data:?}"
+            );
+            self.checksum.input(&data);
+            let chunk = Chunk {
+                offset: self.bytes_read,
+                checksum: Sha3_224::new(&data),
+                data,
+                confirmed: false,
+            };
+            self.bytes_read = chunk.end();
+            self.start_write(&chunk, false);
+            self.chunks.push_back(chunk);
+            while self.chunks.len() >= self.config.max_chunks_in_flight.max(1) {
+                self.wait_for_write().await?;
+            }
+            Ok(UploadingState::NotFinished)
+        } else {
+            while !self.chunks.is_empty() {
+                self.wait_for_write().await?;
+            }
+            // If we haven't got any content, we need to create the file.
+            if self.bytes_read == 0 {
+                self.bin_connection.write_file(&self.remote_path, &[]).await?;
+            }
+            if self.bytes_read != self.file.size {
+                error!(
                     self.logger,
-                    "Received chunk of {self.file.name} of size {data.len()} \
-                    uploading to {self.remote_path:?}: {data:?}"
+                    "The promised file size ({self.file.size}) and uploaded \
+                    data length ({self.bytes_read}) do not match. Leaving as much data as \
+                    received."
                 );
-                let offset = self.bytes_uploaded;
-                self.bin_connection.write_bytes(&self.remote_path, offset, false, &data).await?;
-                self.checksum.input(&data);
-                self.bytes_uploaded += data.len() as u64;
-                Ok(UploadingState::NotFinished)
             }
-            Ok(None) => {
-                // If we haven't got any content, we need to create the file.
-                if self.bytes_uploaded == 0 {
-                    self.bin_connection.write_file(&self.remote_path, &[]).await?;
-                }
-                if self.bytes_uploaded != self.file.size {
-                    error!(
-                        self.logger,
-                        "The promised file size ({self.file.size}) and uploaded \
-                        data length ({self.bytes_uploaded}) do not match. Leaving as much data as \
-                        received."
-                    );
-                    self.bytes_uploaded = self.file.size;
-                }
-                self.check_checksum().await?;
-                Ok(UploadingState::Finished)
+            self.check_checksum().await?;
+            Ok(UploadingState::Finished)
+        }
+    }
+
+    fn start_write(&self, chunk: &Chunk, overwrite: bool) {
+        let offset = chunk.offset;
+        let path = &self.remote_path;
+        let write = self.bin_connection.write_bytes(path, offset, overwrite, &chunk.data);
+        self.writes.push(write.map(move |result| (offset, result)).boxed_local());
+    }
+
+    /// Wait for any chunk write to finish and handle its result.
+    async fn wait_for_write(&mut self) -> FallibleResult {
+        let (offset, result) = match self.writes.next().await {
+            Some(finished) => finished,
+            None => return self.resume(None).await,
+        };
+        let chunk = self.chunks.iter_mut().find(|chunk| chunk.offset == offset);
+        // The chunk may have been already confirmed when resuming after a lost connection.
+        let chunk = match chunk {
+            Some(chunk) if !chunk.confirmed => chunk,
+            _ => return Ok(()),
+        };
+        match result {
+            Ok(remote) if remote == chunk.checksum => {
+                chunk.confirmed = true;
+                self.failed_writes = 0;
+                self.free_confirmed_chunks().await;
+                Ok(())
+            }
+            Ok(remote) => {
+                let local = chunk.checksum.clone();
+                let error = ChunkChecksumMismatch { offset, remote, local };
+                warning!(self.logger, "{error}");
+                let chunk = chunk.clone();
+                self.register_failed_write(error.into())?;
+                self.start_write(&chunk, true);
+                Ok(())
+            }
+            Err(error) => {
+                warning!(self.logger, "Writing chunk at offset {offset} failed: {error}");
+                self.resume(Some(error)).await
             }
-            Err(err) => Err(err),
         }
     }
 
+    /// Free the confirmed chunks at the beginning of the file and notify about the progress.
+    async fn free_confirmed_chunks(&mut self) {
+        let bytes_uploaded_before = self.bytes_uploaded;
+        while let Some(chunk) = self.chunks.front().filter(|chunk| chunk.confirmed) {
+            self.bytes_uploaded = chunk.end();
+            self.chunks.pop_front();
+        }
+        if self.bytes_uploaded != bytes_uploaded_before {
+            let progress =
+                Progress { bytes_uploaded: self.bytes_uploaded, size: self.file.size };
+            self.progress.publish(progress).await;
+        }
+    }
+
+    fn register_failed_write(&mut self, error: failure::Error) -> FallibleResult {
+        self.failed_writes += 1;
+        if self.failed_writes > self.config.max_retries {
+            Err(error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Resume uploading after a failed write: all pending writes are abandoned, and the chunks
+    /// which the Language Server does not have are written again.
+    async fn resume(&mut self, error: Option<failure::Error>) -> FallibleResult {
+        if let Some(error) = error {
+            self.register_failed_write(error)?;
+        }
+        self.writes = default();
+        let info = self.json_connection.file_info(&self.remote_path).await;
+        let bytes_on_server = match info {
+            Ok(info) => info.attributes.byte_size,
+            Err(RpcError::RemoteError(err)) if err.code == code::FILE_NOT_FOUND => 0,
+            Err(error) => return self.register_failed_write(error.into()),
+        };
+        info!(self.logger, "Resuming uploading from byte {bytes_on_server}.");
+        if bytes_on_server < self.bytes_uploaded {
+            let first_available_byte = self.bytes_uploaded;
+            return Err(CannotResumeUploading { bytes_on_server, first_available_byte }.into());
+        }
+        for chunk in &mut self.chunks {
+            if chunk.end() > bytes_on_server {
+                chunk.confirmed = false;
+            }
+        }
+        self.free_confirmed_chunks().await;
+        for chunk in self.chunks.iter().filter(|chunk| !chunk.confirmed) {
+            self.start_write(chunk, true);
+        }
+        Ok(())
+    }
+
     async fn check_checksum(&mut self) -> FallibleResult {
         let remote = self.json_connection.file_checksum(&self.remote_path).await?.checksum;
         let local = Into::<Sha3_224>::into(std::mem::take(&mut self.checksum));
@@ -176,21 +415,49 @@ impl<DP: DataProvider> FileUploadProcess<DP> {
 // === NodeFromDroppedFileHandler ===
 // ==================================
 
+/// What to do when the dropped file has the same content as a file already present in the
+/// project's data directory.
+#[derive(Clone, CloneRef, Copy, Debug, Eq, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Make the node read the already present file, without uploading the dropped one.
+    ReuseExisting,
+    /// Upload the dropped file under a new name, keeping both files.
+    KeepBoth,
+}
+
+impl Default for DuplicatePolicy {
+    fn default() -> Self {
+        Self::ReuseExisting
+    }
+}
+
 /// The handler for nodes created by dragging and dropping files into IDE.
 ///
 /// It is responsible for creating node, uploading file and updating the node's metadata.
 #[derive(Clone, CloneRef, Debug)]
 pub struct NodeFromDroppedFileHandler {
-    logger:  Logger,
-    project: model::Project,
-    graph:   controller::Graph,
+    logger:     Logger,
+    project:    model::Project,
+    graph:      controller::Graph,
+    config:     UploadConfig,
+    duplicates: DuplicatePolicy,
 }
 
 impl NodeFromDroppedFileHandler {
     /// Constructor
     pub fn new(parent: impl AnyLogger, project: model::Project, graph: controller::Graph) -> Self {
         let logger = Logger::new_sub(parent, "NodeFromDroppedFileHandler");
-        Self { logger, project, graph }
+        Self { logger, project, graph, config: default(), duplicates: default() }
+    }
+
+    /// Set the configuration of the uploading processes started by this handler.
+    pub fn with_config(self, config: UploadConfig) -> Self {
+        Self { config, ..self }
+    }
+
+    /// Set what to do with files already present in the project's data directory.
+    pub fn with_duplicate_policy(self, duplicates: DuplicatePolicy) -> Self {
+        Self { duplicates, ..self }
     }
 
     /// Create a node from dropped file and start uploading file.
@@ -243,9 +510,24 @@ impl NodeFromDroppedFileHandler {
     async fn upload_file(
         &self,
         node: ast::Id,
-        file: FileToUpload<impl DataProvider>,
+        mut file: FileToUpload<impl DataProvider>,
     ) -> FallibleResult {
         self.ensure_data_directory_exists().await?;
+        if self.duplicates == DuplicatePolicy::ReuseExisting {
+            match self.find_uploaded_copy(&mut file).await {
+                Ok(Some(existing_name)) => {
+                    info!(self.logger, "File {file.name} is already uploaded as {existing_name}.");
+                    self.update_expression(node, Self::uploaded_node_expression(&existing_name))?;
+                    self.remove_uploading_metadata(node);
+                    return Ok(());
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    warning!(self.logger, "Cannot look for uploaded copy of {file.name}: {err}");
+                    file.data.rewind()?;
+                }
+            }
+        }
         let remote_name = self.establish_remote_file_name(&file.name).await?;
         self.update_metadata(node, |md| md.remote_name = Some(remote_name.clone()));
         self.update_expression(node, Self::uploading_node_expression(&remote_name))?;
@@ -258,18 +540,72 @@ impl NodeFromDroppedFileHandler {
             bin_connection,
             json_connection,
             remote_path,
+            self.config,
         );
+        let update_progress = process.subscribe().for_each(|progress| {
+            self.update_metadata(node, |md| md.bytes_uploaded = progress.bytes_uploaded);
+            future::ready(())
+        });
+        let upload = async move {
+            let result = process.upload().await;
+            // Dropping the process closes the progress stream, so `update_progress` finishes.
+            drop(process);
+            result
+        };
+        let (result, ()) = future::join(upload, update_progress).await;
+        result?;
+        self.update_expression(node, Self::uploaded_node_expression(&remote_name))?;
+        self.remove_uploading_metadata(node);
+        Ok(())
+    }
 
-        while process.upload_chunk().await? == UploadingState::NotFinished {
-            self.update_metadata(node, |md| md.bytes_uploaded = process.bytes_uploaded);
+    /// Find the file in the data directory with the same content as the given file. Only the
+    /// files of the same size are compared by their checksums, so the file to upload is read
+    /// (and then rewound) only if such files exist.
+    ///
+    /// On error, the file may be left partially read.
+    async fn find_uploaded_copy(
+        &self,
+        file: &mut FileToUpload<impl DataProvider>,
+    ) -> FallibleResult<Option<String>> {
+        if let Err(err) = file.data.rewind() {
+            debug!(self.logger, "Not looking for uploaded copy of {file.name}: {err}");
+            return Ok(None);
         }
-        self.update_expression(node, Self::uploaded_node_expression(&remote_name))?;
+        let json_rpc = self.project.json_rpc();
+        let data_path = self.data_path();
+        let mut candidates = vec![];
+        for object in json_rpc.file_list(&data_path).await?.paths {
+            if let FileSystemObject::File { name, .. } = object {
+                let path = data_path.append_im(&name);
+                if json_rpc.file_info(&path).await?.attributes.byte_size == file.size {
+                    candidates.push((name, path));
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        let mut checksum = sha3::Sha3_224::new();
+        while let Some(chunk) = file.data.next_chunk().await? {
+            checksum.input(&chunk);
+        }
+        file.data.rewind()?;
+        let local = Sha3_224::from(checksum);
+        for (name, path) in candidates {
+            if json_rpc.file_checksum(&path).await?.checksum == local {
+                return Ok(Some(name));
+            }
+        }
+        Ok(None)
+    }
+
+    fn remove_uploading_metadata(&self, node: ast::Id) {
         if let Err(err) =
             self.graph.module.with_node_metadata(node, Box::new(|md| md.uploading_file = None))
         {
             warning!(self.logger, "Cannot remove uploading metadata: {err}");
         }
-        Ok(())
     }
 
     fn update_metadata(&self, node: ast::Id, f: impl FnOnce(&mut UploadingFile)) {
@@ -392,7 +728,6 @@ mod test {
     use engine_protocol::language_server::response;
     use engine_protocol::language_server::FileAttributes;
     use engine_protocol::types::UTCDateTime;
-    use futures::future;
     use futures::SinkExt;
     use mockall::Sequence;
 
@@ -409,6 +744,26 @@ mod test {
         }
     }
 
+    /// A provider reading the chunks again after rewinding.
+    #[derive(Clone, Debug)]
+    struct RewindableTestProvider {
+        chunks: Vec<Vec<u8>>,
+        next:   usize,
+    }
+
+    impl DataProvider for RewindableTestProvider {
+        fn next_chunk(&mut self) -> BoxFuture<FallibleResult<Option<Vec<u8>>>> {
+            let chunk = self.chunks.get(self.next).cloned();
+            self.next += 1;
+            futures::future::ready(Ok(chunk)).boxed_local()
+        }
+
+        fn rewind(&mut self) -> FallibleResult {
+            self.next = 0;
+            Ok(())
+        }
+    }
+
     impl DataProvider for TestAsyncProvider {
         fn next_chunk(&mut self) -> BoxFuture<FallibleResult<Option<Vec<u8>>>> {
             self.next()
@@ -520,7 +875,14 @@ mod test {
             Self {
                 test:          TestWithLocalPoolExecutor::set_up(),
                 chunks:        data.chunks.into_iter(),
-                process:       FileUploadProcess::new(logger, file, bin_con, json_con, data.path),
+                process:       FileUploadProcess::new(
+                    logger,
+                    file,
+                    bin_con,
+                    json_con,
+                    data.path,
+                    default(),
+                ),
                 provider_sink: Some(provider_sink),
             }
         }
@@ -567,6 +929,128 @@ mod test {
     }
 
 
+    /// Expect a single write of the chunk, returning the given result.
+    fn expect_chunk_write(
+        binary_client: &mut binary::MockClient,
+        sequence: &mut Sequence,
+        path: &Path,
+        (offset, chunk): (u64, &[u8]),
+        overwrite: bool,
+        result: impl Fn() -> FallibleResult<Sha3_224> + 'static,
+    ) {
+        let path = path.clone();
+        let chunk = chunk.to_vec();
+        binary_client
+            .expect_write_bytes()
+            .withf(move |p, off, ow, ch| {
+                *p == path && ch == chunk && *off == offset && *ow == overwrite
+            })
+            .times(1)
+            .in_sequence(sequence)
+            .returning(move |_, _, _, _| future::ready(result()).boxed_local());
+    }
+
+    /// A write result with the checksum of the given data.
+    fn checksum_of(data: &[u8]) -> impl Fn() -> FallibleResult<Sha3_224> {
+        let checksum = Sha3_224::new(data);
+        move || Ok(checksum.clone())
+    }
+
+    fn run_upload_process(
+        data: &TestData,
+        json_client: language_server::MockClient,
+        binary_client: binary::MockClient,
+        config: UploadConfig,
+    ) -> (FallibleResult, Vec<u64>) {
+        let logger = Logger::new("test::run_upload_process");
+        let bin_con = Rc::new(binary::Connection::new_mock(binary_client));
+        let json_con = Rc::new(language_server::Connection::new_mock(json_client));
+        let file = data.file_to_upload();
+        let path = data.path.clone();
+        let mut process = FileUploadProcess::new(logger, file, bin_con, json_con, path, config);
+        let progress = process.subscribe();
+        let mut executor = TestWithLocalPoolExecutor::set_up();
+        let result = executor.expect_completion(process.upload());
+        drop(process);
+        let progress = progress.map(|progress| progress.bytes_uploaded).collect::<Vec<_>>();
+        (result, executor.expect_completion(progress))
+    }
+
+    #[test]
+    fn resuming_uploading_after_lost_connection() {
+        let data = TestData::new(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8]]);
+        let mut binary_client = binary::MockClient::new();
+        let json_client = language_server::MockClient::default();
+        json_client.require_all_calls();
+        let mut seq = Sequence::new();
+        let chunks = data.chunks.iter().map(Vec::as_slice).collect_vec();
+        let (first, second, third) = (chunks[0], chunks[1], chunks[2]);
+        let bin = &mut binary_client;
+        let path = &data.path;
+        let lost_connection = || {
+            let error: RpcError = RpcError::LostConnection;
+            Err(error.into())
+        };
+        expect_chunk_write(bin, &mut seq, path, (0, first), false, checksum_of(first));
+        expect_chunk_write(bin, &mut seq, path, (3, second), false, lost_connection);
+        expect_chunk_write(bin, &mut seq, path, (3, second), true, checksum_of(second));
+        expect_chunk_write(bin, &mut seq, path, (6, third), false, checksum_of(third));
+        json_client.expect.file_info(enclose!((data.path => path) move |p| {
+            assert_eq!(*p, path);
+            Ok(response::FileInfo { attributes: uploaded_file_attributes(3) })
+        }));
+        let checksum = data.checksum.clone();
+        json_client.expect.file_checksum(move |_| Ok(response::FileChecksum { checksum }));
+
+        let config = UploadConfig { max_chunks_in_flight: 1, ..default() };
+        let (result, progress) = run_upload_process(&data, json_client, binary_client, config);
+        result.unwrap();
+        assert_eq!(progress, vec![3, 6, 8]);
+    }
+
+    #[test]
+    fn rewriting_chunks_with_wrong_checksum() {
+        let data = TestData::new(vec![vec![1, 2, 3], vec![4, 5, 6]]);
+        let mut binary_client = binary::MockClient::new();
+        let json_client = language_server::MockClient::default();
+        json_client.require_all_calls();
+        let mut seq = Sequence::new();
+        let (first, second) = (data.chunks[0].as_slice(), data.chunks[1].as_slice());
+        let bin = &mut binary_client;
+        let path = &data.path;
+        // Both chunks are written at once; the first one is written again after its checksum
+        // mismatch.
+        expect_chunk_write(bin, &mut seq, path, (0, first), false, checksum_of(&[0]));
+        expect_chunk_write(bin, &mut seq, path, (3, second), false, checksum_of(second));
+        expect_chunk_write(bin, &mut seq, path, (0, first), true, checksum_of(first));
+        let checksum = data.checksum.clone();
+        json_client.expect.file_checksum(move |_| Ok(response::FileChecksum { checksum }));
+
+        let config = UploadConfig { max_chunks_in_flight: 2, ..default() };
+        let (result, progress) = run_upload_process(&data, json_client, binary_client, config);
+        result.unwrap();
+        assert_eq!(progress.last(), Some(&6));
+    }
+
+    #[test]
+    fn giving_up_after_too_many_failed_writes() {
+        let data = TestData::new(vec![vec![1, 2, 3]]);
+        let mut binary_client = binary::MockClient::new();
+        let json_client = language_server::MockClient::default();
+        let mut seq = Sequence::new();
+        let chunk = (0, data.chunks[0].as_slice());
+        for overwrite in [false, true] {
+            let bin = &mut binary_client;
+            expect_chunk_write(bin, &mut seq, &data.path, chunk, overwrite, checksum_of(&[0]));
+        }
+
+        let config = UploadConfig { max_chunks_in_flight: 1, max_retries: 1 };
+        let (result, progress) = run_upload_process(&data, json_client, binary_client, config);
+        assert!(result.is_err());
+        assert!(progress.is_empty());
+    }
+
+
     // === NodeFromDroppedFileHandler Tests ===
 
     #[wasm_bindgen_test]
@@ -596,6 +1080,79 @@ mod test {
         assert_eq!(fixture.module.ast().repr(), module_code_uploaded(TEST_FILE));
     }
 
+    #[wasm_bindgen_test]
+    fn reusing_file_with_same_content() {
+        let logger = Logger::new("test::reusing_file_with_same_content");
+        let data = TestData::new(vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]]);
+        let file_size = data.file_size as u64;
+        let checksum = data.checksum.clone();
+        let mut fixture = mock::Unified::new().fixture_customize(|_, json_rpc, _| {
+            json_rpc
+                .expect
+                .file_info(|_| Ok(response::FileInfo { attributes: data_dir_attributes() }));
+            json_rpc.expect.file_list(|path| {
+                assert_eq!(*path, data_path());
+                let file =
+                    |name: &str| FileSystemObject::File { name: name.into(), path: data_path() };
+                Ok(response::FileList { paths: vec![file("other"), file("copy")] })
+            });
+            let file_path = |name: &str| data_path().append_im(name);
+            json_rpc.expect.file_info(move |path| {
+                assert_eq!(*path, file_path("other"));
+                Ok(response::FileInfo { attributes: uploaded_file_attributes(file_size - 1) })
+            });
+            json_rpc.expect.file_info(move |path| {
+                assert_eq!(*path, file_path("copy"));
+                Ok(response::FileInfo { attributes: uploaded_file_attributes(file_size) })
+            });
+            json_rpc.expect.file_checksum(move |path| {
+                assert_eq!(*path, file_path("copy"));
+                Ok(response::FileChecksum { checksum })
+            });
+        });
+
+        let handler = NodeFromDroppedFileHandler::new(logger, fixture.project, fixture.graph);
+        let position = model::module::Position::new(45.0, 70.0);
+        let data_provider = RewindableTestProvider { chunks: data.chunks.clone(), next: 0 };
+        let file =
+            FileToUpload { name: TEST_FILE.to_owned(), size: file_size, data: data_provider };
+        handler.create_node_and_start_uploading(file, position).unwrap();
+        fixture.executor.run_until_stalled();
+        assert_eq!(fixture.module.ast().repr(), module_code_uploaded("copy"));
+    }
+
+    #[wasm_bindgen_test]
+    fn uploading_file_when_looking_for_copy_fails() {
+        let logger = Logger::new("test::uploading_file_when_looking_for_copy_fails");
+        let data = TestData::new(vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]]);
+        let file_size = data.file_size as u64;
+        let mut fixture = mock::Unified::new().fixture_customize(|_, json_rpc, binary_rpc| {
+            json_rpc
+                .expect
+                .file_info(|_| Ok(response::FileInfo { attributes: data_dir_attributes() }));
+            let copy = FileSystemObject::File { name: "copy".into(), path: data_path() };
+            json_rpc.expect.file_list(move |_| Ok(response::FileList { paths: vec![copy] }));
+            json_rpc.expect.file_info(move |_| {
+                Ok(response::FileInfo { attributes: uploaded_file_attributes(file_size) })
+            });
+            // The copy is removed before its checksum is computed.
+            json_rpc.expect.file_checksum(|_| {
+                Err(RpcError::new_remote_error(code::FILE_NOT_FOUND, "FileNotFound"))
+            });
+            json_rpc.expect.file_list(|_| Ok(response::FileList { paths: vec![] }));
+            data.setup_uploading_expectations(json_rpc, binary_rpc);
+        });
+
+        let handler = NodeFromDroppedFileHandler::new(logger, fixture.project, fixture.graph);
+        let position = model::module::Position::new(45.0, 70.0);
+        let data_provider = RewindableTestProvider { chunks: data.chunks.clone(), next: 0 };
+        let file =
+            FileToUpload { name: TEST_FILE.to_owned(), size: file_size, data: data_provider };
+        handler.create_node_and_start_uploading(file, position).unwrap();
+        fixture.executor.run_until_stalled();
+        assert_eq!(fixture.module.ast().repr(), module_code_uploaded(TEST_FILE));
+    }
+
     #[wasm_bindgen_test]
     fn recreating_data_directory() {
         let logger = Logger::new("test::recreating_data_directory");
@@ -717,6 +1274,11 @@ mod test {
         }
     }
 
+    fn uploaded_file_attributes(byte_size: u64) -> FileAttributes {
+        let kind = FileSystemObject::File { name: TEST_FILE.to_owned(), path: data_path() };
+        FileAttributes { kind, byte_size, ..data_dir_attributes() }
+    }
+
     fn module_code_uploading(file_name: &str) -> String {
         format!(
             "{}\n    operator1 = File_Uploading.file_uploading Enso_Project.data/\"{}\"",
@@ -30,6 +30,7 @@ pub mod project;
 pub mod registry;
 pub mod suggestion_database;
 pub mod undo_redo;
+pub mod usages;
 
 pub use execution_context::ExecutionContext;
 pub use module::Module;
@@ -9,6 +9,7 @@ use engine_protocol::language_server;
 use engine_protocol::language_server::ExpressionUpdate;
 use engine_protocol::language_server::ExpressionUpdatePayload;
 use engine_protocol::language_server::MethodPointer;
+use engine_protocol::language_server::ProfilingInfo;
 use engine_protocol::language_server::SuggestionId;
 use engine_protocol::language_server::VisualisationConfiguration;
 use flo_stream::Subscriber;
@@ -16,6 +17,7 @@ use mockall::automock;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::time::Duration;
 use uuid::Uuid;
 
 
@@ -24,6 +26,8 @@ use uuid::Uuid;
 // ==============
 
 pub mod plain;
+pub mod preprocessor;
+pub mod replay;
 pub mod synchronized;
 
 
@@ -52,18 +56,37 @@ pub type ExpressionId = ast::Id;
 pub struct ComputedValueInfo {
     /// The string representing the full qualified typename of the computed value, e.g.
     /// "Standard.Base.Number".
-    pub typename:    Option<ImString>,
-    pub payload:     ExpressionUpdatePayload,
+    pub typename:       Option<ImString>,
+    pub payload:        ExpressionUpdatePayload,
     /// If the expression is a method call (i.e. can be entered), this points to the target method.
-    pub method_call: Option<SuggestionId>,
+    pub method_call:    Option<SuggestionId>,
+    /// The time of evaluating the expression, as measured by the engine. `None` if the engine did
+    /// not measure it, e.g. because the value was taken from the cache.
+    pub execution_time: Option<Duration>,
+    /// The number of the execution which computed the value, see
+    /// [`ComputedValueInfoRegistry::execution_completed`].
+    pub execution:      usize,
+}
+
+impl ComputedValueInfo {
+    /// Check if the computation resulted in a dataflow error or a panic.
+    pub fn is_failing(&self) -> bool {
+        !matches!(self.payload, ExpressionUpdatePayload::Value)
+    }
 }
 
 impl From<ExpressionUpdate> for ComputedValueInfo {
     fn from(update: ExpressionUpdate) -> Self {
+        let execution_times = update.profiling_info.iter().map(|info| match info {
+            ProfilingInfo::ExecutionTime { nano_time } => Duration::from_nanos(*nano_time),
+        });
+        let execution_time = execution_times.reduce(|total, time| total + time);
         ComputedValueInfo {
-            typename:    update.typename.map(ImString::new),
+            typename: update.typename.map(ImString::new),
             method_call: update.method_pointer,
-            payload:     update.payload,
+            payload: update.payload,
+            execution_time,
+            execution: default(),
         }
     }
 }
@@ -72,6 +95,11 @@ impl From<ExpressionUpdate> for ComputedValueInfo {
 /// Ids of expressions that were computed and received updates in this batch.
 pub type ComputedValueExpressions = Vec<ExpressionId>;
 
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Fail)]
+#[fail(display = "No snapshot of computed values named \"{}\".", _0)]
+pub struct NoSuchSnapshot(String);
+
 
 
 // =================================
@@ -83,11 +111,13 @@ pub type ComputedValueExpressions = Vec<ExpressionId>;
 #[derive(Clone, Default, Derivative)]
 #[derivative(Debug)]
 pub struct ComputedValueInfoRegistry {
-    map:     RefCell<HashMap<ExpressionId, Rc<ComputedValueInfo>>>,
+    map:       RefCell<HashMap<ExpressionId, Rc<ComputedValueInfo>>>,
     /// A publisher that emits an update every time a new batch of updates is received from
     /// language server.
     #[derivative(Debug = "ignore")]
-    updates: Publisher<ComputedValueExpressions>,
+    updates:   Publisher<ComputedValueExpressions>,
+    snapshots: RefCell<HashMap<String, Snapshot>>,
+    execution: Cell<usize>,
 }
 
 impl ComputedValueInfoRegistry {
@@ -101,12 +131,18 @@ impl ComputedValueInfoRegistry {
         let updated_expressions = updates.iter().map(|update| update.expression_id).collect();
         for update in updates {
             let id = update.expression_id;
-            let info = Rc::new(ComputedValueInfo::from(update));
-            self.map.borrow_mut().insert(id, info);
+            let info = ComputedValueInfo { execution: self.execution.get(), ..update.into() };
+            self.map.borrow_mut().insert(id, Rc::new(info));
         }
         self.emit(updated_expressions);
     }
 
+    /// Mark the end of the current execution. The values received afterwards are computed by the
+    /// next one.
+    pub fn execution_completed(&self) {
+        self.execution.set(self.execution.get() + 1);
+    }
+
     /// Subscribe to notifications about changes in the registry.
     pub fn subscribe(&self) -> Subscriber<ComputedValueExpressions> {
         self.updates.subscribe()
@@ -155,6 +191,133 @@ impl ComputedValueInfoRegistry {
     pub fn get_type(self: &Rc<Self>, id: ExpressionId) -> StaticBoxFuture<Option<ImString>> {
         self.get_from_info(id, |info| info.typename.clone())
     }
+
+    /// Get the execution time of the given expression, if it was measured.
+    pub fn execution_time(&self, id: &ExpressionId) -> Option<Duration> {
+        self.get(id)?.execution_time
+    }
+
+    /// Get the current state of the registry. It will not be affected by further updates.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot { values: self.map.borrow().clone() }
+    }
+
+    /// Store the current state of the registry under the given name, replacing the snapshot
+    /// previously taken under this name.
+    pub fn take_snapshot(&self, name: impl Into<String>) {
+        let snapshot = self.snapshot();
+        self.snapshots.borrow_mut().insert(name.into(), snapshot);
+    }
+
+    /// Get the snapshot taken under the given name.
+    pub fn named_snapshot(&self, name: &str) -> FallibleResult<Snapshot> {
+        let snapshot = self.snapshots.borrow().get(name).cloned();
+        Ok(snapshot.ok_or_else(|| NoSuchSnapshot(name.into()))?)
+    }
+
+    /// Remove the snapshot taken under the given name. Returns `None` if there was no such
+    /// snapshot.
+    pub fn remove_snapshot(&self, name: &str) -> Option<Snapshot> {
+        self.snapshots.borrow_mut().remove(name)
+    }
+
+    /// Names of all stored snapshots, in alphabetical order.
+    pub fn snapshot_names(&self) -> Vec<String> {
+        self.snapshots.borrow().keys().cloned().sorted().collect()
+    }
+
+    /// Compare two named snapshots, see [`Snapshot::diff`].
+    pub fn diff_snapshots(&self, old: &str, new: &str) -> FallibleResult<Vec<ValueChange>> {
+        Ok(self.named_snapshot(old)?.diff(&self.named_snapshot(new)?))
+    }
+}
+
+
+
+// ================
+// === Snapshot ===
+// ================
+
+/// The frozen state of the [`ComputedValueInfoRegistry`].
+///
+/// The snapshots share the computed value information with the registry, so taking them is cheap.
+/// Each update replaces the information of the expression in the registry, which allows telling
+/// whether the expression was recomputed between two snapshots.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    values: HashMap<ExpressionId, Rc<ComputedValueInfo>>,
+}
+
+impl Snapshot {
+    /// Look up the snapshot for information about given expression.
+    pub fn get(&self, id: &ExpressionId) -> Option<Rc<ComputedValueInfo>> {
+        self.values.get(id).cloned()
+    }
+
+    /// Iterate over the information about all expressions, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&ExpressionId, &Rc<ComputedValueInfo>)> {
+        self.values.iter()
+    }
+
+    /// List the expressions computed after this snapshot was taken and before the `newer` one was,
+    /// ordered by their ids.
+    pub fn diff(&self, newer: &Snapshot) -> Vec<ValueChange> {
+        let changes = newer.values.iter().filter_map(|(id, new)| {
+            let old = self.values.get(id);
+            let recomputed = old.map_or(true, |old| !Rc::ptr_eq(old, new));
+            recomputed.then(|| ValueChange {
+                expression: *id,
+                old:        old.cloned(),
+                new:        new.clone(),
+            })
+        });
+        changes.sorted_by_key(|change| change.expression).collect()
+    }
+}
+
+/// The expression's computed value information before and after being recomputed.
+#[derive(Clone, Debug)]
+pub struct ValueChange {
+    #[allow(missing_docs)]
+    pub expression: ExpressionId,
+    /// The information before recomputing. `None` if the expression was not computed before.
+    pub old:        Option<Rc<ComputedValueInfo>>,
+    /// The information after recomputing.
+    pub new:        Rc<ComputedValueInfo>,
+}
+
+impl ValueChange {
+    /// Check if the expression was computed for the first time.
+    pub fn is_new(&self) -> bool {
+        self.old.is_none()
+    }
+
+    /// Check if the value's type changed. Starting or stopping failing is not considered a type
+    /// change, see [`Self::started_failing`] and [`Self::stopped_failing`].
+    pub fn type_changed(&self) -> bool {
+        match &self.old {
+            Some(old) if !old.is_failing() && !self.new.is_failing() =>
+                old.typename != self.new.typename,
+            _ => false,
+        }
+    }
+
+    /// Check if the expression failed after recomputing, while it did not before.
+    pub fn started_failing(&self) -> bool {
+        let was_failing = self.old.as_ref().map_or(false, |old| old.is_failing());
+        !was_failing && self.new.is_failing()
+    }
+
+    /// Check if the expression did not fail after recomputing, while it did before.
+    pub fn stopped_failing(&self) -> bool {
+        let was_failing = self.old.as_ref().map_or(false, |old| old.is_failing());
+        was_failing && !self.new.is_failing()
+    }
+
+    /// Check if the expression calls another method than before.
+    pub fn method_call_changed(&self) -> bool {
+        self.old.as_ref().map_or(false, |old| old.method_call != self.new.method_call)
+    }
 }
 
 
@@ -177,6 +340,11 @@ impl VisualizationUpdateData {
     pub fn new(data: Vec<u8>) -> VisualizationUpdateData {
         VisualizationUpdateData(data)
     }
+
+    /// Unwraps the binary data.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
 }
 
 impl AsRef<[u8]> for VisualizationUpdateData {
@@ -244,6 +412,25 @@ impl Visualization {
         Visualization { id, expression_id, preprocessor_code, context_module }
     }
 
+    /// Check if the other visualization computes the same data: visualizes the same expression
+    /// with the same preprocessor code evaluated in the same module.
+    pub fn computes_same_data(&self, other: &Visualization) -> bool {
+        self.expression_id == other.expression_id
+            && self.preprocessor_code == other.preprocessor_code
+            && self.context_module == other.context_module
+    }
+
+    /// Apply the chain of preprocessors from the library to the visualized value before the
+    /// visualization's preprocessor code.
+    pub fn chain_preprocessors(
+        mut self,
+        library: &preprocessor::Library,
+        chain: &[preprocessor::Step],
+    ) -> FallibleResult<Self> {
+        self.preprocessor_code = library.compile(chain, &self.preprocessor_code)?;
+        Ok(self)
+    }
+
     /// Creates a `VisualisationConfiguration` that is used in communication with language server.
     pub fn config(&self, execution_context_id: Uuid) -> VisualisationConfiguration {
         let expression = self.preprocessor_code.clone();
@@ -267,6 +454,8 @@ pub type Id = language_server::ContextId;
 pub struct AttachedVisualization {
     visualization: Visualization,
     update_sender: futures::channel::mpsc::UnboundedSender<VisualizationUpdateData>,
+    /// The data of the last update, kept to be recorded, see [`API::recording`].
+    last_update:   Option<VisualizationUpdateData>,
 }
 
 
@@ -349,6 +538,10 @@ pub trait API: Debug {
         data: VisualizationUpdateData,
     ) -> FallibleResult;
 
+    /// Record the computed values and the last data of the active visualizations, to be shown
+    /// later by a [`Replay`] context.
+    fn recording(&self) -> replay::Recording;
+
     /// Attempt detaching all the currently active visualizations.
     ///
     /// The requests are made in parallel (not one by one). Any number of them might fail.
@@ -379,6 +572,8 @@ pub type ExecutionContext = Rc<dyn API>;
 pub type Plain = plain::ExecutionContext;
 /// Execution Context Model which synchronizes all changes with Language Server.
 pub type Synchronized = synchronized::ExecutionContext;
+/// Execution Context Model which shows the recorded results instead of executing the code.
+pub type Replay = replay::ExecutionContext;
 
 
 
@@ -394,6 +589,7 @@ mod tests {
 
     use engine_protocol::language_server::types::test::value_update_with_dataflow_error;
     use engine_protocol::language_server::types::test::value_update_with_dataflow_panic;
+    use engine_protocol::language_server::types::test::value_update_with_method_ptr;
     use engine_protocol::language_server::types::test::value_update_with_type;
 
     #[test]
@@ -466,4 +662,46 @@ mod tests {
         let notification = test.expect_completion(subscriber.next()).unwrap();
         assert_eq!(notification, vec![expr2, expr3]);
     }
+
+    #[test]
+    fn comparing_registry_snapshots() {
+        let _test = TestWithLocalPoolExecutor::set_up();
+        let registry = ComputedValueInfoRegistry::default();
+        let [retyped, failing, unchanged, call, added] = [(); 5].map(|_| ExpressionId::new_v4());
+        registry.apply_updates(vec![
+            value_update_with_type(retyped, "Test.Typename1"),
+            value_update_with_type(failing, "Test.Typename1"),
+            value_update_with_type(unchanged, "Test.Typename1"),
+            value_update_with_method_ptr(call, 1),
+        ]);
+        registry.take_snapshot("before");
+        registry.apply_updates(vec![
+            value_update_with_type(retyped, "Test.Typename2"),
+            value_update_with_dataflow_error(failing),
+            value_update_with_method_ptr(call, 2),
+            value_update_with_type(added, "Test.Typename1"),
+        ]);
+        registry.take_snapshot("after");
+        assert_eq!(registry.snapshot_names(), vec!["after".to_owned(), "before".to_owned()]);
+
+        let changes = registry.diff_snapshots("before", "after").unwrap();
+        let change_of = |id| changes.iter().find(|change| change.expression == id).unwrap();
+        assert_eq!(changes.len(), 4);
+        assert!(changes.iter().all(|change| change.expression != unchanged));
+        assert!(change_of(retyped).type_changed());
+        assert!(!change_of(retyped).started_failing());
+        assert!(change_of(failing).started_failing());
+        assert!(!change_of(failing).type_changed());
+        assert!(change_of(call).method_call_changed());
+        assert!(change_of(added).is_new());
+
+        let reversed = registry.diff_snapshots("after", "before").unwrap();
+        assert_eq!(reversed.len(), 3);
+        assert!(reversed
+            .iter()
+            .any(|change| change.expression == failing && change.stopped_failing()));
+        assert!(registry.diff_snapshots("before", "missing").is_err());
+        assert!(registry.remove_snapshot("before").is_some());
+        assert!(registry.named_snapshot("before").is_err());
+    }
 }
@@ -2,6 +2,8 @@
 
 use crate::prelude::*;
 
+use crate::model::execution_context::replay::RecordedVisualization;
+use crate::model::execution_context::replay::Recording;
 use crate::model::execution_context::AttachedVisualization;
 use crate::model::execution_context::ComputedValueInfoRegistry;
 use crate::model::execution_context::LocalCall;
@@ -109,7 +111,8 @@ impl ExecutionContext {
     ) -> futures::channel::mpsc::UnboundedReceiver<VisualizationUpdateData> {
         let id = visualization.id;
         let (update_sender, receiver) = futures::channel::mpsc::unbounded();
-        let visualization = AttachedVisualization { visualization, update_sender };
+        let last_update = None;
+        let visualization = AttachedVisualization { visualization, update_sender, last_update };
         info!(self.logger, "Inserting to the registry: {id}.");
         self.visualizations.borrow_mut().insert(id, visualization);
         receiver
@@ -127,7 +130,10 @@ impl ExecutionContext {
     ) -> FallibleResult {
         let err = || InvalidVisualizationId(id);
         let mut visualizations = self.visualizations.borrow_mut();
-        let visualization = &mut visualizations.get_mut(&id).ok_or_else(err)?.visualization;
+        let attached = visualizations.get_mut(&id).ok_or_else(err)?;
+        // The last data were computed for the previous setup.
+        attached.last_update = None;
+        let visualization = &mut attached.visualization;
         if let Some(expression) = expression {
             visualization.preprocessor_code = expression;
         }
@@ -137,6 +143,11 @@ impl ExecutionContext {
         Ok(())
     }
 
+    /// Get the data of the last update received by the visualization.
+    pub fn last_update(&self, id: VisualizationId) -> Option<VisualizationUpdateData> {
+        self.visualizations.borrow().get(&id)?.last_update.clone()
+    }
+
     /// Detach the visualization from this execution context.
     ///
     /// This function shadows the asynchronous version from API trait.
@@ -222,7 +233,8 @@ impl model::execution_context::API for ExecutionContext {
         visualization_id: VisualizationId,
         data: VisualizationUpdateData,
     ) -> FallibleResult {
-        if let Some(visualization) = self.visualizations.borrow_mut().get(&visualization_id) {
+        if let Some(visualization) = self.visualizations.borrow_mut().get_mut(&visualization_id) {
+            visualization.last_update = Some(data.clone());
             // TODO [mwu] Should we consider detaching the visualization if the view has dropped the
             //   channel's receiver? Or we need to provide a way to re-establish the channel.
             let _ = visualization.update_sender.unbounded_send(data);
@@ -237,6 +249,15 @@ impl model::execution_context::API for ExecutionContext {
             Err(InvalidVisualizationId(visualization_id).into())
         }
     }
+
+    fn recording(&self) -> Recording {
+        let visualizations = self.visualizations.borrow();
+        let recorded = visualizations.values().filter_map(|attached| {
+            let data = attached.last_update.as_ref()?;
+            RecordedVisualization::new(&attached.visualization, data)
+        });
+        Recording::new(&self.computed_value_info_registry, recorded.collect())
+    }
 }
 
 
@@ -0,0 +1,244 @@
+//! The library of named, parameterised preprocessors, and chaining them before the visualization's
+//! own preprocessor.
+//!
+//! Large values, like tables with millions of rows, should not be sent to the IDE as a whole.
+//! Instead, a visualization may declare a chain of steps reducing the value, like sampling rows or
+//! computing statistics, which are evaluated by the Engine together with the visualization's
+//! preprocessor, so only the reduced data are sent.
+
+use crate::prelude::*;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// The maximum number of compiled chains kept by a [`Library`]. When exceeded, the cache is
+/// cleared.
+pub const MAX_COMPILED_CHAINS: usize = 256;
+
+/// The code of the built-in `summary` preprocessor.
+const SUMMARY_CODE: &str = "x -> x.compute_bulk [Standard.Base.Data.Statistics.Count, \
+    Standard.Base.Data.Statistics.Minimum, Standard.Base.Data.Statistics.Maximum, \
+    Standard.Base.Data.Statistics.Mean, Standard.Base.Data.Statistics.Standard_Deviation]";
+
+/// The code of the built-in `histogram` preprocessor.
+const HISTOGRAM_CODE: &str = "x -> (bounds -> (width -> Standard.Base.Data.Vector.new {bins} \
+    (i -> x.filter (v -> (if width == 0 then 0 else ((v - bounds.first) / width).floor.min \
+    ({bins} - 1)) == i) . length)) ((bounds.second - bounds.first) / {bins})) \
+    (x.compute_bulk [Standard.Base.Data.Statistics.Minimum, \
+    Standard.Base.Data.Statistics.Maximum])";
+
+
+
+// ==============
+// === Errors ===
+// ==============
+
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Fail)]
+#[fail(display = "There is no preprocessor named \"{}\" in the library.", _0)]
+pub struct UnknownPreprocessor(String);
+
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Fail)]
+#[fail(
+    display = "The preprocessor \"{}\" takes {} arguments, but {} were given.",
+    name, expected, given
+)]
+pub struct WrongArgumentCount {
+    name:     String,
+    expected: usize,
+    given:    usize,
+}
+
+
+
+// ============
+// === Step ===
+// ============
+
+/// A single step of a preprocessor chain: the name of a preprocessor in the [`Library`] and its
+/// arguments, being Enso expressions.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Step {
+    pub name:      String,
+    pub arguments: Vec<String>,
+}
+
+impl Step {
+    /// Constructor.
+    pub fn new(name: impl Into<String>, arguments: &[&str]) -> Self {
+        let arguments = arguments.iter().map(|argument| argument.to_string()).collect();
+        Self { name: name.into(), arguments }
+    }
+}
+
+
+
+// ==================
+// === Definition ===
+// ==================
+
+/// A parameterised preprocessor. Its code is an Enso function taking the value to be reduced, in
+/// which the parameters are referred to as `{parameter_name}`.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Definition {
+    pub name:       String,
+    pub parameters: Vec<String>,
+    pub code:       String,
+}
+
+impl Definition {
+    /// Constructor.
+    pub fn new(name: impl Into<String>, parameters: &[&str], code: impl Into<String>) -> Self {
+        let parameters = parameters.iter().map(|parameter| parameter.to_string()).collect();
+        Self { name: name.into(), parameters, code: code.into() }
+    }
+
+    /// Get the preprocessor's code with the parameters replaced by the given arguments.
+    pub fn instantiate(&self, arguments: &[String]) -> FallibleResult<String> {
+        let (expected, given) = (self.parameters.len(), arguments.len());
+        if expected != given {
+            let name = self.name.clone();
+            return Err(WrongArgumentCount { name, expected, given }.into());
+        }
+        let substitute = |code: String, (parameter, argument): (&String, &String)| {
+            code.replace(&format!("{{{}}}", parameter), &format!("({})", argument))
+        };
+        Ok(self.parameters.iter().zip(arguments).fold(self.code.clone(), substitute))
+    }
+}
+
+/// Compose the functions into a single lambda applying them in order. A single function is
+/// returned unchanged.
+pub fn compose(functions: impl IntoIterator<Item = impl AsRef<str>>) -> String {
+    let mut functions = functions.into_iter().map(|f| f.as_ref().to_owned()).collect_vec();
+    if functions.len() == 1 {
+        functions.remove(0)
+    } else {
+        let apply = |argument: String, function: String| format!("({}) ({})", function, argument);
+        let body = functions.into_iter().fold("x".to_owned(), apply);
+        format!("x -> {}", body)
+    }
+}
+
+
+
+// ===============
+// === Library ===
+// ===============
+
+/// The preprocessors which may be used in the visualizations' chains.
+///
+/// The chains are compiled to a single preprocessor code. The compiled chains are cached, so the
+/// identical chains requested by many visualizations are compiled once and always give the same
+/// code. At most [`MAX_COMPILED_CHAINS`] are cached.
+#[derive(Debug)]
+pub struct Library {
+    definitions: HashMap<String, Definition>,
+    compiled:    RefCell<HashMap<(Vec<Step>, String), String>>,
+}
+
+impl Library {
+    /// Create a library without any preprocessors.
+    pub fn empty() -> Self {
+        Self { definitions: default(), compiled: default() }
+    }
+
+    /// Create a library with the built-in preprocessors:
+    /// * `sample count` - take the first `count` rows of a table or elements of a vector;
+    /// * `summary` - compute the count, minimum, maximum, mean and standard deviation of a vector
+    ///   of numbers;
+    /// * `histogram bins` - count the numbers of a vector falling into each of `bins` equal-width
+    ///   ranges between the minimum and the maximum.
+    pub fn with_builtins() -> Self {
+        let mut library = Self::empty();
+        library.register(Definition::new("sample", &["count"], "x -> x.take_start {count}"));
+        library.register(Definition::new("summary", &[], SUMMARY_CODE));
+        library.register(Definition::new("histogram", &["bins"], HISTOGRAM_CODE));
+        library
+    }
+
+    /// Add the preprocessor to the library, replacing any previous one with the same name.
+    pub fn register(&mut self, definition: Definition) {
+        self.definitions.insert(definition.name.clone(), definition);
+        self.compiled.borrow_mut().clear();
+    }
+
+    /// Get the preprocessor of the given name.
+    pub fn get(&self, name: &str) -> FallibleResult<&Definition> {
+        self.definitions.get(name).ok_or_else(|| UnknownPreprocessor(name.to_owned()).into())
+    }
+
+    /// Compile the chain of preprocessors followed by the `code` to a single preprocessor code.
+    /// The `code` is returned unchanged for an empty chain.
+    pub fn compile(&self, chain: &[Step], code: &str) -> FallibleResult<String> {
+        if chain.is_empty() {
+            return Ok(code.to_owned());
+        }
+        let key = (chain.to_vec(), code.to_owned());
+        if let Some(compiled) = self.compiled.borrow().get(&key) {
+            return Ok(compiled.clone());
+        }
+        let steps = chain.iter().map(|step| self.get(&step.name)?.instantiate(&step.arguments));
+        let mut steps = steps.collect::<FallibleResult<Vec<_>>>()?;
+        steps.push(code.to_owned());
+        let compiled = compose(steps);
+        let mut cache = self.compiled.borrow_mut();
+        if cache.len() >= MAX_COMPILED_CHAINS {
+            cache.clear();
+        }
+        cache.insert(key, compiled.clone());
+        Ok(compiled)
+    }
+}
+
+impl Default for Library {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiling_preprocessor_chains() {
+        let library = Library::default();
+        let code = "x -> x.to_json";
+        assert_eq!(library.compile(&[], code).unwrap(), code);
+
+        let chain = [Step::new("sample", &["1000"])];
+        let expected = "x -> (x -> x.to_json) ((x -> x.take_start (1000)) (x))";
+        assert_eq!(library.compile(&chain, code).unwrap(), expected);
+
+        let mut library = Library::empty();
+        library.register(Definition::new("scale", &["factor"], "x -> x * {factor}"));
+        library.register(Definition::new("negate", &[], "x -> 0 - x"));
+        let chain = [Step::new("scale", &["2"]), Step::new("negate", &[])];
+        let expected = "x -> (x -> x.to_text) ((x -> 0 - x) ((x -> x * (2)) (x)))";
+        assert_eq!(library.compile(&chain, "x -> x.to_text").unwrap(), expected);
+
+        let unknown = [Step::new("sample", &["10"])];
+        assert!(library.compile(&unknown, code).is_err());
+        let wrong_arguments = [Step::new("scale", &[])];
+        assert!(library.compile(&wrong_arguments, code).is_err());
+
+        for count in 0..MAX_COMPILED_CHAINS * 2 {
+            let chain = [Step::new("scale", &[&count.to_string()])];
+            library.compile(&chain, code).unwrap();
+        }
+        assert!(library.compiled.borrow().len() <= MAX_COMPILED_CHAINS);
+    }
+}
@@ -0,0 +1,341 @@
+//! Module with the replay Execution Context Model, showing the results recorded in another session
+//! instead of executing the code.
+
+use crate::prelude::*;
+
+use crate::model::execution_context::ComputedValueInfo;
+use crate::model::execution_context::ComputedValueInfoRegistry;
+use crate::model::execution_context::ExpressionId;
+use crate::model::execution_context::LocalCall;
+use crate::model::execution_context::Visualization;
+use crate::model::execution_context::VisualizationId;
+use crate::model::execution_context::VisualizationUpdateData;
+use crate::model::module;
+
+use engine_protocol::language_server::ExpressionUpdate;
+use engine_protocol::language_server::MethodPointer;
+use engine_protocol::language_server::Path;
+use engine_protocol::language_server::ProfilingInfo;
+use futures::future::LocalBoxFuture;
+use serde::Deserialize;
+use serde::Serialize;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// The maximum size of the recorded data of a single visualization. Bigger data, e.g. of large
+/// tables, are not recorded, as the whole recording is saved often.
+pub const MAX_RECORDED_DATA_SIZE: usize = 64 * 1024;
+
+/// The maximum total size of the recorded data of all visualizations.
+pub const MAX_RECORDED_DATA_TOTAL_SIZE: usize = 512 * 1024;
+
+/// The path of the file storing the recording, relative to the project's root. It is kept out of
+/// the project's sources, so saving the results does not modify the modules.
+pub const RECORDING_FILE: [&str; 2] = [".enso", "replay.json"];
+
+
+
+// =================
+// === Recording ===
+// =================
+
+/// The last data received by a visualization, recorded together with the visualization's setup,
+/// as the setup determines what the data are.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecordedVisualization {
+    /// Expression that was visualized.
+    pub expression_id:     ExpressionId,
+    /// The preprocessor code which computed the data.
+    pub preprocessor_code: String,
+    /// The module in which context the preprocessor code was evaluated.
+    pub context_module:    String,
+    /// The visualization data. They are usually JSON, so they are stored as text to keep the
+    /// metadata readable.
+    pub data:              String,
+}
+
+impl RecordedVisualization {
+    /// Record the visualization's data. Returns `None` if the data are not a valid UTF-8 text or
+    /// are bigger than [`MAX_RECORDED_DATA_SIZE`].
+    pub fn new(visualization: &Visualization, data: &VisualizationUpdateData) -> Option<Self> {
+        if data.len() > MAX_RECORDED_DATA_SIZE {
+            return None;
+        }
+        Some(Self {
+            expression_id:     visualization.expression_id,
+            preprocessor_code: visualization.preprocessor_code.clone(),
+            context_module:    visualization.context_module.to_string(),
+            data:              String::from_utf8(data.to_vec()).ok()?,
+        })
+    }
+
+    /// Check if the data were recorded for a visualization with the same setup.
+    pub fn matches(&self, visualization: &Visualization) -> bool {
+        self.expression_id == visualization.expression_id
+            && self.preprocessor_code == visualization.preprocessor_code
+            && self.context_module == visualization.context_module.to_string()
+    }
+}
+
+/// The results of executing the code, recorded to be shown when the code cannot be executed, e.g.
+/// when the engine is not available. It is stored in the [`RECORDING_FILE`].
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Recording {
+    /// The updates restoring the content of the computed value registry.
+    pub updates:        Vec<ExpressionUpdate>,
+    #[allow(missing_docs)]
+    pub visualizations: Vec<RecordedVisualization>,
+}
+
+impl Recording {
+    /// Record the content of the computed value registry and the visualizations' data. The
+    /// content is sorted, so recording the same results gives the same metadata. The
+    /// visualizations exceeding [`MAX_RECORDED_DATA_TOTAL_SIZE`] are skipped.
+    pub fn new(
+        registry: &ComputedValueInfoRegistry,
+        mut visualizations: Vec<RecordedVisualization>,
+    ) -> Self {
+        let snapshot = registry.snapshot();
+        let updates = snapshot.iter().map(|(id, info)| expression_update(*id, info));
+        let updates = updates.sorted_by_key(|update| update.expression_id).collect();
+        visualizations.sort_by_key(|visualization| visualization.expression_id);
+        let mut total_size = 0;
+        visualizations.retain(|visualization| {
+            let fits = total_size + visualization.data.len() <= MAX_RECORDED_DATA_TOTAL_SIZE;
+            if fits {
+                total_size += visualization.data.len();
+            }
+            fits
+        });
+        Self { updates, visualizations }
+    }
+
+    /// The path of the file storing the recording of the project with the given root.
+    pub fn path(project_root_id: Uuid) -> Path {
+        Path::new(project_root_id, RECORDING_FILE)
+    }
+}
+
+/// Recreate the update which resulted in the computed value information.
+fn expression_update(id: ExpressionId, info: &ComputedValueInfo) -> ExpressionUpdate {
+    let execution_time = info.execution_time.map(|time| ProfilingInfo::ExecutionTime {
+        nano_time: time.as_nanos().try_into().unwrap_or(u64::MAX),
+    });
+    ExpressionUpdate {
+        expression_id:  id,
+        typename:       info.typename.as_ref().map(|typename| typename.to_string()),
+        method_pointer: info.method_call,
+        profiling_info: execution_time.into_iter().collect(),
+        from_cache:     true,
+        payload:        info.payload.clone(),
+    }
+}
+
+
+
+// =============
+// === Model ===
+// =============
+
+/// Execution Context Model showing the recorded results instead of executing the code.
+///
+/// It never communicates with the Language Server, so it can be used when the engine is not
+/// available. The call stack and the visualizations may be changed as in other contexts, and the
+/// visualizations get the recorded data, if there are any for their setup. The code changes are
+/// never evaluated, so the context shows the results of the code as it was when recorded.
+#[derive(Debug)]
+pub struct ExecutionContext {
+    model:     model::execution_context::Plain,
+    recording: Recording,
+}
+
+impl ExecutionContext {
+    /// Create a context with the recorded results. It is ready from the beginning.
+    pub fn new(
+        logger: impl Into<Logger>,
+        entry_point: MethodPointer,
+        recording: Recording,
+    ) -> Self {
+        let model = model::execution_context::Plain::new(logger, entry_point);
+        model.computed_value_info_registry.apply_updates(recording.updates.clone());
+        model.is_ready.replace(true);
+        Self { model, recording }
+    }
+
+    /// Send the recorded data to the visualization, if there are any for its current setup.
+    fn send_recorded_data(&self, id: VisualizationId) -> FallibleResult {
+        let visualization = self.model.visualization_info(id)?;
+        let mut recordings = self.recording.visualizations.iter();
+        if let Some(recorded) = recordings.find(|recorded| recorded.matches(&visualization)) {
+            let data = VisualizationUpdateData::new(recorded.data.clone().into_bytes());
+            self.model.dispatch_visualization_update(id, data)?;
+        }
+        Ok(())
+    }
+}
+
+impl model::execution_context::API for ExecutionContext {
+    fn when_ready(&self) -> StaticBoxFuture<Option<()>> {
+        self.model.when_ready()
+    }
+
+    fn current_method(&self) -> MethodPointer {
+        self.model.current_method()
+    }
+
+    fn visualization_info(&self, id: VisualizationId) -> FallibleResult<Visualization> {
+        self.model.visualization_info(id)
+    }
+
+    fn all_visualizations_info(&self) -> Vec<Visualization> {
+        self.model.all_visualizations_info()
+    }
+
+    fn active_visualizations(&self) -> Vec<VisualizationId> {
+        self.model.active_visualizations()
+    }
+
+    fn computed_value_info_registry(&self) -> &Rc<ComputedValueInfoRegistry> {
+        &self.model.computed_value_info_registry
+    }
+
+    fn stack_items<'a>(&'a self) -> Box<dyn Iterator<Item = LocalCall> + 'a> {
+        self.model.stack_items()
+    }
+
+    fn push(&self, stack_item: LocalCall) -> LocalBoxFuture<'_, FallibleResult> {
+        self.model.push(stack_item);
+        futures::future::ready(Ok(())).boxed_local()
+    }
+
+    fn pop(&self) -> LocalBoxFuture<'_, FallibleResult<LocalCall>> {
+        futures::future::ready(self.model.pop()).boxed_local()
+    }
+
+    fn attach_visualization(
+        &self,
+        visualization: Visualization,
+    ) -> LocalBoxFuture<
+        FallibleResult<futures::channel::mpsc::UnboundedReceiver<VisualizationUpdateData>>,
+    > {
+        let id = visualization.id;
+        let stream = self.model.attach_visualization(visualization);
+        let result = self.send_recorded_data(id).map(|()| stream);
+        futures::future::ready(result).boxed_local()
+    }
+
+    fn detach_visualization(
+        &self,
+        id: VisualizationId,
+    ) -> LocalBoxFuture<'_, FallibleResult<Visualization>> {
+        futures::future::ready(self.model.detach_visualization(id)).boxed_local()
+    }
+
+    fn modify_visualization(
+        &self,
+        id: VisualizationId,
+        expression: Option<String>,
+        module: Option<module::QualifiedName>,
+    ) -> BoxFuture<FallibleResult> {
+        let result = self.model.modify_visualization(id, expression, module);
+        let result = result.and_then(|()| self.send_recorded_data(id));
+        futures::future::ready(result).boxed_local()
+    }
+
+    fn dispatch_visualization_update(
+        &self,
+        visualization_id: VisualizationId,
+        data: VisualizationUpdateData,
+    ) -> FallibleResult {
+        self.model.dispatch_visualization_update(visualization_id, data)
+    }
+
+    fn recording(&self) -> Recording {
+        self.recording.clone()
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::executor::test_utils::TestWithLocalPoolExecutor;
+    use crate::model::execution_context::plain::test::MockData;
+
+    use engine_protocol::language_server::types::test::value_update_with_dataflow_error;
+    use engine_protocol::language_server::types::test::value_update_with_type;
+
+    #[test]
+    fn replaying_recorded_results() {
+        let mut test = TestWithLocalPoolExecutor::set_up();
+        let data = MockData::new();
+        let live = data.create();
+        let (value, error) = (ExpressionId::new_v4(), ExpressionId::new_v4());
+        let mut update = value_update_with_type(value, "Standard.Base.Data.Numbers.Integer");
+        update.profiling_info = vec![ProfilingInfo::ExecutionTime { nano_time: 1000 }];
+        let updates = vec![update, value_update_with_dataflow_error(error)];
+        live.computed_value_info_registry.apply_updates(updates);
+        let module = data.module_qualified_name();
+        let visualization = Visualization::new(value, "x -> x.to_json".into(), module.clone());
+        let _receiver = live.attach_visualization(visualization.clone());
+        let payload = VisualizationUpdateData::new(b"[1,2,3]".to_vec());
+        live.dispatch_visualization_update(visualization.id, payload.clone()).unwrap();
+        let big = Visualization::new(error, "x -> x.to_json".into(), module.clone());
+        let _receiver = live.attach_visualization(big.clone());
+        let big_payload = VisualizationUpdateData::new(vec![b'0'; MAX_RECORDED_DATA_SIZE + 1]);
+        live.dispatch_visualization_update(big.id, big_payload).unwrap();
+
+        let recording = live.recording();
+        assert_eq!(recording.updates.len(), 2);
+        assert_eq!(recording.visualizations.len(), 1);
+        let serialized = serde_json::to_string(&recording).unwrap();
+        let recording: Recording = serde_json::from_str(&serialized).unwrap();
+
+        let replay =
+            ExecutionContext::new(Logger::new("test"), data.main_method_pointer(), recording);
+        test.expect_completion(replay.when_ready()).unwrap();
+        let registry = replay.computed_value_info_registry();
+        let value_info = registry.get(&value).unwrap();
+        assert_eq!(value_info.typename, Some("Standard.Base.Data.Numbers.Integer".into()));
+        assert_eq!(value_info.execution_time, Some(std::time::Duration::from_micros(1)));
+        assert!(registry.get(&error).unwrap().is_failing());
+
+        let replayed = Visualization::new(value, "x -> x.to_json".into(), module.clone());
+        let mut receiver = test.expect_completion(replay.attach_visualization(replayed)).unwrap();
+        assert_eq!(test.expect_completion(receiver.next()), Some(payload));
+        let other = Visualization::new(value, "x -> x.length".into(), module);
+        let mut receiver = test.expect_completion(replay.attach_visualization(other)).unwrap();
+        test.expect_pending(receiver.next());
+    }
+
+    #[test]
+    fn limiting_size_of_recorded_data() {
+        let module = MockData::new().module_qualified_name();
+        let record = |index: usize, data: Vec<u8>| {
+            let id = ExpressionId::from_u128(index as u128);
+            let visualization = Visualization::new(id, "x -> x.to_json".into(), module.clone());
+            let data = VisualizationUpdateData::new(data);
+            RecordedVisualization::new(&visualization, &data).unwrap()
+        };
+        let fitting = MAX_RECORDED_DATA_TOTAL_SIZE / MAX_RECORDED_DATA_SIZE;
+        let big_data = vec![b'0'; MAX_RECORDED_DATA_SIZE - 1];
+        let big = (0..fitting + 3).map(|index| record(index, big_data.clone()));
+        // The small data are recorded last, after the big ones exceeded the limit, but still fit.
+        let small = record(fitting + 3, b"[]".to_vec());
+        let recording = Recording::new(&default(), big.chain(std::iter::once(small)).collect());
+        let recorded_size: usize = recording.visualizations.iter().map(|v| v.data.len()).sum();
+        assert!(recorded_size <= MAX_RECORDED_DATA_TOTAL_SIZE);
+        assert_eq!(recording.visualizations.len(), fitting + 1);
+        assert_eq!(recording.visualizations.last().unwrap().data, "[]");
+    }
+}
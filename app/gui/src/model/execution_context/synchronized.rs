@@ -2,6 +2,7 @@
 
 use crate::prelude::*;
 
+use crate::model::execution_context::replay::Recording;
 use crate::model::execution_context::ComputedValueInfoRegistry;
 use crate::model::execution_context::LocalCall;
 use crate::model::execution_context::Visualization;
@@ -42,12 +43,18 @@ pub enum Notification {
 
 /// An ExecutionContext model synchronized with LanguageServer. It will be automatically removed
 /// from LS once dropped.
+///
+/// The visualizations computing the same data share a single visualization attached in the
+/// Language Server, see [`Visualization::computes_same_data`].
 #[derive(Debug)]
 pub struct ExecutionContext {
     id:              model::execution_context::Id,
     model:           model::execution_context::Plain,
     language_server: Rc<language_server::Connection>,
     logger:          Logger,
+    /// The id of the visualization attached in the Language Server for each visualization of
+    /// this context.
+    attached_as:     RefCell<HashMap<VisualizationId, VisualizationId>>,
 }
 
 impl ExecutionContext {
@@ -73,7 +80,8 @@ impl ExecutionContext {
             let logger = Logger::new_sub(&parent, iformat! {"ExecutionContext {id}"});
             let model = model::execution_context::Plain::new(&logger, root_definition);
             info!(logger, "Created. Id: {id}.");
-            let this = Self { id, model, language_server, logger };
+            let attached_as = default();
+            let this = Self { id, model, language_server, logger, attached_as };
             this.push_root_frame().await?;
             info!(this.logger, "Pushed root frame.");
             Ok(this)
@@ -95,7 +103,64 @@ impl ExecutionContext {
         result.map(|res| res.map_err(|err| err.into()))
     }
 
-    /// Detach visualization from current execution context.
+    /// The id of the visualization attached in the Language Server for the given visualization.
+    fn language_server_id(&self, id: VisualizationId) -> VisualizationId {
+        self.attached_as.borrow().get(&id).copied().unwrap_or(id)
+    }
+
+    /// The visualizations sharing the visualization attached in the Language Server with the
+    /// given id.
+    fn sharing(&self, ls_id: VisualizationId) -> Vec<VisualizationId> {
+        let attached_as = self.attached_as.borrow();
+        let sharing = attached_as.iter().filter(|(_, attached)| **attached == ls_id);
+        sharing.map(|(id, _)| *id).collect()
+    }
+
+    /// Find the visualization attached in the Language Server which computes the same data as
+    /// the given one.
+    fn find_shareable(&self, vis: &Visualization) -> Option<VisualizationId> {
+        let attached_as = self.attached_as.borrow();
+        let others = self.model.all_visualizations_info().into_iter();
+        let mut same_data =
+            others.filter(|other| other.id != vis.id && other.computes_same_data(vis));
+        same_data.find_map(|other| attached_as.get(&other.id).copied())
+    }
+
+    /// Attach the visualization, already registered in the model, in the Language Server. If a
+    /// visualization computing the same data is already attached, it is shared instead, and the
+    /// visualization gets its last data at once.
+    async fn attach_in_language_server(&self, vis: &Visualization) -> FallibleResult {
+        if let Some(ls_id) = self.find_shareable(vis) {
+            info!(self.logger, "Visualization {vis.id} shares the attached visualization {ls_id}.");
+            self.attached_as.borrow_mut().insert(vis.id, ls_id);
+            let sharing = self.sharing(ls_id).into_iter();
+            if let Some(data) = sharing.filter_map(|id| self.model.last_update(id)).next() {
+                self.model.dispatch_visualization_update(vis.id, data)?;
+            }
+            return Ok(());
+        }
+        // The visualization's own id may still be used by the visualizations it shared with.
+        let own_id_free = self.sharing(vis.id).is_empty();
+        let ls_id = if own_id_free { vis.id } else { VisualizationId::new_v4() };
+        // The id is registered first, as the updates may be received before the confirmation.
+        self.attached_as.borrow_mut().insert(vis.id, ls_id);
+        let config = vis.config(self.id);
+        let ls = &self.language_server;
+        if let Err(err) = ls.attach_visualisation(&ls_id, &vis.expression_id, &config).await {
+            for id in self.sharing(ls_id) {
+                self.attached_as.borrow_mut().remove(&id);
+                if id != vis.id {
+                    warning!(self.logger, "Detaching {id}, as the shared visualization failed.");
+                    let _ = self.model.detach_visualization(id);
+                }
+            }
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    /// Detach visualization from current execution context. The visualization attached in the
+    /// Language Server is detached only if no other visualization shares it.
     ///
     /// Necessary because the Language Server requires passing both visualization ID and expression
     /// ID for the visualization attach point, and `Visualization` structure contains both.
@@ -104,12 +169,18 @@ impl ExecutionContext {
         vis: Visualization,
     ) -> FallibleResult<Visualization> {
         let vis_id = vis.id;
+        let ls_id = self.language_server_id(vis_id);
         let exe_id = self.id;
         let ast_id = vis.expression_id;
         let ls = self.language_server.clone_ref();
         let logger = self.logger.clone_ref();
-        info!(logger, "About to detach visualization by id: {vis_id}.");
-        ls.detach_visualisation(&exe_id, &vis_id, &ast_id).await?;
+        if self.sharing(ls_id).len() > 1 {
+            info!(logger, "Visualization {vis_id} stops sharing the visualization {ls_id}.");
+        } else {
+            info!(logger, "About to detach visualization by id: {ls_id}.");
+            ls.detach_visualisation(&exe_id, &ls_id, &ast_id).await?;
+        }
+        self.attached_as.borrow_mut().remove(&vis_id);
         if let Err(err) = self.model.detach_visualization(vis_id) {
             warning!(logger, "Failed to update model after detaching visualization: {err:?}.")
         }
@@ -119,10 +190,12 @@ impl ExecutionContext {
     /// Handles the update about expressions being computed.
     pub fn handle_notification(&self, notification: Notification) -> FallibleResult {
         match notification {
-            Notification::Completed =>
+            Notification::Completed => {
+                self.model.computed_value_info_registry.execution_completed();
                 if !self.model.is_ready.replace(true) {
                     info!(self.logger, "Context {self.id} Became ready");
-                },
+                }
+            }
             Notification::ExpressionUpdates(updates) => {
                 self.model.computed_value_info_registry.apply_updates(updates);
             }
@@ -198,17 +271,12 @@ impl model::execution_context::API for ExecutionContext {
         //  We must register our visualization in the model first, because Language server can send
         //  us visualization updates through the binary socket before confirming that visualization
         //  has been successfully attached.
-        let config = vis.config(self.id);
         let stream = self.model.attach_visualization(vis.clone());
 
         async move {
-            let result = self
-                .language_server
-                .attach_visualisation(&vis.id, &vis.expression_id, &config)
-                .await;
-            if let Err(e) = result {
+            if let Err(e) = self.attach_in_language_server(&vis).await {
                 self.model.detach_visualization(vis.id)?;
-                Err(e.into())
+                Err(e)
             } else {
                 Ok(stream)
             }
@@ -233,12 +301,30 @@ impl model::execution_context::API for ExecutionContext {
         expression: Option<String>,
         module: Option<module::QualifiedName>,
     ) -> BoxFuture<FallibleResult> {
+        let ls_id = self.language_server_id(id);
+        let shared = self.sharing(ls_id).len() > 1;
+        let old_vis = self.model.visualization_info(id);
         let result = self.model.modify_visualization(id, expression, module);
         let new_config = self.model.visualization_config(id, self.id);
         async move {
             result?;
-            self.language_server.modify_visualisation(&id, &new_config?).await?;
-            Ok(())
+            if shared {
+                // The other visualizations still need the data of the previous setup.
+                self.attached_as.borrow_mut().remove(&id);
+                let vis = self.model.visualization_info(id)?;
+                let result = self.attach_in_language_server(&vis).await;
+                if result.is_err() {
+                    self.attached_as.borrow_mut().insert(id, ls_id);
+                    // The visualization still shares the previous setup, so must keep its code.
+                    let old = old_vis?;
+                    let (code, module) = (Some(old.preprocessor_code), Some(old.context_module));
+                    self.model.modify_visualization(id, code, module)?;
+                }
+                result
+            } else {
+                self.language_server.modify_visualisation(&ls_id, &new_config?).await?;
+                Ok(())
+            }
         }
         .boxed_local()
     }
@@ -249,7 +335,17 @@ impl model::execution_context::API for ExecutionContext {
         data: VisualizationUpdateData,
     ) -> FallibleResult {
         debug!(self.logger, "Dispatching visualization update through the context {self.id()}");
-        self.model.dispatch_visualization_update(visualization_id, data)
+        let sharing = self.sharing(visualization_id);
+        if sharing.is_empty() {
+            self.model.dispatch_visualization_update(visualization_id, data)
+        } else {
+            let dispatch = |id| self.model.dispatch_visualization_update(id, data.clone());
+            sharing.into_iter().map(dispatch).collect()
+        }
+    }
+
+    fn recording(&self) -> Recording {
+        self.model.recording()
     }
 }
 
@@ -285,6 +381,7 @@ pub mod test {
     use engine_protocol::language_server::response::CreateExecutionContext;
     use engine_protocol::language_server::CapabilityRegistration;
     use engine_protocol::language_server::ExpressionUpdates;
+    use json_rpc::error::RpcError;
     use json_rpc::expect_call;
 
     #[derive(Debug)]
@@ -444,6 +541,84 @@ pub mod test {
         });
     }
 
+    #[test]
+    fn sharing_visualizations_computing_same_data() {
+        let vis = Visualization {
+            id:                model::execution_context::VisualizationId::new_v4(),
+            expression_id:     model::execution_context::ExpressionId::new_v4(),
+            preprocessor_code: "x -> x.to_json".to_string(),
+            context_module:    MockData::new().module_qualified_name(),
+        };
+        let vis2 = Visualization { id: VisualizationId::new_v4(), ..vis.clone() };
+        let Fixture { mut test, context, .. } = Fixture::new_customized(|ls, data| {
+            let exe_id = data.context_id;
+            let vis_id = vis.id;
+            let ast_id = vis.expression_id;
+            let config = vis.config(exe_id);
+
+            expect_call!(ls.attach_visualisation(vis_id,ast_id,config) => Ok(()));
+            expect_call!(ls.detach_visualisation(exe_id,vis_id,ast_id) => Ok(()));
+        });
+
+        test.run_task(async move {
+            let mut events = context.attach_visualization(vis.clone()).await.unwrap().boxed_local();
+            let first = VisualizationUpdateData::new(vec![1]);
+            context.dispatch_visualization_update(vis.id, first.clone()).unwrap();
+            assert_eq!(events.expect_next(), first);
+
+            let events2 = context.attach_visualization(vis2.clone()).await.unwrap();
+            let mut events2 = events2.boxed_local();
+            assert_eq!(events2.expect_next(), first);
+            let second = VisualizationUpdateData::new(vec![2]);
+            context.dispatch_visualization_update(vis.id, second.clone()).unwrap();
+            assert_eq!(events.expect_next(), second);
+            assert_eq!(events2.expect_next(), second);
+
+            context.detach_visualization(vis.id).await.unwrap();
+            events.expect_terminated();
+            let third = VisualizationUpdateData::new(vec![3]);
+            context.dispatch_visualization_update(vis.id, third.clone()).unwrap();
+            assert_eq!(events2.expect_next(), third);
+            context.detach_visualization(vis2.id).await.unwrap();
+            events2.expect_terminated();
+        });
+    }
+
+    #[test]
+    fn restoring_shared_visualization_after_failed_modification() {
+        let vis = Visualization {
+            id:                model::execution_context::VisualizationId::new_v4(),
+            expression_id:     model::execution_context::ExpressionId::new_v4(),
+            preprocessor_code: "x -> x.to_json".to_string(),
+            context_module:    MockData::new().module_qualified_name(),
+        };
+        let vis2 = Visualization { id: VisualizationId::new_v4(), ..vis.clone() };
+        let new_expression = "x -> x";
+        let modified =
+            Visualization { preprocessor_code: new_expression.to_owned(), ..vis2.clone() };
+        let Fixture { mut test, context, .. } = Fixture::new_customized(|ls, data| {
+            let exe_id = data.context_id;
+            let vis_id = vis.id;
+            let vis2_id = vis2.id;
+            let ast_id = vis.expression_id;
+            let config = vis.config(exe_id);
+            let modified_config = modified.config(exe_id);
+
+            expect_call!(ls.attach_visualisation(vis_id,ast_id,config) => Ok(()));
+            let error = Err(RpcError::LostConnection);
+            expect_call!(ls.attach_visualisation(vis2_id,ast_id,modified_config) => error);
+        });
+
+        test.run_task(async move {
+            let _ = context.attach_visualization(vis.clone()).await.unwrap();
+            let _ = context.attach_visualization(vis2.clone()).await.unwrap();
+            let expression = Some(new_expression.to_owned());
+            context.modify_visualization(vis2.id, expression, None).await.unwrap_err();
+            assert_eq!(context.visualization_info(vis2.id).unwrap(), vis2);
+            assert_eq!(context.sharing(vis.id).len(), 2);
+        });
+    }
+
     // TODO [mwu]
     //   The test below has been disabled as shaky, see https://github.com/enso-org/ide/issues/637
     #[ignore]
@@ -455,7 +630,11 @@ pub mod test {
             preprocessor_code: "".to_string(),
             context_module:    MockData::new().module_qualified_name(),
         };
-        let vis2 = Visualization { id: VisualizationId::new_v4(), ..vis.clone() };
+        let vis2 = Visualization {
+            id: VisualizationId::new_v4(),
+            preprocessor_code: "x -> x".to_string(),
+            ..vis.clone()
+        };
 
         let Fixture { mut test, context, .. } = Fixture::new_customized(|ls, data| {
             let exe_id = data.context_id;
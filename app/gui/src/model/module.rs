@@ -22,6 +22,7 @@ use serde::Serialize;
 // === Export ===
 // ==============
 
+pub mod migration;
 pub mod plain;
 pub mod synchronized;
 
@@ -312,8 +313,8 @@ pub struct Notification {
 /// Mapping between ID and metadata.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Metadata {
-    /// Metadata used within ide.
-    #[serde(default, deserialize_with = "enso_prelude::deserialize_or_default")]
+    /// Metadata used within ide. It is migrated to the current schema version when read.
+    #[serde(default, deserialize_with = "migration::deserialize")]
     pub ide: IdeMetadata,
     #[serde(flatten)]
     /// Metadata of other users of ParsedSourceFile<Metadata> API.
@@ -340,17 +341,39 @@ pub struct ProjectMetadata {
     /// The execution context of the displayed graph editor.
     #[serde(default, deserialize_with = "enso_prelude::deserialize_or_default")]
     pub call_stack: Vec<model::execution_context::LocalCall>,
+    /// Fields written by a newer IDE version, kept to not erase them when writing.
+    #[serde(flatten)]
+    unknown:        serde_json::Map<String, serde_json::Value>,
 }
 
 /// Metadata that belongs to ide.
+///
+/// The metadata is stored with the version of its schema, see the [`migration`] module.
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct IdeMetadata {
+    /// The schema version of the metadata.
+    #[serde(default)]
+    version:   migration::Version,
     /// Metadata that belongs to nodes.
-    #[serde(deserialize_with = "enso_prelude::deserialize_or_default")]
-    node:    HashMap<ast::Id, NodeMetadata>,
+    #[serde(default, deserialize_with = "enso_prelude::deserialize_or_default")]
+    node:      HashMap<ast::Id, NodeMetadata>,
     /// The project metadata. This is stored only in the main module's metadata.
     #[serde(default, deserialize_with = "enso_prelude::deserialize_or_default")]
-    project: Option<ProjectMetadata>,
+    project:   Option<ProjectMetadata>,
+    /// Fields written by a newer IDE version, kept to not erase them when writing.
+    #[serde(flatten)]
+    unknown:   serde_json::Map<String, serde_json::Value>,
+    /// The summary of reading the stored metadata.
+    #[serde(skip)]
+    migration: migration::Report,
+}
+
+impl IdeMetadata {
+    /// The summary of migrating the stored metadata to the current schema version, including the
+    /// fields which could not be read.
+    pub fn migration_report(&self) -> &migration::Report {
+        &self.migration
+    }
 }
 
 /// Metadata of specific node.
@@ -377,6 +400,9 @@ pub struct NodeMetadata {
     /// Information about enabled visualization. Exact format is defined by the integration layer.
     #[serde(default)]
     pub visualization:   serde_json::Value,
+    /// Fields written by a newer IDE version, kept to not erase them when writing.
+    #[serde(flatten)]
+    unknown:             serde_json::Map<String, serde_json::Value>,
 }
 
 /// Used for storing node position.
@@ -533,6 +559,13 @@ pub trait API: Debug + model::undo_redo::Aware {
         new_id_map: ast::IdMap,
     ) -> FallibleResult;
 
+    /// Updates AST after code change, when the code after the change is already parsed.
+    fn apply_parsed_code_change(
+        &self,
+        change: TextChange,
+        new_ast: ast::known::Module,
+    ) -> FallibleResult;
+
     /// Sets metadata for given node.
     fn set_node_metadata(&self, id: ast::Id, data: NodeMetadata) -> FallibleResult;
 
@@ -0,0 +1,277 @@
+//! Versioning of the IDE metadata schema and migration of metadata stored in older layouts.
+//!
+//! The IDE part of the module metadata (see [`IdeMetadata`]) is stored together with the version
+//! of its schema. When the metadata is read, the migrations are applied in order, bringing the
+//! stored JSON to the [`CURRENT_VERSION`] layout before it is deserialized. Everything which could
+//! not be carried over is listed in the [`Report`].
+//!
+//! Metadata written by a newer IDE is not migrated. The fields this IDE does not understand are
+//! kept by the metadata structures and written back unchanged, together with the newer version.
+
+use crate::prelude::*;
+
+use crate::model::module::IdeMetadata;
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde_json::Map;
+use serde_json::Value;
+
+
+
+// ===============
+// === Version ===
+// ===============
+
+/// The version of the IDE metadata schema.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct Version(pub u32);
+
+/// The version of the metadata stored before the schema was versioned.
+pub const UNVERSIONED: Version = Version(0);
+
+/// The schema version written by this IDE.
+pub const CURRENT_VERSION: Version = Version(MIGRATIONS.len() as u32);
+
+const VERSION_FIELD: &str = "version";
+
+impl Default for Version {
+    fn default() -> Self {
+        CURRENT_VERSION
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+
+
+// ==============
+// === Report ===
+// ==============
+
+/// A field of the stored metadata which could not be carried over to the current layout.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DroppedField {
+    /// The path to the field in the IDE metadata, e.g. `node.<id>.intended_method`.
+    pub path:  String,
+    /// The stored value of the field.
+    pub value: Value,
+}
+
+/// The summary of reading the stored metadata.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    /// The schema version the metadata was stored with.
+    pub stored_version: Version,
+    /// The fields which were lost during the migration or deserialization.
+    pub dropped:        Vec<DroppedField>,
+}
+
+impl Report {
+    /// Check if the stored metadata was in an older layout.
+    pub fn was_migrated(&self) -> bool {
+        self.stored_version < CURRENT_VERSION
+    }
+
+    /// Check if all the stored fields were read.
+    pub fn is_lossless(&self) -> bool {
+        self.dropped.is_empty()
+    }
+
+    /// Register a field which could not be carried over.
+    pub fn drop_field(&mut self, path: impl Into<String>, value: Value) {
+        self.dropped.push(DroppedField { path: path.into(), value });
+    }
+}
+
+
+
+// ==================
+// === Migrations ===
+// ==================
+
+/// A migration of the IDE metadata JSON object from one schema version to the next one. The
+/// fields the migration is unable to convert should be registered in the report.
+pub type Migration = fn(&mut Map<String, Value>, &mut Report);
+
+/// The migrations of the IDE metadata. The migration at index `i` converts the layout of version
+/// `i` to the layout of version `i + 1`.
+const MIGRATIONS: &[Migration] = &[from_unversioned];
+
+/// The layout of version 1 is the layout used before the schema was versioned, with the version
+/// field added. The oldest unversioned layout stored the nodes' intended methods without the
+/// `defined_on_type` field; such methods are the module methods, so they are defined on the type
+/// of their module.
+fn from_unversioned(object: &mut Map<String, Value>, _: &mut Report) {
+    let nodes = object.get_mut("node").and_then(Value::as_object_mut);
+    let methods = nodes
+        .into_iter()
+        .flat_map(|nodes| nodes.values_mut())
+        .filter_map(|node| node.get_mut("intended_method").and_then(Value::as_object_mut));
+    for method in methods {
+        if !method.contains_key("defined_on_type") {
+            if let Some(module) = method.get("module").cloned() {
+                method.insert("defined_on_type".into(), module);
+            }
+        }
+    }
+}
+
+/// Migrate the stored IDE metadata to the current layout and deserialize it. This never fails: the
+/// parts which cannot be read are replaced with defaults and listed in the migration report of the
+/// returned metadata.
+pub fn migrate(value: Value) -> IdeMetadata {
+    migrate_with(value, MIGRATIONS)
+}
+
+fn migrate_with(value: Value, migrations: &[Migration]) -> IdeMetadata {
+    let mut report = Report { stored_version: UNVERSIONED, dropped: default() };
+    let mut object = match value {
+        Value::Object(object) => object,
+        Value::Null => default(),
+        other => {
+            report.drop_field("", other);
+            default()
+        }
+    };
+    let stored_version = object.get(VERSION_FIELD).and_then(|v| Version::deserialize(v).ok());
+    report.stored_version = stored_version.unwrap_or(UNVERSIONED);
+    for migration in migrations.iter().skip(report.stored_version.0 as usize) {
+        migration(&mut object, &mut report);
+    }
+    let version = report.stored_version.max(Version(migrations.len() as u32));
+    object.insert(VERSION_FIELD.into(), version.0.into());
+    let migrated = Value::Object(object);
+    let mut metadata = IdeMetadata::deserialize(&migrated).unwrap_or_default();
+    let written = serde_json::to_value(&metadata).unwrap_or_default();
+    collect_dropped_fields("", &migrated, &written, &mut report);
+    metadata.migration = report;
+    metadata
+}
+
+/// Deserialize and migrate the IDE metadata. To be used as `deserialize_with` serde attribute.
+pub fn deserialize<'d, D: Deserializer<'d>>(deserializer: D) -> Result<IdeMetadata, D::Error> {
+    Ok(migrate(Value::deserialize(deserializer)?))
+}
+
+/// Register in the report all stored fields which are missing or have a different value in the
+/// metadata to be written.
+fn collect_dropped_fields(path: &str, stored: &Value, written: &Value, report: &mut Report) {
+    match (stored, written) {
+        (Value::Object(stored), Value::Object(written)) =>
+            for (key, value) in stored {
+                let path = if path.is_empty() { key.clone() } else { iformat!("{path}.{key}") };
+                match written.get(key) {
+                    Some(written) => collect_dropped_fields(&path, value, written, report),
+                    None => report.drop_field(path, value.clone()),
+                }
+            },
+        _ =>
+            if !same_values(stored, written) {
+                report.drop_field(path, stored.clone());
+            },
+    }
+}
+
+/// Compare values, ignoring the precision lost by storing numbers as `f32`.
+fn same_values(a: &Value, b: &Value) -> bool {
+    let as_f32 = |n: &serde_json::Number| n.as_f64().map(|n| n as f32);
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => as_f32(a) == as_f32(b),
+        (Value::Array(a), Value::Array(b)) =>
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_values(a, b)),
+        (Value::Object(a), Value::Object(b)) =>
+            a.len() == b.len()
+                && a.iter().all(|(key, a)| b.get(key).map_or(false, |b| same_values(a, b))),
+        _ => a == b,
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::model::module::Position;
+
+    use serde_json::json;
+
+    const NODE_ID: &str = "bd891b65-4c2f-4c05-bc3b-6077b4417cc1";
+
+    #[test]
+    fn migrating_unversioned_metadata() {
+        let stored = json!({"node": {NODE_ID: {
+            "position": {"vector": [-75.5, 52]},
+            "intended_method": {"module": "Base.System.File", "name": "read"},
+        }}});
+        let metadata = migrate(stored);
+        let report = &metadata.migration;
+        assert_eq!(metadata.version, CURRENT_VERSION);
+        assert_eq!(report.stored_version, UNVERSIONED);
+        assert!(report.was_migrated());
+        let node = metadata.node.get(&ast::Id::from_str(NODE_ID).unwrap()).unwrap();
+        assert_eq!(node.position, Some(Position::new(-75.5, 52.0)));
+        let method = node.intended_method.as_ref().unwrap();
+        assert_eq!(method.module.to_string(), "Base.System.File");
+        assert_eq!(method.defined_on_type.to_string(), "Base.System.File");
+        assert_eq!(method.name, "read");
+        assert!(report.is_lossless());
+
+        let written = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(migrate(written).migration, Report::default());
+    }
+
+    #[test]
+    fn keeping_fields_of_newer_versions() {
+        let stored = json!({
+            "version": CURRENT_VERSION.0 + 1,
+            "layers": ["background"],
+            "node": {NODE_ID: {"position": {"vector": [1.0, 2.0]}, "color": "red"}},
+        });
+        let metadata = migrate(stored.clone());
+        assert!(!metadata.migration.was_migrated());
+        assert!(metadata.migration.is_lossless());
+        let written = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(written["version"], stored["version"]);
+        assert_eq!(written["layers"], stored["layers"]);
+        assert_eq!(written["node"][NODE_ID]["color"], "red");
+    }
+
+    #[test]
+    fn applying_migration_chain() {
+        fn rename_nodes(object: &mut Map<String, Value>, _: &mut Report) {
+            if let Some(nodes) = object.remove("nodes") {
+                object.insert("node".into(), nodes);
+            }
+        }
+        fn remove_legacy(object: &mut Map<String, Value>, report: &mut Report) {
+            if let Some(legacy) = object.remove("legacy") {
+                report.drop_field("legacy", legacy);
+            }
+        }
+        let migrations: &[Migration] = &[rename_nodes, remove_legacy];
+        let stored = json!({"nodes": {NODE_ID: {"selected": true}}, "legacy": 1});
+        let metadata = migrate_with(stored, migrations);
+        assert_eq!(metadata.version, Version(2));
+        assert!(metadata.node.get(&ast::Id::from_str(NODE_ID).unwrap()).unwrap().selected);
+        let dropped = vec![DroppedField { path: "legacy".into(), value: json!(1) }];
+        assert_eq!(metadata.migration.dropped, dropped);
+
+        let stored = json!({"version": 1, "nodes": {}, "legacy": 1});
+        let metadata = migrate_with(stored, migrations);
+        assert_eq!(metadata.migration.stored_version, Version(1));
+        assert_eq!(metadata.migration.dropped.len(), 1);
+        assert!(metadata.unknown.contains_key("nodes"));
+    }
+}
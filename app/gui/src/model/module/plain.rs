@@ -162,11 +162,20 @@ impl model::module::API for Module {
         new_id_map: ast::IdMap,
     ) -> FallibleResult {
         let mut code: enso_text::Text = self.ast().repr().into();
+        code.apply_change(change.as_ref());
+        let new_ast = parser.parse(code.into(), new_id_map)?.try_into()?;
+        self.apply_parsed_code_change(change, new_ast)
+    }
+
+    fn apply_parsed_code_change(
+        &self,
+        change: TextChange,
+        new_ast: ast::known::Module,
+    ) -> FallibleResult {
+        let code: enso_text::Text = self.ast().repr().into();
         let replaced_start = code.location_of_byte_offset_snapped(change.range.start);
         let replaced_end = code.location_of_byte_offset_snapped(change.range.end);
         let replaced_location = enso_text::Range::new(replaced_start, replaced_end);
-        code.apply_change(change.as_ref());
-        let new_ast = parser.parse(code.into(), new_id_map)?.try_into()?;
         let notification = NotificationKind::CodeChanged { change, replaced_location };
         self.update_content(notification, |content| content.ast = new_ast)
     }
@@ -6,7 +6,6 @@ use enso_text::unit::*;
 use crate::model::module::Content;
 use crate::model::module::NodeMetadata;
 use crate::model::module::Notification;
-use crate::model::module::NotificationKind;
 use crate::model::module::Path;
 use crate::model::module::ProjectMetadata;
 use crate::model::module::TextChange;
@@ -161,6 +160,7 @@ impl Module {
         // TODO[ao] We should not fail here when metadata are malformed, but discard them and set
         //  default instead.
         let source = parser.parse_with_metadata(opened.content)?;
+        Self::report_metadata_migration(&logger, source.metadata.ide.migration_report());
         let digest = opened.current_version;
         let summary = ContentSummary { digest, end_of_file };
         let model =
@@ -172,6 +172,16 @@ impl Module {
         Ok(this)
     }
 
+    fn report_metadata_migration(logger: &Logger, report: &model::module::migration::Report) {
+        let version = model::module::migration::CURRENT_VERSION;
+        if report.was_migrated() {
+            info!(logger, "Migrated metadata from {report.stored_version} to {version}.");
+        }
+        for dropped in &report.dropped {
+            warning!(logger, "Dropped unreadable metadata field {dropped.path}: {dropped.value}.");
+        }
+    }
+
     /// Create a module mock.
     pub fn mock(model: model::module::Plain) -> Rc<Self> {
         let logger = Logger::new(iformat!("Mocked Module {model.path()}"));
@@ -225,6 +235,14 @@ impl API for Module {
         self.model.apply_code_change(change, parser, new_id_map)
     }
 
+    fn apply_parsed_code_change(
+        &self,
+        change: TextChange,
+        new_ast: ast::known::Module,
+    ) -> FallibleResult {
+        self.model.apply_parsed_code_change(change, new_ast)
+    }
+
     fn set_node_metadata(&self, id: ast::Id, data: NodeMetadata) -> FallibleResult {
         self.model.set_node_metadata(id, data)
     }
@@ -320,31 +338,18 @@ impl Module {
         notification: Notification,
     ) -> FallibleResult<ParsedContentSummary> {
         let Notification { new_file, kind } = notification;
-        debug!(self.logger, "Handling notification: {content:?}.");
+        debug!(self.logger, "Handling notification of kind {kind:?}: {content:?}.");
         match content {
             LanguageServerContent::Desynchronized(summary) =>
                 self.full_invalidation(summary, new_file).await,
-            LanguageServerContent::Synchronized(summary) => match kind {
-                NotificationKind::Invalidate => self.partial_invalidation(summary, new_file).await,
-                NotificationKind::CodeChanged { change, replaced_location } => {
-                    let code_change =
-                        TextEdit { range: replaced_location.into(), text: change.text };
-                    let id_map_change = TextEdit {
-                        range: summary.id_map.into(),
-                        text:  new_file.id_map_slice().to_string(),
-                    };
-                    //id_map goes first, because code change may alter its position.
-                    let edits = vec![id_map_change, code_change];
-                    self.notify_language_server(&summary.summary, &new_file, edits).await
-                }
-                NotificationKind::MetadataChanged => {
-                    let edits = vec![TextEdit {
-                        range: summary.metadata.into(),
-                        text:  new_file.metadata_slice().to_string(),
-                    }];
-                    self.notify_language_server(&summary.summary, &new_file, edits).await
-                }
-            },
+            // Regardless of the notification kind, the edits are computed by comparing the module
+            // sections with the Language Server content. This way even a change replacing a large
+            // part of the module (e.g. the whole metadata after moving a single node) is sent as
+            // a minimal edit, and the Language Server may keep its incremental compilation caches.
+            // If the edit fails, the content is marked as `Desynchronized` (see
+            // `new_ls_content_info`), so the next notification performs the full invalidation.
+            LanguageServerContent::Synchronized(summary) =>
+                self.partial_invalidation(summary, &new_file).await,
         }
     }
 
@@ -356,11 +361,15 @@ impl Module {
         new_file: SourceFile,
     ) -> impl Future<Output = FallibleResult<ParsedContentSummary>> + 'static {
         debug!(self.logger, "Handling full invalidation: {ls_content:?}.");
-        let range = Range::new(Location::default(), ls_content.end_of_file);
-        let edits = vec![TextEdit { range: range.into(), text: new_file.content.clone() }];
+        let edits = vec![Self::edit_for_whole_file(ls_content, &new_file)];
         self.notify_language_server(ls_content, &new_file, edits)
     }
 
+    fn edit_for_whole_file(ls_content: &ContentSummary, new_file: &SourceFile) -> TextEdit {
+        let range = Range::new(Location::default(), ls_content.end_of_file);
+        TextEdit { range: range.into(), text: new_file.content.clone() }
+    }
+
     fn edit_for_snipped(start: &Location, source: Text, target: Text) -> Option<TextEdit> {
         // This is an implicit assumption that always seems to be true. Otherwise finding the
         // correct location for the final edit would be more complex.
@@ -401,27 +410,55 @@ impl Module {
         )
     }
 
+    /// The edits transforming the Language Server content into the new file content. Each section
+    /// of the file (code, id map and metadata) is reduced to a single edit replacing the part
+    /// between the common prefix and suffix of the old and new section. Unchanged sections do not
+    /// produce any edit.
+    fn edits_for_changes(
+        ls_content: &ParsedContentSummary,
+        new_file: &SourceFile,
+    ) -> Vec<TextEdit> {
+        vec![
+            //id_map and metadata go first, because code change may alter their position.
+            Self::edit_for_idmap(ls_content, new_file),
+            Self::edit_for_metadata(ls_content, new_file),
+            Self::edit_for_code(ls_content, new_file),
+        ]
+        .into_iter()
+        .flatten()
+        .collect_vec()
+    }
+
+    /// Check whether applying the edits to the Language Server content results in a text with the
+    /// same version hash as the new file.
+    fn edits_lead_to(
+        ls_content: &ParsedContentSummary,
+        edits: &[TextEdit],
+        new_file: &SourceFile,
+    ) -> bool {
+        let edited = apply_text_edits(ls_content.source.clone(), edits);
+        ContentSummary::new(&edited).digest == Sha3_224::new(new_file.content.as_bytes())
+    }
+
     /// Send update to Language Server with the changed file content. Returns the new content
     /// summary of Language Server state.
     ///
-    /// Note that a heuristic is used to determine the changed file content. The indicated change
-    /// might not be the minimal diff, but will contain all changes.
+    /// The computed edits are verified against the new file version hash before sending. Should
+    /// they not reproduce the new content, the whole file is sent instead.
     fn partial_invalidation(
         &self,
         ls_content: &ParsedContentSummary,
-        new_file: SourceFile,
+        new_file: &SourceFile,
     ) -> impl Future<Output = FallibleResult<ParsedContentSummary>> + 'static {
         debug!(self.logger, "Handling partial invalidation: {ls_content:?}.");
-        let edits = vec![
-            //id_map and metadata go first, because code change may alter their position.
-            Self::edit_for_idmap(ls_content, &new_file),
-            Self::edit_for_metadata(ls_content, &new_file),
-            Self::edit_for_code(ls_content, &new_file),
-        ]
-        .into_iter()
-        .flatten()
-        .collect_vec();
-        self.notify_language_server(&ls_content.summary, &new_file, edits)
+        let edits = Self::edits_for_changes(ls_content, new_file);
+        let edits = if Self::edits_lead_to(ls_content, &edits, new_file) {
+            edits
+        } else {
+            warning!(self.logger, "Computed edits do not match the new module version hash.");
+            vec![Self::edit_for_whole_file(&ls_content.summary, new_file)]
+        };
+        self.notify_language_server(&ls_content.summary, new_file, edits)
     }
 
     /// This is a helper function with all common logic regarding sending the update to
@@ -439,15 +476,30 @@ impl Module {
             old_version: ls_content.digest.clone(),
             new_version: Sha3_224::new(new_file.content.as_bytes()),
         };
-        debug!(self.logger, "Notifying LS with edit: {edit:#?}.");
-        let ls_future_reply = self.language_server.client.apply_text_file_edit(&edit);
+        let ls_future_reply = (!edit.edits.is_empty()).as_some_from(|| {
+            debug!(self.logger, "Notifying LS with edit: {edit:#?}.");
+            self.language_server.client.apply_text_file_edit(&edit)
+        });
         async {
-            ls_future_reply.await?;
+            if let Some(reply) = ls_future_reply {
+                reply.await?;
+            }
             Ok(summary)
         }
     }
 }
 
+/// Apply the Language Server text edits to the text. The edits are applied in order, so the
+/// locations of each edit refer to the text modified by all the previous ones.
+fn apply_text_edits(text: Text, edits: &[TextEdit]) -> Text {
+    edits.iter().fold(text, |mut text, edit| {
+        let start = text.byte_offset_of_location_snapped(edit.range.start.into());
+        let end = text.byte_offset_of_location_snapped(edit.range.end.into());
+        text.apply_change(enso_text::Change { range: Range::new(start, end), text: &edit.text });
+        text
+    })
+}
+
 impl Drop for Module {
     fn drop(&mut self) {
         let file_path = self.path().file_path().clone();
@@ -492,7 +544,6 @@ pub mod test {
     use engine_protocol::language_server::MockClient;
     use engine_protocol::language_server::Position;
     use engine_protocol::language_server::TextRange;
-    use enso_text::Change;
     use enso_text::Text;
     use json_rpc::error::RpcError;
     use wasm_bindgen_test::wasm_bindgen_test;
@@ -543,7 +594,7 @@ pub mod test {
             client.expect.apply_text_file_edit(move |edits| {
                 let content_so_far = this.current_ls_content.get();
                 let result = f(edits);
-                let new_content = apply_edits(content_so_far, edits);
+                let new_content = apply_text_edits(content_so_far, &edits.edits);
                 let actual_old = this.current_ls_version.get();
                 let actual_new =
                     Sha3_224::from_parts(new_content.iter_chunks(..).map(|s| s.as_bytes()));
@@ -564,31 +615,23 @@ pub mod test {
             });
         }
 
-        /// The single text edit with accompanying metadata idmap changes.
-        fn expect_edit_with_metadata(
+        /// The single code edit, possibly preceded by edits of the idmap and metadata sections.
+        fn expect_code_edit(
             &self,
             client: &mut MockClient,
             f: impl FnOnce(&TextEdit) -> json_rpc::Result<()> + 'static,
         ) {
             let this = self.clone();
             self.expect_some_edit(client, move |edit| {
-                if let [edit_idmap, edit_code] = edit.edits.as_slice() {
-                    let code_so_far = this.current_ls_content.get();
-                    let file_so_far = SourceFile::new((&code_so_far).into());
-                    // TODO [mwu]
-                    //  Currently this assumes that the whole idmap is replaced at each edit.
-                    //  This code should be adjusted, if partial metadata updates are implemented.
-                    let idmap_range =
-                        file_so_far.id_map.map(|x| code_so_far.location_of_byte_offset_snapped(x));
-                    let idmap_range = TextRange::from(idmap_range);
-                    assert_eq!(edit_idmap.range, idmap_range);
-                    assert!(SourceFile::looks_like_idmap(&edit_idmap.text));
-                    f(edit_code)
-                } else {
-                    // This test assumes that expected single file edit consists from two text
-                    // edits: first for idmap adjustment and the second for the actual code edit.
-                    panic!("Expected exactly two edits.");
+                let code_so_far = this.current_ls_content.get();
+                let file_so_far = SourceFile::new((&code_so_far).into());
+                let code_end = code_so_far.location_of_byte_offset_snapped(file_so_far.code.end);
+                let code_end = Position::from(code_end);
+                let (edit_code, edits_metadata) = edit.edits.split_last().expect("No code edit.");
+                for edit_metadata in edits_metadata {
+                    assert!(edit_metadata.range.start.line > code_end.line);
                 }
+                f(edit_code)
             });
         }
 
@@ -620,19 +663,6 @@ pub mod test {
         }
     }
 
-    fn apply_edit(code: impl Into<Text>, edit: &TextEdit) -> Text {
-        let mut code = code.into();
-        let start_loc = code.byte_offset_of_location_snapped(edit.range.start.into());
-        let end_loc = code.byte_offset_of_location_snapped(edit.range.end.into());
-        let change = Change { range: Range::new(start_loc, end_loc), text: edit.text.clone() };
-        code.apply_change(change);
-        code
-    }
-
-    fn apply_edits(code: impl Into<Text>, file_edit: &FileEdit) -> Text {
-        let initial = code.into();
-        file_edit.edits.iter().fold(initial, |content, edit| apply_edit(&content, edit))
-    }
 
 
     // === Test cases ===
@@ -663,12 +693,11 @@ pub mod test {
                     assert!(edit.edits.last().map_or(false, |edit| edit.text.contains("Test")));
                     Ok(())
                 });
-                // Replacing `Test` with `Test 2`
-                edit_handler.expect_some_edit(client, |edits| {
-                    let edit_code = &edits.edits[1];
-                    assert_eq!(edit_code.text, "Test 2");
-                    assert_eq!(edit_code.range, TextRange {
-                        start: Position { line: 1, character: 13 },
+                // Replacing `Test` with `Test 2`, which is reduced to inserting ` 2`.
+                edit_handler.expect_code_edit(client, |edit| {
+                    assert_eq!(edit.text, " 2");
+                    assert_eq!(edit.range, TextRange {
+                        start: Position { line: 1, character: 17 },
                         end:   Position { line: 1, character: 17 },
                     });
                     Ok(())
@@ -704,10 +733,10 @@ pub mod test {
                 // Opening module and metadata generation.
                 edit_handler.expect_full_invalidation(client);
                 // Applying code update.
-                edit_handler.expect_edit_with_metadata(client, |edit| {
-                    assert_eq!(edit.text, "Test 2");
+                edit_handler.expect_code_edit(client, |edit| {
+                    assert_eq!(edit.text, " 2");
                     assert_eq!(edit.range, TextRange {
-                        start: Position { line: 1, character: 13 },
+                        start: Position { line: 1, character: 17 },
                         end:   Position { line: 1, character: 17 },
                     });
                     Err(RpcError::LostConnection)
@@ -743,4 +772,26 @@ pub mod test {
         });
         assert_eq!(edit, expected);
     }
+
+    #[test]
+    fn minimal_edit_for_metadata_change() {
+        let file = |x: &str| {
+            let metadata = format!("{{\"position\":[{x},20.0]}}");
+            SourceFile::new(format!("main = 5\n\n\n#### METADATA ####\n[]\n{metadata}"))
+        };
+        let ls_content = ParsedContentSummary::from_source(&file("10.0"));
+        let new_file = file("15.0");
+        let edits = Module::edits_for_changes(&ls_content, &new_file);
+        let expected = TextEdit {
+            range: TextRange {
+                start: Position { line: 5, character: 14 },
+                end:   Position { line: 5, character: 15 },
+            },
+            text:  "5".to_string(),
+        };
+        assert_eq!(edits, vec![expected]);
+        assert!(Module::edits_lead_to(&ls_content, &edits, &new_file));
+        assert!(!Module::edits_lead_to(&ls_content, &[], &new_file));
+        assert!(Module::edits_for_changes(&ls_content, &file("10.0")).is_empty());
+    }
 }
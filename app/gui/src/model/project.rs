@@ -55,6 +55,9 @@ pub trait API: Debug {
     /// Get the suggestions database.
     fn suggestion_db(&self) -> Rc<model::SuggestionDatabase>;
 
+    /// Get the project-wide index of method definitions, method calls and imports.
+    fn usages(&self) -> Rc<model::usages::Index>;
+
     /// Get the list of all content roots attached to the project.
     fn content_roots(&self) -> Vec<Rc<ContentRoot>>;
 
@@ -247,6 +247,7 @@ pub struct Project {
     pub execution_contexts:  Rc<ExecutionContextsRegistry>,
     pub visualization:       controller::Visualization,
     pub suggestion_db:       Rc<SuggestionDatabase>,
+    pub usages:              Rc<model::usages::Index>,
     pub content_roots:       Rc<ContentRoots>,
     pub parser:              Parser,
     pub logger:              Logger,
@@ -279,6 +280,7 @@ impl Project {
         let language_server = &*language_server_rpc;
         let suggestion_db = SuggestionDatabase::create_synchronized(language_server);
         let suggestion_db = Rc::new(suggestion_db.await.map_err(&wrap)?);
+        let usages = Rc::new(model::usages::Index::new(&logger, suggestion_db.clone_ref()));
         let content_roots = ContentRoots::new_from_connection(&logger, &*language_server);
         let content_roots = Rc::new(content_roots);
         let notifications = notification::Publisher::default();
@@ -294,6 +296,7 @@ impl Project {
             execution_contexts,
             visualization,
             suggestion_db,
+            usages,
             content_roots,
             parser,
             logger,
@@ -550,10 +553,13 @@ impl Project {
         let parser = self.parser.clone_ref();
         let urm = self.urm();
         let repository = urm.repository.clone_ref();
+        let usages = self.usages.clone_ref();
+        let name = path.qualified_module_name(self.qualified_name());
         async move {
             let module =
                 module::Synchronized::open(path, language_server, parser, repository).await?;
             urm.module_opened(module.clone());
+            usages.watch_module(name, module.clone());
             Ok(module)
         }
     }
@@ -592,6 +598,10 @@ impl model::project::API for Project {
         self.suggestion_db.clone_ref()
     }
 
+    fn usages(&self) -> Rc<model::usages::Index> {
+        self.usages.clone_ref()
+    }
+
     fn content_roots(&self) -> Vec<Rc<ContentRoot>> {
         self.content_roots.all()
     }
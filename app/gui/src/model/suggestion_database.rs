@@ -243,6 +243,16 @@ impl SuggestionDatabase {
         })
     }
 
+    /// Search the database for Method entries with given name, defined for any type.
+    pub fn lookup_methods_by_name(&self, name: impl Str) -> Vec<Rc<Entry>> {
+        self.entries
+            .borrow()
+            .values()
+            .filter(|entry| entry.kind == Kind::Method && entry.matches_name(name.as_ref()))
+            .cloned()
+            .collect()
+    }
+
     /// An iterator over all examples gathered from suggestions.
     ///
     /// If the database was modified during iteration, the iterator does not panic, but may return
@@ -0,0 +1,334 @@
+//! The project-wide index of method definitions, method calls and imports.
+//!
+//! The index keeps a [`Summary`] of every module of the project: the modules read from disk when
+//! the index is built and the opened modules, which are re-indexed on every change. The calls which
+//! cannot be resolved from the code alone are resolved using the nodes' intended methods and the
+//! suggestion database when the index is queried.
+
+use crate::prelude::*;
+
+use crate::model::module::Metadata;
+use crate::model::module::MethodId;
+use crate::model::module::QualifiedName;
+use crate::model::SuggestionDatabase;
+
+use double_representation::definition;
+use double_representation::usages::same_method;
+use double_representation::usages::CallSite;
+use double_representation::usages::Callee;
+use double_representation::usages::Summary;
+use engine_protocol::language_server;
+use engine_protocol::language_server::MethodPointer;
+use parser::api::ParsedSourceFile;
+
+
+
+// =============
+// === Usage ===
+// =============
+
+/// How the called method was determined.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Resolution {
+    /// The method is referred by name in its module or through the module's name.
+    Code,
+    /// The method is the intended method of the node containing the call.
+    IntendedMethod,
+    /// The method is the only method of the called name in the suggestion database. The call may
+    /// actually refer another method, e.g. one not yet known to the Language Server.
+    SuggestionDatabase,
+}
+
+/// A call of the method in some module of the project.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Usage {
+    /// The module containing the call.
+    pub module:     QualifiedName,
+    #[allow(missing_docs)]
+    pub call:       CallSite,
+    #[allow(missing_docs)]
+    pub resolution: Resolution,
+}
+
+
+
+// =============
+// === Index ===
+// =============
+
+/// The indexed content of a single module.
+#[derive(Clone, Debug, Default)]
+struct IndexedModule {
+    summary:          Summary,
+    intended_methods: HashMap<ast::Id, MethodPointer>,
+}
+
+/// The project-wide index of method definitions, method calls and imports.
+#[derive(Debug)]
+pub struct Index {
+    logger:        Logger,
+    suggestion_db: Rc<SuggestionDatabase>,
+    modules:       RefCell<HashMap<QualifiedName, IndexedModule>>,
+}
+
+impl Index {
+    /// Create an empty index.
+    pub fn new(parent: impl AnyLogger, suggestion_db: Rc<SuggestionDatabase>) -> Self {
+        let logger = Logger::new_sub(parent, "Usages Index");
+        Self { logger, suggestion_db, modules: default() }
+    }
+
+    /// Index all modules in the project's source directory. The modules which cannot be read or
+    /// parsed are skipped, and so are the modules already indexed, e.g. the opened ones, which
+    /// are kept up to date by [`Index::watch_module`].
+    pub async fn index_source_directory(&self, project: &model::Project) -> FallibleResult {
+        let json_rpc = project.json_rpc();
+        let parser = project.parser();
+        let root_id = project.project_content_root_id();
+        let source_dir = language_server::Path::new(root_id, &[ast::constants::SOURCE_DIRECTORY]);
+        for file_path in list_files_recursively(&json_rpc, source_dir).await? {
+            let path = match model::module::Path::from_file_path(file_path) {
+                Ok(path) => path,
+                Err(_) => continue,
+            };
+            let name = project.qualified_module_name(&path);
+            let read = json_rpc.read_file(path.file_path()).await;
+            let parsed = read.map_err(Into::into).and_then(|response| {
+                parser.parse_with_metadata::<Metadata>(response.contents).map_err(Into::into)
+            });
+            match parsed {
+                Ok(_) if self.contains(&name) => {}
+                Ok(source_file) => self.index_source_file(name, &source_file),
+                Err(err) => warning!(self.logger, "Cannot index module {name}: {err}"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Check if the module is indexed.
+    pub fn contains(&self, name: &QualifiedName) -> bool {
+        self.modules.borrow().contains_key(name)
+    }
+
+    /// Index the module read from its source file, replacing its previous summary.
+    pub fn index_source_file(&self, name: QualifiedName, source: &ParsedSourceFile<Metadata>) {
+        let nodes = source.metadata.ide.node.iter();
+        let intended_methods = nodes
+            .filter_map(|(id, node)| Some((*id, method_pointer(node.intended_method.as_ref()?))))
+            .collect();
+        self.insert(name, &source.ast, intended_methods);
+    }
+
+    /// Index the opened module, replacing its previous summary.
+    pub fn index_module(&self, name: QualifiedName, module: &model::Module) {
+        let ast = module.ast();
+        let nodes = ast.iter_recursive().filter_map(|ast| ast.id());
+        let intended_methods = nodes
+            .filter_map(|id| {
+                let metadata = module.node_metadata(id).ok()?;
+                Some((id, method_pointer(metadata.intended_method.as_ref()?)))
+            })
+            .collect();
+        self.insert(name, &ast, intended_methods);
+    }
+
+    /// Index the opened module and keep its summary up to date until the module or the index is
+    /// dropped.
+    pub fn watch_module(self: &Rc<Self>, name: QualifiedName, module: model::Module) {
+        self.index_module(name.clone(), &module);
+        let weak = Rc::downgrade(self);
+        let notifications = module.subscribe();
+        let module = Rc::downgrade(&module);
+        executor::global::spawn(notifications.for_each(move |_| {
+            if let (Some(index), Some(module)) = (weak.upgrade(), module.upgrade()) {
+                index.index_module(name.clone(), &module);
+            }
+            futures::future::ready(())
+        }));
+    }
+
+    /// Remove the module from the index, e.g. after it was deleted.
+    pub fn remove_module(&self, name: &QualifiedName) {
+        self.modules.borrow_mut().remove(name);
+    }
+
+    fn insert(
+        &self,
+        name: QualifiedName,
+        ast: &ast::known::Module,
+        intended_methods: HashMap<ast::Id, MethodPointer>,
+    ) {
+        debug!(self.logger, "Indexing module {name}.");
+        let summary = Summary::new(&name, ast);
+        let indexed = IndexedModule { summary, intended_methods };
+        self.modules.borrow_mut().insert(name, indexed);
+    }
+
+    /// Find the module and the definition of the method.
+    pub fn definition(&self, method: &MethodPointer) -> Option<(QualifiedName, definition::Id)> {
+        self.modules.borrow().iter().find_map(|(name, module)| {
+            let mut definitions = module.summary.definitions.iter();
+            let found = definitions.find(|definition| same_method(&definition.method, method));
+            found.map(|definition| (name.clone(), definition.id.clone()))
+        })
+    }
+
+    /// Find all calls of the method in the project.
+    pub fn callers(&self, method: &MethodPointer) -> Vec<Usage> {
+        let modules = self.modules.borrow();
+        let mut usages = modules
+            .iter()
+            .flat_map(|(name, module)| {
+                module.summary.calls.iter().filter_map(move |call| {
+                    let (called, resolution) = self.resolve(module, call)?;
+                    same_method(&called, method).then(|| Usage {
+                        module: name.clone(),
+                        call: call.clone(),
+                        resolution,
+                    })
+                })
+            })
+            .collect_vec();
+        usages.sort_by_cached_key(|usage| usage.module.to_string());
+        usages
+    }
+
+    /// Find all modules importing the given module, e.g. `Standard.Table`.
+    pub fn importers(&self, imported: &QualifiedName) -> Vec<QualifiedName> {
+        let modules = self.modules.borrow();
+        let importers = modules.iter().filter(|(_, module)| {
+            let mut imports = module.summary.imports.iter();
+            imports.any(|import| import.qualified_name().contains(imported))
+        });
+        let mut importers = importers.map(|(name, _)| name.clone()).collect_vec();
+        importers.sort_by_cached_key(ToString::to_string);
+        importers
+    }
+
+    fn resolve(
+        &self,
+        module: &IndexedModule,
+        call: &CallSite,
+    ) -> Option<(MethodPointer, Resolution)> {
+        let name = match &call.callee {
+            Callee::Method(method) => return Some((method.clone(), Resolution::Code)),
+            Callee::Name(name) => name,
+        };
+        let intended = call.node.and_then(|node| module.intended_methods.get(&node));
+        if let Some(method) = intended.filter(|method| &method.name == name) {
+            Some((method.clone(), Resolution::IntendedMethod))
+        } else {
+            let methods = self.suggestion_db.lookup_methods_by_name(name);
+            match methods.as_slice() {
+                [entry] => {
+                    let method = MethodPointer::try_from(entry.as_ref()).ok()?;
+                    Some((method, Resolution::SuggestionDatabase))
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+fn method_pointer(method: &MethodId) -> MethodPointer {
+    MethodPointer {
+        module:          method.module.to_string(),
+        defined_on_type: method.defined_on_type.clone().into(),
+        name:            method.name.clone(),
+    }
+}
+
+/// List all files in the directory and its subdirectories.
+async fn list_files_recursively(
+    json_rpc: &language_server::Connection,
+    root: language_server::Path,
+) -> FallibleResult<Vec<language_server::Path>> {
+    let mut files = Vec::new();
+    let mut directories = vec![root];
+    while let Some(directory) = directories.pop() {
+        for object in json_rpc.file_list(&directory).await?.paths {
+            match &object {
+                language_server::FileSystemObject::File { .. } => files.push((&object).into()),
+                language_server::FileSystemObject::Directory { .. } =>
+                    directories.push((&object).into()),
+                _ => {}
+            }
+        }
+    }
+    Ok(files)
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::model::suggestion_database::entry::Kind;
+    use crate::model::suggestion_database::entry::Scope;
+    use crate::model::suggestion_database::Entry;
+
+    use parser::Parser;
+
+    fn index_code(index: &Index, name: &str, code: &str) {
+        let ast = Parser::new_or_panic().parse_module(code, default()).unwrap();
+        let source = ParsedSourceFile { ast, metadata: default() };
+        index.index_source_file(QualifiedName::from_text(name).unwrap(), &source);
+    }
+
+    #[test]
+    fn finding_callers_and_importers() {
+        let suggestion_db = Rc::new(SuggestionDatabase::new_empty(Logger::new("test")));
+        let index = Index::new(Logger::new("test"), suggestion_db.clone_ref());
+        index_code(&index, "local.Project.Main", "process x = x + 1\n\nmain = process 1");
+        let report = "import Standard.Table\nimport local.Project.Main\n\nmain =\n    \
+            table = Table.read \"data.csv\"\n    Main.process table\n    table.summarize";
+        index_code(&index, "local.Project.Report", report);
+
+        let process = MethodPointer {
+            module:          "local.Project.Main".into(),
+            defined_on_type: "local.Project.Main".into(),
+            name:            "process".into(),
+        };
+        let (module, definition) = index.definition(&process).unwrap();
+        assert_eq!(module.to_string(), "local.Project.Main");
+        assert_eq!(definition, definition::Id::new_plain_name("process"));
+
+        let callers = index.callers(&process);
+        let modules = callers.iter().map(|usage| usage.module.to_string()).collect_vec();
+        assert_eq!(modules, vec!["local.Project.Main", "local.Project.Report"]);
+        assert!(callers.iter().all(|usage| usage.resolution == Resolution::Code));
+
+        let table = QualifiedName::from_text("Standard.Table").unwrap();
+        let importers = index.importers(&table).iter().map(ToString::to_string).collect_vec();
+        assert_eq!(importers, vec!["local.Project.Report"]);
+
+        let summarize = MethodPointer {
+            module:          "Standard.Table.Data.Table".into(),
+            defined_on_type: "Standard.Table.Data.Table.Table".into(),
+            name:            "summarize".into(),
+        };
+        assert!(index.callers(&summarize).is_empty());
+        suggestion_db.put_entry(1, Entry {
+            kind:               Kind::Method,
+            module:             QualifiedName::from_text(summarize.module.as_str()).unwrap(),
+            name:               summarize.name.clone(),
+            arguments:          vec![],
+            return_type:        "Standard.Table.Data.Table.Table".into(),
+            documentation_html: None,
+            self_type:          Some(summarize.defined_on_type.as_str().try_into().unwrap()),
+            scope:              Scope::Everywhere,
+        });
+        let callers = index.callers(&summarize);
+        assert_eq!(callers.len(), 1);
+        assert_eq!(callers[0].resolution, Resolution::SuggestionDatabase);
+
+        index.remove_module(&QualifiedName::from_text("local.Project.Report").unwrap());
+        assert_eq!(index.callers(&process).len(), 1);
+        assert!(index.importers(&table).is_empty());
+    }
+}
@@ -4,16 +4,19 @@
 use crate::prelude::*;
 use enso_web::traits::*;
 
+use crate::controller::upload::DuplicatePolicy;
 use crate::controller::upload::NodeFromDroppedFileHandler;
 use crate::executor::global::spawn_stream_handler;
 use crate::presenter::graph::state::State;
 
 use enso_frp as frp;
+use ensogl::system::web::sleep;
 use futures::future::LocalBoxFuture;
 use ide_view as view;
 use ide_view::graph_editor::component::node as node_view;
 use ide_view::graph_editor::component::visualization as visualization_view;
 use ide_view::graph_editor::EdgeEndpoint;
+use std::time::Duration;
 
 
 // ==============
@@ -63,6 +66,10 @@ const DEFAULT_NODE_X_POSITION: f32 = -100.0;
 /// node was added by editing text.
 const DEFAULT_NODE_Y_POSITION: f32 = 200.0;
 
+/// The delay of saving the execution results in the project's recording file. The results
+/// received during the delay are saved together.
+const RECORDING_SAVE_DELAY: Duration = Duration::from_secs(5);
+
 /// Default node position -- acts as a starting points for laying out nodes with no position defined
 /// in the metadata.
 pub fn default_node_position() -> Vector2 {
@@ -82,6 +89,7 @@ struct Model {
     controller:       controller::ExecutedGraph,
     view:             view::graph_editor::GraphEditor,
     state:            Rc<State>,
+    recording_queued: Rc<Cell<bool>>,
     _visualization:   Visualization,
     _execution_stack: CallStack,
 }
@@ -108,6 +116,7 @@ impl Model {
             controller,
             view,
             state,
+            recording_queued: default(),
             _visualization: visualization,
             _execution_stack: execution_stack,
         }
@@ -141,6 +150,23 @@ impl Model {
         );
     }
 
+    /// Save the execution results in the project's recording file after [`RECORDING_SAVE_DELAY`],
+    /// so they can be shown when the project is opened while the code cannot be executed.
+    fn queue_saving_recording(&self) {
+        if !self.recording_queued.replace(true) {
+            let queued = self.recording_queued.clone_ref();
+            let controller = self.controller.clone_ref();
+            let logger = self.logger.clone_ref();
+            executor::global::spawn(async move {
+                sleep(RECORDING_SAVE_DELAY).await;
+                queued.set(false);
+                if let Err(err) = controller.save_recording().await {
+                    warning!(logger, "Failed to save the execution results: {err}");
+                }
+            });
+        }
+    }
+
     /// Node was removed in view.
     fn node_removed(&self, id: ViewNodeId) {
         self.update_ast(
@@ -158,12 +184,23 @@ impl Model {
             || {
                 let connection = self.view.model.edges.get_cloned_ref(&id)?;
                 let ast_to_create = self.state.update_from_view().create_connection(connection)?;
+                self.warn_if_incompatible(&ast_to_create);
                 Some(self.controller.connect(&ast_to_create))
             },
             "create connection",
         );
     }
 
+    /// Log a warning if the connection passes a value of a type not matching the argument.
+    fn warn_if_incompatible(&self, connection: &AstConnection) {
+        match self.controller.connection_compatibility(connection) {
+            Ok(compatibility) if !compatibility.is_allowed() =>
+                warning!(self.logger, "Connecting values of incompatible types: {compatibility}"),
+            Ok(_) => {}
+            Err(err) => warning!(self.logger, "Failed to check connection types: {err}"),
+        }
+    }
+
     /// Connection was removed in view.
     fn connection_removed(&self, id: ViewConnection) {
         self.update_ast(
@@ -190,6 +227,42 @@ impl Model {
         );
     }
 
+    /// Arrange the given nodes (or all nodes if `None`) using the automatic layout. The extents
+    /// of nodes are taken from their views, so the visualizations are taken into account.
+    fn auto_layout_requested(&self, selection: &Option<Vec<ViewNodeId>>) {
+        self.update_ast(
+            || {
+                debug!(self.logger, "Arranging nodes automatically.");
+                let graph = self.controller.graph();
+                let ast_ids = match selection {
+                    Some(nodes) => nodes
+                        .iter()
+                        .filter_map(|node| self.state.ast_node_id_of_view(*node))
+                        .collect_vec(),
+                    None => match graph.nodes() {
+                        Ok(nodes) => nodes.iter().map(|node| node.id()).collect_vec(),
+                        Err(err) => return Some(Err(err)),
+                    },
+                };
+                let extents = ast_ids
+                    .iter()
+                    .filter_map(|id| {
+                        let view_id = self.state.view_id_of_ast_node(*id)?;
+                        let bounding_box = self.view.model.node_bounding_box(view_id);
+                        let position = self.view.model.node_position(view_id);
+                        let size = Vector2(bounding_box.width(), bounding_box.height());
+                        let top_left = Vector2(bounding_box.left(), bounding_box.top());
+                        let origin = position - top_left;
+                        Some((*id, controller::graph::layout::Extent::new(size, origin)))
+                    })
+                    .collect::<HashMap<_, _>>();
+                let selection = selection.as_ref().map(|_| ast_ids.as_slice());
+                Some(graph.auto_layout(&extents, selection))
+            },
+            "arrange nodes",
+        );
+    }
+
     fn update_ast<F>(&self, f: F, action: &str)
     where F: FnOnce() -> Option<FallibleResult> {
         if let Some(Err(err)) = f() {
@@ -288,7 +361,13 @@ impl Model {
             data: file,
         };
         let position = model::module::Position { vector: position };
-        let handler = NodeFromDroppedFileHandler::new(&self.logger, project, graph);
+        let duplicates = if enso_config::ARGS.keep_duplicate_uploads.unwrap_or(false) {
+            DuplicatePolicy::KeepBoth
+        } else {
+            DuplicatePolicy::ReuseExisting
+        };
+        let handler = NodeFromDroppedFileHandler::new(&self.logger, project, graph)
+            .with_duplicate_policy(duplicates);
         if let Err(err) = handler.create_node_and_start_uploading(to_upload, position) {
             error!(self.logger, "Error when creating node from dropped file: {err}");
         }
@@ -548,6 +627,7 @@ impl Graph {
             eval view.on_edge_endpoints_set((edge_id) model.new_connection_created(*edge_id));
             eval view.on_edge_endpoint_unset(((edge_id,_)) model.connection_removed(*edge_id));
             eval view.nodes_collapsed(((nodes, _)) model.nodes_collapsed(nodes));
+            eval view.auto_layout_requested((selection) model.auto_layout_requested(selection));
             eval view.enabled_visualization_path(((node_id, path)) model.node_visualization_changed(*node_id, path.clone()));
 
 
@@ -580,8 +660,10 @@ impl Graph {
                     Notification::Invalidate => update_view.emit(()),
                     Notification::PortsUpdate => update_view.emit(()),
                 },
-                executed::Notification::ComputedValueInfo(expressions) =>
-                    update_expressions.emit(expressions),
+                executed::Notification::ComputedValueInfo(expressions) => {
+                    model.queue_saving_recording();
+                    update_expressions.emit(expressions)
+                }
                 executed::Notification::EnteredNode(_) => update_view.emit(()),
                 executed::Notification::SteppedOutOfNode(_) => update_view.emit(()),
             }
@@ -623,6 +705,11 @@ impl controller::upload::DataProvider for ensogl_drop_manager::File {
             .map(|f| f.map_err(|e| StringError::new(e.print_to_string()).into()))
             .boxed_local()
     }
+
+    fn rewind(&mut self) -> FallibleResult {
+        ensogl_drop_manager::File::rewind(self)
+            .map_err(|e| StringError::new(e.print_to_string()).into())
+    }
 }
 
 
@@ -631,7 +718,7 @@ impl controller::upload::DataProvider for ensogl_drop_manager::File {
 // === Error ===
 // =============
 
-/// Generic error representation. This is used only once in the lines above. Probably should be
+/// Generic error representation. This is used only in the lines above. Probably should be
 /// removed in the future.
 #[derive(Debug, Fail)]
 #[fail(display = "{}", message)]
@@ -309,9 +309,14 @@ impl Visualization {
 // === Helper Functions ===
 // ========================
 
+/// Deserialize the data received from the engine. Data in the columnar format is passed as binary
+/// data, see [`visualization_view::columnar`] to learn more. All other data must be JSON text.
 fn deserialize_visualization_data(
     data: VisualizationUpdateData,
 ) -> FallibleResult<visualization_view::Data> {
+    if visualization_view::columnar::is_columnar(&data) {
+        return Ok(visualization_view::Data::from(data.into_bytes()));
+    }
     let binary = data.as_ref();
     let as_text = std::str::from_utf8(binary)?;
     let as_json: serde_json::Value = serde_json::from_str(as_text)?;
@@ -4,6 +4,7 @@ use crate::prelude::*;
 
 use crate::controller::ExecutedGraph;
 use crate::executor::global::spawn;
+use crate::model::execution_context::preprocessor;
 use crate::model::execution_context::Visualization;
 use crate::model::execution_context::VisualizationId;
 use crate::model::execution_context::VisualizationUpdateData;
@@ -220,12 +221,16 @@ impl Description {
 /// state with the Language Server.
 ///
 /// As this type wraps asynchronous operations, it should be stored using `Rc` pointer.
+///
+/// The preprocessor chains declared by visualizations are compiled by the Manager's library, so
+/// the same chains declared for many nodes are compiled once.
 #[derive(Debug)]
 pub struct Manager {
     logger:              Logger,
     visualizations:      SharedHashMap<ast::Id, Description>,
     executed_graph:      ExecutedGraph,
     project:             model::Project,
+    preprocessors:       preprocessor::Library,
     notification_sender: futures::channel::mpsc::UnboundedSender<Notification>,
 }
 
@@ -246,6 +251,7 @@ impl Manager {
             visualizations: default(),
             executed_graph,
             project,
+            preprocessors: default(),
             notification_sender,
         };
         (Rc::new(ret), notification_receiver)
@@ -362,12 +368,17 @@ impl Manager {
     fn prepare_visualization(&self, desired: Desired) -> FallibleResult<Visualization> {
         let context_module = desired.metadata.preprocessor.module;
         let resolved_module = self.resolve_context_module(&context_module)?;
-        Ok(Visualization {
+        let chain = desired.metadata.preprocessor.chain.iter().map(|step| preprocessor::Step {
+            name:      step.name.to_string(),
+            arguments: step.arguments.iter().map(|argument| argument.to_string()).collect(),
+        });
+        let visualization = Visualization {
             id:                desired.visualization_id,
             expression_id:     desired.expression_id,
             preprocessor_code: desired.metadata.preprocessor.code.to_string(),
             context_module:    resolved_module,
-        })
+        };
+        visualization.chain_preprocessors(&self.preprocessors, &chain.collect_vec())
     }
 
     /// Remove (set desired state to None) each visualization not attached to any of the `targets`.
@@ -535,6 +546,7 @@ mod tests {
     use futures::future::ready;
     use ide_view::graph_editor::component::visualization::instance::ContextModule;
     use ide_view::graph_editor::component::visualization::instance::PreprocessorConfiguration;
+    use ide_view::graph_editor::component::visualization::instance::PreprocessorStep;
     use std::assert_matches::assert_matches;
     use wasm_bindgen_test::wasm_bindgen_test;
 
@@ -558,6 +570,7 @@ mod tests {
                 preprocessor: PreprocessorConfiguration {
                     module: ContextModule::Specific(self.inner.module_name().to_string().into()),
                     code:   code.into().into(),
+                    chain:  default(),
                 },
             }
         }
@@ -644,7 +657,7 @@ mod tests {
         visualization: &Visualization,
         metadata: &Metadata,
     ) -> bool {
-        let PreprocessorConfiguration { module, code } = &metadata.preprocessor;
+        let PreprocessorConfiguration { module, code, .. } = &metadata.preprocessor;
         visualization.preprocessor_code == code.to_string()
             && visualization.context_module == manager.resolve_context_module(module).unwrap()
     }
@@ -718,4 +731,27 @@ mod tests {
         assert_matches!(requests.expect_next(), ExecutionContextRequest::Attach(vis)
             if matching_metadata(&manager,&vis,&desired_vis_3.metadata));
     }
+
+    #[wasm_bindgen_test]
+    fn attaching_visualization_with_preprocessor_chain() {
+        let fixture = VisOperationsTester::new(Fixture::new());
+        let node_id = fixture.node_id;
+        let mut metadata = fixture.vis_metadata("x -> x.to_json");
+        let sample = PreprocessorStep::new("sample", ["100"]);
+        metadata.preprocessor = metadata.preprocessor.with_chain([sample]);
+        let VisOperationsTester { mut requests, manager, mut inner, is_ready, .. } = fixture;
+        is_ready.replace(true);
+
+        manager.request_visualization(node_id, metadata.clone());
+        inner.run_until_stalled();
+        let expected_code = "x -> (x -> x.to_json) ((x -> x.take_start (100)) (x))";
+        assert_matches!(requests.expect_one(), ExecutionContextRequest::Attach(vis)
+            if vis.preprocessor_code == expected_code);
+
+        let unknown = PreprocessorStep::new("unknown", None::<&str>);
+        metadata.preprocessor = metadata.preprocessor.with_chain([unknown]);
+        manager.request_visualization(node_id, metadata);
+        inner.run_until_stalled();
+        assert_matches!(requests.expect_one(), ExecutionContextRequest::Detach(_));
+    }
 }
@@ -8,6 +8,8 @@ use crate::presenter;
 use crate::presenter::graph::ViewNodeId;
 
 use enso_frp as frp;
+use ensogl::application::shortcut::keymap;
+use ensogl::application::View;
 use ide_view as view;
 use ide_view::project::SearcherParams;
 
@@ -213,6 +215,7 @@ impl Project {
         let graph_controller = self.model.graph_controller.clone_ref();
 
         self.init_analytics()
+            .load_project_keymap()
             .setup_notification_handler()
             .attach_frp_to_values_computed_notifications(graph_controller, values_computed)
     }
@@ -240,6 +243,28 @@ impl Project {
         self
     }
 
+    /// Load the keymap of the opened project, replacing the keymap of the previously opened one.
+    fn load_project_keymap(self) -> Self {
+        let shortcuts = self.model.view.app().shortcuts.clone_ref();
+        shortcuts.unset_keymap(keymap::Layer::Project);
+        let controller = self.model.controller.clone_ref();
+        let logger = self.model.logger.clone_ref();
+        executor::global::spawn(async move {
+            match controller.read_keymap().await {
+                Ok(Some(project_keymap)) => {
+                    let format = keymap::Format::from_extension(&project_keymap.file_name);
+                    if let Some(format) = format {
+                        let contents = &project_keymap.contents;
+                        shortcuts.load_keymap(keymap::Layer::Project, contents, format);
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => warning!(logger, "Failed to read the project keymap: {err}"),
+            }
+        });
+        self
+    }
+
     fn setup_notification_handler(self) -> Self {
         let notifications = self.model.controller.model.subscribe();
         let weak = Rc::downgrade(&self.model);
@@ -44,7 +44,9 @@ class Histogram extends Visualization {
 
     constructor(data) {
         super(data)
-        this.setPreprocessor('here.process_to_json_text', 'Standard.Visualization.Histogram')
+        this.setPreprocessor('here.process_to_json_text', 'Standard.Visualization.Histogram', [
+            { name: 'histogram', arguments: [DEFAULT_NUMBER_OF_BINS.toString()] },
+        ])
         this._dataBins = []
     }
 
@@ -16,8 +16,11 @@ class TableVisualization extends Visualization {
 
     constructor(data) {
         super(data)
-        this.setPreprocessorModule('Standard.Visualization.Table.Visualization')
-        this.setPreprocessorCode(`x -> here.prepare_visualization x 1000`)
+        this.setPreprocessor(
+            `x -> here.prepare_visualization x 1000`,
+            'Standard.Visualization.Table.Visualization',
+            [{ name: 'sample', arguments: ['1000'] }]
+        )
     }
 
     onDataReceived(data) {
@@ -208,7 +208,7 @@ impl Model {
                 self.set_data(&input);
                 Ok(())
             }
-            Data::Binary => Err(DataError::BinaryNotSupported),
+            Data::Binary { .. } => Err(DataError::BinaryNotSupported),
         }
     }
 
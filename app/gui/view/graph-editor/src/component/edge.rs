@@ -17,6 +17,13 @@ use ensogl_hardcoded_theme as theme;
 use nalgebra::Rotation2;
 
 
+// ==============
+// === Export ===
+// ==============
+
+pub mod routing;
+
+
 
 // =================
 // === Constants ===
@@ -689,6 +696,17 @@ impl LayoutLine for front::line::View {
     }
 }
 
+impl back::line::View {
+    /// Lay out the line along an arbitrary segment, used to draw the routed edges.
+    fn layout_segment(&self, from: Vector2<f32>, to: Vector2<f32>) {
+        let delta = to - from;
+        let size = Vector2(LINE_SHAPE_WIDTH, delta.norm() + LINE_SIDES_OVERLAP);
+        self.size.set(size);
+        self.set_position_xy((from + to) / 2.0);
+        self.mod_rotation(|r| r.z = delta.y.atan2(delta.x) - RIGHT_ANGLE);
+    }
+}
+
 impl LayoutLine for back::line::View {
     fn layout_v(&self, start: Vector2<f32>, len: f32) {
         let pos = Vector2(start.x, start.y + len / 2.0);
@@ -1069,6 +1087,9 @@ pub struct Frp {
     pub redraw:          frp::Source,
     pub set_disabled:    frp::Source<bool>,
     pub set_color:       frp::Source<color::Lcha>,
+    /// Route around the nodes, in scene coordinates. If set, the edge is drawn along the route
+    /// instead of the default layout.
+    pub set_route:       frp::Source<Option<routing::Route>>,
 
     pub hover_position: frp::Source<Option<Vector2<f32>>>,
     pub shape_events:   PointerTargetProxy,
@@ -1088,6 +1109,7 @@ impl Frp {
             def hover_position  = source();
             def set_disabled    = source();
             def set_color       = source();
+            def set_route       = source();
         }
         let shape_events = PointerTargetProxy::new(network);
         Self {
@@ -1099,6 +1121,7 @@ impl Frp {
             redraw,
             set_disabled,
             set_color,
+            set_route,
             hover_position,
             shape_events,
         }
@@ -1210,6 +1233,7 @@ impl Edge {
             eval  shape_events.on_mouse_over ((id) hover_target.set(Some(*id)));
             eval_ shape_events.on_mouse_out       (hover_target.set(None));
             eval_ input.redraw                    (model.redraw());
+            eval  input.set_route ((route) model.set_route(route.clone()));
 
 
             // === Colors ===
@@ -1275,6 +1299,10 @@ pub struct EdgeModelData {
     layout_state:   Rc<Cell<LayoutState>>,
     hover_position: Rc<Cell<Option<Vector2<f32>>>>,
     hover_target:   Rc<Cell<Option<display::object::Id>>>,
+    route:          Rc<RefCell<Option<routing::Route>>>,
+    /// Lines drawing the route segments. Unused lines are kept hidden to be reused later.
+    route_lines:    Rc<RefCell<Vec<back::line::View>>>,
+    network:        frp::WeakNetwork,
     scene:          Scene,
 }
 
@@ -1306,6 +1334,9 @@ impl EdgeModelData {
         let hover_position = default();
         let layout_state = Rc::new(Cell::new(LayoutState::UpLeft));
         let hover_target = default();
+        let route = default();
+        let route_lines = default();
+        let network = network.downgrade();
 
         let scene = scene.into();
         Self {
@@ -1323,6 +1354,9 @@ impl EdgeModelData {
             layout_state,
             hover_position,
             hover_target,
+            route,
+            route_lines,
+            network,
             scene,
         }
     }
@@ -1333,12 +1367,14 @@ impl EdgeModelData {
         let color: color::Lcha = color.opaque.into();
         let color_rgba = color::Rgba::from(color);
         self.shapes().iter().for_each(|shape| shape.set_color(color_rgba));
+        self.route_lines.borrow().iter().for_each(|line| line.set_color(color_rgba));
         self.joint.color_rgba.set(color_rgba.into());
     }
 
     fn set_focus_color(&self, color: color::Lcha) {
         let color: color::Lcha = color.opaque.into();
         self.shapes().iter().for_each(|shape| shape.set_color_focus(color.into()));
+        self.route_lines.borrow().iter().for_each(|line| line.set_color_focus(color.into()));
     }
 
     fn base_color(&self, color: color::Lcha, is_disabled: bool) -> color::Lcha {
@@ -1359,9 +1395,72 @@ impl EdgeModelData {
         color::mix(bg_color, color, 0.25)
     }
 
+    fn set_route(&self, route: Option<routing::Route>) {
+        *self.route.borrow_mut() = route;
+    }
+
+    /// Check whether the edge is drawn along a route set with `set_route`. Edges being dragged are
+    /// never routed.
+    pub fn is_routed(&self) -> bool {
+        self.target_attached.get() && self.route.borrow().is_some()
+    }
+
+    fn new_route_line(&self) -> back::line::View {
+        let line = back::line::View::new(Logger::new_sub(&self.logger, "route_line"));
+        line.color_rgba.set(self.back.main_line.color_rgba.get());
+        line.focus_color_rgba.set(self.back.main_line.focus_color_rgba.get());
+        self.display_object.add_child(&line);
+        if let Some(network) = self.network.upgrade() {
+            let frp = &self.frp.shape_events;
+            let event = &line.events;
+            let id = EdgeShape::id(&line);
+            frp::extend! { network
+                eval_ event.mouse_down_primary (frp.on_mouse_down.emit(id));
+                eval_ event.mouse_over (frp.on_mouse_over.emit(id));
+                eval_ event.mouse_out (frp.on_mouse_out.emit(id));
+            }
+        }
+        line
+    }
+
+    /// Lay out the edge along its route, replacing the default shapes. If the edge is not routed,
+    /// restore the default shapes and return `false`, so they can be laid out.
+    fn redraw_route(&self) -> bool {
+        let is_routed = self.is_routed();
+        let mut lines = self.route_lines.borrow_mut();
+        if !is_routed {
+            lines.iter().for_each(|line| line.size.set(Vector2::zero()));
+            if !self.front.has_parent() {
+                self.display_object.add_child(&self.front);
+                self.display_object.add_child(&self.back);
+            }
+            return false;
+        }
+        self.front.unset_parent();
+        self.back.unset_parent();
+        self.joint.size.set(Vector2::zero());
+        let origin = self.display_object.position().xy();
+        let route = self.route.borrow().as_ref().map(|route| route.translated(-origin));
+        let segments = route.iter().flat_map(|route| route.segments()).collect_vec();
+        while lines.len() < segments.len() {
+            lines.push(self.new_route_line());
+        }
+        for (index, line) in lines.iter().enumerate() {
+            match segments.get(index) {
+                Some((from, to)) => line.layout_segment(*from, *to),
+                None => line.size.set(Vector2::zero()),
+            }
+        }
+        true
+    }
+
     /// Redraws the connection.
     #[allow(clippy::cognitive_complexity)]
     pub fn redraw(&self) {
+        if self.redraw_route() {
+            return;
+        }
+
         // === Variables ===
 
         let fg = &self.front;
@@ -1805,7 +1904,7 @@ impl EdgeModelData {
     /// problematic if the start and end of the edge have the same y-position or even if they are
     /// almost level. That is why, we then switch to using the euclidean distance instead.
     pub fn port_to_detach_for_position(&self, point: Vector2<f32>) -> PortType {
-        if self.input_and_output_y_too_close() {
+        if self.is_routed() || self.input_and_output_y_too_close() {
             return self.closest_end_for_point(point);
         }
         let input_port_is_in_upper_half = self.layout_state.get().is_input_above_output();
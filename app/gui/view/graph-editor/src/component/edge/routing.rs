@@ -0,0 +1,804 @@
+//! Obstacle-avoiding routing of edges.
+//!
+//! Given the bounding boxes of the nodes and the connections between them, the router computes a
+//! route for every connection: a polyline going around the nodes instead of under them. The
+//! routes are computed in three steps:
+//! 1. Connections sharing a port are assigned separate lanes spread along the port's side, so they
+//!    do not overlap where they leave or enter the node.
+//! 2. Every connection is routed independently as the cheapest orthogonal path in the sparse grid
+//!    spanned by the obstacle boundaries. The cost is the path length with a penalty for each
+//!    bend.
+//! 3. Parallel segments of different routes running close to each other are bundled into a
+//!    common track, unless that would make them cross an obstacle.
+//!
+//! With the [`Style::Spline`] style the corners of the routes are rounded afterwards.
+//!
+//! The module is purely geometrical and does not depend on the scene, so the routes may be
+//! computed (and tested) without any views.
+
+use crate::prelude::*;
+
+use crate::selection::BoundingBox;
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// Tolerance used when comparing coordinates.
+const EPSILON: f32 = 0.001;
+/// Number of line segments approximating every rounded corner of a [`Style::Spline`] route.
+const CORNER_STEPS: usize = 6;
+/// The cost of a bend grows with its distance from the point halfway between the terminals,
+/// scaled by this factor. It makes the routes of equal length turn in the middle of the free space.
+const CENTERING_WEIGHT: f32 = 0.01;
+/// Unit steps in the grid, indexed by direction. Opposite directions differ in the lowest bit.
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+
+
+// =============
+// === Style ===
+// =============
+
+/// The shape of the computed routes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Style {
+    /// Routes consisting of horizontal and vertical segments only.
+    Orthogonal,
+    /// Orthogonal routes with rounded corners.
+    Spline,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::Orthogonal
+    }
+}
+
+
+
+// ================
+// === Terminal ===
+// ================
+
+/// The side of a node through which an edge leaves or enters it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[allow(missing_docs)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Side {
+    /// The unit vector pointing outwards of the node through this side.
+    pub fn normal(self) -> Vector2 {
+        match self {
+            Side::Top => Vector2(0.0, 1.0),
+            Side::Bottom => Vector2(0.0, -1.0),
+            Side::Left => Vector2(-1.0, 0.0),
+            Side::Right => Vector2(1.0, 0.0),
+        }
+    }
+
+    /// The unit vector along this side, pointing towards larger coordinates.
+    fn tangent(self) -> Vector2 {
+        match self {
+            Side::Top | Side::Bottom => Vector2(1.0, 0.0),
+            Side::Left | Side::Right => Vector2(0.0, 1.0),
+        }
+    }
+}
+
+/// An end of a routed connection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Terminal {
+    /// The position of the port. It may lay inside the node.
+    pub position: Vector2,
+    /// The side of the node through which the route leaves (or enters) it.
+    pub side:     Side,
+    /// The index of the node's bounding box in the obstacles, if the terminal belongs to a node.
+    pub node:     Option<usize>,
+}
+
+impl Terminal {
+    /// Constructor.
+    pub fn new(position: Vector2, side: Side, node: Option<usize>) -> Self {
+        Self { position, side, node }
+    }
+
+    /// Key identifying the port of this terminal, used to find connections sharing a port.
+    fn port_key(&self, is_target: bool) -> (bool, Option<usize>, Side, i64, i64) {
+        let x = self.position.x.round() as i64;
+        let y = self.position.y.round() as i64;
+        (is_target, self.node, self.side, x, y)
+    }
+}
+
+/// A connection to be routed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(missing_docs)]
+pub struct Connection {
+    pub source: Terminal,
+    pub target: Terminal,
+}
+
+impl Connection {
+    /// Constructor.
+    pub fn new(source: Terminal, target: Terminal) -> Self {
+        Self { source, target }
+    }
+}
+
+
+
+// ===============
+// === Options ===
+// ===============
+
+/// Parameters of the routing.
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    /// The shape of the routes.
+    pub style:           Style,
+    /// The minimum distance between a route and a node it does not connect.
+    pub margin:          f32,
+    /// The cost of a single bend, in units of route length.
+    pub bend_penalty:    f32,
+    /// The distance between lanes of connections sharing a port.
+    pub lane_spacing:    f32,
+    /// Parallel segments of different routes closer than this distance are bundled.
+    pub bundle_distance: f32,
+    /// The radius of rounded corners of [`Style::Spline`] routes.
+    pub corner_radius:   f32,
+    /// Only the obstacles this close to the terminals are considered at first. If the route found
+    /// this way crosses any farther obstacle, the routing is repeated with all the obstacles.
+    pub search_margin:   f32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            style:           default(),
+            margin:          10.0,
+            bend_penalty:    40.0,
+            lane_spacing:    8.0,
+            bundle_distance: 12.0,
+            corner_radius:   10.0,
+            search_margin:   200.0,
+        }
+    }
+}
+
+
+
+// =============
+// === Route ===
+// =============
+
+/// The route of a single connection: a polyline from the source terminal to the target terminal.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Route {
+    /// The consecutive points of the route, including both terminals.
+    pub points: Vec<Vector2>,
+}
+
+impl Route {
+    /// The segments of the route, from the source to the target.
+    pub fn segments(&self) -> impl Iterator<Item = (Vector2, Vector2)> + '_ {
+        self.points.iter().copied().tuple_windows()
+    }
+
+    /// The total length of the route.
+    pub fn length(&self) -> f32 {
+        self.segments().map(|(from, to)| (to - from).norm()).sum()
+    }
+
+    /// Check whether the route passes through the interior of the given bounding box.
+    pub fn crosses(&self, bounding_box: &BoundingBox) -> bool {
+        self.segments().any(|(from, to)| segment_crosses(from, to, bounding_box))
+    }
+
+    /// Return the route moved by the given offset.
+    pub fn translated(&self, offset: Vector2) -> Self {
+        Self { points: self.points.iter().map(|point| point + offset).collect() }
+    }
+}
+
+
+
+// ===============
+// === Routing ===
+// ===============
+
+/// Compute routes of the given connections, avoiding the obstacles. The returned routes are in the
+/// same order as the connections.
+pub fn route(
+    obstacles: &[BoundingBox],
+    connections: &[Connection],
+    options: &Options,
+) -> Vec<Route> {
+    let obstacles = obstacles.iter().map(|bbox| inflated(bbox, options.margin)).collect_vec();
+    let router = Router { obstacles: &obstacles, options };
+    let terminals = assign_lanes(connections, options.lane_spacing);
+    let mut paths = terminals
+        .into_iter()
+        .map(|(source, target)| router.route_connection(source, target))
+        .collect_vec();
+    router.bundle(&mut paths);
+    paths
+        .into_iter()
+        .map(|path| {
+            let points = simplified(path.points);
+            let points = match options.style {
+                Style::Orthogonal => points,
+                Style::Spline => rounded(&points, options.corner_radius),
+            };
+            Route { points }
+        })
+        .collect()
+}
+
+/// Spread the terminals of connections sharing a port along the port's side. The lanes are
+/// ordered by the position of the opposite ends of the connections, so the connections leaving
+/// a port do not cross each other. Returns the source and target terminal of every connection.
+fn assign_lanes(connections: &[Connection], spacing: f32) -> Vec<(Terminal, Terminal)> {
+    let mut terminals = connections.iter().map(|c| (c.source, c.target)).collect_vec();
+    let mut ports: HashMap<_, Vec<usize>> = default();
+    for (index, connection) in connections.iter().enumerate() {
+        ports.entry(connection.source.port_key(false)).or_default().push(index);
+        ports.entry(connection.target.port_key(true)).or_default().push(index);
+    }
+    for ((is_target, _, side, _, _), mut members) in ports {
+        if members.len() < 2 {
+            continue;
+        }
+        let tangent = side.tangent();
+        let opposite_end = |index: usize| {
+            let connection = &connections[index];
+            let end = if is_target { connection.source } else { connection.target };
+            end.position.dot(&tangent)
+        };
+        members.sort_by(|a, b| opposite_end(*a).total_cmp(&opposite_end(*b)));
+        let center = (members.len() - 1) as f32 / 2.0;
+        for (lane, index) in members.into_iter().enumerate() {
+            let offset = tangent * (lane as f32 - center) * spacing;
+            let (source, target) = &mut terminals[index];
+            let terminal = if is_target { target } else { source };
+            terminal.position += offset;
+        }
+    }
+    terminals
+}
+
+
+// === Path ===
+
+/// A route being computed, together with the obstacles it must avoid.
+#[derive(Clone, Debug)]
+struct Path {
+    points:             Vec<Vector2>,
+    /// The obstacles the interior segments must not cross.
+    blocking:           Vec<usize>,
+    /// The obstacles the first and the last segment must not cross. The nodes being connected
+    /// are not among them, as the terminals usually lay inside these nodes.
+    terminals_blocking: Vec<usize>,
+}
+
+
+// === Router ===
+
+#[derive(Clone, Copy, Debug)]
+struct Router<'a> {
+    /// The obstacles inflated by the margin.
+    obstacles: &'a [BoundingBox],
+    options:   &'a Options,
+}
+
+impl<'a> Router<'a> {
+    /// The point at the margin of the terminal's node, where the route leaves (or enters) it.
+    fn stub(&self, terminal: Terminal) -> Vector2 {
+        let position = terminal.position;
+        match terminal.node.and_then(|index| self.obstacles.get(index)) {
+            Some(bbox) => match terminal.side {
+                Side::Top => Vector2(position.x, bbox.top()),
+                Side::Bottom => Vector2(position.x, bbox.bottom()),
+                Side::Left => Vector2(bbox.left(), position.y),
+                Side::Right => Vector2(bbox.right(), position.y),
+            },
+            None => position + terminal.side.normal() * self.options.margin,
+        }
+    }
+
+    fn route_connection(&self, source: Terminal, target: Terminal) -> Path {
+        let source_stub = self.stub(source);
+        let target_stub = self.stub(target);
+        // Obstacles overlapping the stubs (e.g. nodes overlapping the connected ones) cannot be
+        // avoided, so they are ignored.
+        let blocking = (0..self.obstacles.len())
+            .filter(|&index| {
+                let bbox = &self.obstacles[index];
+                !strictly_contains(bbox, source_stub) && !strictly_contains(bbox, target_stub)
+            })
+            .collect_vec();
+        let connected = [source.node, target.node];
+        let terminals_blocking =
+            blocking.iter().copied().filter(|index| !connected.contains(&Some(*index))).collect();
+        let mut window = BoundingBox::from_corners(source_stub, target_stub);
+        window.grow_x(2.0 * self.options.search_margin);
+        window.grow_y(2.0 * self.options.search_margin);
+        let nearby = blocking
+            .iter()
+            .copied()
+            .filter(|&index| self.obstacles[index].intersects(&window))
+            .collect_vec();
+        let start = source.side.normal();
+        let end = -target.side.normal();
+        let search =
+            |obstacles: &[usize]| self.search(source_stub, start, target_stub, end, obstacles);
+        let is_clear = |points: &Vec<Vector2>| self.is_clear(points, &blocking);
+        let found = search(nearby.as_slice()).filter(is_clear).or_else(|| search(&blocking));
+        let middle = found.unwrap_or_else(|| {
+            vec![source_stub, Vector2(source_stub.x, target_stub.y), target_stub]
+        });
+        let points = iter::once(source.position)
+            .chain(middle)
+            .chain(iter::once(target.position))
+            .collect_vec();
+        let points = simplified(points);
+        Path { points, blocking, terminals_blocking }
+    }
+
+    /// Check whether the polyline does not cross any of the given obstacles.
+    fn is_clear(&self, points: &[Vector2], obstacles: &[usize]) -> bool {
+        points.iter().copied().tuple_windows().all(|(from, to)| {
+            obstacles.iter().all(|&index| !segment_crosses(from, to, &self.obstacles[index]))
+        })
+    }
+
+    /// Check whether no segment of the path crosses any of its blocking obstacles.
+    fn is_valid(&self, path: &Path, points: &[Vector2]) -> bool {
+        let last = points.len().saturating_sub(2);
+        points.iter().copied().tuple_windows().enumerate().all(|(index, (from, to))| {
+            let is_terminal = index == 0 || index == last;
+            let blocking = if is_terminal { &path.terminals_blocking } else { &path.blocking };
+            blocking.iter().all(|&obstacle| !segment_crosses(from, to, &self.obstacles[obstacle]))
+        })
+    }
+
+    /// Find the cheapest orthogonal path from `from` to `to` avoiding the given obstacles. The
+    /// path leaves `from` in the `start` direction and enters `to` in the `end` direction; any
+    /// other direction costs an additional bend. Returns `None` if the points are not connected.
+    fn search(
+        &self,
+        from: Vector2,
+        start: Vector2,
+        to: Vector2,
+        end: Vector2,
+        obstacles: &[usize],
+    ) -> Option<Vec<Vector2>> {
+        let obstacles = obstacles.iter().map(|&index| &self.obstacles[index]).collect_vec();
+        let center = (from + to) / 2.0;
+        let mut xs = vec![from.x, to.x, center.x];
+        let mut ys = vec![from.y, to.y, center.y];
+        for bbox in &obstacles {
+            xs.push(bbox.left());
+            xs.push(bbox.right());
+            ys.push(bbox.bottom());
+            ys.push(bbox.top());
+        }
+        let xs = sorted_coordinates(xs);
+        let ys = sorted_coordinates(ys);
+        let find = |coordinates: &[f32], value: f32| {
+            coordinates.iter().position(|coordinate| (coordinate - value).abs() < EPSILON)
+        };
+        let rows = ys.len();
+        let start_cell = find(&xs, from.x)? * rows + find(&ys, from.y)?;
+        let end_cell = find(&xs, to.x)? * rows + find(&ys, to.y)?;
+        let point = |cell: usize| Vector2(xs[cell / rows], ys[cell % rows]);
+        let is_free = |from: Vector2, to: Vector2| {
+            obstacles.iter().all(|bbox| !segment_crosses(from, to, bbox))
+        };
+        let bend_cost = |at: Vector2| {
+            let offset = at - center;
+            self.options.bend_penalty + (offset.x.abs() + offset.y.abs()) * CENTERING_WEIGHT
+        };
+
+        // The search state is a cell together with the direction the path entered it.
+        let state_count = xs.len() * rows * DIRECTIONS.len();
+        let mut costs = vec![f32::INFINITY; state_count];
+        let mut previous = vec![usize::MAX; state_count];
+        let start_state = start_cell * DIRECTIONS.len() + direction_index(start);
+        let end_direction = direction_index(end);
+        costs[start_state] = 0.0;
+        let mut queue = BinaryHeap::new();
+        queue.push(Candidate { cost: 0.0, state: start_state });
+        let mut best: Option<(f32, usize)> = None;
+        while let Some(Candidate { cost, state }) = queue.pop() {
+            if cost > costs[state] {
+                continue;
+            }
+            if best.map_or(false, |(best_cost, _)| cost >= best_cost) {
+                break;
+            }
+            let cell = state / DIRECTIONS.len();
+            let direction = state % DIRECTIONS.len();
+            let position = point(cell);
+            if cell == end_cell {
+                let bend = if direction == end_direction { 0.0 } else { bend_cost(position) };
+                if best.map_or(true, |(best_cost, _)| cost + bend < best_cost) {
+                    best = Some((cost + bend, state));
+                }
+                continue;
+            }
+            let column = (cell / rows) as isize;
+            let row = (cell % rows) as isize;
+            for (next_direction, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let is_reversal = next_direction == direction ^ 1;
+                let next_column = column + dx;
+                let next_row = row + dy;
+                let column_exists = (0..xs.len() as isize).contains(&next_column);
+                let row_exists = (0..rows as isize).contains(&next_row);
+                if is_reversal || !column_exists || !row_exists {
+                    continue;
+                }
+                let next_cell = next_column as usize * rows + next_row as usize;
+                let next_position = point(next_cell);
+                if !is_free(position, next_position) {
+                    continue;
+                }
+                let bend = if next_direction == direction { 0.0 } else { bend_cost(position) };
+                let next_cost = cost + (next_position - position).norm() + bend;
+                let next_state = next_cell * DIRECTIONS.len() + next_direction;
+                if next_cost < costs[next_state] {
+                    costs[next_state] = next_cost;
+                    previous[next_state] = state;
+                    queue.push(Candidate { cost: next_cost, state: next_state });
+                }
+            }
+        }
+
+        let (_, mut state) = best?;
+        let mut cells = vec![state / DIRECTIONS.len()];
+        while state != start_state {
+            state = previous[state];
+            cells.push(state / DIRECTIONS.len());
+        }
+        Some(cells.into_iter().rev().map(point).collect())
+    }
+
+    /// Move parallel interior segments of different paths running close to each other onto their
+    /// average coordinate. A segment is not moved if that would make its path cross an obstacle.
+    fn bundle(&self, paths: &mut [Path]) {
+        let mut segments = paths
+            .iter()
+            .enumerate()
+            .flat_map(|(path_index, path)| {
+                let interior = 1..path.points.len().saturating_sub(2);
+                interior.filter_map(move |index| {
+                    ParallelSegment::new(
+                        path_index,
+                        index,
+                        path.points[index],
+                        path.points[index + 1],
+                    )
+                })
+            })
+            .collect_vec();
+        segments.sort_by(|a, b| {
+            a.is_horizontal.cmp(&b.is_horizontal).then(a.coordinate.total_cmp(&b.coordinate))
+        });
+
+        let mut bundles: Vec<Vec<ParallelSegment>> = default();
+        for segment in segments {
+            let joins_last = bundles.last().map_or(false, |bundle: &Vec<ParallelSegment>| {
+                let first = &bundle[0];
+                let is_close =
+                    segment.coordinate - first.coordinate <= self.options.bundle_distance;
+                let is_parallel = segment.is_horizontal == first.is_horizontal;
+                let overlaps = bundle.iter().any(|other| segment.overlaps(other));
+                is_close && is_parallel && overlaps
+            });
+            match bundles.last_mut() {
+                Some(bundle) if joins_last => bundle.push(segment),
+                _ => bundles.push(vec![segment]),
+            }
+        }
+
+        for bundle in bundles {
+            let is_shared = bundle.iter().map(|segment| segment.path).unique().count() > 1;
+            if !is_shared {
+                continue;
+            }
+            let track =
+                bundle.iter().map(|segment| segment.coordinate).sum::<f32>() / bundle.len() as f32;
+            for segment in bundle {
+                let path = &paths[segment.path];
+                let mut points = path.points.clone();
+                for point in &mut points[segment.index..=segment.index + 1] {
+                    if segment.is_horizontal {
+                        point.y = track;
+                    } else {
+                        point.x = track;
+                    }
+                }
+                if self.is_valid(path, &points) {
+                    paths[segment.path].points = points;
+                }
+            }
+        }
+    }
+}
+
+
+// === ParallelSegment ===
+
+/// An axis-aligned segment of a path, considered for bundling.
+#[derive(Clone, Copy, Debug)]
+struct ParallelSegment {
+    path:          usize,
+    /// The index of the segment's first point in the path.
+    index:         usize,
+    is_horizontal: bool,
+    /// The y coordinate of a horizontal segment, or the x coordinate of a vertical one.
+    coordinate:    f32,
+    /// The extent of the segment along its direction.
+    range:         (f32, f32),
+}
+
+impl ParallelSegment {
+    fn new(path: usize, index: usize, from: Vector2, to: Vector2) -> Option<Self> {
+        let is_horizontal = (from.y - to.y).abs() < EPSILON;
+        let is_vertical = (from.x - to.x).abs() < EPSILON;
+        if is_horizontal == is_vertical {
+            return None;
+        }
+        let (coordinate, range) = if is_horizontal {
+            (from.y, (from.x.min(to.x), from.x.max(to.x)))
+        } else {
+            (from.x, (from.y.min(to.y), from.y.max(to.y)))
+        };
+        Some(Self { path, index, is_horizontal, coordinate, range })
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.range.0 < other.range.1 - EPSILON && other.range.0 < self.range.1 - EPSILON
+    }
+}
+
+
+// === Candidate ===
+
+/// A search state in the priority queue, ordered so the cheapest candidate is popped first.
+#[derive(Clone, Copy, Debug)]
+struct Candidate {
+    cost:  f32,
+    state: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then(other.state.cmp(&self.state))
+    }
+}
+
+
+
+// ================
+// === Geometry ===
+// ================
+
+fn inflated(bbox: &BoundingBox, margin: f32) -> BoundingBox {
+    let mut bbox = *bbox;
+    bbox.grow_x(2.0 * margin);
+    bbox.grow_y(2.0 * margin);
+    bbox
+}
+
+fn strictly_contains(bbox: &BoundingBox, point: Vector2) -> bool {
+    let inside_x = point.x > bbox.left() + EPSILON && point.x < bbox.right() - EPSILON;
+    let inside_y = point.y > bbox.bottom() + EPSILON && point.y < bbox.top() - EPSILON;
+    inside_x && inside_y
+}
+
+/// Check whether the segment passes through the interior of the bounding box. Segments touching
+/// the boundary only do not cross it. Uses the Liang–Barsky clipping.
+fn segment_crosses(from: Vector2, to: Vector2, bbox: &BoundingBox) -> bool {
+    let left = bbox.left() + EPSILON;
+    let right = bbox.right() - EPSILON;
+    let bottom = bbox.bottom() + EPSILON;
+    let top = bbox.top() - EPSILON;
+    if left >= right || bottom >= top {
+        return false;
+    }
+    let delta = to - from;
+    let constraints = [
+        (-delta.x, from.x - left),
+        (delta.x, right - from.x),
+        (-delta.y, from.y - bottom),
+        (delta.y, top - from.y),
+    ];
+    let mut enter = 0.0_f32;
+    let mut exit = 1.0_f32;
+    for (direction, distance) in constraints {
+        if direction.abs() < f32::EPSILON {
+            if distance < 0.0 {
+                return false;
+            }
+        } else if direction < 0.0 {
+            enter = enter.max(distance / direction);
+        } else {
+            exit = exit.min(distance / direction);
+        }
+    }
+    enter < exit
+}
+
+fn direction_index(direction: Vector2) -> usize {
+    match (direction.x.abs() >= direction.y.abs(), direction.x > 0.0, direction.y > 0.0) {
+        (true, true, _) => 0,
+        (true, false, _) => 1,
+        (false, _, true) => 2,
+        (false, _, false) => 3,
+    }
+}
+
+fn sorted_coordinates(mut coordinates: Vec<f32>) -> Vec<f32> {
+    coordinates.sort_by(|a, b| a.total_cmp(b));
+    coordinates.dedup_by(|a, b| (*a - *b).abs() < EPSILON);
+    coordinates
+}
+
+/// Remove repeated points and the middle points of straight orthogonal runs.
+fn simplified(points: Vec<Vector2>) -> Vec<Vector2> {
+    let mut result: Vec<Vector2> = Vec::with_capacity(points.len());
+    for point in points {
+        if result.last().map_or(false, |last| (last - point).norm() < EPSILON) {
+            continue;
+        }
+        if let [.., before, last] = result.as_slice() {
+            let is_vertical_run =
+                (before.x - last.x).abs() < EPSILON && (last.x - point.x).abs() < EPSILON;
+            let is_horizontal_run =
+                (before.y - last.y).abs() < EPSILON && (last.y - point.y).abs() < EPSILON;
+            if is_vertical_run || is_horizontal_run {
+                result.pop();
+            }
+        }
+        result.push(point);
+    }
+    result
+}
+
+/// Replace the corners of the polyline with quadratic curves approximated by line segments. The
+/// curves never extend beyond the half of the adjacent segments.
+fn rounded(points: &[Vector2], radius: f32) -> Vec<Vector2> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut result = vec![points[0]];
+    for (previous, corner, next) in points.iter().copied().tuple_windows() {
+        let incoming = corner - previous;
+        let outgoing = next - corner;
+        let radius = radius.min(incoming.norm() / 2.0).min(outgoing.norm() / 2.0);
+        if radius < EPSILON {
+            result.push(corner);
+            continue;
+        }
+        let start = corner - incoming.normalize() * radius;
+        let end = corner + outgoing.normalize() * radius;
+        for step in 0..=CORNER_STEPS {
+            let t = step as f32 / CORNER_STEPS as f32;
+            let point =
+                start * (1.0 - t).powi(2) + corner * (2.0 * (1.0 - t) * t) + end * t.powi(2);
+            result.push(point);
+        }
+    }
+    result.extend(points.last().copied());
+    result
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(x: f32, y: f32) -> BoundingBox {
+        BoundingBox::from_corners(Vector2(x - 50.0, y - 14.0), Vector2(x + 50.0, y + 14.0))
+    }
+
+    fn connection(nodes: &[BoundingBox], source: usize, target: usize) -> Connection {
+        let center = |bbox: &BoundingBox| {
+            Vector2((bbox.left() + bbox.right()) / 2.0, (bbox.top() + bbox.bottom()) / 2.0)
+        };
+        let source = Terminal::new(center(&nodes[source]), Side::Bottom, Some(source));
+        let target = Terminal::new(center(&nodes[target]), Side::Top, Some(target));
+        Connection::new(source, target)
+    }
+
+    fn is_orthogonal(route: &Route) -> bool {
+        route
+            .segments()
+            .all(|(from, to)| (from.x - to.x).abs() < EPSILON || (from.y - to.y).abs() < EPSILON)
+    }
+
+    #[test]
+    fn routing_around_obstacles() {
+        let nodes = [node(0.0, 0.0), node(0.0, -300.0)];
+        let connections = [connection(&nodes, 0, 1)];
+        let routes = route(&nodes, &connections, &default());
+        assert_eq!(routes[0].points, vec![Vector2(0.0, 0.0), Vector2(0.0, -300.0)]);
+
+        let obstacle = BoundingBox::from_corners(Vector2(-60.0, -170.0), Vector2(60.0, -130.0));
+        let nodes = [nodes[0], nodes[1], obstacle];
+        for style in [Style::Orthogonal, Style::Spline] {
+            let options = Options { style, ..default() };
+            let routes = route(&nodes, &connections, &options);
+            let route = &routes[0];
+            assert!(!route.crosses(&obstacle), "{style:?} route {route:?} crosses the obstacle.");
+            assert_eq!(route.points.first(), Some(&Vector2(0.0, 0.0)));
+            assert_eq!(route.points.last(), Some(&Vector2(0.0, -300.0)));
+            assert!(route.length() > 300.0);
+            assert_eq!(is_orthogonal(route), style == Style::Orthogonal);
+        }
+    }
+
+    #[test]
+    fn assigning_lanes() {
+        let nodes = [node(0.0, 0.0), node(300.0, -300.0), node(-300.0, -300.0), node(0.0, -300.0)];
+        let connections = (1..4).map(|target| connection(&nodes, 0, target)).collect_vec();
+        let routes = route(&nodes, &connections, &default());
+        let starts = routes.iter().map(|route| route.points[0]).collect_vec();
+        assert_eq!(starts, vec![Vector2(8.0, 0.0), Vector2(-8.0, 0.0), Vector2(0.0, 0.0)]);
+        for (route, connection) in routes.iter().zip(&connections) {
+            assert!(is_orthogonal(route));
+            let target = connection.target.node;
+            let others = (1..4).filter(|&index| Some(index) != target);
+            assert!(others.into_iter().all(|index| !route.crosses(&nodes[index])));
+        }
+    }
+
+    #[test]
+    fn bundling_parallel_segments() {
+        let nodes = [node(0.0, 0.0), node(400.0, -300.0), node(-300.0, -10.0), node(100.0, -296.0)];
+        let connections = [connection(&nodes, 0, 1), connection(&nodes, 2, 3)];
+        let horizontal_tracks = |options: &Options| {
+            route(&nodes, &connections, options)
+                .iter()
+                .map(|route| {
+                    assert_eq!(route.points.len(), 4, "Unexpected route {route:?}.");
+                    route.points[1].y
+                })
+                .collect_vec()
+        };
+        let unbundled = horizontal_tracks(&Options { bundle_distance: 0.0, ..default() });
+        assert_eq!(unbundled, vec![-150.0, -153.0]);
+        let bundled = horizontal_tracks(&default());
+        assert_eq!(bundled, vec![-151.5, -151.5]);
+    }
+}
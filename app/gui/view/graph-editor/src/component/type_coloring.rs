@@ -9,6 +9,7 @@ use ensogl::data::color;
 use ensogl::display::shape::StyleWatch;
 use ensogl::display::style::data::DataMatch;
 use ensogl_hardcoded_theme as theme;
+use span_tree::type_compatibility;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
 
@@ -66,12 +67,15 @@ pub fn compute_for_selection(tp: Option<&Type>, styles: &StyleWatch) -> color::L
     opt_color.unwrap_or_else(|| styles.get_color(theme::code::types::any::selection).into())
 }
 
-/// Computes LCH hue value based on incoming type information.
+/// Computes LCH hue value based on incoming type information. The hue depends only on the type's
+/// fully qualified base name, the key compared by [`type_compatibility::same_type`], so the types
+/// with the same module path get the same color regardless of their parameters.
 fn auto_hue(tp: &Type, styles: &StyleWatch) -> f32 {
     // Defines how many hue values we can have based on our incoming type name.
     let hue_steps = styles.get_number_or(theme::code::types::hue_steps, 512.0);
     let hue_shift = styles.get_number_or(theme::code::types::hue_shift, 0.0);
-    (hash(tp) % (hue_steps as u64)) as f32 / hue_steps + hue_shift
+    let name = type_compatibility::base_name(tp.as_str());
+    (hash(name) % (hue_steps as u64)) as f32 / hue_steps + hue_shift
 }
 
 /// Compute the hash of the type for use in the `compute` function.
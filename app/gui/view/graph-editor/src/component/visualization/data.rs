@@ -3,6 +3,13 @@
 use crate::prelude::*;
 
 
+// ==============
+// === Export ===
+// ==============
+
+pub mod columnar;
+
+
 
 // ============
 // === Json ===
@@ -29,6 +36,35 @@ impl From<serde_json::Value> for Json {
     }
 }
 
+
+
+// ==============
+// === Binary ===
+// ==============
+
+/// Binary data with a fast clone operation. Used for transmitting visualization data via FRP
+/// networks.
+#[derive(Clone, CloneRef, Debug, Default)]
+pub struct Binary {
+    rc: Rc<Vec<u8>>,
+}
+
+impl Deref for Binary {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.rc
+    }
+}
+
+impl From<Vec<u8>> for Binary {
+    fn from(t: Vec<u8>) -> Self {
+        let rc = Rc::new(t);
+        Self { rc }
+    }
+}
+
+
+
 // ===================
 // === Data Format ===
 // ====================
@@ -77,7 +113,19 @@ impl Default for Format {
 #[allow(missing_docs)]
 pub enum Data {
     Json { content: Json },
-    Binary, // TODO replace with actual binary data stream.
+    Binary { content: Binary },
+}
+
+impl Data {
+    /// Decode the binary data as a chunk of a table in the columnar format. See the `columnar`
+    /// module to learn more.
+    pub fn columnar_chunk(&self) -> Result<columnar::Chunk, DataError> {
+        match self {
+            Self::Binary { content } =>
+                columnar::Chunk::decode(content).map_err(|_| DataError::InvalidDataType),
+            Self::Json { .. } => Err(DataError::InvalidDataType),
+        }
+    }
 }
 
 impl Default for Data {
@@ -94,6 +142,13 @@ impl From<serde_json::Value> for Data {
     }
 }
 
+impl From<Vec<u8>> for Data {
+    fn from(t: Vec<u8>) -> Self {
+        let content = t.into();
+        Self::Binary { content }
+    }
+}
+
 
 
 // ==============
@@ -104,7 +159,7 @@ impl From<serde_json::Value> for Data {
 /// violates some other assumption of the visualization.
 #[derive(Copy, Clone, Debug)]
 pub enum DataError {
-    /// Visualization received a binary data package, which is currently not supported.
+    /// Visualization received a binary data package, which it does not support.
     BinaryNotSupported,
     /// Indicates that that the provided data type does not match the expected data format.
     InvalidDataType,
@@ -0,0 +1,536 @@
+//! Columnar binary encoding of tabular visualization data.
+//!
+//! Sending large tables as JSON is slow, both because of the size of the JSON text and the cost of
+//! parsing it. The columnar format stores every column as a contiguous, typed array, similarly to
+//! Apache Arrow, so it can be decoded without parsing individual values and passed to JavaScript as
+//! typed arrays. Large tables can be split into chunks of rows, which are sent as separate
+//! visualization updates, so the visualization can show the first rows before the whole table is
+//! received. The `Assembler` merges the received chunks, so the visualizations get all rows
+//! received so far.
+//!
+//! All numbers are little-endian. A message consists of the following parts:
+//! ```text
+//! magic:        4 bytes, "ECOL"
+//! version:      u8
+//! chunk_index:  u32
+//! chunk_count:  u32
+//! row_offset:   u64, the index of the first row of this chunk in the whole table
+//! row_count:    u32
+//! column_count: u32
+//! columns:      column_count times:
+//!     name_length: u32
+//!     name:        name_length bytes of UTF-8 text
+//!     type:        u8, 0 = float (f64), 1 = integer (i64), 2 = boolean, 3 = text
+//!     has_nulls:   u8, 0 or 1
+//!     validity:    if has_nulls, a bitmap of ceil(row_count / 8) bytes, bit set for valid values
+//!     values:      float and integer: row_count * 8 bytes
+//!                  boolean: a bitmap of ceil(row_count / 8) bytes
+//!                  text: (row_count + 1) u32 offsets, followed by offsets[row_count] bytes of
+//!                        UTF-8 text
+//! ```
+
+use crate::prelude::*;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// Bytes at the beginning of every columnar message.
+pub const MAGIC: &[u8; 4] = b"ECOL";
+
+/// The version of the format supported by this module.
+pub const VERSION: u8 = 1;
+
+const FLOAT_TAG: u8 = 0;
+const INTEGER_TAG: u8 = 1;
+const BOOLEAN_TAG: u8 = 2;
+const TEXT_TAG: u8 = 3;
+
+
+
+// =============
+// === Error ===
+// =============
+
+/// Errors which can occur when decoding or assembling columnar data.
+#[derive(Clone, Debug, Fail, PartialEq)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[fail(display = "The data is not a columnar message.")]
+    NotColumnar,
+    #[fail(display = "Unsupported columnar format version {}.", _0)]
+    UnsupportedVersion(u8),
+    #[fail(display = "Unexpected end of columnar data at byte {}.", _0)]
+    UnexpectedEnd(usize),
+    #[fail(display = "Unknown type {} of column \"{}\".", tag, column)]
+    UnknownColumnType { column: String, tag: u8 },
+    #[fail(display = "Invalid UTF-8 text in columnar data at byte {}.", _0)]
+    InvalidUtf8(usize),
+    #[fail(display = "Invalid text offsets in column \"{}\".", _0)]
+    InvalidOffsets(String),
+    #[fail(display = "Received chunk {} while chunk {} was expected.", received, expected)]
+    UnexpectedChunk { received: u32, expected: u32 },
+    #[fail(display = "The columns of the chunk do not match the columns of the table.")]
+    SchemaMismatch,
+}
+
+/// Checks whether the data is a columnar message.
+pub fn is_columnar(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+
+
+// ==============
+// === Column ===
+// ==============
+
+/// Values of a single column.
+#[derive(Clone, Debug, PartialEq)]
+#[allow(missing_docs)]
+pub enum ColumnData {
+    Float(Vec<f64>),
+    Integer(Vec<i64>),
+    Boolean(Vec<bool>),
+    Text(Vec<String>),
+}
+
+impl ColumnData {
+    /// The number of values.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Float(values) => values.len(),
+            Self::Integer(values) => values.len(),
+            Self::Boolean(values) => values.len(),
+            Self::Text(values) => values.len(),
+        }
+    }
+
+    /// Checks whether the column has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            Self::Float(_) => FLOAT_TAG,
+            Self::Integer(_) => INTEGER_TAG,
+            Self::Boolean(_) => BOOLEAN_TAG,
+            Self::Text(_) => TEXT_TAG,
+        }
+    }
+
+    /// Append values of the other column of the same type.
+    fn append(&mut self, other: ColumnData) -> Result<(), Error> {
+        match (self, other) {
+            (Self::Float(values), Self::Float(other)) => values.extend(other),
+            (Self::Integer(values), Self::Integer(other)) => values.extend(other),
+            (Self::Boolean(values), Self::Boolean(other)) => values.extend(other),
+            (Self::Text(values), Self::Text(other)) => values.extend(other),
+            _ => return Err(Error::SchemaMismatch),
+        }
+        Ok(())
+    }
+}
+
+/// A named column of a table. The `validity` contains `false` for every null value. If it is
+/// `None`, all values are valid.
+#[derive(Clone, Debug, PartialEq)]
+#[allow(missing_docs)]
+pub struct Column {
+    pub name:     String,
+    pub data:     ColumnData,
+    pub validity: Option<Vec<bool>>,
+}
+
+impl Column {
+    /// Constructor of a column without null values.
+    pub fn new(name: impl Into<String>, data: ColumnData) -> Self {
+        let name = name.into();
+        let validity = None;
+        Self { name, data, validity }
+    }
+
+    /// Checks whether the value in the given row is not null. Returns `false` for rows out of
+    /// range.
+    pub fn is_valid(&self, row: usize) -> bool {
+        match &self.validity {
+            Some(validity) => validity.get(row).copied().unwrap_or(false),
+            None => row < self.data.len(),
+        }
+    }
+
+    fn append(&mut self, other: Column) -> Result<(), Error> {
+        if self.name != other.name {
+            return Err(Error::SchemaMismatch);
+        }
+        let (len, other_len) = (self.data.len(), other.data.len());
+        self.validity = match (self.validity.take(), other.validity) {
+            (None, None) => None,
+            (validity, other) => {
+                let mut validity = validity.unwrap_or_else(|| vec![true; len]);
+                validity.extend(other.unwrap_or_else(|| vec![true; other_len]));
+                Some(validity)
+            }
+        };
+        self.data.append(other.data)
+    }
+}
+
+
+
+// =============
+// === Table ===
+// =============
+
+/// A table of columns of equal length.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[allow(missing_docs)]
+pub struct Table {
+    pub row_count: usize,
+    pub columns:   Vec<Column>,
+}
+
+impl Table {
+    /// Constructor. All columns must have the same number of values.
+    pub fn new(columns: Vec<Column>) -> Self {
+        let row_count = columns.first().map(|column| column.data.len()).unwrap_or_default();
+        Self { row_count, columns }
+    }
+
+    /// The column with the given name.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Append rows of the other table with the same columns.
+    pub fn append(&mut self, other: Table) -> Result<(), Error> {
+        if self.columns.len() != other.columns.len() {
+            return Err(Error::SchemaMismatch);
+        }
+        for (column, other) in self.columns.iter_mut().zip(other.columns) {
+            column.append(other)?;
+        }
+        self.row_count += other.row_count;
+        Ok(())
+    }
+}
+
+
+
+// =============
+// === Chunk ===
+// =============
+
+/// A part of a table sent in a single message.
+#[derive(Clone, Debug, PartialEq)]
+#[allow(missing_docs)]
+pub struct Chunk {
+    pub index:      u32,
+    pub count:      u32,
+    pub row_offset: u64,
+    pub table:      Table,
+}
+
+impl Chunk {
+    /// Constructor of a chunk containing the whole table.
+    pub fn whole(table: Table) -> Self {
+        Self { index: 0, count: 1, row_offset: 0, table }
+    }
+
+    /// Checks whether this is the last chunk of the table.
+    pub fn is_last(&self) -> bool {
+        self.index + 1 >= self.count
+    }
+
+    /// Encode the chunk as a columnar message.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        out.extend(self.index.to_le_bytes());
+        out.extend(self.count.to_le_bytes());
+        out.extend(self.row_offset.to_le_bytes());
+        out.extend((self.table.row_count as u32).to_le_bytes());
+        out.extend((self.table.columns.len() as u32).to_le_bytes());
+        for column in &self.table.columns {
+            out.extend((column.name.len() as u32).to_le_bytes());
+            out.extend(column.name.as_bytes());
+            out.push(column.data.tag());
+            out.push(column.validity.is_some() as u8);
+            if let Some(validity) = &column.validity {
+                out.extend(encode_bitmap(validity));
+            }
+            match &column.data {
+                ColumnData::Float(values) =>
+                    values.iter().for_each(|v| out.extend(v.to_le_bytes())),
+                ColumnData::Integer(values) =>
+                    values.iter().for_each(|v| out.extend(v.to_le_bytes())),
+                ColumnData::Boolean(values) => out.extend(encode_bitmap(values)),
+                ColumnData::Text(values) => {
+                    let mut offset = 0_u32;
+                    out.extend(offset.to_le_bytes());
+                    for value in values {
+                        offset += value.len() as u32;
+                        out.extend(offset.to_le_bytes());
+                    }
+                    values.iter().for_each(|value| out.extend(value.as_bytes()));
+                }
+            }
+        }
+        out
+    }
+
+    /// Decode the columnar message.
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if !is_columnar(data) {
+            return Err(Error::NotColumnar);
+        }
+        let mut reader = Reader { data, position: MAGIC.len() };
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let index = reader.u32()?;
+        let count = reader.u32()?;
+        let row_offset = reader.u64()?;
+        let row_count = reader.u32()? as usize;
+        let column_count = reader.u32()? as usize;
+        let columns = (0..column_count).map(|_| reader.column(row_count)).collect::<Result<_, _>>();
+        let table = Table { row_count, columns: columns? };
+        Ok(Self { index, count, row_offset, table })
+    }
+}
+
+fn encode_bitmap(values: &[bool]) -> Vec<u8> {
+    let mut out = vec![0_u8; (values.len() + 7) / 8];
+    for (index, _) in values.iter().enumerate().filter(|(_, value)| **value) {
+        out[index / 8] |= 1 << (index % 8);
+    }
+    out
+}
+
+
+
+// ==============
+// === Reader ===
+// ==============
+
+/// A cursor over the columnar message.
+#[derive(Debug)]
+struct Reader<'a> {
+    data:     &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], Error> {
+        let end = self.position.checked_add(count).filter(|end| *end <= self.data.len());
+        let end = end.ok_or(Error::UnexpectedEnd(self.data.len()))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let bytes = self.bytes(N)?;
+        Ok(bytes.try_into().expect("The slice has the requested length."))
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn text(&mut self, length: usize) -> Result<String, Error> {
+        let position = self.position;
+        let bytes = self.bytes(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::InvalidUtf8(position))
+    }
+
+    fn bitmap(&mut self, count: usize) -> Result<Vec<bool>, Error> {
+        let bytes = self.bytes((count + 7) / 8)?;
+        Ok((0..count).map(|index| bytes[index / 8] & (1 << (index % 8)) != 0).collect())
+    }
+
+    fn column(&mut self, row_count: usize) -> Result<Column, Error> {
+        let name_length = self.u32()? as usize;
+        let name = self.text(name_length)?;
+        let tag = self.u8()?;
+        let has_nulls = self.u8()? != 0;
+        let validity = if has_nulls { Some(self.bitmap(row_count)?) } else { None };
+        let data = match tag {
+            FLOAT_TAG => {
+                let values = (0..row_count).map(|_| self.array().map(f64::from_le_bytes));
+                ColumnData::Float(values.collect::<Result<_, _>>()?)
+            }
+            INTEGER_TAG => {
+                let values = (0..row_count).map(|_| self.array().map(i64::from_le_bytes));
+                ColumnData::Integer(values.collect::<Result<_, _>>()?)
+            }
+            BOOLEAN_TAG => ColumnData::Boolean(self.bitmap(row_count)?),
+            TEXT_TAG => {
+                let offsets = (0..=row_count).map(|_| self.u32().map(|offset| offset as usize));
+                let offsets = offsets.collect::<Result<Vec<_>, _>>()?;
+                let is_sorted = offsets.windows(2).all(|pair| pair[0] <= pair[1]);
+                if offsets[0] != 0 || !is_sorted {
+                    return Err(Error::InvalidOffsets(name));
+                }
+                let text_start = self.position;
+                let text = self.bytes(offsets[row_count])?;
+                let values = offsets.windows(2).map(|pair| {
+                    let value = text[pair[0]..pair[1]].to_vec();
+                    String::from_utf8(value).map_err(|_| Error::InvalidUtf8(text_start + pair[0]))
+                });
+                ColumnData::Text(values.collect::<Result<_, _>>()?)
+            }
+            tag => return Err(Error::UnknownColumnType { column: name, tag }),
+        };
+        Ok(Column { name, data, validity })
+    }
+}
+
+
+
+// =================
+// === Assembler ===
+// =================
+
+/// Merges chunks of a table received in consecutive messages. Receiving the first chunk of a table
+/// discards the previously assembled one.
+#[derive(Clone, Debug, Default)]
+pub struct Assembler {
+    table:    Option<Table>,
+    received: u32,
+    expected: u32,
+}
+
+impl Assembler {
+    /// Constructor.
+    pub fn new() -> Self {
+        default()
+    }
+
+    /// Add the chunk to the assembled table. Chunks must be pushed in order. In case of an error,
+    /// the assembled table is discarded and the assembler waits for the first chunk of a new table.
+    pub fn push(&mut self, chunk: Chunk) -> Result<(), Error> {
+        let result = self.push_internal(chunk);
+        if result.is_err() {
+            *self = default();
+        }
+        result
+    }
+
+    fn push_internal(&mut self, chunk: Chunk) -> Result<(), Error> {
+        if chunk.index == 0 {
+            self.table = Some(chunk.table);
+        } else {
+            let expected = self.received;
+            let received = chunk.index;
+            match &mut self.table {
+                Some(table) if received == expected => table.append(chunk.table)?,
+                _ => return Err(Error::UnexpectedChunk { received, expected }),
+            }
+        }
+        self.received = chunk.index + 1;
+        self.expected = chunk.count;
+        Ok(())
+    }
+
+    /// Checks whether all chunks of the table were received.
+    pub fn is_complete(&self) -> bool {
+        self.table.is_some() && self.received >= self.expected
+    }
+
+    /// The rows received so far.
+    pub fn table(&self) -> Option<&Table> {
+        self.table.as_ref()
+    }
+
+    /// The fraction of received chunks, between 0 and 1.
+    pub fn progress(&self) -> f32 {
+        if self.expected == 0 {
+            0.0
+        } else {
+            self.received as f32 / self.expected as f32
+        }
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_table() -> Table {
+        let mut price = Column::new("price", ColumnData::Float(vec![1.5, 0.0, -2.25]));
+        price.validity = Some(vec![true, false, true]);
+        Table::new(vec![
+            Column::new("id", ColumnData::Integer(vec![1, 2, i64::MAX])),
+            price,
+            Column::new("active", ColumnData::Boolean(vec![true, false, true])),
+            Column::new("name", ColumnData::Text(vec!["Łódź".into(), "".into(), "Kraków".into()])),
+        ])
+    }
+
+    #[test]
+    fn encoding_and_decoding() {
+        let chunk = Chunk::whole(sample_table());
+        let encoded = chunk.encode();
+        assert!(is_columnar(&encoded));
+        let decoded = Chunk::decode(&encoded).unwrap();
+        assert_eq!(decoded, chunk);
+        assert!(decoded.is_last());
+        let price = decoded.table.column("price").unwrap();
+        assert!(price.is_valid(0));
+        assert!(!price.is_valid(1));
+        assert!(!price.is_valid(3));
+        assert!(!decoded.table.column("id").unwrap().is_valid(3));
+
+        assert_eq!(Chunk::decode(b"[1,2,3]"), Err(Error::NotColumnar));
+        let truncated = &encoded[..encoded.len() - 1];
+        assert_eq!(Chunk::decode(truncated), Err(Error::UnexpectedEnd(truncated.len())));
+        let mut wrong_version = encoded.clone();
+        wrong_version[MAGIC.len()] = VERSION + 1;
+        assert_eq!(Chunk::decode(&wrong_version), Err(Error::UnsupportedVersion(VERSION + 1)));
+    }
+
+    #[test]
+    fn assembling_chunks() {
+        let chunk = |index: u32, values: Vec<i64>, validity: Option<Vec<bool>>| {
+            let row_offset = index as u64 * 2;
+            let column = Column { name: "x".into(), data: ColumnData::Integer(values), validity };
+            Chunk { index, count: 3, row_offset, table: Table::new(vec![column]) }
+        };
+        let mut assembler = Assembler::new();
+        assembler.push(chunk(0, vec![1, 2], None)).unwrap();
+        assert!(!assembler.is_complete());
+        assembler.push(chunk(1, vec![3, 4], Some(vec![false, true]))).unwrap();
+        assert_eq!(assembler.progress(), 2.0 / 3.0);
+        assembler.push(chunk(2, vec![5], None)).unwrap();
+        assert!(assembler.is_complete());
+        let table = assembler.table().unwrap();
+        assert_eq!(table.row_count, 5);
+        let column = &table.columns[0];
+        assert_eq!(column.data, ColumnData::Integer(vec![1, 2, 3, 4, 5]));
+        assert_eq!(column.validity, Some(vec![true, true, false, true, true]));
+
+        assembler.push(chunk(0, vec![1, 2], None)).unwrap();
+        let error = Error::UnexpectedChunk { received: 2, expected: 1 };
+        assert_eq!(assembler.push(chunk(2, vec![5], None)), Err(error));
+        assert!(assembler.table().is_none());
+    }
+}
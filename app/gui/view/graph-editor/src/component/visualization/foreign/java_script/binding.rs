@@ -6,6 +6,8 @@ use crate::component::type_coloring;
 use crate::component::visualization::foreign::java_script::PreprocessorCallback;
 #[cfg(target_arch = "wasm32")]
 use crate::component::visualization::instance::PreprocessorConfiguration;
+#[cfg(target_arch = "wasm32")]
+use crate::component::visualization::instance::PreprocessorStep;
 use crate::Type;
 
 use ensogl::data::color;
@@ -196,10 +198,23 @@ impl JsConsArgs {
         self.theme.clone()
     }
 
-    /// Helper method to emit an preprocessor change event from the visualisation.
-    pub fn emit_preprocessor_change(&self, code: Option<String>, module: Option<String>) {
+    /// Helper method to emit an preprocessor change event from the visualisation. The `chain` is
+    /// an array of the preprocessor steps, see [`PreprocessorStep`]. Throws if it is not.
+    pub fn emit_preprocessor_change(
+        &self,
+        code: Option<String>,
+        module: Option<String>,
+        chain: JsValue,
+    ) -> Result<(), JsValue> {
+        let chain: Vec<PreprocessorStep> = if chain.is_null() || chain.is_undefined() {
+            default()
+        } else {
+            let error = |err| js_sys::Error::new(&format!("Invalid preprocessor chain: {err}"));
+            chain.into_serde().map_err(error)?
+        };
         let closure = &self.set_preprocessor;
         let preprocessor_config = PreprocessorConfiguration::from_options(code, module);
-        (*closure)(preprocessor_config);
+        (*closure)(preprocessor_config.with_chain(chain));
+        Ok(())
     }
 }
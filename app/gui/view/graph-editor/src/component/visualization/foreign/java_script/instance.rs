@@ -95,6 +95,8 @@ pub struct InstanceModel {
     #[derivative(Debug = "ignore")]
     preprocessor_change: PreprocessorCallbackCell,
     scene:               Scene,
+    /// The table assembled from the chunks of columnar data received so far.
+    columnar_table:      Rc<RefCell<visualization::columnar::Assembler>>,
 }
 
 impl InstanceModel {
@@ -178,6 +180,7 @@ impl InstanceModel {
         let set_size = Rc::new(set_size);
         let object = Rc::new(object);
         let scene = scene.clone_ref();
+        let columnar_table = default();
         Ok(InstanceModel {
             root_node,
             logger,
@@ -186,6 +189,7 @@ impl InstanceModel {
             object,
             preprocessor_change,
             scene,
+            columnar_table,
         })
     }
 
@@ -206,16 +210,25 @@ impl InstanceModel {
     #[cfg(not(target_arch = "wasm32"))]
     fn set_size(&self, _size: Vector2) {}
 
+    /// Pass the data to the visualization. JSON data is converted to a JS object. Binary data is
+    /// decoded as a columnar table chunk and merged with the previously received chunks of the same
+    /// table, so the visualization gets all rows received so far, see `columnar_table_to_js` to
+    /// learn more.
     #[cfg(target_arch = "wasm32")]
     fn receive_data(&self, data: &Data) -> result::Result<(), DataError> {
-        let data_json = match data {
-            Data::Json { content } => content,
-            _ => return Err(DataError::BinaryNotSupported),
-        };
-        let data_json: &serde_json::Value = data_json.deref();
-        let data_js = match JsValue::from_serde(data_json) {
-            Ok(value) => value,
-            Err(_) => return Err(DataError::InvalidDataType),
+        let data_js = match data {
+            Data::Json { content } => {
+                let data_json: &serde_json::Value = content.deref();
+                JsValue::from_serde(data_json).map_err(|_| DataError::InvalidDataType)?
+            }
+            Data::Binary { .. } => {
+                let mut assembler = self.columnar_table.borrow_mut();
+                assembler.push(data.columnar_chunk()?).map_err(|error| {
+                    warning!(self.logger, "Failed to assemble the columnar table: {error}");
+                    DataError::InvalidDataType
+                })?;
+                columnar_table_to_js(&assembler)
+            }
         };
         self.try_call1(&self.on_data_received, &data_js)
             .map_err(|_| DataError::InternalComputationError)?;
@@ -255,6 +268,61 @@ impl InstanceModel {
 
 
 
+// === Columnar Data ===
+
+/// Convert the columnar table assembled so far to a JS object of the following shape:
+/// ```text
+/// { rowCount, complete, progress, columns: [{ name, type, values, validity }] }
+/// ```
+/// The `complete` flag tells whether all chunks of the table were received, and `progress` is the
+/// fraction of the received chunks. Float and integer values are passed as `Float64Array`s, so
+/// integers larger than 2^53 lose precision. Boolean values and the validity (`null` if the column
+/// has no null values) are passed as `Uint8Array`s and text values as arrays of strings.
+#[cfg(target_arch = "wasm32")]
+fn columnar_table_to_js(assembler: &visualization::columnar::Assembler) -> JsValue {
+    use visualization::columnar::ColumnData;
+    let set = |object: &js_sys::Object, key: &str, value: &JsValue| {
+        // Setting a property of a plain object cannot fail.
+        let _ = js_sys::Reflect::set(object, &key.into(), value);
+    };
+    let bools = |values: &[bool]| {
+        let bytes = values.iter().map(|value| *value as u8).collect_vec();
+        JsValue::from(js_sys::Uint8Array::from(&bytes[..]))
+    };
+    let empty_table = visualization::columnar::Table::default();
+    let table = assembler.table().unwrap_or(&empty_table);
+    let columns = js_sys::Array::new();
+    for column in &table.columns {
+        let (tp, values): (&str, JsValue) = match &column.data {
+            ColumnData::Float(values) => ("float", js_sys::Float64Array::from(&values[..]).into()),
+            ColumnData::Integer(values) => {
+                let values = values.iter().map(|value| *value as f64).collect_vec();
+                ("integer", js_sys::Float64Array::from(&values[..]).into())
+            }
+            ColumnData::Boolean(values) => ("boolean", bools(values)),
+            ColumnData::Text(values) => {
+                let values = values.iter().map(|value| JsValue::from_str(value));
+                ("text", values.collect::<js_sys::Array>().into())
+            }
+        };
+        let validity = column.validity.as_ref().map_or(JsValue::NULL, |validity| bools(validity));
+        let object = js_sys::Object::new();
+        set(&object, "name", &column.name.as_str().into());
+        set(&object, "type", &tp.into());
+        set(&object, "values", &values);
+        set(&object, "validity", &validity);
+        columns.push(&object);
+    }
+    let object = js_sys::Object::new();
+    set(&object, "rowCount", &(table.row_count as u32).into());
+    set(&object, "complete", &assembler.is_complete().into());
+    set(&object, "progress", &assembler.progress().into());
+    set(&object, "columns", &columns);
+    object.into()
+}
+
+
+
 // ================
 // === Instance ===
 // ================
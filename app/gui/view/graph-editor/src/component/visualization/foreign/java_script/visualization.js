@@ -17,6 +17,7 @@ export class Visualization {
         // invoke `__emitPreprocessorChange__()` on this.
         this.__preprocessorCode__ = null
         this.__preprocessorModule__ = null
+        this.__preprocessorChain__ = []
 
         this.dom = api.root()
         this.theme = api.theme()
@@ -30,7 +31,8 @@ export class Visualization {
     __emitPreprocessorChange__() {
         this.__api__.emit_preprocessor_change(
             this.__preprocessorCode__,
-            this.__preprocessorModule__
+            this.__preprocessorModule__,
+            this.__preprocessorChain__
         )
     }
 
@@ -108,10 +110,48 @@ export class Visualization {
     }
 
     /**
-     * Set both preprocessor's code and context module.
+     * Get the current preprocessor chain.
      *
-     * This is like calling both {@link setPreprocessorModule} and
-     * {@link setPreprocessorCode}, however may be more efficient, as it will emit
+     * See the [setter documentation]{@link setPreprocessorChain} for more information.
+     *
+     * @returns {Array} The steps applied to the node value before the preprocessor code.
+     */
+    getPreprocessorChain() {
+        return this.__preprocessorChain__
+    }
+
+    /**
+     * Set the chain of preprocessors reducing the node value before the
+     * [preprocessor code]{@link setPreprocessorCode} is applied.
+     *
+     * The steps are evaluated by the Engine, so large values, like tables with millions of
+     * rows, can be reduced before they are sent to the visualization. Each step refers to a
+     * preprocessor of the IDE's library by its name and gives its arguments as Enso code. The
+     * built-in preprocessors are:
+     * - `sample count` - take the first `count` rows of a table or elements of a vector;
+     * - `summary` - compute the count, minimum, maximum, mean and standard deviation of a
+     *   vector of numbers;
+     * - `histogram bins` - count the numbers of a vector falling into each of `bins`
+     *   equal-width ranges.
+     *
+     * The visualizations declaring the same chain and code for the same node share the
+     * computed data.
+     *
+     * @param chain array of steps, for example:
+     *              <pre><code>[{name: 'sample', arguments: ['1000']}]</code></pre>
+     */
+    setPreprocessorChain(chain) {
+        if (JSON.stringify(chain) !== JSON.stringify(this.__preprocessorChain__)) {
+            this.__preprocessorChain__ = chain
+            this.__emitPreprocessorChange__()
+        }
+    }
+
+    /**
+     * Set both preprocessor's code and context module, and optionally the chain.
+     *
+     * This is like calling {@link setPreprocessorModule}, {@link setPreprocessorCode}
+     * and {@link setPreprocessorChain}, however may be more efficient, as it will emit
      * only one update request.
      *
      * During the visualization construction phase no partial updates are emitted,
@@ -119,11 +159,18 @@ export class Visualization {
      *
      * @param code preprocessor code to be set.
      * @param module context module for the preprocessor execution.
+     * @param chain preprocessor steps applied before the code, the current chain if not given.
      */
-    setPreprocessor(code, module) {
-        if (code !== this.__preprocessorCode__ || code !== this.__preprocessorModule__) {
+    setPreprocessor(code, module, chain = this.__preprocessorChain__) {
+        const chainChanged = JSON.stringify(chain) !== JSON.stringify(this.__preprocessorChain__)
+        if (
+            code !== this.__preprocessorCode__ ||
+            module !== this.__preprocessorModule__ ||
+            chainChanged
+        ) {
             this.__preprocessorCode__ = code
             this.__preprocessorModule__ = module
+            this.__preprocessorChain__ = chain
             this.__emitPreprocessorChange__()
         }
     }
@@ -9,6 +9,7 @@ use enso_frp as frp;
 use ensogl::display;
 use ensogl::display::DomSymbol;
 use ensogl::display::Scene;
+use serde::Deserialize;
 
 
 
@@ -52,6 +53,34 @@ impl ContextModule {
 }
 
 
+// === PreprocessorStep ===
+
+/// A step reducing the node value on the server before the preprocessor `code` is applied, e.g.
+/// sampling the rows of a large table. It refers to a preprocessor from the library known to the
+/// controllers by its name.
+///
+/// The JavaScript visualizations declare the steps as objects like
+/// `{name: "sample", arguments: ["1000"]}`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Hash)]
+pub struct PreprocessorStep {
+    /// The name of the preprocessor in the library.
+    pub name:      ImString,
+    /// The arguments of the preprocessor, being Enso expressions.
+    #[serde(default)]
+    pub arguments: Vec<ImString>,
+}
+
+impl PreprocessorStep {
+    /// Constructor.
+    pub fn new(
+        name: impl Into<ImString>,
+        arguments: impl IntoIterator<Item = impl Into<ImString>>,
+    ) -> Self {
+        Self { name: name.into(), arguments: arguments.into_iter().map(Into::into).collect() }
+    }
+}
+
+
 // === PreprocessorConfiguration ===
 
 /// Information on how the preprocessor should be set up for the visualization.
@@ -62,6 +91,8 @@ pub struct PreprocessorConfiguration {
     pub code:   enso::Code,
     /// The module that provides context for `code` evaluation.
     pub module: ContextModule,
+    /// The steps applied in order to the node value before `code`.
+    pub chain:  Rc<Vec<PreprocessorStep>>,
 }
 
 impl PreprocessorConfiguration {
@@ -88,13 +119,24 @@ impl PreprocessorConfiguration {
         PreprocessorConfiguration {
             module: ContextModule::Specific(module.into()),
             code:   code.into(),
+            chain:  default(),
         }
     }
+
+    /// Set the steps reducing the node value before the preprocessor code is applied.
+    pub fn with_chain(mut self, chain: impl IntoIterator<Item = PreprocessorStep>) -> Self {
+        self.chain = Rc::new(chain.into_iter().collect());
+        self
+    }
 }
 
 impl Default for PreprocessorConfiguration {
     fn default() -> Self {
-        Self { code: DEFAULT_VISUALIZATION_EXPRESSION.into(), module: default() }
+        Self {
+            code:   DEFAULT_VISUALIZATION_EXPRESSION.into(),
+            module: default(),
+            chain:  default(),
+        }
     }
 }
 
@@ -41,6 +41,7 @@ pub mod view;
 mod selection;
 
 use crate::application::command::FrpNetworkProvider;
+use crate::component::edge::routing;
 use crate::component::node;
 use crate::component::type_coloring;
 use crate::component::visualization;
@@ -90,6 +91,9 @@ pub mod prelude {
 // =================
 
 const SNAP_DISTANCE_THRESHOLD: f32 = 10.0;
+/// The distance below which the dragged nodes are considered to reach their final position after
+/// snapping to the grid.
+const SNAP_SETTLE_PRECISION: f32 = 0.01;
 const VIZ_PREVIEW_MODE_TOGGLE_TIME_MS: f32 = 300.0;
 const MACOS_TRAFFIC_LIGHTS_CONTENT_WIDTH: f32 = 52.0;
 const MACOS_TRAFFIC_LIGHTS_CONTENT_HEIGHT: f32 = 12.0;
@@ -524,6 +528,16 @@ ensogl::define_endpoints_2! {
         stop_editing(),
         /// Remove all nodes from the graph.
         collapse_selected_nodes(),
+        /// Arrange all nodes automatically, following the data flow.
+        auto_layout_nodes(),
+        /// Arrange the selected nodes automatically, following the data flow.
+        auto_layout_selected_nodes(),
+        /// Route the edges around the nodes with the given style, or draw them using the default
+        /// layout if `None`.
+        set_edge_routing(Option<routing::Style>),
+        /// Switch the edge routing to the next mode: default layout, orthogonal routes, and
+        /// orthogonal routes with rounded corners.
+        cycle_edge_routing(),
         /// Indicate whether this node had an error or not.
         set_node_error_status(NodeId,Option<node::error::Error>),
         /// Indicate whether this node has finished execution.
@@ -668,6 +682,8 @@ ensogl::define_endpoints_2! {
         node_added                (NodeId, Option<NodeSource>, bool),
         node_removed              (NodeId),
         nodes_collapsed           ((Vec<NodeId>,NodeId)),
+        /// Automatic layout was requested for the given nodes, or for all nodes if `None`.
+        auto_layout_requested     (Option<Vec<NodeId>>),
         node_hovered              (Option<Switch<NodeId>>),
         node_selected             (NodeId),
         node_deselected           (NodeId),
@@ -1645,6 +1661,10 @@ pub struct GraphEditorModel {
     profiling_button:     component::profiling::Button,
     styles_frp:           StyleWatchFrp,
     selection_controller: selection::Controller,
+    edge_routing:         Rc<Cell<Option<routing::Style>>>,
+    /// The final position of the dragged nodes which are still snapping to the grid after the drag
+    /// ended. The edges are routed again when it is reached.
+    drag_settle_position: Rc<Cell<Option<Vector2>>>,
 }
 
 
@@ -1674,6 +1694,8 @@ impl GraphEditorModel {
         let styles_frp = StyleWatchFrp::new(&scene.style_sheet);
         let selection_controller =
             selection::Controller::new(&frp, &app.cursor, &scene.mouse.frp, &touch_state, &nodes);
+        let edge_routing = default();
+        let drag_settle_position = default();
 
         Self {
             logger,
@@ -1695,6 +1717,8 @@ impl GraphEditorModel {
             add_node_button,
             styles_frp,
             selection_controller,
+            edge_routing,
+            drag_settle_position,
         }
         .init()
     }
@@ -2155,6 +2179,121 @@ impl GraphEditorModel {
         };
     }
 
+    fn set_edge_routing(&self, style: Option<routing::Style>) {
+        self.edge_routing.set(style);
+        if style.is_some() {
+            self.refresh_edge_routes();
+        } else {
+            for edge in self.edges.all.values() {
+                edge.view.frp.set_route.emit(None);
+                edge.view.frp.redraw.emit(());
+            }
+        }
+    }
+
+    fn cycle_edge_routing(&self) {
+        let next = match self.edge_routing.get() {
+            None => Some(routing::Style::Orthogonal),
+            Some(routing::Style::Orthogonal) => Some(routing::Style::Spline),
+            Some(routing::Style::Spline) => None,
+        };
+        self.set_edge_routing(next);
+    }
+
+    /// Draw the edges of the node using the default layout until the routes are refreshed. Used
+    /// while the node is dragged, as routing all edges in every frame would be too slow.
+    fn unroute_node_edges(&self, node_id: NodeId) {
+        if self.edge_routing.get().is_some() {
+            let edges = self.node_in_edges(node_id).into_iter().chain(self.node_out_edges(node_id));
+            for edge_id in edges {
+                self.map_edge(edge_id, |edge| {
+                    edge.view.frp.set_route.emit(None);
+                    edge.view.frp.redraw.emit(());
+                });
+            }
+        }
+    }
+
+    /// Route the edges again when the dragged nodes stop moving. After the drag ends, the nodes may
+    /// still be snapping to the grid, from the `current` position of the dragged node to its
+    /// `target` position.
+    fn refresh_edge_routes_after_drag(&self, current: Vector2, target: Vector2) {
+        if (target - current).norm() < SNAP_SETTLE_PRECISION {
+            self.drag_settle_position.set(None);
+            self.refresh_edge_routes();
+        } else {
+            self.drag_settle_position.set(Some(target));
+        }
+    }
+
+    /// Handle the dragged node movement after the drag ended, routing the edges again when the
+    /// node reaches its final position. See `refresh_edge_routes_after_drag`.
+    fn dragged_node_moved(&self, position: Vector2) {
+        if let Some(target) = self.drag_settle_position.get() {
+            if (target - position).norm() < SNAP_SETTLE_PRECISION {
+                self.drag_settle_position.set(None);
+                self.refresh_edge_routes();
+            }
+        }
+    }
+
+    /// Route all the connected edges around the nodes. Does nothing if the edge routing is
+    /// disabled.
+    fn refresh_edge_routes(&self) {
+        let style = match self.edge_routing.get() {
+            Some(style) => style,
+            None => return,
+        };
+        let node_ids = self.nodes.all.keys();
+        let node_indices: HashMap<NodeId, usize> =
+            node_ids.iter().enumerate().map(|(index, id)| (*id, index)).collect();
+        let obstacles = node_ids.iter().map(|id| self.node_bounding_box(*id)).collect_vec();
+        let mut routed = Vec::new();
+        let mut connections = Vec::new();
+        for edge_id in self.edges.keys() {
+            match self.edge_routing_connection(edge_id, &node_indices) {
+                Some(connection) => {
+                    routed.push(edge_id);
+                    connections.push(connection);
+                }
+                None => {
+                    self.map_edge(edge_id, |edge| edge.view.frp.set_route.emit(None));
+                }
+            }
+        }
+        let options = routing::Options { style, ..default() };
+        let routes = routing::route(&obstacles, &connections, &options);
+        for (edge_id, route) in routed.into_iter().zip(routes) {
+            self.map_edge(edge_id, |edge| {
+                edge.view.frp.set_route.emit(Some(route));
+                edge.view.frp.redraw.emit(());
+            });
+        }
+    }
+
+    /// The connection to be routed for the edge. Returns `None` if the edge is not attached to
+    /// nodes on both ends.
+    fn edge_routing_connection(
+        &self,
+        edge_id: EdgeId,
+        node_indices: &HashMap<NodeId, usize>,
+    ) -> Option<routing::Connection> {
+        let edge = self.edges.get_cloned_ref(&edge_id)?;
+        let source = edge.source()?;
+        let target = edge.target()?;
+        let source_node = self.nodes.get_cloned_ref(&source.node_id)?;
+        let target_node = self.nodes.get_cloned_ref(&target.node_id)?;
+        let source_offset = Vector2(source_node.model().width() / 2.0, 0.0);
+        let source_position = source_node.position().xy() + source_offset;
+        let target_offset = target_node.model().input.port_offset(&target.port).unwrap_or_default();
+        let target_position = target_node.position().xy() + target_offset;
+        let source_index = node_indices.get(&source.node_id).copied();
+        let target_index = node_indices.get(&target.node_id).copied();
+        let source = routing::Terminal::new(source_position, routing::Side::Bottom, source_index);
+        let target = routing::Terminal::new(target_position, routing::Side::Top, target_index);
+        Some(routing::Connection::new(source, target))
+    }
+
     fn map_node<T>(&self, id: NodeId, f: impl FnOnce(Node) -> T) -> Option<T> {
         self.nodes.get_cloned_ref(&id).map(f)
     }
@@ -2416,7 +2555,11 @@ impl application::View for GraphEditor {
             (Press, "!node_editing", "backspace", "remove_selected_nodes"),
             (Press, "!node_editing", "delete", "remove_selected_nodes"),
             (Press, "has_detached_edge", "escape", "drop_dragged_edge"),
-            (Press, "", "cmd g", "collapse_selected_nodes"), // === Visualization ===
+            (Press, "", "cmd g", "collapse_selected_nodes"),
+            (Press, "!node_editing", "cmd shift l", "auto_layout_nodes"),
+            (Press, "!node_editing", "cmd alt l", "auto_layout_selected_nodes"),
+            (Press, "!node_editing", "cmd alt r", "cycle_edge_routing"),
+            // === Visualization ===
             (Press, "!node_editing", "space", "press_visualization_visibility"),
             (DoublePress, "!node_editing", "space", "double_press_visualization_visibility"),
             (Release, "!node_editing", "space", "release_visualization_visibility"),
@@ -2957,6 +3100,17 @@ fn new_graph_editor(app: &Application) -> GraphEditor {
     }
 
 
+    // === Auto Layout ===
+    frp::extend! { network
+    let model_clone = model.clone_ref();
+    selected_to_layout <- inputs.auto_layout_selected_nodes.map(move |_|
+        Some(model_clone.nodes.all_selected())
+    );
+    out.auto_layout_requested <+ inputs.auto_layout_nodes.constant(None);
+    out.auto_layout_requested <+ selected_to_layout;
+    }
+
+
     // === Set Node Expression ===
     frp::extend! { network
 
@@ -3080,6 +3234,15 @@ fn new_graph_editor(app: &Application) -> GraphEditor {
     tgt_after_drag_new_pos <- tgt_after_drag.map(f!([model](id)(*id,model.node_position(id))));
     out.node_position_set_batched <+ tgt_after_drag_new_pos;
 
+    // The position the dragged node snaps to after the drag ends, when the snapping animations
+    // reach their targets.
+    pos_after_snap <- after_drag.map2(&node_tgt_pos_rt,
+        f!([x_snap_strength,y_snap_strength,node_tgt_pos_anim](_,rt) {
+            let w     = Vector2(x_snap_strength.target_value(),y_snap_strength.target_value());
+            let w_inv = Vector2(1.0,1.0) - w;
+            rt.component_mul(&w_inv) + node_tgt_pos_anim.target_value().component_mul(&w)
+        }));
+
 
     // === Mouse style ===
 
@@ -3445,6 +3608,26 @@ fn new_graph_editor(app: &Application) -> GraphEditor {
     }
 
 
+    // === Edge Routing ===
+    frp::extend! { network
+
+    eval inputs.set_edge_routing ((style) model.set_edge_routing(*style));
+    eval_ inputs.cycle_edge_routing  (model.cycle_edge_routing());
+    // The edges are routed again when the nodes stop moving, not in every frame of dragging. After
+    // the drag ends, the nodes may still be snapping to the grid.
+    eval out.node_position_set       (((id,_)) model.unroute_node_edges(*id));
+    drag_end_pos <- pos_after_snap.map2(&node_tgt_pos,|target,current| (*current,*target));
+    eval drag_end_pos (((current,target)) model.refresh_edge_routes_after_drag(*current,*target));
+    eval node_tgt_pos                ((pos) model.dragged_node_moved(*pos));
+    eval_ node_down                  (model.drag_settle_position.set(None));
+    eval_ inputs.set_node_position   (model.refresh_edge_routes());
+    eval_ out.node_removed           (model.refresh_edge_routes());
+    eval_ out.on_edge_endpoint_set   (model.refresh_edge_routes());
+    eval_ out.on_edge_endpoint_unset (model.refresh_edge_routes());
+    eval_ out.on_edge_drop           (model.refresh_edge_routes());
+    }
+
+
 
     // =====================
     // === Pointer Style ===
@@ -3844,6 +4027,38 @@ mod tests {
         assert_eq!(node_4.position().xy(), aligned_pos);
     }
 
+    #[test]
+    fn test_routing_edges_after_drag_ending_in_snap() {
+        let (app, graph_editor) = init();
+        let mouse = &app.display.default_scene.mouse;
+        let (_, node_1) = graph_editor.add_node_by_api_at_pos(Vector2(0.0, 0.0));
+        let (node_id_2, node_2) = graph_editor.add_node_by_api_at_pos(Vector2(300.0, -200.0));
+        // Connecting the nodes.
+        let port = node_1.model().output_port_shape().expect("No output port.");
+        port.events.emit_mouse_down(PrimaryButton);
+        port.events.emit_mouse_up(PrimaryButton);
+        node_2.model().input.frp.set_ports_active(true, None);
+        let port = node_2.model().input_port_shape().expect("No input port.");
+        port.hover.events.emit_mouse_down(PrimaryButton);
+        port.hover.events.emit_mouse_up(PrimaryButton);
+        let edge_id = graph_editor.on_edge_add.value();
+        let edge = graph_editor.edges().get_cloned_ref(&edge_id).expect("Edge was not added.");
+        graph_editor.set_edge_routing(Some(routing::Style::Orthogonal));
+        assert!(edge.view.is_routed());
+        // Dragging the 2nd node close enough to the 1st one to snap to its x coordinate.
+        graph_editor.model.touch_state.nodes.down.emit(node_id_2);
+        mouse.frp.position.emit(Vector2(-296.0, 0.0));
+        assert!(!edge.view.is_routed());
+        mouse.frp.up.emit(frp::io::mouse::Button::Button0);
+        // The node is still snapping, so the edges are not routed yet.
+        assert!(!edge.view.is_routed());
+        // Emulating the frames of the snapping animation.
+        graph_editor.model.dragged_node_moved(Vector2(2.0, -200.0));
+        assert!(!edge.view.is_routed());
+        graph_editor.model.dragged_node_moved(Vector2(0.0, -200.0));
+        assert!(edge.view.is_routed());
+    }
+
 
     // === Test utilities ===
 
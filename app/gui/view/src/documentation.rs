@@ -197,7 +197,7 @@ impl Model {
                     return Err(visualization::DataError::InternalComputationError);
                 }
             },
-            visualization::Data::Binary =>
+            visualization::Data::Binary { .. } =>
                 return Err(visualization::DataError::BinaryNotSupported),
         };
         self.display_doc(&string);
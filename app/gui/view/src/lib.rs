@@ -45,3 +45,93 @@ pub use welcome_screen;
 pub mod prelude {
     pub use ensogl::prelude::*;
 }
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    use ensogl::application::shortcut;
+    use ensogl::application::View;
+    use ensogl_component::list_view;
+    use graph_editor::component::breadcrumbs::ProjectName;
+
+    /// Shortcut conflicts which were reviewed and are intended, like a key closing several panels
+    /// at once. Add a conflict here only if both commands should be triggered together.
+    const ACCEPTED_SHORTCUT_CONFLICTS: &[&str] = &[
+        "CodeEditor.hide / GraphEditor.drop_dragged_edge on Press 'escape'",
+        "CodeEditor.hide / ProjectView.close_open_dialog on Press 'escape'",
+        "CodeEditor.hide / ProjectView.close_searcher on Press 'escape'",
+        "CodeEditor.hide / TextArea.keep_oldest_cursor_only on Press 'escape'",
+        "GraphEditor.close_fullscreen_visualization / GraphEditor.press_visualization_visibility on Press 'space'",
+        "GraphEditor.close_fullscreen_visualization / ProjectView.disable_prompt on Press 'space'",
+        "GraphEditor.disable_quick_visualization_preview / GraphEditor.edit_mode_off on Release 'cmd'",
+        "GraphEditor.drop_dragged_edge / ProjectView.close_open_dialog on Press 'escape'",
+        "GraphEditor.drop_dragged_edge / ProjectView.close_searcher on Press 'escape'",
+        "GraphEditor.drop_dragged_edge / TextArea.keep_oldest_cursor_only on Press 'escape'",
+        "GraphEditor.edit_mode_off / TextArea.stop_newest_selection_end_follow_mouse on Release 'cmd left-mouse-button'",
+        "GraphEditor.edit_mode_on / GraphEditor.enable_quick_visualization_preview on Press 'cmd'",
+        "GraphEditor.edit_mode_on / TextArea.add_cursor_at_mouse_position on Press 'cmd left-mouse-button'",
+        "GraphEditor.edit_mode_on / TextArea.start_newest_selection_end_follow_mouse on Press 'cmd left-mouse-button'",
+        "GraphEditor.enable_node_multi_select / TextArea.set_newest_selection_end_to_mouse_position on Press 'shift left-mouse-button'",
+        "GraphEditor.enter_hovered_node / GraphEditor.start_node_creation_from_port on DoublePress 'left-mouse-button'",
+        "GraphEditor.enter_hovered_node / ProjectName.start_editing on DoublePress 'left-mouse-button'",
+        "GraphEditor.enter_hovered_node / TextArea.select_word_at_cursor on DoublePress 'left-mouse-button'",
+        "GraphEditor.enter_selected_node / ListView.chose_selected_entry on Press 'enter'",
+        "GraphEditor.enter_selected_node / ProjectName.commit on Press 'enter'",
+        "GraphEditor.node_press / TextArea.set_cursor_at_mouse_position on Press 'left-mouse-button'",
+        "GraphEditor.node_press / TextArea.start_newest_selection_end_follow_mouse on Press 'left-mouse-button'",
+        "GraphEditor.node_release / TextArea.stop_newest_selection_end_follow_mouse on Release 'left-mouse-button'",
+        "GraphEditor.press_visualization_visibility / ProjectView.disable_prompt on Press 'space'",
+        "GraphEditor.remove_selected_nodes / TextArea.delete_left on Press 'backspace'",
+        "GraphEditor.remove_selected_nodes / TextArea.delete_right on Press 'delete'",
+        "GraphEditor.start_node_creation / ProjectView.disable_prompt on Press 'tab'",
+        "GraphEditor.start_node_creation / Searcher.use_as_suggestion on Press 'tab'",
+        "GraphEditor.start_node_creation_from_port / ProjectName.start_editing on DoublePress 'left-mouse-button'",
+        "GraphEditor.start_node_creation_from_port / TextArea.select_word_at_cursor on DoublePress 'left-mouse-button'",
+        "ListView.chose_selected_entry / ProjectName.commit on Press 'enter'",
+        "ListView.move_selection_down / TextArea.cursor_move_down on PressAndRepeat 'down'",
+        "ListView.move_selection_to_first / TextArea.cursor_move_left_of_line on Press 'home'",
+        "ListView.move_selection_to_last / TextArea.cursor_move_right_of_line on Press 'end'",
+        "ListView.move_selection_up / TextArea.cursor_move_up on PressAndRepeat 'up'",
+        "ProjectName.start_editing / TextArea.select_word_at_cursor on DoublePress 'left-mouse-button'",
+        "ProjectView.close_open_dialog / ProjectView.close_searcher on Press 'escape'",
+        "ProjectView.close_open_dialog / TextArea.keep_oldest_cursor_only on Press 'escape'",
+        "ProjectView.close_searcher / TextArea.keep_oldest_cursor_only on Press 'escape'",
+        "ProjectView.disable_prompt / ProjectView.show_open_dialog on Press 'cmd o'",
+        "ProjectView.disable_prompt / Searcher.use_as_suggestion on Press 'tab'",
+        "TextArea.add_cursor_at_mouse_position / TextArea.start_newest_selection_end_follow_mouse on Press 'cmd left-mouse-button'",
+        "TextArea.set_cursor_at_mouse_position / TextArea.start_newest_selection_end_follow_mouse on Press 'left-mouse-button'",
+    ];
+
+    fn default_shortcuts() -> Vec<shortcut::Shortcut> {
+        let mut shortcuts = graph_editor::GraphEditor::default_shortcuts();
+        shortcuts.extend(ProjectName::default_shortcuts());
+        shortcuts.extend(code_editor::View::default_shortcuts());
+        shortcuts.extend(project::View::default_shortcuts());
+        shortcuts.extend(searcher::View::default_shortcuts());
+        shortcuts.extend(ensogl_text::Area::default_shortcuts());
+        shortcuts.extend(list_view::ListView::<list_view::entry::Label>::default_shortcuts());
+        shortcuts
+    }
+
+    #[test]
+    fn no_new_shortcut_conflicts() {
+        let conflicts = shortcut::analysis::conflicts(&default_shortcuts());
+        let conflicts = conflicts.iter().map(|conflict| conflict.to_string());
+        let new_conflicts = conflicts
+            .filter(|conflict| !ACCEPTED_SHORTCUT_CONFLICTS.contains(&conflict.as_str()))
+            .collect_vec();
+        assert!(
+            new_conflicts.is_empty(),
+            "New shortcut conflicts found. Fix them or add them to the accepted list:\n{}",
+            new_conflicts.join("\n")
+        );
+    }
+}
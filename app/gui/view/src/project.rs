@@ -30,6 +30,15 @@ use ide_view_graph_editor::NodeSource;
 
 
 
+// =================
+// === Constants ===
+// =================
+
+/// The name under which the theme file passed in the IDE config is registered.
+pub const USER_THEME_NAME: &str = "user";
+
+
+
 // ===========
 // === FRP ===
 // ===========
@@ -213,6 +222,22 @@ impl Model {
         self.set_html_style("dark-theme");
     }
 
+    /// Load the theme file passed in the IDE config, if any, and enable it instead of the builtin
+    /// theme. The file may extend the builtin themes, see the
+    /// `ensogl::display::style::theme::file` module docs.
+    fn load_user_theme(&self) {
+        if let Some(source) = ARGS.theme_file.as_ref() {
+            let format_name = ARGS.theme_file_format.as_deref().unwrap_or("toml");
+            match display::style::theme::file::Format::from_extension(format_name) {
+                Some(format) => match self.app.themes.load(USER_THEME_NAME, source, format) {
+                    Ok(_) => self.app.themes.set_enabled(&[USER_THEME_NAME]),
+                    Err(err) => error!(self.logger, "Cannot load the theme file: {err}"),
+                },
+                None => warning!(self.logger, "Unsupported theme file format: '{format_name}'."),
+            }
+        }
+    }
+
     fn set_html_style(&self, style: &'static str) {
         web::document.with_element_by_id_or_warn("root", |root| root.set_class_name(style));
     }
@@ -357,6 +382,7 @@ impl View {
     pub fn new(app: &Application) -> Self {
         ensogl_hardcoded_theme::builtin::dark::register(app);
         ensogl_hardcoded_theme::builtin::light::register(app);
+        app.themes.set_schema(ensogl_hardcoded_theme::schema());
         let theme = match ARGS.theme.as_deref() {
             Some("dark") => {
                 ensogl_hardcoded_theme::builtin::dark::enable(app);
@@ -384,6 +410,7 @@ impl View {
         // FIXME[WD]: Think how to refactor it, as it needs to be done before model, as we do not
         //   want shader recompilation. Model uses styles already.
         model.set_style(theme);
+        model.load_user_theme();
         // TODO[WD]: This should not be needed after the theme switching issue is implemented.
         //   See: https://github.com/enso-org/ide/issues/795
         app.themes.update();
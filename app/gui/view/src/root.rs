@@ -6,8 +6,10 @@
 
 use ensogl::prelude::*;
 
+use enso_config::ARGS;
 use enso_frp as frp;
 use ensogl::application;
+use ensogl::application::shortcut::keymap;
 use ensogl::application::Application;
 use ensogl::display;
 use std::rc::Rc;
@@ -84,6 +86,22 @@ impl Model {
         if self.project_view.get().is_none() {
             let view = self.app.new_view::<crate::project::View>();
             self.project_view.set(Some(view));
+            // The user keymap is loaded when all the project views are instantiated, so its
+            // bindings can be fully validated.
+            self.load_user_keymap();
+        }
+    }
+
+    /// Load the user keymap passed in the IDE config, if any.
+    fn load_user_keymap(&self) {
+        if let Some(source) = ARGS.keymap.as_ref() {
+            let format_name = ARGS.keymap_format.as_deref().unwrap_or("json");
+            match keymap::Format::from_extension(format_name) {
+                Some(format) => {
+                    self.app.shortcuts.load_keymap(keymap::Layer::User, source, format);
+                }
+                None => warning!(self.logger, "Unsupported keymap format: '{format_name}'."),
+            }
         }
     }
 }
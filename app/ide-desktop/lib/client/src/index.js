@@ -191,6 +191,20 @@ optParser.options('theme', {
     type: `string`,
 })
 
+optParser.options('theme-file', {
+    group: styleOptionsGroup,
+    describe: 'Load the theme from the provided TOML or JSON file',
+    requiresArg: true,
+    type: `string`,
+})
+
+optParser.options('keymap', {
+    group: styleOptionsGroup,
+    describe: 'Load the user keymap from the provided TOML or JSON file',
+    requiresArg: true,
+    type: `string`,
+})
+
 optParser.options('node-labels', {
     group: styleOptionsGroup,
     describe: 'Show node labels. Defaults to `true`.',
@@ -222,6 +236,14 @@ optParser.options('data-gathering', {
     default: true,
 })
 
+optParser.options('keep-duplicate-uploads', {
+    describe:
+        'Upload the dropped files even if the project already contains a file with the same ' +
+        'content, instead of reusing that file',
+    type: 'boolean',
+    default: false,
+})
+
 // === Parsing ===
 
 function parseCmdArgs() {
@@ -531,6 +553,7 @@ function createWindow() {
         crash_report_host: args.crashReportHost,
         data_gathering: args.dataGathering,
         node_labels: args.nodeLabels,
+        keep_duplicate_uploads: args.keepDuplicateUploads,
         verbose: args.verbose,
     }
     Electron.ipcMain.on('error', (event, data) => console.error(data))
@@ -549,6 +572,22 @@ function createWindow() {
     if (args.workflow) {
         urlCfg.test_workflow = args.workflow
     }
+    if (args.themeFile) {
+        try {
+            urlCfg.theme_file = fss.readFileSync(args.themeFile, 'utf8')
+            urlCfg.theme_file_format = path.extname(args.themeFile).slice(1)
+        } catch (e) {
+            console.error(`Cannot read the theme file '${args.themeFile}'.`, e)
+        }
+    }
+    if (args.keymap) {
+        try {
+            urlCfg.keymap = fss.readFileSync(args.keymap, 'utf8')
+            urlCfg.keymap_format = path.extname(args.keymap).slice(1)
+        } catch (e) {
+            console.error(`Cannot read the keymap file '${args.keymap}'.`, e)
+        }
+    }
 
     Electron.ipcMain.on('quit-ide', () => {
         Electron.app.quit()
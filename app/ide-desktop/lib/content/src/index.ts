@@ -816,6 +816,11 @@ class Config {
     public authentication_enabled: boolean
     public email: string
     public application_config_url: string
+    public keep_duplicate_uploads: boolean
+    public theme_file: string
+    public theme_file_format: string
+    public keymap: string
+    public keymap_format: string
     public test_workflow: string
 
     static default() {
@@ -879,6 +884,16 @@ class Config {
         this.application_config_url = ok(other.application_config_url)
             ? tryAsString(other.application_config_url)
             : this.application_config_url
+        this.keep_duplicate_uploads =
+            parseBoolean(other.keep_duplicate_uploads) ?? this.keep_duplicate_uploads
+        this.theme_file = ok(other.theme_file) ? tryAsString(other.theme_file) : this.theme_file
+        this.theme_file_format = ok(other.theme_file_format)
+            ? tryAsString(other.theme_file_format)
+            : this.theme_file_format
+        this.keymap = ok(other.keymap) ? tryAsString(other.keymap) : this.keymap
+        this.keymap_format = ok(other.keymap_format)
+            ? tryAsString(other.keymap_format)
+            : this.keymap_format
         this.test_workflow = ok(other.test_workflow)
             ? tryAsString(other.test_workflow)
             : this.test_workflow
@@ -15,6 +15,8 @@
 
 use ensogl_core::prelude::ImString;
 
+use ensogl_core::display::style::theme::audit;
+
 
 
 // ==============
@@ -63,6 +65,25 @@ macro_rules! _define_theme_modules {
     };
 }
 
+/// `define_themes` helper collecting paths of all the theme values.
+macro_rules! _define_theme_paths {
+    ($paths:ident [$($path:ident)*]) => {};
+    ($paths:ident [$($path:ident)*]
+        $qual:ident . $($var:ident).+ = $($e:expr),* $(;$($rest:tt)*)?) => {
+            _define_theme_paths!{
+                $paths [$($path)*] $qual { $($var).+ = $($e),* } $($($rest)*)?
+            }
+    };
+    ($paths:ident [$($path:ident)*] $var:ident = $($e:expr),* $(;$($rest:tt)*)?) => {
+        $paths.push(stringify!($($path.)*$var));
+        _define_theme_paths!{$paths [$($path)*] $($($rest)*)?}
+    };
+    ($paths:ident [$($path:ident)*] $path_segment:ident {$($t:tt)*} $($rest:tt)*) => {
+        _define_theme_paths!{$paths [$($path)* $path_segment] $($t)*}
+        _define_theme_paths!{$paths [$($path)*] $($rest)*}
+    };
+}
+
 /// Select the theme expression by its number.
 macro_rules! _select_theme_expr {
     (0 $e0:expr                                         $(,$rest:tt)*) => {
@@ -89,6 +110,11 @@ macro_rules! _define_themes_wrappers_and_literals {
         pub mod builtin {
             use super::*;
             $(_define_theme_wrapper_and_literals! {$name $id $body})*
+
+            /// Names and definitions of all the builtin themes.
+            pub fn all() -> Vec<(&'static str, ensogl_core::display::style::theme::Theme)> {
+                vec![$((stringify!($name), $name::theme())),*]
+            }
         }
     }
 }
@@ -108,11 +134,16 @@ macro_rules! _define_theme_wrapper_and_literals {
             use ensogl_core::data::color::Rgba;
             use ensogl_core::display::style::theme;
 
-            /// Registers the theme in the application.
-            pub fn register(theme_manager:impl AsRef<theme::Manager>) {
+            /// Creates the theme.
+            pub fn theme() -> theme::Theme {
                 let $name = theme::Theme::new();
                 _define_theme_literals!{$id $name [] $($t)*}
-                theme_manager.as_ref().register(stringify!($name),$name);
+                $name
+            }
+
+            /// Registers the theme in the application.
+            pub fn register(theme_manager:impl AsRef<theme::Manager>) {
+                theme_manager.as_ref().register(stringify!($name),theme());
             }
 
             /// Enables the current theme.
@@ -128,9 +159,26 @@ macro_rules! _define_theme_wrapper_and_literals {
 /// literals. It adds the `var` module with string constants, so now, instead of having to get data
 /// by string literal - like `style.get("foo.bar.baz",fallback)`, you can do
 /// `style.get(theme::foo::bar::baz,fallback)`.
+///
+/// It also generates the `paths` function listing all the defined paths, and the `schema` function
+/// used to validate themes loaded from files at runtime. The kinds of values in the schema are
+/// taken from the first theme.
 macro_rules! define_themes {
-    ($ids:tt $($t:tt)*) => {
-        _define_themes_wrappers_and_literals!{$ids { $($t)* }}
+    ([$first:ident : $first_id:tt $(, $name:ident : $id:tt)*] $($t:tt)*) => {
+        _define_themes_wrappers_and_literals!{[$first:$first_id $(, $name:$id)*] { $($t)* }}
+
+        /// Paths of all the values defined by the builtin themes.
+        pub fn paths() -> Vec<&'static str> {
+            let mut paths = Vec::new();
+            _define_theme_paths!{paths [] $($t)*}
+            paths
+        }
+
+        /// Schema of the builtin themes, used to validate themes loaded from files at runtime.
+        pub fn schema() -> ensogl_core::display::style::theme::file::Schema {
+            let reference = builtin::$first::theme();
+            ensogl_core::display::style::theme::file::Schema::new(paths(), &reference)
+        }
 
         #[allow(non_upper_case_globals)]
         #[allow(missing_docs)]
@@ -166,6 +214,30 @@ impl Default for Theme {
 
 
 
+// ======================
+// === Contrast Pairs ===
+// ======================
+
+/// Foreground and background styles displayed on top of each other, which are required to have a
+/// readable contrast in every theme. See the [`audit`] module docs to learn more.
+pub fn contrast_pairs() -> Vec<audit::Pair> {
+    use graph_editor::visualization;
+    vec![
+        audit::Pair::new(text, application::background),
+        audit::Pair::new(graph_editor::node::text, graph_editor::node::background),
+        audit::Pair::new(visualization::text, visualization::background),
+        audit::Pair::new(visualization::action_bar::text, visualization::action_bar::background)
+            .over(visualization::background),
+        audit::Pair::new(graph_editor::prompt::text, graph_editor::prompt::background),
+        audit::Pair::new(application::status_bar::text, application::status_bar::background),
+        audit::Pair::new(application::project_list::text, application::project_list::background),
+        audit::Pair::new(widget::list_view::text, widget::list_view::background),
+        audit::Pair::new(component::label::text, component::label::background),
+    ]
+}
+
+
+
 // ===========================
 // === Light & Dark Themes ===
 // ===========================
@@ -626,3 +698,44 @@ define_themes! { [light:0, dark:1]
         size   = 12.0, 12.0;
     }
 }
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ensogl_core::display::style::data::Data;
+    use ensogl_core::display::style::Value;
+
+    #[test]
+    fn builtin_themes_match_schema() {
+        let schema = schema();
+        assert_eq!(schema.paths().count(), paths().len());
+        for (name, theme) in builtin::all() {
+            for (path, value) in theme.values() {
+                let data = match value {
+                    Value::Data(data) => data,
+                    Value::Expression(_) => continue,
+                };
+                let result = schema.check(&path, &data);
+                assert_eq!(result, Ok(()), "Theme '{}' has an invalid value {}.", name, data);
+            }
+        }
+        assert!(schema.contains(application::background.str));
+        assert!(!schema.contains("application.unknown"));
+        let wrong_type = schema.check(application::background.str, &Data::Number(1.0));
+        assert!(wrong_type.is_err());
+    }
+
+    #[test]
+    fn builtin_themes_have_readable_contrast() {
+        for (name, theme) in builtin::all() {
+            let report = audit::audit(&theme, &contrast_pairs());
+            assert!(report.is_ok(), "Theme '{}' has unreadable styles:\n{}", name, report);
+        }
+    }
+}
@@ -58,7 +58,9 @@ pub struct File {
     pub mime_type: ImString,
     pub size:      u64,
     #[derivative(Debug = "ignore")]
-    reader:        Rc<Option<ReadableStreamDefaultReader>>,
+    blob:          Rc<Option<web_sys::Blob>>,
+    #[derivative(Debug = "ignore")]
+    reader:        Rc<RefCell<Option<ReadableStreamDefaultReader>>>,
 }
 
 impl File {
@@ -67,10 +69,19 @@ impl File {
         let name = ImString::new(file.name());
         let size = file.size() as u64;
         let mime_type = ImString::new(file.type_());
-        let blob = AsRef::<web_sys::Blob>::as_ref(file);
+        let blob = AsRef::<web_sys::Blob>::as_ref(file).clone();
         let reader = blob.stream_reader()?;
-        let reader = Rc::new(Some(reader));
-        Ok(File { name, mime_type, size, reader })
+        let reader = Rc::new(RefCell::new(Some(reader)));
+        Ok(File { name, mime_type, size, blob: Rc::new(Some(blob)), reader })
+    }
+
+    /// Start reading the file content from the beginning: the next [`Self::read_chunk`] call will
+    /// return the first chunk again. The clones of this structure share the reading position.
+    pub fn rewind(&self) -> Result<(), web::JsValue> {
+        if let Some(blob) = &*self.blob {
+            *self.reader.borrow_mut() = Some(blob.stream_reader()?);
+        }
+        Ok(())
     }
 
     #[cfg(target_arch = "wasm32")]
@@ -82,7 +93,8 @@ impl File {
     /// See https://developer.mozilla.org/en-US/docs/Web/API/ReadableStreamDefaultReader/read and
     /// https://github.com/w3c/FileAPI/issues/144#issuecomment-570982732.
     pub async fn read_chunk(&self) -> Result<Option<Vec<u8>>, web::JsValue> {
-        if let Some(reader) = &*self.reader {
+        let reader = self.reader.borrow().clone();
+        if let Some(reader) = reader {
             let js_result = JsFuture::from(reader.read()).await?;
             let is_done = js_sys::Reflect::get(&js_result, &"done".into())?.as_bool().unwrap();
             if is_done {
@@ -212,6 +212,26 @@ impl Registry {
         self.id_map.borrow_mut().insert(id, instance);
     }
 
+    /// Names of all commands provided by the view with the given label, or `None` if the view was
+    /// not registered. Commands are defined by view instances, so the result is empty until the
+    /// first instance of the view is created.
+    pub fn command_names(&self, label: &str) -> Option<HashSet<String>> {
+        self.name_map.borrow().get(label).map(|instances| {
+            let command_maps = instances.iter().map(|instance| instance.command_map.borrow());
+            command_maps.flat_map(|map| map.keys().cloned().collect_vec()).collect()
+        })
+    }
+
+    /// Names of all statuses provided by the view with the given label, which can be used in
+    /// shortcut conditions. Like in `command_names`, the result is empty until the first instance
+    /// of the view is created, and `None` means that the view was not registered.
+    pub fn status_names(&self, label: &str) -> Option<HashSet<String>> {
+        self.name_map.borrow().get(label).map(|instances| {
+            let status_maps = instances.iter().map(|instance| instance.status_map.borrow());
+            status_maps.flat_map(|map| map.keys().cloned().collect_vec()).collect()
+        })
+    }
+
     /// Queries the command map by command name and applies the provided function to the result.
     /// Emits warnings in case the command could not be found.
     fn with_command_mut<T: View>(
@@ -3,6 +3,7 @@
 use crate::prelude::*;
 use enso_shortcuts::traits::*;
 
+use crate::animation::delayed::DelayedAnimation;
 use crate::frp;
 use crate::frp::io::keyboard;
 use crate::frp::io::mouse::Mouse;
@@ -15,6 +16,9 @@ use enso_shortcuts as shortcuts;
 // === Export ===
 // ==============
 
+pub mod analysis;
+pub mod keymap;
+
 pub use shortcuts::ActionType;
 
 
@@ -220,13 +224,23 @@ impl Shortcut {
 /// dropped, the shortcut will be lazily removed. This is useful when defining shortcuts by GUI
 /// components. When a component is unloaded, all its default shortcuts should be removed as well.
 ///
+/// The default shortcuts can be modified by keymaps loaded from files, see the `keymap` module to
+/// learn more.
+///
+/// Shortcut patterns can be multi-stroke sequences with chords separated by commas, like
+/// "ctrl k, ctrl s". The `pending_sequence` stream emits the chords entered so far, so the UI can
+/// show that the rest of the sequence is awaited. See `enso_shortcuts::SequenceRegistry` docs to
+/// learn more.
+///
 /// ## Implementation Notes
 /// There should be a layer for user shortcuts which will remember handles permanently until a
 /// shortcut is unregistered.
 #[derive(Clone, CloneRef, Debug)]
 pub struct Registry {
-    model:   RegistryModel,
-    network: frp::Network,
+    model:                RegistryModel,
+    network:              frp::Network,
+    /// The chords of the pending multi-stroke sequence, emitted after every input event.
+    pub pending_sequence: frp::Stream<Option<String>>,
 }
 
 /// Internal representation of `Registry`.
@@ -236,7 +250,9 @@ pub struct RegistryModel {
     keyboard:           keyboard::Keyboard,
     mouse:              Mouse,
     command_registry:   command::Registry,
-    shortcuts_registry: shortcuts::HashSetRegistry<Shortcut>,
+    shortcuts_registry: shortcuts::SequenceRegistry<Shortcut>,
+    defaults:           Rc<RefCell<Vec<Shortcut>>>,
+    keymaps:            Rc<RefCell<keymap::Layers>>,
 }
 
 impl Deref for Registry {
@@ -265,7 +281,21 @@ impl Registry {
             event      <- any(kb_down,kb_up,mouse_down,mouse_up);
             eval event ((m) model.process_rules(m));
         }
-        Self { model, network }
+
+        // The pending sequence is cancelled when its timeout expires, even if no other key is
+        // pressed, so the UI stops showing it.
+        let sequence_timer = DelayedAnimation::new(&network);
+        sequence_timer.frp.set_duration(0.0);
+        frp::extend! { network
+            time_left <- event.filter_map(f_!(model.shortcuts_registry.pending_time_left()));
+            sequence_timer.frp.reset     <+ event.constant(());
+            sequence_timer.frp.set_delay <+ time_left;
+            sequence_timer.frp.start     <+ time_left.constant(());
+            timed_out <- sequence_timer.frp.on_end.map(f_!(model.shortcuts_registry.cancel_pending()));
+            prefix    <- event.map(f_!(model.shortcuts_registry.pending_prefix()));
+            pending_sequence <- any(prefix,timed_out.constant(None)).on_change();
+        }
+        Self { model, network, pending_sequence }
     }
 }
 
@@ -282,7 +312,90 @@ impl RegistryModel {
         let mouse = mouse.clone_ref();
         let command_registry = command_registry.clone_ref();
         let shortcuts_registry = default();
-        Self { logger, keyboard, mouse, command_registry, shortcuts_registry }
+        let defaults = default();
+        let keymaps = default();
+        Self { logger, keyboard, mouse, command_registry, shortcuts_registry, defaults, keymaps }
+    }
+
+    /// Set the keymap of the provided layer, replacing the previous one. The keymap is validated
+    /// once, when it is set. Bindings which failed the validation are skipped and the validation
+    /// errors are returned. The bindings for views not registered or instantiated yet are kept, as
+    /// their views may appear later, but they cannot be fully validated.
+    pub fn set_keymap(&self, layer: keymap::Layer, keymap: keymap::Keymap) -> Vec<keymap::Error> {
+        let (keymap, errors) = keymap.validated(&self.command_registry);
+        for error in &errors {
+            if error.is_unresolved() {
+                debug!(&self.logger, "Keymap binding not validated: {error}");
+            } else {
+                warning!(&self.logger, "Invalid keymap binding: {error}");
+            }
+        }
+        self.keymaps.borrow_mut().set(layer, keymap);
+        self.rebuild();
+        errors
+    }
+
+    /// Parse the keymap from a TOML or JSON document and set it as the keymap of the provided
+    /// layer. See `set_keymap` to learn more.
+    pub fn load_keymap(
+        &self,
+        layer: keymap::Layer,
+        source: &str,
+        format: keymap::Format,
+    ) -> Vec<keymap::Error> {
+        match keymap::Keymap::parse(source, format) {
+            Ok(keymap) => self.set_keymap(layer, keymap),
+            Err(error) => {
+                warning!(&self.logger, "{error}");
+                vec![error]
+            }
+        }
+    }
+
+    /// Remove the keymap of the provided layer.
+    pub fn unset_keymap(&self, layer: keymap::Layer) {
+        self.keymaps.borrow_mut().unset(layer);
+        self.rebuild();
+    }
+
+    /// All currently active shortcuts, that is, the default shortcuts modified by the keymaps.
+    pub fn active_shortcuts(&self) -> Vec<Shortcut> {
+        self.keymaps.borrow().apply(&self.defaults.borrow())
+    }
+
+    /// Conflicts between the active shortcuts. See the `analysis` module to learn more.
+    pub fn conflicts(&self) -> Vec<analysis::Conflict> {
+        analysis::conflicts(&self.active_shortcuts())
+    }
+
+    /// The cheat sheet of the active shortcuts, grouped by view.
+    pub fn cheat_sheet(&self) -> analysis::CheatSheet {
+        analysis::CheatSheet::new(&self.active_shortcuts())
+    }
+
+    /// Add the default shortcut. The same shortcut may be added many times, e.g. when a view is
+    /// registered again, but it is stored only once.
+    fn add_default(&self, shortcut: Shortcut) {
+        if self.defaults.borrow().contains(&shortcut) {
+            return;
+        }
+        let removed = self.keymaps.borrow().removes(&shortcut);
+        if !removed {
+            self.add_to_registry(&shortcut);
+        }
+        self.defaults.borrow_mut().push(shortcut);
+    }
+
+    fn add_to_registry(&self, shortcut: &Shortcut) {
+        let rule = &shortcut.rule;
+        self.shortcuts_registry.add(rule.tp, &rule.pattern, shortcut.clone());
+    }
+
+    fn rebuild(&self) {
+        self.shortcuts_registry.clear();
+        for shortcut in self.active_shortcuts() {
+            self.add_to_registry(&shortcut);
+        }
     }
 
     fn process_rules(&self, rules: &[Shortcut]) {
@@ -334,10 +447,6 @@ impl RegistryModel {
 impl Add<Shortcut> for &Registry {
     type Output = ();
     fn add(self, shortcut: Shortcut) {
-        self.model.shortcuts_registry.add(
-            shortcut.rule.tp,
-            &shortcut.rule.pattern,
-            shortcut.clone(),
-        );
+        self.model.add_default(shortcut);
     }
 }
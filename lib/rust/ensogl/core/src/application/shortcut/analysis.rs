@@ -0,0 +1,407 @@
+//! Analysis of shortcuts registered by views. With many views registering their default shortcuts,
+//! it is easy to bind the same keys to several commands which are triggered together. The
+//! `conflicts` function finds shortcuts triggered by the same input event under conditions which
+//! can be true at the same time. The `CheatSheet` presents all shortcuts grouped by view and can be
+//! exported as a Markdown table or a JSON document.
+
+use crate::prelude::*;
+
+use super::ActionType;
+use super::Condition;
+use super::Shortcut;
+
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// The maximum number of variables in a pair of conditions checked exhaustively. Conditions with
+/// more variables are assumed to be possibly true at the same time.
+const MAX_CONDITION_VARIABLES: usize = 16;
+
+/// Keys which can be pressed on either side of the keyboard. The side is ignored when comparing
+/// shortcuts.
+const SIDE_KEYS: &[&str] = &["ctrl", "alt", "alt-graph", "meta", "cmd", "shift"];
+
+/// Aliases of key names, as understood by the shortcut registry.
+const KEY_ALIASES: &[(&str, &str)] = &[
+    ("control", "ctrl"),
+    ("option", "alt"),
+    ("command", "cmd"),
+    ("left", "arrow-left"),
+    ("right", "arrow-right"),
+    ("up", "arrow-up"),
+    ("down", "arrow-down"),
+    ("comma", ","),
+    ("left-mouse-button", "mouse-button-0"),
+    ("middle-mouse-button", "mouse-button-1"),
+    ("right-mouse-button", "mouse-button-2"),
+];
+
+
+
+// ==============
+// === Chords ===
+// ==============
+
+/// A set of keys pressed at the same time.
+type Chord = BTreeSet<String>;
+
+fn normalize_key(key: &str) -> String {
+    let key = key.to_lowercase();
+    let alias = |key: &str| KEY_ALIASES.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string());
+    let key = alias(&key).unwrap_or(key);
+    let side_key = key.strip_suffix("-left").or_else(|| key.strip_suffix("-right"));
+    let side_key = side_key.map(|k| alias(k).unwrap_or_else(|| k.to_string()));
+    match side_key {
+        Some(side_key) if SIDE_KEYS.contains(&side_key.as_str()) => side_key,
+        _ => key,
+    }
+}
+
+/// Split the pattern to chords. Chords of multi-stroke sequences are separated by commas.
+fn chords(pattern: &str) -> Vec<Chord> {
+    let chords = pattern.split(',').map(|chord| chord.split_whitespace().map(normalize_key));
+    chords.map(|keys| keys.collect::<Chord>()).filter(|chord| !chord.is_empty()).collect()
+}
+
+/// All platform-specific variants of the chord. The "cmd" key means "meta" on macOS and "ctrl" on
+/// other platforms.
+fn chord_variants(chord: &Chord) -> Vec<Chord> {
+    if chord.contains("cmd") {
+        let variant = |key: &str| {
+            let mut chord = chord.clone();
+            chord.remove("cmd");
+            chord.insert(key.into());
+            chord
+        };
+        vec![variant("ctrl"), variant("meta")]
+    } else {
+        vec![chord.clone()]
+    }
+}
+
+fn chords_match(a: &Chord, b: &Chord) -> bool {
+    let b_variants = chord_variants(b);
+    chord_variants(a).iter().any(|a| b_variants.contains(a))
+}
+
+/// Checks whether the first sequence of chords is a prefix of the second one (or is equal to it).
+fn is_prefix(prefix: &[Chord], sequence: &[Chord]) -> bool {
+    prefix.len() <= sequence.len() && prefix.iter().zip(sequence).all(|(a, b)| chords_match(a, b))
+}
+
+/// Press and press-and-repeat actions are both triggered by the first key press.
+fn trigger(tp: ActionType) -> ActionType {
+    match tp {
+        ActionType::PressAndRepeat => ActionType::Press,
+        tp => tp,
+    }
+}
+
+
+
+// ==================
+// === Conditions ===
+// ==================
+
+/// A condition variable. Variables are statuses of the view instance, so variables of different
+/// views are independent.
+type Variable<'a> = (&'a str, &'a str);
+
+fn variables<'a>(condition: &'a Condition, target: &'a str, out: &mut Vec<Variable<'a>>) {
+    match condition {
+        Condition::Always | Condition::Never => {}
+        Condition::When(name) =>
+            if !out.contains(&(target, name.as_str())) {
+                out.push((target, name))
+            },
+        Condition::Not(a) => variables(a, target, out),
+        Condition::Or(a, b) | Condition::And(a, b) => {
+            variables(a, target, out);
+            variables(b, target, out);
+        }
+    }
+}
+
+fn evaluate(condition: &Condition, target: &str, values: &HashMap<Variable<'_>, bool>) -> bool {
+    match condition {
+        Condition::Always => true,
+        Condition::Never => false,
+        Condition::When(name) => values.get(&(target, name.as_str())).copied().unwrap_or_default(),
+        Condition::Not(a) => !evaluate(a, target, values),
+        Condition::Or(a, b) => evaluate(a, target, values) || evaluate(b, target, values),
+        Condition::And(a, b) => evaluate(a, target, values) && evaluate(b, target, values),
+    }
+}
+
+/// Checks whether the conditions of both shortcuts can be true at the same time.
+fn conditions_overlap(a: &Shortcut, b: &Shortcut) -> bool {
+    let mut vars = Vec::new();
+    variables(&a.condition, &a.target, &mut vars);
+    variables(&b.condition, &b.target, &mut vars);
+    if vars.len() > MAX_CONDITION_VARIABLES {
+        return true;
+    }
+    (0..1_u32 << vars.len()).any(|bits| {
+        let values = vars.iter().enumerate().map(|(i, var)| (*var, bits & (1 << i) != 0));
+        let values = values.collect();
+        evaluate(&a.condition, &a.target, &values) && evaluate(&b.condition, &b.target, &values)
+    })
+}
+
+/// Format the condition in the syntax accepted by the condition parser. Parentheses, which are not
+/// supported by the parser, are used only for nested alternatives.
+fn condition_expr(condition: &Condition) -> String {
+    match condition {
+        Condition::Always => "".into(),
+        Condition::Never => "never".into(),
+        Condition::When(name) => name.clone(),
+        Condition::Not(a) => match &**a {
+            Condition::When(name) => format!("!{}", name),
+            a => format!("!({})", condition_expr(a)),
+        },
+        Condition::Or(a, b) => format!("{} | {}", condition_expr(a), condition_expr(b)),
+        Condition::And(a, b) => {
+            let operand = |c: &Condition| match c {
+                Condition::Or(..) => format!("({})", condition_expr(c)),
+                c => condition_expr(c),
+            };
+            format!("{} & {}", operand(a), operand(b))
+        }
+    }
+}
+
+
+
+// ================
+// === Conflict ===
+// ================
+
+/// The kind of a conflict between two shortcuts.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ConflictKind {
+    /// Both shortcuts are triggered by the same input event.
+    Collision,
+    /// The keys of the first shortcut are the first chords of the second one's sequence, so the
+    /// first shortcut is triggered while the sequence is being entered.
+    Prefix,
+}
+
+/// Two shortcuts of different commands which can be triggered together. The shortcuts are ordered
+/// by their target and command names, so the textual representation of conflicts is stable.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[allow(missing_docs)]
+pub struct Conflict {
+    pub kind:   ConflictKind,
+    pub first:  Shortcut,
+    pub second: Shortcut,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (first, second) = (&self.first, &self.second);
+        let first_name = format!("{}.{}", first.target, first.command.name);
+        let second_name = format!("{}.{}", second.target, second.command.name);
+        match self.kind {
+            ConflictKind::Collision => {
+                let (tp, pattern) = (first.rule.tp, &first.rule.pattern);
+                write!(f, "{first_name} / {second_name} on {tp:?} '{pattern}'")
+            }
+            ConflictKind::Prefix => {
+                let (prefix, sequence) = (&first.rule.pattern, &second.rule.pattern);
+                write!(f, "{first_name} / {second_name} on prefix '{prefix}' of '{sequence}'")
+            }
+        }
+    }
+}
+
+fn conflict_kind(a: &Shortcut, b: &Shortcut) -> Option<ConflictKind> {
+    let (a_chords, b_chords) = (chords(&a.rule.pattern), chords(&b.rule.pattern));
+    let same_length = a_chords.len() == b_chords.len();
+    if same_length && trigger(a.rule.tp) == trigger(b.rule.tp) && is_prefix(&a_chords, &b_chords) {
+        Some(ConflictKind::Collision)
+    } else if !same_length && (is_prefix(&a_chords, &b_chords) || is_prefix(&b_chords, &a_chords)) {
+        Some(ConflictKind::Prefix)
+    } else {
+        None
+    }
+}
+
+/// Find all conflicts between the provided shortcuts. Shortcuts of the same command are never
+/// reported, as triggering a command by several shortcuts is not ambiguous.
+pub fn conflicts(shortcuts: &[Shortcut]) -> Vec<Conflict> {
+    let mut out = Vec::new();
+    for (index, a) in shortcuts.iter().enumerate() {
+        for b in &shortcuts[index + 1..] {
+            let same_command = a.target == b.target && a.command == b.command;
+            if same_command || !conditions_overlap(a, b) {
+                continue;
+            }
+            if let Some(kind) = conflict_kind(a, b) {
+                let key = |s: &Shortcut| (s.target.clone(), s.command.name.clone());
+                let is_prefix_first = chords(&a.rule.pattern).len() < chords(&b.rule.pattern).len();
+                let swap = match kind {
+                    ConflictKind::Collision => key(a) > key(b),
+                    ConflictKind::Prefix => !is_prefix_first,
+                };
+                let (first, second) = if swap { (b, a) } else { (a, b) };
+                let (first, second) = (first.clone(), second.clone());
+                out.push(Conflict { kind, first, second });
+            }
+        }
+    }
+    out.into_iter().unique().collect()
+}
+
+
+
+// ==================
+// === CheatSheet ===
+// ==================
+
+/// A single shortcut presented in the cheat sheet.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[allow(missing_docs)]
+pub struct CheatSheetEntry {
+    pub command: String,
+    pub keys:    String,
+    pub action:  ActionType,
+    pub when:    String,
+}
+
+impl CheatSheetEntry {
+    fn matches(&self, query: &str) -> bool {
+        let fields = [&self.command, &self.keys, &self.when];
+        fields.iter().any(|field| field.to_lowercase().contains(query))
+    }
+}
+
+/// All shortcuts grouped by the views they target. Within a view, the shortcuts are sorted by
+/// command name.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[allow(missing_docs)]
+pub struct CheatSheet {
+    pub views: BTreeMap<String, Vec<CheatSheetEntry>>,
+}
+
+impl CheatSheet {
+    /// Constructor.
+    pub fn new(shortcuts: &[Shortcut]) -> Self {
+        let mut views = BTreeMap::<String, Vec<CheatSheetEntry>>::new();
+        for shortcut in shortcuts {
+            let command = shortcut.command.name.clone();
+            let keys = shortcut.rule.pattern.clone();
+            let action = shortcut.rule.tp;
+            let when = condition_expr(&shortcut.condition);
+            let entry = CheatSheetEntry { command, keys, action, when };
+            views.entry(shortcut.target.clone()).or_default().push(entry);
+        }
+        for entries in views.values_mut() {
+            entries.sort_by(|a, b| (&a.command, &a.keys).cmp(&(&b.command, &b.keys)));
+        }
+        Self { views }
+    }
+
+    /// The cheat sheet with entries matching the query only. The query is matched
+    /// case-insensitively against the view name, the command name, the keys, and the condition.
+    pub fn search(&self, query: &str) -> Self {
+        let query = query.to_lowercase();
+        let views = self.views.iter().filter_map(|(view, entries)| {
+            let view_matches = view.to_lowercase().contains(&query);
+            let entries = entries.iter().filter(|entry| view_matches || entry.matches(&query));
+            let entries = entries.cloned().collect_vec();
+            (!entries.is_empty()).then(|| (view.clone(), entries))
+        });
+        Self { views: views.collect() }
+    }
+
+    /// Serialize the cheat sheet to a JSON document.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Serialization of a cheat sheet cannot fail.")
+    }
+
+    /// Format the cheat sheet as a Markdown document with a table per view.
+    pub fn to_markdown(&self) -> String {
+        let escape = |s: &str| s.replace('|', "\\|");
+        let mut out = String::new();
+        for (view, entries) in &self.views {
+            out.push_str(&format!("## {}\n\n", view));
+            out.push_str("| Command | Keys | Action | When |\n");
+            out.push_str("|---|---|---|---|\n");
+            for entry in entries {
+                let (command, keys, when) = (&entry.command, escape(&entry.keys), &entry.when);
+                let (action, when) = (entry.action, escape(when));
+                out.push_str(&format!("| {command} | {keys} | {action:?} | {when} |\n"));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::shortcut::Rule;
+    use ActionType::*;
+
+    fn shortcut(tp: ActionType, keys: &str, target: &str, command: &str, when: &str) -> Shortcut {
+        Shortcut::new_when(Rule::new(tp, keys), target, command, when)
+    }
+
+    #[test]
+    fn finding_conflicts() {
+        let shortcuts = vec![
+            shortcut(Press, "cmd s", "Project", "save", ""),
+            shortcut(PressAndRepeat, "s ctrl-left", "Editor", "step", "focused"),
+            shortcut(Press, "control s", "Project", "save_all", "editing"),
+            shortcut(Press, "ctrl s", "Project", "search", "!editing"),
+            shortcut(Release, "ctrl s", "Project", "release", ""),
+            shortcut(Press, "ctrl s", "Project", "never", "editing & !editing"),
+            shortcut(Press, "ctrl k, ctrl s", "Editor", "save_all", ""),
+            shortcut(Press, "ctrl k", "Editor", "kill", ""),
+        ];
+        let conflicts = conflicts(&shortcuts).iter().map(|c| c.to_string()).collect_vec();
+        assert_eq!(conflicts, vec![
+            "Editor.step / Project.save on PressAndRepeat 's ctrl-left'",
+            "Project.save / Project.save_all on Press 'cmd s'",
+            "Project.save / Project.search on Press 'cmd s'",
+            "Editor.step / Project.save_all on PressAndRepeat 's ctrl-left'",
+            "Editor.step / Project.search on PressAndRepeat 's ctrl-left'",
+            "Editor.kill / Editor.save_all on prefix 'ctrl k' of 'ctrl k, ctrl s'",
+        ]);
+    }
+
+    #[test]
+    fn cheat_sheet() {
+        let shortcuts = vec![
+            shortcut(Press, "cmd s", "Project", "save", ""),
+            shortcut(Press, "tab", "Searcher", "use_as_suggestion", ""),
+            shortcut(Press, "cmd o", "Project", "open", "!a | b & c"),
+        ];
+        let sheet = CheatSheet::new(&shortcuts);
+        assert_eq!(sheet.views.keys().collect_vec(), vec!["Project", "Searcher"]);
+        assert_eq!(sheet.views["Project"][0].command, "open");
+        assert_eq!(sheet.views["Project"][0].when, "!a | b & c");
+        let found = sheet.search("SAVE");
+        assert_eq!(found.views.len(), 1);
+        assert_eq!(found.views["Project"].len(), 1);
+        assert_eq!(sheet.search("searcher").views["Searcher"].len(), 1);
+        let markdown = found.to_markdown();
+        let expected = "## Project\n\n| Command | Keys | Action | When |\n|---|---|---|---|\n\
+                        | save | cmd s | Press |  |\n\n";
+        assert_eq!(markdown, expected);
+        assert!(sheet.to_json().contains("\"use_as_suggestion\""));
+    }
+}
@@ -0,0 +1,559 @@
+//! User-configurable keymaps. A keymap is a list of bindings loaded from a TOML or JSON file,
+//! which can add new shortcuts, override, or unbind the default shortcuts defined by views (see
+//! `View::default_shortcuts`). Keymaps are organized in layers. The default shortcuts are modified
+//! by the user keymap first, and then by the project keymap. The keys of a binding can be a
+//! multi-stroke sequence with chords separated by commas, like "ctrl k, ctrl s".
+//!
+//! An example keymap file:
+//! ```toml
+//! [[bindings]]
+//! target = "GraphEditor"
+//! command = "collapse_selected_nodes"
+//! keys = "ctrl shift g"
+//!
+//! [[bindings]]
+//! target = "GraphEditor"
+//! command = "start_node_creation"
+//! op = "Add"
+//! keys = "n"
+//! when = "!node_editing"
+//!
+//! [[bindings]]
+//! target = "GraphEditor"
+//! command = "enter_selected_node"
+//! op = "Unbind"
+//! ```
+//!
+//! The same keymap in JSON:
+//! ```json
+//! { "bindings": [
+//!     { "target": "GraphEditor", "command": "collapse_selected_nodes", "keys": "ctrl shift g" },
+//!     { "target": "GraphEditor", "command": "start_node_creation", "op": "Add", "keys": "n",
+//!       "when": "!node_editing" },
+//!     { "target": "GraphEditor", "command": "enter_selected_node", "op": "Unbind" }
+//! ]}
+//! ```
+
+use crate::prelude::*;
+
+use super::ActionType;
+use super::Condition;
+use super::Rule;
+use super::Shortcut;
+use crate::application::command;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// Keys which can be used as either left or right key, like "ctrl" or "ctrl-left".
+const SIDE_KEYS: &[&str] =
+    &["ctrl", "control", "alt", "option", "alt-graph", "meta", "cmd", "command", "shift"];
+
+/// Names of keys which are not characters and are not side-aware. Function keys and mouse buttons
+/// are handled separately.
+const NAMED_KEYS: &[&str] = &[
+    "arrow-down",
+    "arrow-left",
+    "arrow-right",
+    "arrow-up",
+    "down",
+    "left",
+    "right",
+    "up",
+    "backspace",
+    "caps-lock",
+    "comma",
+    "context-menu",
+    "delete",
+    "end",
+    "enter",
+    "escape",
+    "home",
+    "insert",
+    "num-lock",
+    "page-down",
+    "page-up",
+    "pause",
+    "print-screen",
+    "scroll-lock",
+    "space",
+    "tab",
+    "left-mouse-button",
+    "middle-mouse-button",
+    "right-mouse-button",
+];
+
+/// Checks whether the provided name is a valid key name in a shortcut pattern.
+pub fn is_valid_key_name(name: &str) -> bool {
+    let is_side_key = |name: &str| SIDE_KEYS.contains(&name);
+    let side_key = name.strip_suffix("-left").or_else(|| name.strip_suffix("-right"));
+    let function_key = name.strip_prefix('f').and_then(|n| n.parse::<usize>().ok());
+    let mouse_button = name.strip_prefix("mouse-button-").and_then(|n| n.parse::<usize>().ok());
+    name.chars().count() == 1
+        || is_side_key(name)
+        || side_key.map_or(false, is_side_key)
+        || NAMED_KEYS.contains(&name)
+        || function_key.map_or(false, |n| (1..=24).contains(&n))
+        || mouse_button.map_or(false, |n| n < 5)
+}
+
+/// The names of the statuses used in the shortcut condition, or `None` if the condition is not
+/// valid. See `Condition::parse` for the supported syntax.
+pub fn condition_statuses(condition: &str) -> Option<Vec<&str>> {
+    let condition = condition.trim();
+    if condition.is_empty() {
+        return Some(default());
+    }
+    let is_name_char = |c: char| c.is_alphanumeric() || c == '_';
+    let terms = condition.split(|c| c == '|' || c == '&').map(|term| term.trim());
+    let statuses = terms.map(|term| term.strip_prefix('!').map_or(term, |status| status.trim()));
+    let is_status = |status: &&str| !status.is_empty() && status.chars().all(is_name_char);
+    statuses.map(|status| is_status(&status).then(|| status)).collect()
+}
+
+
+
+// =============
+// === Error ===
+// =============
+
+/// Keymap loading error.
+#[derive(Clone, Debug, Eq, Fail, PartialEq)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[fail(display = "Failed to parse the keymap: {}", _0)]
+    Parse(String),
+    #[fail(display = "Unknown view '{}'.", _0)]
+    UnknownTarget(String),
+    #[fail(display = "Unknown command '{}' of view '{}'.", command, target)]
+    UnknownCommand { target: String, command: String },
+    #[fail(display = "Invalid key name '{}' in the shortcut '{}'.", key, pattern)]
+    InvalidKey { key: String, pattern: String },
+    #[fail(display = "The binding of command '{}' of view '{}' has no keys.", command, target)]
+    MissingKeys { target: String, command: String },
+    #[fail(display = "Invalid condition '{}' of the binding for view '{}'.", condition, target)]
+    InvalidCondition { target: String, condition: String },
+    #[fail(display = "Unknown status '{}' in the condition of view '{}'.", status, target)]
+    UnknownStatus { target: String, status: String },
+    #[fail(display = "The commands of view '{}' are not known until it is instantiated.", _0)]
+    NotInstantiated(String),
+}
+
+impl Error {
+    /// Checks whether the error may disappear when more views are registered or instantiated. The
+    /// bindings with such errors are kept, see `Keymap::validated`.
+    pub fn is_unresolved(&self) -> bool {
+        matches!(self, Self::UnknownTarget(_) | Self::NotInstantiated(_))
+    }
+}
+
+
+
+// =============
+// === Layer ===
+// =============
+
+/// Keymap layer. Layers are applied on top of the default shortcuts in the order of definition.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[allow(missing_docs)]
+pub enum Layer {
+    User,
+    Project,
+}
+
+
+
+// ==============
+// === Format ===
+// ==============
+
+/// Supported keymap file formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// Guess the format from a file name or a bare extension, like `keymap.toml` or `json`.
+    pub fn from_extension(file_name: &str) -> Option<Self> {
+        let extension = file_name.rsplit('.').next().unwrap_or_default().to_lowercase();
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+}
+
+
+
+// ===============
+// === Binding ===
+// ===============
+
+/// The way a binding modifies shortcuts defined by the lower layers.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Operation {
+    /// Add a new shortcut, keeping the existing ones.
+    Add,
+    /// Remove all shortcuts of the command and add a new one.
+    Override,
+    /// Remove all shortcuts of the command.
+    Unbind,
+}
+
+impl Default for Operation {
+    fn default() -> Self {
+        Self::Override
+    }
+}
+
+/// A single keymap entry. If the condition `when` is provided, `Override` and `Unbind` operations
+/// affect only the shortcuts defined with the same condition. Otherwise, all shortcuts of the
+/// command are affected.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[allow(missing_docs)]
+pub struct Binding {
+    pub target:  String,
+    pub command: String,
+    #[serde(default)]
+    pub op:      Operation,
+    #[serde(default)]
+    pub keys:    Option<String>,
+    #[serde(default = "default_action_type")]
+    pub action:  ActionType,
+    #[serde(default)]
+    pub when:    Option<String>,
+}
+
+fn default_action_type() -> ActionType {
+    ActionType::Press
+}
+
+impl Binding {
+    /// Checks whether this binding removes the provided shortcut.
+    pub fn removes(&self, shortcut: &Shortcut) -> bool {
+        let removes = self.op != Operation::Add;
+        let action = &shortcut.action;
+        let same_command = action.target == self.target && action.command.name == self.command;
+        let same_condition = self.when.as_ref().map_or(true, |when| {
+            let condition = Condition::from(when.as_str());
+            action.condition == condition
+        });
+        removes && same_command && same_condition
+    }
+
+    /// The shortcut added by this binding, if any.
+    pub fn shortcut(&self) -> Option<Shortcut> {
+        let adds = self.op != Operation::Unbind;
+        let keys = self.keys.as_ref().filter(|_| adds)?;
+        let rule = Rule::new(self.action, keys.as_str());
+        let target = self.target.as_str();
+        let command = self.command.as_str();
+        let when = self.when.as_deref().unwrap_or_default();
+        Some(Shortcut::new_when(rule, target, command, when))
+    }
+
+    /// Check the binding for errors. See `Keymap::validate` to learn more.
+    pub fn validate(&self, commands: &command::Registry) -> Vec<Error> {
+        let mut errors = Vec::new();
+        let target = || self.target.clone();
+        let command = || self.command.clone();
+        match commands.command_names(&self.target) {
+            None => errors.push(Error::UnknownTarget(target())),
+            Some(names) if names.is_empty() => errors.push(Error::NotInstantiated(target())),
+            Some(names) =>
+                if !names.contains(&self.command) {
+                    errors.push(Error::UnknownCommand { target: target(), command: command() })
+                },
+        }
+        if let Some(when) = &self.when {
+            let known_statuses = commands.status_names(&self.target).unwrap_or_default();
+            let is_unknown =
+                |status: &&str| !known_statuses.is_empty() && !known_statuses.contains(*status);
+            match condition_statuses(when) {
+                None => {
+                    let condition = when.clone();
+                    errors.push(Error::InvalidCondition { target: target(), condition })
+                }
+                Some(statuses) =>
+                    for status in statuses.into_iter().filter(is_unknown) {
+                        let status = status.to_string();
+                        errors.push(Error::UnknownStatus { target: target(), status })
+                    },
+            }
+        }
+        match &self.keys {
+            None if self.op != Operation::Unbind =>
+                errors.push(Error::MissingKeys { target: target(), command: command() }),
+            None => {}
+            Some(pattern) => {
+                let keys = pattern.split(|c: char| c == ',' || c.is_whitespace());
+                for key in keys.filter(|key| !key.is_empty()) {
+                    if !is_valid_key_name(&key.to_lowercase()) {
+                        let key = key.into();
+                        let pattern = pattern.clone();
+                        errors.push(Error::InvalidKey { key, pattern })
+                    }
+                }
+            }
+        }
+        errors
+    }
+}
+
+
+
+// ==============
+// === Keymap ===
+// ==============
+
+/// A set of bindings modifying the default shortcuts.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[allow(missing_docs)]
+pub struct Keymap {
+    #[serde(default)]
+    pub bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    /// Parse the keymap from a document in the provided format.
+    pub fn parse(source: &str, format: Format) -> Result<Self, Error> {
+        match format {
+            Format::Json => Self::from_json(source),
+            Format::Toml => Self::from_toml(source),
+        }
+    }
+
+    /// Parse the keymap from a JSON document.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(|err| Error::Parse(err.to_string()))
+    }
+
+    /// Parse the keymap from a TOML document.
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        toml::from_str(toml).map_err(|err| Error::Parse(err.to_string()))
+    }
+
+    /// Check all bindings for errors: unknown views, unknown commands, invalid key names, invalid
+    /// conditions, and bindings without keys. Commands and statuses can be checked only for views
+    /// which have at least one instance created, as the views define them per instance.
+    pub fn validate(&self, commands: &command::Registry) -> Vec<Error> {
+        self.bindings.iter().flat_map(|binding| binding.validate(commands)).collect()
+    }
+
+    /// Return the keymap without the bindings for which the validation failed, together with the
+    /// list of validation errors. The bindings having only unresolved errors (see
+    /// `Error::is_unresolved`) are kept, as their views may be registered later.
+    pub fn validated(self, commands: &command::Registry) -> (Self, Vec<Error>) {
+        let mut all_errors = Vec::new();
+        let bindings = self
+            .bindings
+            .into_iter()
+            .filter(|binding| {
+                let errors = binding.validate(commands);
+                let is_valid = errors.iter().all(Error::is_unresolved);
+                all_errors.extend(errors);
+                is_valid
+            })
+            .collect();
+        (Self { bindings }, all_errors)
+    }
+
+    /// Checks whether the provided shortcut is removed by any binding of this keymap.
+    pub fn removes(&self, shortcut: &Shortcut) -> bool {
+        self.bindings.iter().any(|binding| binding.removes(shortcut))
+    }
+
+    /// Apply the keymap to the provided list of shortcuts.
+    pub fn apply(&self, shortcuts: &mut Vec<Shortcut>) {
+        for binding in &self.bindings {
+            shortcuts.retain(|shortcut| !binding.removes(shortcut));
+            shortcuts.extend(binding.shortcut());
+        }
+    }
+}
+
+
+
+// ==============
+// === Layers ===
+// ==============
+
+/// Keymaps assigned to layers.
+#[derive(Clone, Debug, Default)]
+pub struct Layers {
+    keymaps: BTreeMap<Layer, Keymap>,
+}
+
+impl Layers {
+    /// Set the keymap of the given layer, replacing the previous one.
+    pub fn set(&mut self, layer: Layer, keymap: Keymap) {
+        self.keymaps.insert(layer, keymap);
+    }
+
+    /// Remove the keymap of the given layer.
+    pub fn unset(&mut self, layer: Layer) {
+        self.keymaps.remove(&layer);
+    }
+
+    /// Checks whether the provided default shortcut is removed by any layer.
+    pub fn removes(&self, shortcut: &Shortcut) -> bool {
+        self.keymaps.values().any(|keymap| keymap.removes(shortcut))
+    }
+
+    /// Apply all layers in order to the provided default shortcuts.
+    pub fn apply(&self, defaults: &[Shortcut]) -> Vec<Shortcut> {
+        let mut shortcuts = defaults.to_vec();
+        for keymap in self.keymaps.values() {
+            keymap.apply(&mut shortcuts);
+        }
+        shortcuts
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::shortcut::ActionType::*;
+
+    fn defaults() -> Vec<Shortcut> {
+        vec![
+            Shortcut::new_when(Rule::new(Press, "tab"), "GraphEditor", "add_node", "!editing"),
+            Shortcut::new(Rule::new(Press, "cmd g"), "GraphEditor", "collapse"),
+            Shortcut::new(Rule::new(Press, "enter"), "GraphEditor", "enter_node"),
+            Shortcut::new(Rule::new(Press, "escape"), "Searcher", "close"),
+        ]
+    }
+
+    #[test]
+    fn key_names() {
+        let valid =
+            ["a", "+", "`", "comma", "ctrl", "ctrl-left", "cmd-right", "f12", "mouse-button-2"];
+        let invalid = ["ctrl-up", "f25", "mouse-button-7", "arrow", "foo"];
+        for name in valid {
+            assert!(is_valid_key_name(name), "{} should be valid", name);
+        }
+        for name in invalid {
+            assert!(!is_valid_key_name(name), "{} should be invalid", name);
+        }
+    }
+
+    #[test]
+    fn conditions() {
+        assert_eq!(condition_statuses(""), Some(vec![]));
+        assert_eq!(condition_statuses("a & !b | c_d"), Some(vec!["a", "b", "c_d"]));
+        assert_eq!(condition_statuses("! a"), Some(vec!["a"]));
+        for invalid in ["a &", "!", "a b", "(a)", "a || b"] {
+            assert_eq!(condition_statuses(invalid), None, "{} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn postponing_validation() {
+        let commands = command::Registry::create(Logger::new("test"));
+        let keymap = Keymap::from_json(
+            r#"{"bindings": [
+                {"target": "GraphEditor", "command": "collapse", "keys": "ctrl shift g"},
+                {"target": "GraphEditor", "command": "add_node", "keys": "foo"},
+                {"target": "GraphEditor", "command": "enter_node", "keys": "a", "when": "a &"}
+            ]}"#,
+        )
+        .unwrap();
+        let (keymap, errors) = keymap.validated(&commands);
+        // The view is not registered yet, so only the binding without other errors is kept.
+        assert_eq!(keymap.bindings.len(), 1);
+        assert_eq!(keymap.bindings[0].command, "collapse");
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors.iter().filter(|error| error.is_unresolved()).count(), 3);
+        let mut layers = Layers::default();
+        layers.set(Layer::User, keymap);
+        assert_eq!(layers.apply(&[]).len(), 1);
+    }
+
+    #[test]
+    fn parsing() {
+        let json = r#"{"bindings": [
+            {"target": "GraphEditor", "command": "collapse", "keys": "ctrl shift g"},
+            {"target": "Searcher", "command": "close", "op": "Unbind"},
+            {"target": "GraphEditor", "command": "add_node", "op": "Add", "keys": "n",
+             "action": "Release", "when": "!editing"}
+        ]}"#;
+        let keymap = Keymap::from_json(json).unwrap();
+        assert_eq!(keymap.bindings.len(), 3);
+        assert_eq!(keymap.bindings[0].op, Operation::Override);
+        assert_eq!(keymap.bindings[0].action, Press);
+        assert_eq!(keymap.bindings[1].keys, None);
+        assert_eq!(keymap.bindings[2].action, Release);
+        assert!(matches!(Keymap::from_json("{\"bindings\": 1}"), Err(Error::Parse(_))));
+        let toml = r#"
+            [[bindings]]
+            target = "GraphEditor"
+            command = "collapse"
+            keys = "ctrl shift g"
+
+            [[bindings]]
+            target = "Searcher"
+            command = "close"
+            op = "Unbind"
+        "#;
+        let from_toml = Keymap::parse(toml, Format::Toml).unwrap();
+        assert_eq!(from_toml.bindings[..], keymap.bindings[..2]);
+        assert_eq!(Format::from_extension("keymap.TOML"), Some(Format::Toml));
+        assert_eq!(Format::from_extension("json"), Some(Format::Json));
+        assert_eq!(Format::from_extension("keymap.yaml"), None);
+        assert!(matches!(Keymap::from_toml("bindings = 1"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn layering() {
+        let user = Keymap::from_json(
+            r#"{"bindings": [
+                {"target": "GraphEditor", "command": "collapse", "keys": "ctrl shift g"},
+                {"target": "GraphEditor", "command": "add_node", "op": "Add", "keys": "n",
+                 "when": "!editing"},
+                {"target": "Searcher", "command": "close", "op": "Unbind"}
+            ]}"#,
+        )
+        .unwrap();
+        let project = Keymap::from_json(
+            r#"{"bindings": [
+                {"target": "GraphEditor", "command": "add_node", "keys": "a", "when": "!editing"},
+                {"target": "GraphEditor", "command": "enter_node", "op": "Unbind", "when": "x"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut layers = Layers::default();
+        // The project layer is applied after the user layer, no matter the order of setting.
+        layers.set(Layer::Project, project);
+        layers.set(Layer::User, user);
+        let shortcuts = layers.apply(&defaults());
+        let patterns = shortcuts
+            .iter()
+            .map(|s| (s.action.command.name.as_str(), s.rule.pattern.as_str()))
+            .collect_vec();
+        let expected = vec![
+            // The unbinding with a different condition does not affect the shortcut.
+            ("enter_node", "enter"),
+            ("collapse", "ctrl shift g"),
+            ("add_node", "a"),
+        ];
+        assert_eq!(patterns, expected);
+        assert!(layers.removes(&defaults()[0]));
+        assert!(!layers.removes(&defaults()[2]));
+    }
+}
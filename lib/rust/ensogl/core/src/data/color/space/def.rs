@@ -51,7 +51,7 @@ macro_rules! define_color_spaces {
                         stringify!($name)   => Ok(AnyFormat::$name($name::from_slice(&args))),
                         stringify!($a_name) => Ok(AnyFormat::$a_name($a_name::from_slice(&args))),
                     )*
-                    _ => panic!("Impossible.")
+                    _ => Err(ParseError::new(format!("Unknown color format '{}'.", head)))
                 }
             }
         }
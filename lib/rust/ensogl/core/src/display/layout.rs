@@ -6,5 +6,6 @@
 // ==============
 
 pub mod alignment;
+pub mod flex;
 
 pub use alignment::Alignment;
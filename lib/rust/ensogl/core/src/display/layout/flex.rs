@@ -0,0 +1,404 @@
+//! Flexbox-style layout of display object children. A container stacks its children in a row or
+//! a column, separates them with gaps, keeps them inside its padding, and distributes the free
+//! space between them according to their grow and shrink factors, respecting their min and max
+//! sizes.
+//!
+//! This module contains only the layout description and a pure solver. The solver is used by
+//! [`crate::display::object::Instance`], which re-runs it lazily when the layout dirty flag of a
+//! container is set.
+//!
+//! All positions use the display object conventions: the origin of a container and of its
+//! children is placed in the center of their boxes and the y axis points up.
+
+use crate::prelude::*;
+
+
+
+// =================
+// === Direction ===
+// =================
+
+/// The axis along which children are stacked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Children are placed from left to right.
+    Row,
+    /// Children are placed from top to bottom.
+    Column,
+}
+
+impl Default for Direction {
+    fn default() -> Self {
+        Self::Row
+    }
+}
+
+impl Direction {
+    /// The component of the vector along the main axis.
+    pub fn main(self, v: Vector2<f32>) -> f32 {
+        match self {
+            Self::Row => v.x,
+            Self::Column => v.y,
+        }
+    }
+
+    /// The component of the vector along the cross axis.
+    pub fn cross(self, v: Vector2<f32>) -> f32 {
+        match self {
+            Self::Row => v.y,
+            Self::Column => v.x,
+        }
+    }
+
+    /// Build a vector from its main and cross axis components.
+    pub fn vector(self, main: f32, cross: f32) -> Vector2<f32> {
+        match self {
+            Self::Row => Vector2(main, cross),
+            Self::Column => Vector2(cross, main),
+        }
+    }
+}
+
+
+
+// =============
+// === Align ===
+// =============
+
+/// Placement of children along the cross axis. `Start` means the top edge for rows and the left
+/// edge for columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    /// Children fill the whole cross size of the container, limited by their min and max sizes.
+    Stretch,
+}
+
+impl Default for Align {
+    fn default() -> Self {
+        Self::Start
+    }
+}
+
+
+
+// ===============
+// === Padding ===
+// ===============
+
+/// Space between the edges of a container and its children.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[allow(missing_docs)]
+pub struct Padding {
+    pub left:   f32,
+    pub right:  f32,
+    pub top:    f32,
+    pub bottom: f32,
+}
+
+impl Padding {
+    /// Constructor.
+    pub fn new(left: f32, right: f32, top: f32, bottom: f32) -> Self {
+        Self { left, right, top, bottom }
+    }
+
+    /// The same padding on every side.
+    pub fn all(value: f32) -> Self {
+        Self::new(value, value, value, value)
+    }
+
+    /// Total padding along each axis.
+    pub fn total(&self) -> Vector2<f32> {
+        Vector2(self.left + self.right, self.top + self.bottom)
+    }
+}
+
+
+
+// =============
+// === Style ===
+// =============
+
+/// Layout description of a container.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[allow(missing_docs)]
+pub struct Style {
+    pub direction: Direction,
+    pub padding:   Padding,
+    /// Space between consecutive children.
+    pub gap:       f32,
+    pub align:     Align,
+}
+
+impl Style {
+    /// A container stacking its children from left to right.
+    pub fn row() -> Self {
+        Self { direction: Direction::Row, ..default() }
+    }
+
+    /// A container stacking its children from top to bottom.
+    pub fn column() -> Self {
+        Self { direction: Direction::Column, ..default() }
+    }
+
+    /// Set the padding.
+    pub fn with_padding(self, padding: Padding) -> Self {
+        Self { padding, ..self }
+    }
+
+    /// Set the gap between children.
+    pub fn with_gap(self, gap: f32) -> Self {
+        Self { gap, ..self }
+    }
+
+    /// Set the cross axis alignment.
+    pub fn with_align(self, align: Align) -> Self {
+        Self { align, ..self }
+    }
+
+    /// The smallest size fitting all the items without growing or shrinking them.
+    pub fn measure(&self, items: &[Item]) -> Vector2<f32> {
+        let dir = self.direction;
+        let gaps = self.gap * items.len().saturating_sub(1) as f32;
+        let main = items.iter().map(|item| dir.main(item.basis())).sum::<f32>() + gaps;
+        let cross = items.iter().map(|item| dir.cross(item.basis())).fold(0.0, f32::max);
+        dir.vector(main, cross) + self.padding.total()
+    }
+
+    /// Compute the frames of the items placed in a container of the given size. The frames are
+    /// returned in the order of the items.
+    pub fn solve(&self, items: &[Item], size: Vector2<f32>) -> Vec<Frame> {
+        let dir = self.direction;
+        let padding = self.padding.total();
+        let gaps = self.gap * items.len().saturating_sub(1) as f32;
+        let inner_main = (dir.main(size) - dir.main(padding) - gaps).max(0.0);
+        let inner_cross = (dir.cross(size) - dir.cross(padding)).max(0.0);
+        let main_sizes = distribute(dir, items, inner_main);
+        let (start_main, start_cross, end_cross) = match dir {
+            Direction::Row => (
+                -size.x / 2.0 + self.padding.left,
+                size.y / 2.0 - self.padding.top,
+                -size.y / 2.0 + self.padding.bottom,
+            ),
+            Direction::Column => (
+                size.y / 2.0 - self.padding.top,
+                -size.x / 2.0 + self.padding.left,
+                size.x / 2.0 - self.padding.right,
+            ),
+        };
+        // Rows advance along the x axis, columns advance down the y axis. The cross axis of rows
+        // starts at the top edge, while the cross axis of columns starts at the left edge.
+        let main_sign = if dir == Direction::Row { 1.0 } else { -1.0 };
+        let cross_sign = -main_sign;
+        let mut offset = 0.0;
+        let frames = items.iter().zip(main_sizes).map(|(item, main)| {
+            let cross = match self.align {
+                Align::Stretch => item.clamp_cross(dir, inner_cross),
+                _ => dir.cross(item.basis()),
+            };
+            let main_center = start_main + main_sign * (offset + main / 2.0);
+            let cross_center = match self.align {
+                Align::Start | Align::Stretch => start_cross + cross_sign * cross / 2.0,
+                Align::Center => (start_cross + end_cross) / 2.0,
+                Align::End => end_cross - cross_sign * cross / 2.0,
+            };
+            offset += main + self.gap;
+            let position = dir.vector(main_center, cross_center);
+            let size = dir.vector(main, cross);
+            Frame { position, size }
+        });
+        frames.collect()
+    }
+}
+
+/// Resolve the main axis sizes of the items, growing or shrinking them to fill the available
+/// space. Items reaching their min or max size are frozen and the remaining space is distributed
+/// again among the other items.
+fn distribute(dir: Direction, items: &[Item], available: f32) -> Vec<f32> {
+    let bases = items.iter().map(|item| dir.main(item.basis())).collect_vec();
+    let mut sizes = bases.clone();
+    let mut frozen = vec![false; items.len()];
+    loop {
+        let used: f32 = sizes.iter().zip(&frozen).map(|(s, f)| if *f { *s } else { 0.0 }).sum();
+        let unfrozen_bases: f32 =
+            bases.iter().zip(&frozen).map(|(b, f)| if *f { 0.0 } else { *b }).sum();
+        let free = available - used - unfrozen_bases;
+        let growing = free > 0.0;
+        let weight = |ix: usize| {
+            let item = &items[ix];
+            if growing {
+                item.grow
+            } else {
+                item.shrink * bases[ix]
+            }
+        };
+        let unfrozen = (0..items.len()).filter(|ix| !frozen[*ix]).collect_vec();
+        let total_weight: f32 = unfrozen.iter().map(|ix| weight(*ix)).sum();
+        if free == 0.0 || total_weight <= 0.0 {
+            unfrozen.iter().for_each(|ix| sizes[*ix] = bases[*ix]);
+            break sizes;
+        }
+        let mut violated = false;
+        for ix in unfrozen {
+            let target = bases[ix] + free * weight(ix) / total_weight;
+            let clamped = items[ix].clamp_main(dir, target);
+            sizes[ix] = clamped;
+            if clamped != target {
+                frozen[ix] = true;
+                violated = true;
+            }
+        }
+        if !violated {
+            break sizes;
+        }
+    }
+}
+
+
+
+// ============
+// === Item ===
+// ============
+
+/// Layout description of a child of a container.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Item {
+    /// Preferred size of the item. For containers, a zero component means that the size along
+    /// that axis is computed from the content.
+    pub size:   Vector2<f32>,
+    /// The share of the free space the item takes when the container is bigger than its content.
+    pub grow:   f32,
+    /// The share of the missing space the item gives up when the container is smaller than its
+    /// content. The share is additionally weighted by the item size.
+    pub shrink: f32,
+    #[allow(missing_docs)]
+    pub min:    Vector2<f32>,
+    #[allow(missing_docs)]
+    pub max:    Vector2<f32>,
+}
+
+impl Default for Item {
+    fn default() -> Self {
+        let size = Vector2(0.0, 0.0);
+        let min = Vector2(0.0, 0.0);
+        let max = Vector2(f32::INFINITY, f32::INFINITY);
+        Self { size, grow: 0.0, shrink: 1.0, min, max }
+    }
+}
+
+impl Item {
+    /// Constructor of an item of the given preferred size.
+    pub fn new(size: Vector2<f32>) -> Self {
+        Self { size, ..default() }
+    }
+
+    /// Set the grow factor.
+    pub fn with_grow(self, grow: f32) -> Self {
+        Self { grow, ..self }
+    }
+
+    /// Set the shrink factor.
+    pub fn with_shrink(self, shrink: f32) -> Self {
+        Self { shrink, ..self }
+    }
+
+    /// Set the minimum size.
+    pub fn with_min(self, min: Vector2<f32>) -> Self {
+        Self { min, ..self }
+    }
+
+    /// Set the maximum size.
+    pub fn with_max(self, max: Vector2<f32>) -> Self {
+        Self { max, ..self }
+    }
+
+    /// The preferred size limited by the min and max sizes.
+    pub fn basis(&self) -> Vector2<f32> {
+        Vector2(
+            self.size.x.min(self.max.x).max(self.min.x),
+            self.size.y.min(self.max.y).max(self.min.y),
+        )
+    }
+
+    fn clamp_main(&self, dir: Direction, value: f32) -> f32 {
+        value.min(dir.main(self.max)).max(dir.main(self.min))
+    }
+
+    fn clamp_cross(&self, dir: Direction, value: f32) -> f32 {
+        value.min(dir.cross(self.max)).max(dir.cross(self.min))
+    }
+}
+
+
+
+// =============
+// === Frame ===
+// =============
+
+/// The box assigned to an item by the layout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    /// The center of the box in the container coordinate space.
+    pub position: Vector2<f32>,
+    #[allow(missing_docs)]
+    pub size:     Vector2<f32>,
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(x: f32, y: f32, width: f32, height: f32) -> Frame {
+        Frame { position: Vector2(x, y), size: Vector2(width, height) }
+    }
+
+    #[test]
+    fn stacking_in_a_row() {
+        let style = Style::row().with_gap(10.0).with_padding(Padding::all(5.0));
+        let items = [Item::new(Vector2(20.0, 10.0)), Item::new(Vector2(40.0, 30.0))];
+        let size = style.measure(&items);
+        assert_eq!(size, Vector2(80.0, 40.0));
+        let frames = style.solve(&items, size);
+        assert_eq!(frames, vec![frame(-25.0, 10.0, 20.0, 10.0), frame(15.0, 0.0, 40.0, 30.0)]);
+    }
+
+    #[test]
+    fn stacking_in_a_column() {
+        let style = Style::column().with_gap(4.0).with_align(Align::Center);
+        let items = [Item::new(Vector2(20.0, 10.0)), Item::new(Vector2(40.0, 30.0))];
+        let size = style.measure(&items);
+        assert_eq!(size, Vector2(40.0, 44.0));
+        let frames = style.solve(&items, size);
+        assert_eq!(frames, vec![frame(0.0, 17.0, 20.0, 10.0), frame(0.0, -7.0, 40.0, 30.0)]);
+
+        let frames = style.with_align(Align::End).solve(&items, size);
+        assert_eq!(frames[0].position, Vector2(10.0, 17.0));
+        let frames = style.with_align(Align::Stretch).solve(&items, size);
+        assert_eq!(frames[0].size, Vector2(40.0, 10.0));
+    }
+
+    #[test]
+    fn growing_and_shrinking() {
+        let style = Style::row();
+        let fixed = Item::new(Vector2(20.0, 10.0)).with_shrink(0.0);
+        let growing = Item::new(Vector2(10.0, 10.0)).with_grow(1.0);
+        let limited = growing.with_grow(3.0).with_max(Vector2(30.0, 10.0));
+        let frames = style.solve(&[fixed, growing, limited], Vector2(100.0, 10.0));
+        let widths = frames.iter().map(|f| f.size.x).collect_vec();
+        assert_eq!(widths, vec![20.0, 50.0, 30.0]);
+
+        let shrinking = Item::new(Vector2(40.0, 10.0)).with_min(Vector2(35.0, 0.0));
+        let frames = style.solve(&[fixed, shrinking, shrinking], Vector2(80.0, 10.0));
+        let widths = frames.iter().map(|f| f.size.x).collect_vec();
+        assert_eq!(widths, vec![20.0, 35.0, 35.0]);
+    }
+}
@@ -6,6 +6,7 @@ use crate::data::dirty::traits::*;
 use crate::prelude::*;
 
 use crate::data::dirty;
+use crate::display::layout::flex;
 use crate::display::scene::layer::Layer;
 use crate::display::scene::layer::WeakLayer;
 use crate::display::scene::Scene;
@@ -116,6 +117,7 @@ type ChildrenDirty = dirty::SharedSet<usize, OnDirtyCallback>;
 type RemovedChildren<Host> = dirty::SharedVector<WeakInstance<Host>, OnDirtyCallback>;
 type TransformDirty = dirty::SharedBool<OnDirtyCallback>;
 type SceneLayerDirty = dirty::SharedBool<OnDirtyCallback>;
+type LayoutDirty = dirty::SharedBool<OnDirtyCallback>;
 
 
 // === Definition ===
@@ -133,6 +135,7 @@ pub struct DirtyFlags<Host> {
     removed_children: RemovedChildren<Host>,
     transform:        TransformDirty,
     scene_layer:      SceneLayerDirty,
+    layout:           LayoutDirty,
     #[derivative(Debug = "ignore")]
     on_dirty:         Rc<RefCell<Box<dyn Fn()>>>,
 }
@@ -152,7 +155,9 @@ impl<Host> DirtyFlags<Host> {
         let transform = TransformDirty::new(sub_logger, on_dirty_callback(&on_dirty));
         let sub_logger = logger::WarningLogger::new_sub(&logger, "scene_layer");
         let scene_layer = SceneLayerDirty::new(sub_logger, on_dirty_callback(&on_dirty));
-        Self { parent, children, removed_children, transform, scene_layer, on_dirty }
+        let sub_logger = logger::WarningLogger::new_sub(&logger, "layout");
+        let layout = LayoutDirty::new(sub_logger, on_dirty_callback(&on_dirty));
+        Self { parent, children, removed_children, transform, scene_layer, layout, on_dirty }
     }
 
     fn set_on_dirty<F: 'static + Fn()>(&self, f: F) {
@@ -198,6 +203,14 @@ pub struct Model<Host = Scene> {
     children:        RefCell<OptVec<WeakInstance<Host>>>,
     transform:       RefCell<CachedTransform>,
     visible:         Cell<bool>,
+    /// Layout of children, if this object is a layout container.
+    layout_style:    Cell<Option<flex::Style>>,
+    /// Layout description of this object as a child of a layout container.
+    layout_item:     Cell<flex::Item>,
+    /// Size assigned to this object by the layout of its parent.
+    assigned_size:   Cell<Option<Vector2<f32>>>,
+    /// Size of this object resolved by the last layout pass.
+    layout_size:     Cell<Vector2<f32>>,
     logger:          Logger,
 }
 
@@ -214,6 +227,10 @@ impl<Host> Model<Host> {
         let host = default();
         let assigned_layers = default();
         let layers = default();
+        let layout_style = default();
+        let layout_item = default();
+        let assigned_size = default();
+        let layout_size = Cell::new(Vector2(0.0, 0.0));
         Self {
             host,
             assigned_layers,
@@ -224,6 +241,10 @@ impl<Host> Model<Host> {
             children,
             transform,
             visible,
+            layout_style,
+            layout_item,
+            assigned_size,
+            layout_size,
             logger,
         }
     }
@@ -269,6 +290,7 @@ impl<Host> Model<Host> {
             self.dirty.children.unset(&index);
             self.dirty.removed_children.set(child);
         });
+        self.mark_layout_dirty();
     }
 
     /// Removes the binding to the parent object. Parent is not updated.
@@ -276,6 +298,8 @@ impl<Host> Model<Host> {
         info!(self.logger, "Removing parent bind.");
         self.dirty.unset_on_dirty();
         self.dirty.parent.set();
+        self.assigned_size.set(None);
+        self.dirty.layout.set();
     }
 }
 
@@ -293,6 +317,8 @@ impl<Host> Model<Host> {
         parent_layers_changed: bool,
         parent_layers: &[WeakLayer],
     ) {
+        self.update_layout();
+
         // === Scene Layers Update ===
         let has_new_parent = self.dirty.parent.check();
         let assigned_layers_ref = self.assigned_layers.borrow();
@@ -456,6 +482,7 @@ impl<Host> Model<Host> {
     fn register_child<T: Object<Host>>(&self, child: &T) -> usize {
         let index = self.children.borrow_mut().insert(child.weak_display_object());
         self.dirty.children.set(index);
+        self.mark_layout_dirty();
         index
     }
 
@@ -573,6 +600,106 @@ impl<Host> Model<Host> {
 }
 
 
+// === Layout ===
+
+impl<Host> Model<Host> {
+    /// Layout of children of this object. See the docs of [`flex`] to learn more.
+    pub fn layout(&self) -> Option<flex::Style> {
+        self.layout_style.get()
+    }
+
+    /// Layout description of this object as a child of a layout container.
+    pub fn layout_item(&self) -> flex::Item {
+        self.layout_item.get()
+    }
+
+    /// Size of this object resolved by the last layout pass. Objects which are not placed by a
+    /// layout container have the preferred size of their layout item.
+    pub fn layout_size(&self) -> Vector2<f32> {
+        self.layout_size.get()
+    }
+
+    fn set_layout(&self, style: Option<flex::Style>) {
+        self.layout_style.set(style);
+        self.mark_layout_dirty();
+    }
+
+    fn set_layout_item(&self, item: flex::Item) {
+        self.layout_item.set(item);
+        self.mark_layout_dirty();
+    }
+
+    /// Mark the layout of this object as outdated. Because the size of a container depends on its
+    /// content, the layouts of all the containers it is nested in are marked as well.
+    fn mark_layout_dirty(&self) {
+        self.dirty.layout.set();
+        if let Some(parent) = self.parent() {
+            if parent.layout_style.get().is_some() {
+                parent.mark_layout_dirty();
+            }
+        }
+    }
+
+    /// The layout item of this object with the size of its content filled in on the axes the item
+    /// does not specify.
+    fn layout_basis(&self) -> flex::Item {
+        let mut item = self.layout_item.get();
+        if let Some(style) = self.layout_style.get() {
+            let content = style.measure(&self.children_layout_items().1);
+            if item.size.x == 0.0 {
+                item.size.x = content.x;
+            }
+            if item.size.y == 0.0 {
+                item.size.y = content.y;
+            }
+        }
+        item
+    }
+
+    fn children_layout_items(&self) -> (Vec<Instance<Host>>, Vec<flex::Item>) {
+        let children = self.children.borrow().iter().filter_map(|t| t.upgrade()).collect_vec();
+        let items = children.iter().map(|child| child.layout_basis()).collect();
+        (children, items)
+    }
+
+    /// Place the children of this object if it is a layout container with an outdated layout. The
+    /// children which are layout containers themselves are laid out when they are updated.
+    fn update_layout(&self) {
+        if self.dirty.layout.check() {
+            if let Some(style) = self.layout_style.get() {
+                debug!(self.logger, "Updating layout.", || {
+                    let size =
+                        self.assigned_size.get().unwrap_or_else(|| self.layout_basis().basis());
+                    self.layout_size.set(size);
+                    let (children, items) = self.children_layout_items();
+                    for (child, frame) in children.iter().zip(style.solve(&items, size)) {
+                        child.mod_position(|p| {
+                            p.x = frame.position.x;
+                            p.y = frame.position.y;
+                        });
+                        child.assign_layout_size(frame.size);
+                    }
+                })
+            } else {
+                let size = self.assigned_size.get().unwrap_or_else(|| self.layout_item().basis());
+                self.layout_size.set(size);
+            }
+            self.dirty.layout.unset();
+        }
+    }
+
+    fn assign_layout_size(&self, size: Vector2<f32>) {
+        if self.assigned_size.get() != Some(size) {
+            self.assigned_size.set(Some(size));
+            self.layout_size.set(size);
+            if self.layout_style.get().is_some() {
+                self.dirty.layout.set();
+            }
+        }
+    }
+}
+
+
 
 // ==========
 // === Id ===
@@ -1150,6 +1277,40 @@ pub trait ObjectOps<Host = Scene>: Object<Host> {
     fn set_rotation_z(&self, t: f32) {
         self.mod_rotation(|p| p.z = t)
     }
+
+
+    // === Layout ===
+
+    /// Make this display object a layout container placing its children according to the style.
+    fn set_layout(&self, style: flex::Style) {
+        self.display_object().rc.set_layout(Some(style));
+    }
+
+    /// Stop placing the children of this display object automatically. Their positions are kept.
+    fn unset_layout(&self) {
+        self.display_object().rc.set_layout(None);
+    }
+
+    fn layout_item(&self) -> flex::Item {
+        self.display_object().rc.layout_item()
+    }
+
+    /// Set the size, grow and shrink factors used when this object is placed by a layout
+    /// container.
+    fn set_layout_item(&self, item: flex::Item) {
+        self.display_object().rc.set_layout_item(item);
+    }
+
+    fn mod_layout_item<F: FnOnce(&mut flex::Item)>(&self, f: F) {
+        let mut item = self.layout_item();
+        f(&mut item);
+        self.set_layout_item(item);
+    }
+
+    /// Size of this object resolved by the last layout pass, available after the update.
+    fn layout_size(&self) -> Vector2<f32> {
+        self.display_object().rc.layout_size()
+    }
 }
 
 
@@ -1603,4 +1764,45 @@ mod tests {
         assert_eq!(node2.display_layers(), vec![layer2.downgrade()]);
         assert_eq!(node3.display_layers(), vec![layer1.downgrade()]);
     }
+
+    #[test]
+    fn layout_test() {
+        let panel = Instance::<()>::new(Logger::new("panel"));
+        let header = Instance::<()>::new(Logger::new("header"));
+        let row = Instance::<()>::new(Logger::new("row"));
+        let node1 = Instance::<()>::new(Logger::new("node1"));
+        let node2 = Instance::<()>::new(Logger::new("node2"));
+        let padding = flex::Padding::all(10.0);
+        let style = flex::Style::column().with_padding(padding).with_gap(10.0);
+        panel.set_layout(style.with_align(flex::Align::Stretch));
+        panel.set_layout_item(flex::Item::new(Vector2(100.0, 0.0)));
+        row.set_layout(flex::Style::row().with_gap(5.0));
+        header.set_layout_item(flex::Item::new(Vector2(0.0, 20.0)));
+        node1.set_layout_item(flex::Item::new(Vector2(30.0, 20.0)));
+        node2.set_layout_item(flex::Item::new(Vector2(10.0, 20.0)).with_grow(1.0));
+        panel.add_child(&header);
+        panel.add_child(&row);
+        row.add_child(&node1);
+        row.add_child(&node2);
+
+        panel.update(&());
+        assert_eq!(panel.layout_size(), Vector2(100.0, 70.0));
+        assert_eq!(header.layout_size(), Vector2(80.0, 20.0));
+        assert_eq!(header.global_position(), Vector3::new(0.0, 15.0, 0.0));
+        assert_eq!(row.layout_size(), Vector2(80.0, 20.0));
+        assert_eq!(node1.global_position(), Vector3::new(-25.0, -15.0, 0.0));
+        assert_eq!(node2.global_position(), Vector3::new(17.5, -15.0, 0.0));
+        assert_eq!(node2.layout_size(), Vector2(45.0, 20.0));
+
+        node1.mod_layout_item(|item| item.size.x = 50.0);
+        panel.update(&());
+        assert_eq!(node1.global_position(), Vector3::new(-15.0, -15.0, 0.0));
+        assert_eq!(node2.global_position(), Vector3::new(27.5, -15.0, 0.0));
+        assert_eq!(node2.layout_size(), Vector2(25.0, 20.0));
+
+        row.remove_child(&node1);
+        panel.update(&());
+        assert_eq!(node2.global_position(), Vector3::new(0.0, -15.0, 0.0));
+        assert_eq!(node2.layout_size(), Vector2(80.0, 20.0));
+    }
 }
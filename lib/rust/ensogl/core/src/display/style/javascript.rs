@@ -9,6 +9,7 @@ use crate::system::web;
 
 use super::sheet::Data;
 use super::sheet::Value;
+use super::theme::file::Format;
 use super::theme::Manager;
 #[cfg(target_arch = "wasm32")]
 use js_sys;
@@ -27,8 +28,8 @@ mod js {
             return `theme-interactive-mode-${name}`
         }
 
-        export function create_theme_manager_ref(list,choose,get,snapshot,diff) {
-            return {list,choose,get,snapshot,diff}
+        export function create_theme_manager_ref(list,choose,get,snapshot,diff,load) {
+            return {list,choose,get,snapshot,diff,load}
         }
 
         export function add_interactive_mode_style(name,path,value) {
@@ -100,6 +101,7 @@ mod js {
             get: &Get,
             snapshot: &Snapshot,
             diff: &Diff,
+            load: &Load,
         ) -> JsValue;
 
         #[allow(unsafe_code)]
@@ -117,6 +119,7 @@ mod js {
     pub type Choose = Closure<dyn Fn(String)>;
     pub type Snapshot = Closure<dyn Fn(String)>;
     pub type Diff = Closure<dyn Fn(String, String) -> String>;
+    pub type Load = Closure<dyn Fn(String, String, String) -> String>;
     pub type Get = Closure<dyn Fn(String) -> JsValue>;
     pub type Set = Closure<dyn Fn(String, String)>;
     pub type InteractiveMode = Closure<dyn Fn()>;
@@ -139,6 +142,16 @@ pub fn expose_to_window(manager: &Manager) {
         diff.iter().map(|t| format!("{} = {:?}",t.path,t.value)).join("\n")
     }));
 
+    let load: js::Load = Closure::new(f!([manager](name:String,source:String,file_name:String) {
+        match Format::from_extension(&file_name) {
+            None => format!("Unsupported theme file format: '{}'.", file_name),
+            Some(format) => match manager.load(name, &source, format) {
+                Ok(issues) => issues.iter().map(|t| t.to_string()).join("\n"),
+                Err(err) => err.to_string(),
+            },
+        }
+    }));
+
     let owned_manager = manager.clone_ref();
     let get: js::Get = Closure::new(move |name: String| {
         let theme = owned_manager.get(&name).unwrap();
@@ -171,7 +184,8 @@ pub fn expose_to_window(manager: &Manager) {
     #[cfg(target_arch = "wasm32")]
     let window = web::window;
     #[cfg(target_arch = "wasm32")]
-    let theme_manger_ref = js::create_theme_manager_ref(&list, &choose, &get, &snapshot, &diff);
+    let theme_manger_ref =
+        js::create_theme_manager_ref(&list, &choose, &get, &snapshot, &diff, &load);
     #[cfg(target_arch = "wasm32")]
     js_sys::Reflect::set(&window, &"theme".into(), &theme_manger_ref).ok();
 
@@ -179,5 +193,6 @@ pub fn expose_to_window(manager: &Manager) {
     mem::forget(choose);
     mem::forget(snapshot);
     mem::forget(diff);
+    mem::forget(load);
     mem::forget(get);
 }
@@ -15,6 +15,14 @@ use super::sheet::Path;
 use super::sheet::Value;
 
 
+// ==============
+// === Export ===
+// ==============
+
+pub mod audit;
+pub mod file;
+
+
 
 // =============
 // === Theme ===
@@ -65,6 +73,20 @@ impl Theme {
         self.on_mut.run_all();
     }
 
+    /// Apply changes, like the ones computed by [`Theme::diff`], to this theme. Changes with no
+    /// value remove the style. Callbacks are triggered once if there was any change.
+    pub fn apply_changes<I>(&self, changes: I)
+    where I: IntoIterator<Item = Change> {
+        let mut changed = false;
+        for change in changes {
+            self.tree.borrow_mut().set(&change.path.rev_segments, change.value);
+            changed = true;
+        }
+        if changed {
+            self.on_mut.run_all();
+        }
+    }
+
     /// Add a new callback which will be triggered everytime this theme is modified.
     pub fn on_mut(&self, callback: impl callback::MutNoArgs) -> callback::Handle {
         self.on_mut.add(callback)
@@ -229,6 +251,7 @@ pub struct Manager {
     current_dirty: dirty::SharedBool,
     enabled_dirty: dirty::SharedVector<String>,
     initialized:   Rc<Cell<bool>>,
+    schema:        Rc<RefCell<Option<file::Schema>>>,
 }
 
 impl Manager {
@@ -240,7 +263,8 @@ impl Manager {
         let data = default();
         let handles = default();
         let initialized = default();
-        Self { logger, data, handles, current_dirty, enabled_dirty, initialized }
+        let schema = default();
+        Self { logger, data, handles, current_dirty, enabled_dirty, initialized, schema }
     }
 
     /// Return a theme of the given name.
@@ -272,6 +296,46 @@ impl Manager {
         self.set_enabled(&[name]);
     }
 
+    /// Set the schema used to validate themes loaded with [`Manager::load`].
+    pub fn set_schema(&self, schema: file::Schema) {
+        *self.schema.borrow_mut() = Some(schema);
+    }
+
+    /// Load a theme from the source of a theme file (see the [`file`] module docs) and register
+    /// it with the provided name. If a theme of this name is already registered, it is updated in
+    /// place with the changes computed by [`Theme::diff`], so enabled themes are hot-reloaded on
+    /// the next [`Manager::update`]. Entries which do not match the schema are skipped and
+    /// returned as issues.
+    pub fn load(
+        &self,
+        name: impl Str,
+        source: &str,
+        format: file::Format,
+    ) -> Result<Vec<file::Issue>, file::Error> {
+        let name = name.into();
+        let mut theme_file = file::ThemeFile::parse(source, format)?;
+        if let Some(schema) = &*self.schema.borrow() {
+            theme_file.validate(schema);
+        }
+        let base = match &theme_file.extends {
+            Some(base) if base == &name => return Err(file::Error::SelfExtending { name }),
+            Some(base) => {
+                let unknown_base = || file::Error::UnknownBase { name: base.clone() };
+                Some(self.get(base).ok_or_else(unknown_base)?)
+            }
+            None => None,
+        };
+        let theme = theme_file.to_theme(base.as_ref());
+        match self.get(&name) {
+            Some(current) => current.apply_changes(current.diff(&theme)),
+            None => self.register_internal(name, theme),
+        }
+        for issue in &theme_file.issues {
+            warning!(self.logger, "{issue}");
+        }
+        Ok(theme_file.issues)
+    }
+
     fn register_internal(&self, name: String, theme: Theme) {
         let dirty = self.current_dirty.clone_ref();
         let handle = theme.on_mut(move || dirty.set());
@@ -0,0 +1,409 @@
+//! Accessibility audit of themes. Computes the WCAG 2 contrast ratios of declared pairs of
+//! foreground and background styles, like the node text over the node background, and suggests
+//! the nearest compliant `Lch` lightness for the failing foregrounds.
+//!
+//! See https://www.w3.org/TR/WCAG21/#contrast-minimum for the definitions used here.
+
+use crate::prelude::*;
+
+use crate::data::color;
+use crate::display::style::data::Data;
+use crate::display::style::sheet::Value;
+use crate::display::style::Path;
+
+use super::Theme;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// Number of lightness steps checked in each direction when looking for a suggestion.
+const SUGGESTION_STEPS: usize = 1000;
+
+/// Maximum number of nested expressions evaluated when resolving a style.
+const MAX_EXPRESSION_DEPTH: usize = 32;
+
+
+
+// =============
+// === Error ===
+// =============
+
+/// Reasons why a pair could not be audited.
+#[derive(Clone, Debug, Fail, PartialEq)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[fail(display = "The theme does not define '{}'.", path)]
+    MissingStyle { path: String },
+    #[fail(display = "The style '{}' is not a color.", path)]
+    NotAColor { path: String },
+}
+
+
+
+// =============
+// === Level ===
+// =============
+
+/// WCAG conformance levels of text contrast.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    /// Minimum contrast of large text (at least 18pt, or 14pt bold).
+    AaLarge,
+    /// Minimum contrast of normal text.
+    Aa,
+    /// Enhanced contrast of normal text.
+    Aaa,
+}
+
+impl Level {
+    /// The minimum contrast ratio required by this level.
+    pub fn min_ratio(self) -> f32 {
+        match self {
+            Self::AaLarge => 3.0,
+            Self::Aa => 4.5,
+            Self::Aaa => 7.0,
+        }
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::AaLarge => "AA large",
+            Self::Aa => "AA",
+            Self::Aaa => "AAA",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+
+// ================
+// === Contrast ===
+// ================
+
+/// The relative luminance of the color, as defined by WCAG.
+pub fn relative_luminance(color: color::Rgb) -> f32 {
+    let clamped = color::Rgb::new(
+        color.red.clamp(0.0, 1.0),
+        color.green.clamp(0.0, 1.0),
+        color.blue.clamp(0.0, 1.0),
+    );
+    let linear = clamped.into_linear();
+    0.2126 * linear.red + 0.7152 * linear.green + 0.0722 * linear.blue
+}
+
+/// The contrast ratio of two opaque colors, in range `[1.0 .. 21.0]`.
+pub fn contrast_ratio(first: color::Rgb, second: color::Rgb) -> f32 {
+    let first = relative_luminance(first);
+    let second = relative_luminance(second);
+    (first.max(second) + 0.05) / (first.min(second) + 0.05)
+}
+
+/// The opaque color seen when `foreground` is drawn over `background`.
+pub fn blend(foreground: color::Rgba, background: color::Rgb) -> color::Rgb {
+    let alpha = foreground.alpha;
+    let mix = |fg: f32, bg: f32| fg * alpha + bg * (1.0 - alpha);
+    color::Rgb::new(
+        mix(foreground.red, background.red),
+        mix(foreground.green, background.green),
+        mix(foreground.blue, background.blue),
+    )
+}
+
+/// The contrast ratio of a possibly translucent `foreground` drawn over `background`.
+pub fn contrast_over(foreground: color::Rgba, background: color::Rgb) -> f32 {
+    contrast_ratio(blend(foreground, background), background)
+}
+
+/// Find the foreground color with the lightness closest to the original one, which has at least
+/// the `min_ratio` contrast over the `background`. The chroma, hue, and alpha are preserved.
+/// Returns [`None`] if no lightness meets the requirement.
+pub fn suggest_lightness(
+    foreground: color::Rgba,
+    background: color::Rgb,
+    min_ratio: f32,
+) -> Option<color::Lcha> {
+    let original = color::Lcha::from(foreground);
+    let passes = |lightness: f32| {
+        let lightness = lightness.clamp(0.0, 1.0);
+        let candidate = color::Lcha::new(lightness, original.chroma, original.hue, original.alpha);
+        let passes = contrast_over(color::Rgba::from(candidate), background) >= min_ratio;
+        passes.then(|| candidate)
+    };
+    (0..=SUGGESTION_STEPS).find_map(|step| {
+        let delta = step as f32 / SUGGESTION_STEPS as f32;
+        let darker = original.lightness - delta;
+        let lighter = original.lightness + delta;
+        let darker = (darker >= 0.0).then(|| passes(darker)).flatten();
+        darker.or_else(|| (lighter <= 1.0).then(|| passes(lighter)).flatten())
+    })
+}
+
+
+
+// ============
+// === Pair ===
+// ============
+
+/// A declared pair of foreground and background styles, like a text color and the color of the
+/// panel the text is displayed on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pair {
+    /// Path of the foreground color.
+    pub foreground: Path,
+    /// Path of the background color.
+    pub background: Path,
+    /// Path of the color behind the background. Translucent backgrounds are blended over it. If
+    /// not set, the background alpha is ignored.
+    pub backdrop:   Option<Path>,
+    /// The required conformance level.
+    pub level:      Level,
+}
+
+impl Pair {
+    /// Constructor. The pair is required to conform to the [`Level::Aa`] level.
+    pub fn new(foreground: impl Into<Path>, background: impl Into<Path>) -> Self {
+        let foreground = foreground.into();
+        let background = background.into();
+        let backdrop = None;
+        let level = Level::Aa;
+        Self { foreground, background, backdrop, level }
+    }
+
+    /// Set the required conformance level.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set the color behind a translucent background.
+    pub fn over(mut self, backdrop: impl Into<Path>) -> Self {
+        self.backdrop = Some(backdrop.into());
+        self
+    }
+}
+
+impl Display for Pair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' over '{}'", self.foreground, self.background)?;
+        if let Some(backdrop) = &self.backdrop {
+            write!(f, " over '{}'", backdrop)?;
+        }
+        Ok(())
+    }
+}
+
+
+
+// ==============
+// === Report ===
+// ==============
+
+/// The audit result of a single pair.
+#[derive(Clone, Debug)]
+pub struct PairReport {
+    /// The audited pair.
+    pub pair:       Pair,
+    /// The resolved foreground color.
+    pub foreground: color::Rgba,
+    /// The resolved background color, blended over the backdrop if needed.
+    pub background: color::Rgb,
+    /// The contrast ratio of the foreground over the background.
+    pub ratio:      f32,
+    /// The nearest compliant foreground color, if the pair does not pass.
+    pub suggestion: Option<color::Lcha>,
+}
+
+impl PairReport {
+    /// Check whether the pair meets its required level.
+    pub fn passes(&self) -> bool {
+        self.ratio >= self.pair.level.min_ratio()
+    }
+}
+
+impl Display for PairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.passes() { "PASS" } else { "FAIL" };
+        let level = self.pair.level;
+        let min_ratio = level.min_ratio();
+        write!(f, "[{}] {}: {:.2}:1 ", status, self.pair, self.ratio)?;
+        write!(f, "({} requires {}:1)", level, min_ratio)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", suggested lightness {:.3}", suggestion.lightness)?;
+        }
+        Ok(())
+    }
+}
+
+/// The audit result of a theme.
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// Results of all the pairs which could be resolved.
+    pub pairs:  Vec<PairReport>,
+    /// Pairs which could not be resolved, with the reason.
+    pub errors: Vec<(Pair, Error)>,
+}
+
+impl Report {
+    /// Pairs not meeting their required level.
+    pub fn failures(&self) -> impl Iterator<Item = &PairReport> {
+        self.pairs.iter().filter(|pair| !pair.passes())
+    }
+
+    /// Check whether all the pairs were resolved and meet their required levels.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty() && self.failures().next().is_none()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pair in &self.pairs {
+            writeln!(f, "{}", pair)?;
+        }
+        for (pair, error) in &self.errors {
+            writeln!(f, "[ERROR] {}: {}", pair, error)?;
+        }
+        Ok(())
+    }
+}
+
+
+
+// =============
+// === Audit ===
+// =============
+
+/// Audit the contrast of the declared pairs in the theme.
+pub fn audit(theme: &Theme, pairs: &[Pair]) -> Report {
+    let values: HashMap<String, Value> = theme.values().into_iter().collect();
+    let mut report = Report::default();
+    for pair in pairs {
+        match audit_pair(&values, pair) {
+            Ok(pair_report) => report.pairs.push(pair_report),
+            Err(error) => report.errors.push((pair.clone(), error)),
+        }
+    }
+    report
+}
+
+fn audit_pair(values: &HashMap<String, Value>, pair: &Pair) -> Result<PairReport, Error> {
+    let foreground = resolve_color(values, &pair.foreground)?;
+    let background = resolve_color(values, &pair.background)?;
+    let background = match &pair.backdrop {
+        Some(backdrop) => blend(background, resolve_color(values, backdrop)?.opaque),
+        None => background.opaque,
+    };
+    let ratio = contrast_over(foreground, background);
+    let min_ratio = pair.level.min_ratio();
+    let suggestion =
+        (ratio < min_ratio).then(|| suggest_lightness(foreground, background, min_ratio)).flatten();
+    let pair = pair.clone();
+    Ok(PairReport { pair, foreground, background, ratio, suggestion })
+}
+
+fn resolve_color(values: &HashMap<String, Value>, path: &Path) -> Result<color::Rgba, Error> {
+    let path = path.to_string();
+    match resolve(values, &path, MAX_EXPRESSION_DEPTH) {
+        None => Err(Error::MissingStyle { path }),
+        Some(Data::Color(color)) => Ok(color),
+        Some(_) => Err(Error::NotAColor { path }),
+    }
+}
+
+/// Resolve the value of the style, evaluating expressions in the same way as the style sheet.
+fn resolve(values: &HashMap<String, Value>, path: &str, depth: usize) -> Option<Data> {
+    match values.get(path)? {
+        Value::Data(data) => Some(data.clone()),
+        Value::Expression(_) if depth == 0 => None,
+        Value::Expression(expr) => {
+            let args = expr.args.iter().map(|arg| resolve(values, &arg.to_string(), depth - 1));
+            let args = args.collect::<Option<Vec<_>>>()?;
+            let args = args.iter().collect_vec();
+            Some((expr.function)(&args))
+        }
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_approx(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.01, "{} is not close to {}.", actual, expected);
+    }
+
+    #[test]
+    fn contrast_ratios() {
+        let black = color::Rgb::new(0.0, 0.0, 0.0);
+        let white = color::Rgb::new(1.0, 1.0, 1.0);
+        assert_approx(contrast_ratio(black, white), 21.0);
+        assert_approx(contrast_ratio(white, black), 21.0);
+        assert_approx(contrast_ratio(white, white), 1.0);
+        // The WCAG reference value for rgb(118,118,118) over white is 4.54.
+        let gray = color::Rgb::from_base_255(118.0, 118.0, 118.0);
+        assert_approx(contrast_ratio(gray, white), 4.54);
+        let translucent_black = color::Rgba::new(0.0, 0.0, 0.0, 0.0);
+        assert_approx(contrast_over(translucent_black, white), 1.0);
+    }
+
+    #[test]
+    fn suggesting_lightness() {
+        let white = color::Rgb::new(1.0, 1.0, 1.0);
+        let light_gray = color::Rgba::from(color::Lcha::new(0.8, 0.0, 0.0, 1.0));
+        let suggestion = suggest_lightness(light_gray, white, Level::Aa.min_ratio()).unwrap();
+        assert!(suggestion.lightness < 0.8);
+        let suggested = color::Rgba::from(suggestion);
+        assert!(contrast_over(suggested, white) >= Level::Aa.min_ratio());
+        let slightly_darker = color::Lcha::new(suggestion.lightness + 0.01, 0.0, 0.0, 1.0);
+        assert!(contrast_over(color::Rgba::from(slightly_darker), white) < Level::Aa.min_ratio());
+        let faint = color::Rgba::new(0.0, 0.0, 0.0, 0.1);
+        assert_eq!(suggest_lightness(faint, white, Level::Aa.min_ratio()), None);
+    }
+
+    #[test]
+    fn auditing_theme() {
+        let theme = Theme::new();
+        theme.set("panel.background", color::Rgba::new(1.0, 1.0, 1.0, 1.0));
+        theme.set("panel.overlay", color::Rgba::new(0.0, 0.0, 0.0, 0.7));
+        theme.set("panel.text", color::Rgba::new(0.0, 0.0, 0.0, 1.0));
+        theme.set("panel.hint", color::Lcha::new(0.9, 0.0, 0.0, 1.0));
+        theme.set("panel.label", Path::from("panel.text"));
+        theme.set("panel.size", 12.0);
+        let pairs = [
+            Pair::new("panel.text", "panel.background").level(Level::Aaa),
+            Pair::new("panel.label", "panel.background"),
+            Pair::new("panel.hint", "panel.background"),
+            Pair::new("panel.text", "panel.overlay").over("panel.background"),
+            Pair::new("panel.size", "panel.background"),
+            Pair::new("panel.missing", "panel.background"),
+        ];
+        let report = audit(&theme, &pairs);
+        assert!(!report.is_ok());
+        let ratios = report.pairs.iter().map(|pair| pair.ratio).collect_vec();
+        assert_eq!(ratios.len(), 4);
+        assert_approx(ratios[0], 21.0);
+        assert_approx(ratios[1], 21.0);
+        let failures = report.failures().map(|pair| pair.pair.clone()).collect_vec();
+        assert_eq!(failures, vec![pairs[2].clone(), pairs[3].clone()]);
+        assert!(report.pairs[0].suggestion.is_none());
+        assert!(report.pairs[2].suggestion.is_some());
+        let errors = report.errors.iter().map(|(_, error)| error.clone()).collect_vec();
+        let expected_errors =
+            vec![Error::NotAColor { path: "panel.size".into() }, Error::MissingStyle {
+                path: "panel.missing".into(),
+            }];
+        assert_eq!(errors, expected_errors);
+    }
+}
@@ -0,0 +1,421 @@
+//! Themes loaded at runtime from TOML or JSON files.
+//!
+//! A theme file is a (possibly nested) table of style paths and values, for example:
+//!
+//! ```toml
+//! extends = "light"
+//!
+//! [graph_editor.node]
+//! background = "rgba(1.0,1.0,1.0,1.0)"
+//! corner_radius = 14.0
+//! ```
+//!
+//! Numbers become [`Data::Number`], strings parsed by [`color::AnyFormat`] become
+//! [`Data::Color`], and other strings become [`Data::Text`]. The optional `extends` key names a
+//! registered theme the file inherits all styles from. Entries are validated against a [`Schema`],
+//! which is usually generated from the compile-time theme definitions, and invalid entries are
+//! reported as [`Issue`]s instead of being applied.
+
+use crate::prelude::*;
+
+use crate::data::color;
+use crate::display::style::data::Data;
+use crate::display::style::sheet::Value;
+use crate::display::style::Path;
+
+use super::Theme;
+
+use std::collections::BTreeMap;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// The key naming the base theme of a theme file.
+pub const EXTENDS_KEY: &str = "extends";
+
+/// Maximum number of references followed when resolving the kind of a style in [`Schema`].
+const MAX_REFERENCE_DEPTH: usize = 32;
+
+
+
+// =============
+// === Error ===
+// =============
+
+/// Errors preventing a theme file from being loaded.
+#[derive(Clone, Debug, Fail)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[fail(display = "Cannot parse the theme file: {}", message)]
+    Parse { message: String },
+    #[fail(display = "The root of a theme file must be a table of styles.")]
+    NotATable,
+    #[fail(display = "The 'extends' key must be a name of a theme.")]
+    InvalidBase,
+    #[fail(display = "Unknown base theme '{}'.", name)]
+    UnknownBase { name: String },
+    #[fail(display = "Theme '{}' cannot extend itself.", name)]
+    SelfExtending { name: String },
+}
+
+/// Problems with single entries of a theme file. Entries with issues are skipped, and the rest of
+/// the file is loaded.
+#[derive(Clone, Debug, Fail, PartialEq)]
+#[allow(missing_docs)]
+pub enum Issue {
+    #[fail(display = "Unknown theme path '{}'.", path)]
+    UnknownPath { path: String },
+    #[fail(display = "Wrong type of '{}': expected {}, found {}.", path, expected, found)]
+    WrongType { path: String, expected: Kind, found: Kind },
+    #[fail(display = "Unsupported value of '{}': {}.", path, value)]
+    UnsupportedValue { path: String, value: String },
+}
+
+
+
+// ==============
+// === Format ===
+// ==============
+
+/// Supported theme file formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// Guess the format from a file name or a bare extension, like `dark.toml` or `json`.
+    pub fn from_extension(file_name: &str) -> Option<Self> {
+        let extension = file_name.rsplit('.').next().unwrap_or_default().to_lowercase();
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    fn parse(self, source: &str) -> Result<serde_json::Value, Error> {
+        let parse_error = |message: String| Error::Parse { message };
+        match self {
+            Self::Json => serde_json::from_str(source).map_err(|e| parse_error(e.to_string())),
+            Self::Toml => {
+                let value = source.parse::<toml::Value>();
+                let value = value.map_err(|e| parse_error(e.to_string()))?;
+                serde_json::to_value(value).map_err(|e| parse_error(e.to_string()))
+            }
+        }
+    }
+}
+
+
+
+// ============
+// === Kind ===
+// ============
+
+/// Type of a style value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Kind {
+    Number,
+    Color,
+    Text,
+}
+
+impl Kind {
+    /// The kind of the given data, if it is valid.
+    pub fn of(data: &Data) -> Option<Self> {
+        match data {
+            Data::Invalid(_) => None,
+            Data::Number(_) => Some(Self::Number),
+            Data::Color(_) => Some(Self::Color),
+            Data::Text(_) => Some(Self::Text),
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Number => "number",
+            Self::Color => "color",
+            Self::Text => "text",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+
+// ==============
+// === Schema ===
+// ==============
+
+/// Set of known style paths with the kinds of their values. Paths whose kind cannot be determined
+/// (e.g. computed by an expression) accept values of any kind.
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    kinds: BTreeMap<String, Option<Kind>>,
+}
+
+impl Schema {
+    /// Constructor. The kinds of the `paths` are taken from the values of the `reference` theme.
+    /// Style references are followed to the referenced value.
+    pub fn new<'a>(paths: impl IntoIterator<Item = &'a str>, reference: &Theme) -> Self {
+        let values: HashMap<String, Value> = reference.values().into_iter().collect();
+        let kind_of = |path: &str| Self::resolve_kind(&values, path, MAX_REFERENCE_DEPTH);
+        let kinds = paths.into_iter().map(|path| (path.to_owned(), kind_of(path))).collect();
+        Self { kinds }
+    }
+
+    /// Schema containing all the paths defined in the given theme.
+    pub fn from_theme(theme: &Theme) -> Self {
+        let paths = theme.values().into_iter().map(|(path, _)| path).collect_vec();
+        Self::new(paths.iter().map(|path| path.as_str()), theme)
+    }
+
+    fn resolve_kind(values: &HashMap<String, Value>, path: &str, depth: usize) -> Option<Kind> {
+        match values.get(path)? {
+            Value::Data(data) => Kind::of(data),
+            Value::Expression(_) if depth == 0 => None,
+            Value::Expression(expr) => match expr.args.as_slice() {
+                [arg] => Self::resolve_kind(values, &arg.to_string(), depth - 1),
+                _ => None,
+            },
+        }
+    }
+
+    /// Check whether the path is known.
+    pub fn contains(&self, path: &str) -> bool {
+        self.kinds.contains_key(path)
+    }
+
+    /// The kind of values expected at the path, if the path is known and its kind is determined.
+    pub fn kind(&self, path: &str) -> Option<Kind> {
+        self.kinds.get(path).copied().flatten()
+    }
+
+    /// All known paths, sorted.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.kinds.keys().map(|path| path.as_str())
+    }
+
+    /// Check whether the data can be assigned to the path.
+    pub fn check(&self, path: &str, data: &Data) -> Result<(), Issue> {
+        let path = path.to_owned();
+        match self.kinds.get(&path) {
+            None => Err(Issue::UnknownPath { path }),
+            Some(expected) => match (*expected, Kind::of(data)) {
+                (Some(expected), Some(found)) if expected != found =>
+                    Err(Issue::WrongType { path, expected, found }),
+                (_, None) => Err(Issue::UnsupportedValue { path, value: data.to_string() }),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+
+
+// =================
+// === ThemeFile ===
+// =================
+
+/// Parsed contents of a theme file.
+#[derive(Clone, Debug, Default)]
+pub struct ThemeFile {
+    /// The name of the theme this file inherits from.
+    pub extends: Option<String>,
+    /// Styles defined in the file, sorted by path.
+    pub entries: Vec<(String, Data)>,
+    /// Problems found in the file so far. Entries with issues are not present in `entries`.
+    pub issues:  Vec<Issue>,
+}
+
+impl ThemeFile {
+    /// Parse the source of a theme file.
+    pub fn parse(source: &str, format: Format) -> Result<Self, Error> {
+        let root = match format.parse(source)? {
+            serde_json::Value::Object(root) => root,
+            _ => return Err(Error::NotATable),
+        };
+        let mut file = Self::default();
+        for (key, value) in root {
+            if key == EXTENDS_KEY {
+                match value {
+                    serde_json::Value::String(name) => file.extends = Some(name),
+                    _ => return Err(Error::InvalidBase),
+                }
+            } else {
+                file.add_value(key, value);
+            }
+        }
+        file.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(file)
+    }
+
+    fn add_value(&mut self, path: String, value: serde_json::Value) {
+        match value {
+            serde_json::Value::Object(table) =>
+                for (key, value) in table {
+                    self.add_value(format!("{}.{}", path, key), value);
+                },
+            serde_json::Value::Number(number) => match number.as_f64() {
+                Some(number) => self.entries.push((path, Data::Number(number as f32))),
+                None => self.unsupported(path, number),
+            },
+            serde_json::Value::String(text) => {
+                let data = match text.parse::<color::AnyFormat>() {
+                    Ok(color) => Data::Color(color.into()),
+                    Err(_) => Data::Text(text),
+                };
+                self.entries.push((path, data));
+            }
+            other => self.unsupported(path, other),
+        }
+    }
+
+    fn unsupported(&mut self, path: String, value: impl ToString) {
+        let value = value.to_string();
+        self.issues.push(Issue::UnsupportedValue { path, value });
+    }
+
+    /// Validate the entries against the schema. Entries not matching the schema are removed and
+    /// reported in `issues`.
+    pub fn validate(&mut self, schema: &Schema) {
+        for (path, data) in mem::take(&mut self.entries) {
+            match schema.check(&path, &data) {
+                Ok(()) => self.entries.push((path, data)),
+                Err(issue) => self.issues.push(issue),
+            }
+        }
+    }
+
+    /// Create a theme with all the styles of the `base` theme, overridden by the file entries.
+    pub fn to_theme(&self, base: Option<&Theme>) -> Theme {
+        let theme = base.map(|base| base.deep_clone()).unwrap_or_default();
+        for (path, data) in &self.entries {
+            theme.set(Path::from(path.as_str()), data.clone());
+        }
+        theme
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::style::data::DataMatch;
+    use crate::display::style::theme::Manager;
+
+    fn base_theme() -> Theme {
+        let theme = Theme::new();
+        theme.set("application.background", color::Rgba::new(1.0, 1.0, 1.0, 1.0));
+        theme.set("application.text.font", "DejaVuSans");
+        theme.set("application.text.size", 12.0);
+        theme.set("application.text.color", Path::from("application.background"));
+        theme
+    }
+
+    fn value(theme: &Theme, path: &str) -> Option<Data> {
+        theme.values().into_iter().find(|(p, _)| p == path).and_then(|(_, value)| match value {
+            Value::Data(data) => Some(data),
+            Value::Expression(_) => None,
+        })
+    }
+
+    #[test]
+    fn parsing_formats() {
+        let json = r#"{
+            "extends": "light",
+            "application": { "background": "rgba(0.0,0.0,0.0,1.0)", "text.size": 14 }
+        }"#;
+        let toml = r#"
+            extends = "light"
+            [application]
+            background = "rgba(0.0,0.0,0.0,1.0)"
+            text.size = 14
+        "#;
+        for (source, format) in [(json, Format::Json), (toml, Format::Toml)] {
+            let file = ThemeFile::parse(source, format).unwrap();
+            assert_eq!(file.extends.as_deref(), Some("light"));
+            let paths = file.entries.iter().map(|(path, _)| path.as_str()).collect_vec();
+            assert_eq!(paths, vec!["application.background", "application.text.size"]);
+            assert_eq!(file.entries[0].1, Data::Color(color::Rgba::new(0.0, 0.0, 0.0, 1.0)));
+            assert_eq!(file.entries[1].1, Data::Number(14.0));
+            assert!(file.issues.is_empty());
+        }
+        assert_eq!(Format::from_extension("themes/dark.TOML"), Some(Format::Toml));
+        assert_eq!(Format::from_extension("json"), Some(Format::Json));
+        assert_eq!(Format::from_extension("dark.yaml"), None);
+        assert!(matches!(ThemeFile::parse("[1,2]", Format::Json), Err(Error::NotATable)));
+        assert!(matches!(ThemeFile::parse("{", Format::Json), Err(Error::Parse { .. })));
+        assert!(matches!(ThemeFile::parse("extends = 1", Format::Toml), Err(Error::InvalidBase)));
+    }
+
+    #[test]
+    fn validating_against_schema() {
+        let schema = Schema::from_theme(&base_theme());
+        assert_eq!(schema.kind("application.background"), Some(Kind::Color));
+        assert_eq!(schema.kind("application.text.color"), Some(Kind::Color));
+        assert_eq!(schema.kind("application.text.font"), Some(Kind::Text));
+        let source = r#"{
+            "application.background": 1.0,
+            "application.text.font": "DejaVuSansMono",
+            "application.text.sise": 14,
+            "application.text.size": [14]
+        }"#;
+        let mut file = ThemeFile::parse(source, Format::Json).unwrap();
+        file.validate(&schema);
+        let paths = file.entries.iter().map(|(path, _)| path.as_str()).collect_vec();
+        assert_eq!(paths, vec!["application.text.font"]);
+        let expected_issues = vec![
+            Issue::UnsupportedValue { path: "application.text.size".into(), value: "[14]".into() },
+            Issue::WrongType {
+                path:     "application.background".into(),
+                expected: Kind::Color,
+                found:    Kind::Number,
+            },
+            Issue::UnknownPath { path: "application.text.sise".into() },
+        ];
+        assert_eq!(file.issues, expected_issues);
+    }
+
+    #[test]
+    fn loading_and_hot_reloading() {
+        let manager = Manager::new();
+        manager.register("base", base_theme());
+        manager.set_schema(Schema::from_theme(&base_theme()));
+
+        let source = "extends = 'base'\napplication.text.size = 16\napplication.txt = 1";
+        let issues = manager.load("custom", source, Format::Toml).unwrap();
+        assert_eq!(issues, vec![Issue::UnknownPath { path: "application.txt".into() }]);
+        let custom = manager.get("custom").unwrap();
+        assert_eq!(value(&custom, "application.text.size").number(), Some(16.0));
+        assert_eq!(value(&custom, "application.text.font").text(), Some("DejaVuSans".into()));
+
+        let changed = Rc::new(Cell::new(0));
+        let _handle = custom.on_mut(f!(changed.set(changed.get() + 1)));
+        let source = "application.text.size = 18";
+        assert_eq!(manager.load("custom", source, Format::Toml).unwrap(), vec![]);
+        assert_eq!(changed.get(), 1);
+        let reloaded = manager.get("custom").unwrap();
+        assert_eq!(value(&reloaded, "application.text.size").number(), Some(18.0));
+        assert_eq!(value(&reloaded, "application.text.font"), None);
+
+        let self_extending = manager.load("base", "extends = 'base'", Format::Toml);
+        assert!(matches!(self_extending, Err(Error::SelfExtending { .. })));
+        let unknown_base = manager.load("other", "extends = 'none'", Format::Toml);
+        assert!(matches!(unknown_base, Err(Error::UnknownBase { .. })));
+    }
+}
@@ -1,18 +1,246 @@
-//! This module defines FRP Graphviz bindings. It allows visualizing the FRP network as Graphviz
-//! diagram.
-//! WARNING
-//! THIS MODULE IS IN A VERY WORK-IN-PROGRESS STATE. IT WILL BE CHANGED SOON.
-
-// === Non-Standard Linter Configuration ===
-#![allow(missing_docs)]
-#![allow(dead_code)]
+//! Debugging utilities of FRP networks. This module allows inspecting the structure of an FRP
+//! network (its nodes, their labels, output types, and links between them), exporting it as a
+//! Graphviz diagram or JSON document, and recording which nodes fired during a propagation of a
+//! single input event.
 
 use crate::prelude::*;
 
+use crate::network::LinkType;
+use crate::node::Id;
+use crate::node::Label;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+
+
+// ================
+// === NodeInfo ===
+// ================
+
+/// Description of a single FRP node.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NodeInfo {
+    /// Unique identifier of the node. It is stable for the lifetime of the node only.
+    pub id:          usize,
+    /// Label of the node, usually the name of the variable it was assigned to in the network
+    /// definition.
+    pub label:       String,
+    /// Node type, like `Map2` or `Gate`.
+    pub variant:     String,
+    /// Name of the type of values emitted by the node.
+    pub output_type: String,
+}
+
+
+
+// ================
+// === EdgeInfo ===
+// ================
+
+/// Kind of connection between FRP nodes.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[allow(missing_docs)]
+pub enum EdgeKind {
+    /// The target node is notified about every event emitted by the source node.
+    Event,
+    /// The target node samples the last value of the source node when it needs it.
+    Behavior,
+    /// The connection has both event and behavior semantics.
+    Mixed,
+}
+
+impl From<LinkType> for EdgeKind {
+    fn from(tp: LinkType) -> Self {
+        match tp {
+            LinkType::Event => Self::Event,
+            LinkType::Behavior => Self::Behavior,
+            LinkType::Mixed => Self::Mixed,
+        }
+    }
+}
+
+/// Description of a connection between two FRP nodes. Please note that the source or the target
+/// of the edge may belong to another network.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[allow(missing_docs)]
+pub struct EdgeInfo {
+    pub source: usize,
+    pub target: usize,
+    pub kind:   EdgeKind,
+}
+
+impl EdgeInfo {
+    /// Constructor.
+    pub fn new(source: Id, target: Id, kind: impl Into<EdgeKind>) -> Self {
+        let source = source.into();
+        let target = target.into();
+        let kind = kind.into();
+        Self { source, target, kind }
+    }
+}
+
+
+
+// ===================
+// === NetworkInfo ===
+// ===================
+
+/// A snapshot of the structure of an FRP network. Can be obtained by `Network::introspect`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[allow(missing_docs)]
+pub struct NetworkInfo {
+    pub label: String,
+    pub nodes: Vec<NodeInfo>,
+    pub edges: Vec<EdgeInfo>,
+}
+
+impl NetworkInfo {
+    /// Find the description of the node with the given id.
+    pub fn node(&self, id: impl Into<usize>) -> Option<&NodeInfo> {
+        let id = id.into();
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Find all nodes with the given label.
+    pub fn nodes_labeled<'a>(&'a self, label: &'a str) -> impl Iterator<Item = &'a NodeInfo> {
+        self.nodes.iter().filter(move |node| node.label == label)
+    }
+
+    /// All edges going out of the node with the given id.
+    pub fn edges_from(&self, id: impl Into<usize>) -> impl Iterator<Item = &EdgeInfo> {
+        let id = id.into();
+        self.edges.iter().filter(move |edge| edge.source == id)
+    }
+
+    /// All edges going into the node with the given id.
+    pub fn edges_to(&self, id: impl Into<usize>) -> impl Iterator<Item = &EdgeInfo> {
+        let id = id.into();
+        self.edges.iter().filter(move |edge| edge.target == id)
+    }
+
+    /// Export the network as a Graphviz Dot code.
+    pub fn to_graphviz(&self) -> String {
+        let mut viz = Graphviz::default();
+        for node in &self.nodes {
+            viz.add_node(node.id, &node.variant, &node.label);
+        }
+        for edge in &self.edges {
+            viz.add_link(edge.source, edge.target, edge.kind);
+        }
+        viz.into()
+    }
+
+    /// Export the network as a JSON document.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Serialization of network info cannot fail.")
+    }
+}
+
+
+
+// =============
+// === Trace ===
+// =============
+
+/// A single event emitted by an FRP node.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TraceEntry {
+    /// The id of the node which emitted the event.
+    pub id:    usize,
+    /// The label of the node which emitted the event.
+    pub label: String,
+    /// The nesting level of the event. Events emitted directly by the input node have depth 0,
+    /// events emitted by their targets have depth 1, and so on.
+    pub depth: usize,
+    /// The `Debug` representation of the emitted value.
+    pub value: String,
+}
+
+/// A list of events emitted during propagation of an input event, in the order of emission. Can be
+/// recorded by `trace` or `Network::trace_propagation`.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Trace {
+    /// Emitted events, in the order of emission.
+    pub entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    /// Labels of the nodes which fired, in the order of emission.
+    pub fn labels(&self) -> Vec<&str> {
+        self.entries.iter().map(|entry| entry.label.as_str()).collect()
+    }
+
+    /// Export the trace as a JSON document.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Serialization of a trace cannot fail.")
+    }
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            let indent = "  ".repeat(entry.depth);
+            writeln!(f, "{}{} = {}", indent, entry.label, entry.value)?;
+        }
+        Ok(())
+    }
+}
+
+
+// === Tracer ===
 
+#[derive(Debug, Default)]
+struct Tracer {
+    entries: Vec<TraceEntry>,
+    depth:   usize,
+}
+
+thread_local! {
+    static TRACER: RefCell<Option<Tracer>> = default();
+    static TRACING: Cell<bool> = default();
+}
+
+/// Checks whether the events are being traced currently. It is cheap and is meant to be called
+/// before every event emission.
+pub(crate) fn is_tracing() -> bool {
+    TRACING.with(|tracing| tracing.get())
+}
+
+/// Record an event emission. Must be paired with `trace_emission_end`.
+pub(crate) fn trace_emission_start(id: Id, label: Label, value: &dyn Debug) {
+    TRACER.with(|tracer| {
+        if let Some(tracer) = tracer.borrow_mut().as_mut() {
+            let id = id.into();
+            let label = label.into();
+            let depth = tracer.depth;
+            let value = format!("{:?}", value);
+            tracer.entries.push(TraceEntry { id, label, depth, value });
+            tracer.depth += 1;
+        }
+    })
+}
+
+/// Mark the end of the event emission started with `trace_emission_start`.
+pub(crate) fn trace_emission_end() {
+    TRACER.with(|tracer| {
+        if let Some(tracer) = tracer.borrow_mut().as_mut() {
+            tracer.depth = tracer.depth.saturating_sub(1);
+        }
+    })
+}
+
+/// Record all events emitted by all FRP nodes while evaluating `f`. Traces can be nested, in such
+/// a case the inner trace does not report its events to the outer one.
+pub fn trace(f: impl FnOnce()) -> Trace {
+    let outer = TRACER.with(|tracer| tracer.borrow_mut().replace(default()));
+    TRACING.with(|tracing| tracing.set(true));
+    f();
+    let tracer = TRACER.with(|tracer| mem::replace(&mut *tracer.borrow_mut(), outer));
+    TRACING.with(|tracing| tracing.set(TRACER.with(|tracer| tracer.borrow().is_some())));
+    Trace { entries: tracer.map(|t| t.entries).unwrap_or_default() }
+}
 
-//use crate::DataType;
-//
 
 
 // ================
@@ -39,14 +267,13 @@ impl VizNode {
 pub struct VizLink {
     source_display_id: usize,
     target_display_id: usize,
-    //    message_type      : DataType,
-    data_type:         String,
+    kind:              EdgeKind,
 }
 
 impl VizLink {
     /// Constructor.
-    pub fn new(source_display_id: usize, target_display_id: usize, data_type: String) -> Self {
-        Self { source_display_id, target_display_id, data_type }
+    pub fn new(source_display_id: usize, target_display_id: usize, kind: EdgeKind) -> Self {
+        Self { source_display_id, target_display_id, kind }
     }
 }
 
@@ -54,9 +281,8 @@ impl VizLink {
 /// Graphviz FRP system visualizer.
 #[derive(Debug, Default)]
 pub struct Graphviz {
-    nodes:  HashMap<usize, VizNode>,
-    labels: HashMap<usize, String>,
-    links:  Vec<VizLink>,
+    nodes: BTreeMap<usize, VizNode>,
+    links: Vec<VizLink>,
 }
 
 impl Graphviz {
@@ -64,45 +290,22 @@ impl Graphviz {
     pub fn add_node<Tp: Str, Label: Str>(&mut self, id: usize, tp: Tp, label: Label) {
         let tp = tp.into();
         let label = label.into();
-        self.nodes.insert(id, VizNode::new(tp, label.clone()));
-        self.labels.insert(id, label);
-    }
-    //
-    //    /// Defines a new link between nodes.
-    //    pub fn add_link<S:Str>
-    //    (&mut self, source:usize, target:usize, message_type:DataType, data_type:S) {
-    //        let link = VizLink::new(source,target,message_type,data_type.into());
-    //        self.links.push(link);
-    //    }
-    //
-    //    /// Checks if a node with the given id is already registered in the node map.
-    //    pub fn contains(&mut self, id:usize) -> bool {
-    //        self.nodes.contains_key(&id)
-    //    }
-    //
-    //    /// Takes a set of nodes and outputs a map from `display_id` to a particular node. In case
-    // the    /// `display_id` points to several nodes, the node types `Hold` and `Recursive`
-    // has weaker    /// preference.
-    //    fn create_node_map(&self) -> HashMap<usize,VizNode> {
-    //        let mut node_map : HashMap<usize,VizNode> = default();
-    //        for node in self.nodes.values() {
-    //            let entry        = node_map.entry(node.display_id);
-    //            let merged_entry = entry.and_modify(|node2|{
-    //                let variant = &node2.variant;
-    //                if variant == "Hold" || variant == "Recursive" {
-    //                    *node2 = node.clone();
-    //                }
-    //            });
-    //            merged_entry.or_insert_with(|| node.clone());
-    //        }
-    //        node_map
-    //    }
-    //
-    //    /// Outputs a Graphviz Dot code.
+        self.nodes.insert(id, VizNode::new(tp, label));
+    }
+
+    /// Defines a new link between nodes.
+    pub fn add_link(&mut self, source: usize, target: usize, kind: EdgeKind) {
+        self.links.push(VizLink::new(source, target, kind));
+    }
+
+    /// Checks if a node with the given id is already registered in the node map.
+    pub fn contains(&self, id: usize) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
+    /// Outputs a Graphviz Dot code.
     pub fn to_code(&self) -> String {
         let mut code = String::default();
-        //        let node_map = self.create_node_map();
-        //
         for (idx, node) in self.nodes.iter() {
             let color = match node.variant.as_str() {
                 "Toggle" => "534666",
@@ -119,24 +322,23 @@ impl Graphviz {
             let line = iformat!("\n{idx} {fill} {label}");
             code.push_str(&line);
         }
-        //
-        //        for link in &self.links {
-        //            let source    = &link.source_display_id;
-        //            let target    = &link.target_display_id;
-        //            let data_type = &link.data_type;
-        //            let not_loop  = source != target;
-        //            if not_loop {
-        //                let style = match link.message_type {
-        //                    DataType::Behavior => "[style=\"dashed\"]",
-        //                    _ => ""
-        //                };
-        //                let label = if data_type == "()" { "" } else { &data_type };
-        //                let label = iformat!("[label=\"  {label}\"]");
-        //                let line  = iformat!("\n{source} -> {target} {style} {label}");
-        //                code.push_str(&line);
-        //            }
-        //        }
-        //
+        for link in &self.links {
+            let source = &link.source_display_id;
+            let target = &link.target_display_id;
+            for id in [source, target] {
+                if !self.contains(*id) {
+                    let line = iformat!("\n{id} [label=\"<external>\" fillcolor=\"#aaaaaa\"]");
+                    code.push_str(&line);
+                }
+            }
+            let style = match link.kind {
+                EdgeKind::Event => "",
+                EdgeKind::Behavior => "[style=\"dashed\"]",
+                EdgeKind::Mixed => "[style=\"bold\"]",
+            };
+            let line = iformat!("\n{source} -> {target} {style}");
+            code.push_str(&line);
+        }
         let fonts = "[fontname=\"Helvetica Neue\" fontsize=11]";
         let node_shape = "[shape=box penwidth=0 margin=0.12 style=\"rounded,filled\"]";
         let node_style = "[fontcolor=white fillcolor=\"#5397dc\"]";
@@ -145,7 +347,6 @@ impl Graphviz {
         let nodes_cfg = iformat!("node {fonts} {node_shape} {node_style};");
         let edges_cfg = iformat!("edge {fonts} {edge_style};");
         iformat!("digraph G {{ \n{graph_cfg} \n{nodes_cfg} \n{edges_cfg} \n{code} \n}}")
-        //        todo!()
     }
 }
 
@@ -175,16 +376,18 @@ pub trait GraphvizBuilder {
 
     /// Converts the current object to Graphviz and displays it in a new tab in a web browser.
     fn display_graphviz(&self) {
-        let code = self.to_graphviz();
-        let url = percent_encoding::utf8_percent_encode(&code, percent_encoding::NON_ALPHANUMERIC);
-        let url = format!("https://dreampuf.github.io/GraphvizOnline/#{}", url);
-        crate::web::window.open_with_url_and_target(&url, "_blank").unwrap();
+        display_graphviz_code(&self.to_graphviz())
     }
 }
 
+/// Displays the provided Graphviz diagram in a new tab in a web browser.
 pub fn display_graphviz(viz: Graphviz) {
-    let code: String = viz.into();
-    let url = percent_encoding::utf8_percent_encode(&code, percent_encoding::NON_ALPHANUMERIC);
+    display_graphviz_code(&String::from(viz))
+}
+
+/// Displays the provided Graphviz Dot code in a new tab in a web browser.
+pub fn display_graphviz_code(code: &str) {
+    let url = percent_encoding::utf8_percent_encode(code, percent_encoding::NON_ALPHANUMERIC);
     let url = format!("https://dreampuf.github.io/GraphvizOnline/#{}", url);
     crate::web::window.open_with_url_and_target(&url, "_blank").unwrap();
 }
@@ -199,3 +402,62 @@ where
         self.content().graphviz_build(builder)
     }
 }
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as frp;
+
+    #[test]
+    fn introspection() {
+        frp::new_network! { network
+            def source  = source::<usize>();
+            def flag    = source::<bool>();
+            def gated   = source.gate(&flag);
+            def counter = gated.count();
+        }
+        let info = network.introspect();
+        assert_eq!(info.nodes.len(), 4);
+        let node = |label| info.nodes_labeled(label).next().unwrap().id;
+        let edge = |source, target, kind| EdgeInfo { source, target, kind };
+        let (source, flag, gated, counter) =
+            (node("source"), node("flag"), node("gated"), node("counter"));
+        assert!(info.edges.contains(&edge(source, gated, EdgeKind::Event)));
+        assert!(info.edges.contains(&edge(flag, gated, EdgeKind::Behavior)));
+        assert!(info.edges.contains(&edge(gated, counter, EdgeKind::Event)));
+        assert_eq!(info.edges.len(), 3);
+        assert_eq!(info.node(counter).unwrap().output_type, "usize");
+
+        let dot = info.to_graphviz();
+        assert!(dot.contains(&format!("{} -> {}", source, gated)));
+        assert!(dot.contains(&format!("{} -> {} [style=\"dashed\"]", flag, gated)));
+        let json: NetworkInfo = serde_json::from_str(&info.to_json()).unwrap();
+        assert_eq!(json, info);
+    }
+
+    #[test]
+    fn tracing() {
+        frp::new_network! { network
+            def source  = source::<usize>();
+            def doubled = source.map(|t| t * 2);
+            def text    = doubled.map(|t| t.to_string());
+        }
+        let trace = network.trace_propagation(|| source.emit(4));
+        assert_eq!(trace.labels(), vec!["source", "doubled", "text"]);
+        let values = trace.entries.iter().map(|e| e.value.as_str()).collect_vec();
+        assert_eq!(values, vec!["4", "8", "\"8\""]);
+        let depths = trace.entries.iter().map(|e| e.depth).collect_vec();
+        assert_eq!(depths, vec![0, 1, 2]);
+
+        // Events emitted outside of the traced closure are not recorded.
+        source.emit(5);
+        assert_eq!(trace.entries.len(), 3);
+        assert!(!is_tracing());
+    }
+}
@@ -8,5 +8,6 @@
 pub mod js;
 pub mod keyboard;
 pub mod mouse;
+pub mod record;
 
 pub use mouse::Mouse;
@@ -8,6 +8,8 @@ use crate::io::js::Listener;
 
 use enso_web::KeyboardEvent;
 use inflector::Inflector;
+use serde::Deserialize;
+use serde::Serialize;
 use unicode_segmentation::UnicodeSegmentation;
 
 
@@ -17,7 +19,7 @@ use unicode_segmentation::UnicodeSegmentation;
 // ============
 
 /// The key placement enum.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 #[allow(missing_docs)]
 pub enum Side {
     Left,
@@ -46,7 +48,7 @@ macro_rules! define_keys {
         ///
         /// For reference, see the following links:
         /// https://developer.mozilla.org/en-US/docs/Web/API/KeyboardEvent/key/Key_Values
-        #[derive(Clone,Debug,Eq,Hash,PartialEq)]
+        #[derive(Clone,Debug,Deserialize,Eq,Hash,PartialEq,Serialize)]
         #[allow(missing_docs)]
         pub enum Key {
             $($side(Side),)*
@@ -187,7 +189,7 @@ impl Default for Key {
 /// https://developer.mozilla.org/en-US/docs/Web/API/KeyboardEvent/code and
 /// https://developer.mozilla.org/en-US/docs/Web/API/KeyboardEvent/key
 #[allow(missing_docs)]
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct KeyWithCode {
     pub key:  Key,
     pub code: String,
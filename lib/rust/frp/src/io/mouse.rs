@@ -7,6 +7,8 @@ use crate::data::bitfield::BitField;
 use crate::data::bitfield::BitField32;
 
 use nalgebra::Vector2;
+use serde::Deserialize;
+use serde::Serialize;
 
 
 
@@ -20,7 +22,7 @@ use nalgebra::Vector2;
 /// JS supports up to 5 mouse buttons currently:
 /// https://developer.mozilla.org/en-US/docs/Web/API/MouseEvent/button
 /// https://developer.mozilla.org/en-US/docs/Web/API/MouseEvent/buttons
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Deserialize, Hash, PartialEq, Eq, Serialize)]
 #[allow(missing_docs)]
 pub enum Button {
     Button0,
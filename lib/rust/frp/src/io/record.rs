@@ -0,0 +1,372 @@
+//! Recording and replaying of user input events. The `Recorder` listens to the keyboard and mouse
+//! FRP sources and stores every input event together with its timestamp. The recorded
+//! `InputTrace` can be saved to a file and fed back into fresh `Keyboard` and `Mouse` instances by
+//! the `Replayer`, which drives a `ManualClock` instead of the real one. As all FRP events are
+//! propagated synchronously, the replay is fully deterministic, which allows reproducing bug
+//! reports and writing GUI regression tests running headlessly.
+
+use crate::prelude::*;
+
+use crate as frp;
+use crate::io::keyboard::KeyWithCode;
+use crate::io::keyboard::Keyboard;
+use crate::io::mouse::Button;
+use crate::io::mouse::Mouse;
+
+use nalgebra::Vector2;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+
+
+
+// =============
+// === Clock ===
+// =============
+
+/// A source of timestamps of recorded events. The time is expressed in milliseconds.
+pub trait Clock {
+    /// The current time.
+    fn now(&self) -> f64;
+}
+
+impl<F: Fn() -> f64> Clock for F {
+    fn now(&self) -> f64 {
+        self()
+    }
+}
+
+
+// === ManualClock ===
+
+/// A clock which time is changed manually only. The `time` stream emits the new time after every
+/// change, so networks which depend on time (like animations) can be driven by it during replay.
+#[derive(Clone, CloneRef, Debug)]
+#[allow(missing_docs)]
+pub struct ManualClock {
+    pub network: frp::Network,
+    pub time:    frp::Source<f64>,
+    current:     Rc<Cell<f64>>,
+}
+
+impl ManualClock {
+    /// Constructor. The clock starts at time 0.
+    pub fn new() -> Self {
+        frp::new_network! { network
+            time <- source();
+        }
+        let current = default();
+        Self { network, time, current }
+    }
+
+    /// Set the current time. Emits the `time` event if the time has changed.
+    pub fn set(&self, time: f64) {
+        if self.current.get() != time {
+            self.current.set(time);
+            self.time.emit(time);
+        }
+    }
+
+    /// Move the clock forward by the provided amount of milliseconds.
+    pub fn advance(&self, delta: f64) {
+        self.set(self.current.get() + delta)
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f64 {
+        self.current.get()
+    }
+}
+
+
+
+// ==================
+// === InputEvent ===
+// ==================
+
+/// A single user input event, as emitted by the keyboard and mouse FRP sources.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[allow(missing_docs)]
+pub enum InputEvent {
+    KeyDown(KeyWithCode),
+    KeyUp(KeyWithCode),
+    WindowDefocused,
+    MouseDown(Button),
+    MouseUp(Button),
+    MouseWheel,
+    MouseMove { x: f32, y: f32 },
+}
+
+impl InputEvent {
+    /// Constructor of the `MouseMove` event.
+    pub fn mouse_move(position: &Vector2<f32>) -> Self {
+        Self::MouseMove { x: position.x, y: position.y }
+    }
+
+    /// Emit the event in the provided keyboard and mouse FRP sources.
+    pub fn emit(&self, keyboard: &Keyboard, mouse: &Mouse) {
+        match self {
+            Self::KeyDown(key) => keyboard.source.down.emit(key),
+            Self::KeyUp(key) => keyboard.source.up.emit(key),
+            Self::WindowDefocused => keyboard.source.window_defocused.emit(()),
+            Self::MouseDown(button) => mouse.down.emit(button),
+            Self::MouseUp(button) => mouse.up.emit(button),
+            Self::MouseWheel => mouse.wheel.emit(()),
+            Self::MouseMove { x, y } => mouse.position.emit(Vector2::new(*x, *y)),
+        }
+    }
+}
+
+/// An input event with the time of its occurrence, in milliseconds.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[allow(missing_docs)]
+pub struct TimedInputEvent {
+    pub time:  f64,
+    pub event: InputEvent,
+}
+
+
+
+// ==================
+// === InputTrace ===
+// ==================
+
+/// A list of input events recorded during a session, ordered by time.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[allow(missing_docs)]
+pub struct InputTrace {
+    pub events: Vec<TimedInputEvent>,
+}
+
+impl InputTrace {
+    /// Serialize the trace to a JSON document.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Serialization of an input trace cannot fail.")
+    }
+
+    /// Deserialize the trace from a JSON document.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Save the trace to the provided file.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    /// Load a trace from the provided file.
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|err| err.into())
+    }
+
+    /// The time of the last event, or 0 if the trace is empty.
+    pub fn duration(&self) -> f64 {
+        self.events.last().map(|event| event.time).unwrap_or_default()
+    }
+}
+
+
+
+// ================
+// === Recorder ===
+// ================
+
+#[derive(Clone, CloneRef)]
+struct RecorderModel {
+    clock:  Rc<dyn Clock>,
+    events: Rc<RefCell<Vec<TimedInputEvent>>>,
+}
+
+impl RecorderModel {
+    fn record(&self, event: InputEvent) {
+        let time = self.clock.now();
+        self.events.borrow_mut().push(TimedInputEvent { time, event });
+    }
+}
+
+impl Debug for RecorderModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RecorderModel")
+    }
+}
+
+/// Records all events emitted by the keyboard and mouse FRP sources. The recording lasts as long as
+/// the recorder is alive.
+#[derive(Clone, CloneRef, Debug)]
+#[allow(missing_docs)]
+pub struct Recorder {
+    pub network: frp::Network,
+    model:       RecorderModel,
+}
+
+impl Recorder {
+    /// Constructor. The timestamps of events will be read from the provided clock.
+    pub fn new(keyboard: &Keyboard, mouse: &Mouse, clock: impl Clock + 'static) -> Self {
+        let clock: Rc<dyn Clock> = Rc::new(clock);
+        let events = default();
+        let model = RecorderModel { clock, events };
+        let network = frp::Network::new("input_recorder");
+        let kb = &keyboard.source;
+        frp::extend! { network
+            eval kb.down ((key) model.record(InputEvent::KeyDown(key.clone())));
+            eval kb.up ((key) model.record(InputEvent::KeyUp(key.clone())));
+            eval_ kb.window_defocused (model.record(InputEvent::WindowDefocused));
+            eval mouse.down ((button) model.record(InputEvent::MouseDown(*button)));
+            eval mouse.up ((button) model.record(InputEvent::MouseUp(*button)));
+            eval_ mouse.wheel (model.record(InputEvent::MouseWheel));
+            eval mouse.position ((pos) model.record(InputEvent::mouse_move(pos)));
+        }
+        Self { network, model }
+    }
+
+    /// All events recorded so far.
+    pub fn trace(&self) -> InputTrace {
+        InputTrace { events: self.model.events.borrow().clone() }
+    }
+
+    /// Remove all recorded events.
+    pub fn clear(&self) {
+        self.model.events.borrow_mut().clear()
+    }
+}
+
+
+
+// ================
+// === Replayer ===
+// ================
+
+/// Feeds recorded input events into the keyboard and mouse FRP sources. Before emitting an event,
+/// the clock is set to the event's time.
+#[derive(Debug)]
+#[allow(missing_docs)]
+pub struct Replayer {
+    pub clock: ManualClock,
+    keyboard:  Keyboard,
+    mouse:     Mouse,
+    trace:     InputTrace,
+    next:      usize,
+}
+
+impl Replayer {
+    /// Constructor.
+    pub fn new(trace: InputTrace, keyboard: &Keyboard, mouse: &Mouse) -> Self {
+        let clock = ManualClock::new();
+        let keyboard = keyboard.clone_ref();
+        let mouse = mouse.clone_ref();
+        Self { clock, keyboard, mouse, trace, next: 0 }
+    }
+
+    /// Checks whether all events were replayed.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.trace.events.len()
+    }
+
+    /// Replay the next event. Returns `false` if there are no more events to replay.
+    pub fn step(&mut self) -> bool {
+        match self.trace.events.get(self.next) {
+            Some(event) => {
+                self.clock.set(event.time);
+                event.event.emit(&self.keyboard, &self.mouse);
+                self.next += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replay all events which occurred not later than the provided time and set the clock to it.
+    pub fn replay_until(&mut self, time: f64) {
+        while self.trace.events.get(self.next).map_or(false, |event| event.time <= time) {
+            self.step();
+        }
+        self.clock.set(time);
+    }
+
+    /// Replay all remaining events.
+    pub fn replay_all(&mut self) {
+        while self.step() {}
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::keyboard::Key;
+    use crate::io::mouse::Button0;
+
+    fn record_session(keyboard: &Keyboard, mouse: &Mouse) -> InputTrace {
+        let clock = ManualClock::new();
+        let recorder = Recorder::new(keyboard, mouse, clock.clone_ref());
+        let ctrl = KeyWithCode::new("Control".into(), "ControlLeft".into());
+        mouse.position.emit(Vector2::new(10.0, 20.0));
+        clock.advance(16.0);
+        keyboard.source.down.emit(&ctrl);
+        clock.advance(16.0);
+        mouse.down.emit(Button0);
+        mouse.position.emit(Vector2::new(15.0, 25.0));
+        clock.advance(100.0);
+        mouse.up.emit(Button0);
+        keyboard.source.up.emit(&ctrl);
+        recorder.trace()
+    }
+
+    #[test]
+    fn recording() {
+        let trace = record_session(&Keyboard::new(), &Mouse::new());
+        let times = trace.events.iter().map(|event| event.time).collect_vec();
+        assert_eq!(times, vec![0.0, 16.0, 32.0, 32.0, 132.0, 132.0]);
+        assert_eq!(trace.events[2].event, InputEvent::MouseDown(Button0));
+        assert_eq!(trace.events[3].event, InputEvent::MouseMove { x: 15.0, y: 25.0 });
+        assert_eq!(trace.duration(), 132.0);
+        assert_eq!(InputTrace::from_json(&trace.to_json()).unwrap(), trace);
+    }
+
+    #[test]
+    fn replaying() {
+        let trace = record_session(&Keyboard::new(), &Mouse::new());
+        let trace = InputTrace::from_json(&trace.to_json()).unwrap();
+        let keyboard = Keyboard::new();
+        let mouse = Mouse::new();
+        let down_keys: Rc<RefCell<Vec<Key>>> = default();
+        let (keyboard_down, mouse_position) = (&keyboard.down, &mouse.position);
+        frp::new_network! { network
+            is_control_down <- keyboard.is_control_down.sampler();
+            is_mouse_down   <- mouse.is_down_primary.sampler();
+            position        <- mouse_position.sampler();
+            eval keyboard_down ((key) down_keys.borrow_mut().push(key.clone()));
+        }
+        let mut replayer = Replayer::new(trace, &keyboard, &mouse);
+
+        replayer.replay_until(20.0);
+        assert_eq!(replayer.clock.now(), 20.0);
+        assert_eq!(position.value(), Vector2::new(10.0, 20.0));
+        assert!(is_control_down.value());
+        assert!(!is_mouse_down.value());
+
+        replayer.replay_until(50.0);
+        assert!(is_mouse_down.value());
+        assert_eq!(position.value(), Vector2::new(15.0, 25.0));
+
+        replayer.replay_all();
+        assert!(replayer.is_finished());
+        assert_eq!(replayer.clock.now(), 132.0);
+        assert!(!is_control_down.value());
+        assert!(!is_mouse_down.value());
+        assert_eq!(*down_keys.borrow(), vec![Key::Control(crate::io::keyboard::Side::Left)]);
+    }
+}
@@ -40,8 +40,16 @@ pub struct WeakNetwork {
 }
 
 /// Network item.
-pub trait Item: HasId + HasLabel + stream::HasOutputTypeLabel {}
-impl<T> Item for T where T: HasId + HasLabel + stream::HasOutputTypeLabel {}
+pub trait Item:
+    HasId + HasLabel + stream::HasOutputTypeLabel + stream::InputBehaviors + stream::EventTargets {
+}
+impl<T> Item for T where T: HasId
+        + HasLabel
+        + stream::HasOutputTypeLabel
+        + stream::InputBehaviors
+        + stream::EventTargets
+{
+}
 
 /// Internal data of `Network`.
 #[derive(Derivative)]
@@ -128,13 +136,56 @@ impl Network {
         self.data.bridges.borrow_mut().push(sub_network.clone_ref())
     }
 
+    /// Describe the structure of the network: its nodes and the links between them. The edges
+    /// leading to or from nodes of other networks are included as well.
+    pub fn introspect(&self) -> debug::NetworkInfo {
+        let label = self.data.label.clone();
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for node in self.data.nodes.borrow().iter() {
+            let id = node.id();
+            nodes.push(debug::NodeInfo {
+                id:          id.into(),
+                label:       node.label().into(),
+                variant:     node.output_type_label(),
+                output_type: node.output_value_type_label(),
+            });
+            for link in node.input_behaviors() {
+                edges.push(debug::EdgeInfo::new(link.source, id, link.tp));
+            }
+            for target in node.event_targets() {
+                edges.push(debug::EdgeInfo::new(id, target, LinkType::Event));
+            }
+        }
+        for (target, link) in self.data.links.borrow().iter() {
+            edges.push(debug::EdgeInfo::new(link.source, *target, link.tp));
+        }
+        let is_mixed = |source, target| {
+            let mixed = debug::EdgeInfo { source, target, kind: debug::EdgeKind::Mixed };
+            edges.contains(&mixed)
+        };
+        let edges = edges
+            .iter()
+            .filter(|e| e.kind != debug::EdgeKind::Event || !is_mixed(e.source, e.target))
+            .copied()
+            .unique()
+            .collect_vec();
+        debug::NetworkInfo { label, nodes, edges }
+    }
+
+    /// Record all events emitted by the nodes of this network while evaluating `f`. See
+    /// `debug::trace` to learn more.
+    pub fn trace_propagation(&self, f: impl FnOnce()) -> debug::Trace {
+        let ids: HashSet<usize> =
+            self.data.nodes.borrow().iter().map(|node| node.id().into()).collect();
+        let mut trace = debug::trace(f);
+        trace.entries.retain(|entry| ids.contains(&entry.id));
+        trace
+    }
+
     /// Draw the network using GraphViz.
     pub fn draw(&self) {
-        let mut viz = debug::Graphviz::default();
-        self.data.nodes.borrow().iter().for_each(|node| {
-            viz.add_node(node.id().into(), node.output_type_label(), node.label());
-        });
-        debug::display_graphviz(viz);
+        debug::display_graphviz_code(&self.introspect().to_graphviz());
     }
 }
 
@@ -5,6 +5,7 @@ use crate::node::*;
 use crate::prelude::*;
 
 use crate::data::watch;
+use crate::debug;
 
 
 
@@ -85,6 +86,8 @@ impl Display for DisabledCallStack {
 pub trait HasOutputTypeLabel {
     /// Output type label of this object.
     fn output_type_label(&self) -> String;
+    /// Name of the type of values emitted by this object.
+    fn output_value_type_label(&self) -> String;
 }
 
 
@@ -107,6 +110,19 @@ impl<T> InputBehaviors for T {
 
 
 
+// ====================
+// === EventTargets ===
+// ====================
+
+/// Returns ids of all nodes registered as event targets of this node. For visualization purposes
+/// only.
+#[allow(missing_docs)]
+pub trait EventTargets {
+    fn event_targets(&self) -> Vec<Id>;
+}
+
+
+
 // ====================
 // === EventEmitter ===
 // ====================
@@ -143,6 +159,9 @@ pub trait WeakEventConsumer<T> {
     /// Returns true is the consumer is already dropped.
     fn is_dropped(&self) -> bool;
 
+    /// The id of the consumer node. For visualization purposes only.
+    fn consumer_id(&self) -> Id;
+
     /// Callback for a new incoming event. Returns true if the event was consumed or false if it was
     /// not. Not consuming an event means that the event receiver was already dropped.
     fn on_event_if_exists(&self, stack: CallStack, value: &T) -> bool;
@@ -253,6 +272,25 @@ impl<Out: Default> NodeData<Out> {
     fn use_caching(&self) -> bool {
         !self.watch_counter.is_zero()
     }
+
+    /// The id of this node. It is equal to the id of every `Stream` and `Node` pointing to it.
+    fn id(&self) -> Id {
+        let raw = self as *const Self as *const () as usize;
+        raw.into()
+    }
+
+    /// Ids of all alive event targets. Targets are skipped if this node is currently emitting an
+    /// event, as they can not be accessed then.
+    fn event_targets(&self) -> Vec<Id> {
+        let mut ids = Vec::new();
+        for targets in [&self.targets, &self.new_targets] {
+            if let Ok(targets) = targets.try_borrow() {
+                let alive = targets.iter().filter(|target| !target.data.is_dropped());
+                ids.extend(alive.map(|target| target.data.consumer_id()));
+            }
+        }
+        ids
+    }
 }
 
 impl<Out: Data> HasOutput for NodeData<Out> {
@@ -269,6 +307,10 @@ impl<Out: Data> EventEmitter for NodeData<Out> {
             });
             WARNING!("{backtrace()}")
         } else {
+            let tracing = debug::is_tracing();
+            if tracing {
+                debug::trace_emission_start(self.id(), self.label, value);
+            }
             self.ongoing_evaluations.set(self.ongoing_evaluations.get() + 1);
             if self.use_caching() {
                 *self.value_cache.borrow_mut() = value.clone();
@@ -287,6 +329,9 @@ impl<Out: Data> EventEmitter for NodeData<Out> {
                 }
             }
             self.ongoing_evaluations.set(self.ongoing_evaluations.get() - 1);
+            if tracing {
+                debug::trace_emission_end();
+            }
         }
     }
 
@@ -582,6 +627,10 @@ where
         self.definition.is_expired()
     }
 
+    fn consumer_id(&self) -> Id {
+        self.id()
+    }
+
     fn on_event_if_exists(&self, stack: CallStack, value: &T) -> bool {
         self.upgrade()
             .map(|node| {
@@ -675,6 +724,10 @@ where Def: HasOutputStatic + InputBehaviors
         }
         label.into()
     }
+
+    fn output_value_type_label(&self) -> String {
+        type_name::<Output<Def>>().into()
+    }
 }
 
 
@@ -684,8 +737,7 @@ impl<Def: HasOutputStatic> InputBehaviors for Node<Def>
 where Def: InputBehaviors
 {
     fn input_behaviors(&self) -> Vec<Link> {
-        vec![] // FIXME
-               //        self.data.input_behaviors()
+        (*self.definition).input_behaviors()
     }
 }
 
@@ -693,7 +745,16 @@ impl<Def: HasOutputStatic> InputBehaviors for WeakNode<Def>
 where Def: InputBehaviors
 {
     fn input_behaviors(&self) -> Vec<Link> {
-        self.stream.input_behaviors()
+        self.upgrade().map(|node| node.input_behaviors()).unwrap_or_default()
+    }
+}
+
+
+// === EventTargets ===
+
+impl<Def: HasOutputStatic> EventTargets for Node<Def> {
+    fn event_targets(&self) -> Vec<Id> {
+        self.stream.data.event_targets()
     }
 }
 
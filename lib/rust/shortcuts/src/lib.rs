@@ -22,6 +22,8 @@ extern crate test;
 use enso_automata::*;
 use enso_prelude::*;
 use enso_web as web;
+use serde::Deserialize;
+use serde::Serialize;
 
 pub use enso_logger as logger;
 pub use enso_logger::AnyLogger;
@@ -90,7 +92,7 @@ pub const DOUBLE_EVENT_TIME_MS: f32 = 300.0;
 /// As a clarification, the event `DoublePress` is emitted on second press of a button/key happening
 /// in short time interval from the first one. `DoubleClick`, on the other hand, happens on release,
 /// not on press.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 #[allow(missing_docs)]
 pub enum ActionType {
     Press,
@@ -666,6 +668,7 @@ fn key_aliases() -> HashMap<String, String> {
     insert(&mut map, "right", "arrow-right");
     insert(&mut map, "up", "arrow-up");
     insert(&mut map, "down", "arrow-down");
+    insert(&mut map, "comma", ",");
     insert(&mut map, "left-mouse-button", "mouse-button-0");
     insert(&mut map, "middle-mouse-button", "mouse-button-1");
     insert(&mut map, "right-mouse-button", "mouse-button-2");
@@ -693,6 +696,11 @@ impl<T> HashSetRegistry<T> {
     pub fn new() -> Self {
         default()
     }
+
+    /// Remove all shortcut definitions. The state of currently pressed keys is preserved.
+    pub fn clear(&self) {
+        self.rc.borrow_mut().actions.clear()
+    }
 }
 
 impl<T: HashSetRegistryItem> Registry<T> for HashSetRegistry<T> {
@@ -711,6 +719,293 @@ impl<T: HashSetRegistryItem> Registry<T> for HashSetRegistry<T> {
 
 
 
+// ========================
+// === SequenceRegistry ===
+// ========================
+
+/// The maximum time between consecutive chords of a multi-stroke sequence. If the next chord is
+/// not pressed in this time, the pending sequence is cancelled.
+pub const SEQUENCE_TIMEOUT_MS: f32 = 2000.0;
+
+/// A single chord of a registered sequence. Used as the item type of the underlying registry. If
+/// the action of the last chord is not triggered on press, like `Release` or `DoublePress`, the
+/// chord is also registered with the `awaiting` flag set, to keep the sequence pending until the
+/// action is triggered.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[allow(missing_docs)]
+pub struct SequenceStep {
+    pub sequence: usize,
+    pub index:    usize,
+    pub awaiting: bool,
+}
+
+/// Two bindings, where the chords of the `prefix` binding are the first chords of the `sequence`
+/// binding. When the `prefix` is pressed, its action is triggered immediately, and the `sequence`
+/// is still waiting for its next chords.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[allow(missing_docs)]
+pub struct PrefixConflict {
+    pub prefix:   String,
+    pub sequence: String,
+}
+
+#[derive(Clone, Debug)]
+struct SequenceDefinition<T> {
+    chords: Vec<String>,
+    action: T,
+}
+
+/// Sequences which first chords were pressed, mapped to the number of the pressed chords.
+#[derive(Clone, Debug)]
+struct PendingSequences {
+    matched: HashMap<usize, usize>,
+    time:    f32,
+}
+
+/// Internal model for `SequenceRegistry`.
+#[derive(Debug, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct SequenceRegistryModel<T> {
+    chords:    HashSetRegistry<SequenceStep>,
+    sequences: Vec<SequenceDefinition<T>>,
+    pending:   Option<PendingSequences>,
+}
+
+impl<T: Clone> SequenceRegistryModel<T> {
+    /// Add a new sequence definition. See `SequenceRegistry` docs to learn more.
+    pub fn add(&mut self, action_type: ActionType, expr: impl AsRef<str>, action: impl Into<T>) {
+        let chords = split_chords(expr.as_ref());
+        let sequence = self.sequences.len();
+        let last = chords.len().saturating_sub(1);
+        for (index, chord) in chords.iter().enumerate() {
+            let awaiting = false;
+            if index == last {
+                self.chords.add(action_type, chord, SequenceStep { sequence, index, awaiting });
+                if index > 0 && action_type != Press && action_type != PressAndRepeat {
+                    let awaiting = true;
+                    self.chords.add(Press, chord, SequenceStep { sequence, index, awaiting });
+                }
+            } else {
+                self.chords.add(Press, chord, SequenceStep { sequence, index, awaiting });
+            }
+        }
+        let action = action.into();
+        self.sequences.push(SequenceDefinition { chords, action });
+    }
+
+    /// Remove all sequence definitions and cancel the pending sequence.
+    pub fn clear(&mut self) {
+        self.chords.clear();
+        self.sequences.clear();
+        self.pending = None;
+    }
+
+    /// Handle the key press.
+    pub fn on_press(&mut self, input: impl AsRef<str>) -> Vec<T> {
+        let input = input.as_ref();
+        let steps = self.chords.on_press(input);
+        self.on_steps(steps, Some(input))
+    }
+
+    /// Handle the key release.
+    pub fn on_release(&mut self, input: impl AsRef<str>) -> Vec<T> {
+        let steps = self.chords.on_release(input);
+        self.on_steps(steps, None)
+    }
+
+    /// Advances the pending sequences by the chord steps triggered by the last event. If any
+    /// pending sequence was continued, the actions of sequences starting with this chord are not
+    /// triggered, so "ctrl k, ctrl s" does not trigger the action bound to "ctrl s" alone.
+    fn on_steps(&mut self, steps: Vec<SequenceStep>, pressed: Option<&str>) -> Vec<T> {
+        let time = web::time_from_start() as f32;
+        self.cancel_expired(time);
+        let mut continued = false;
+        let mut continued_actions = Vec::new();
+        let mut advanced = HashMap::new();
+        let mut started_actions = Vec::new();
+        let mut started = HashMap::new();
+        for step in steps {
+            let definition = &self.sequences[step.sequence];
+            let is_last = step.index + 1 == definition.chords.len();
+            let matched = self.pending.as_ref().and_then(|p| p.matched.get(&step.sequence));
+            if matched == Some(&step.index) {
+                continued = true;
+                if step.awaiting {
+                    advanced.insert(step.sequence, step.index);
+                } else if is_last {
+                    continued_actions.push(definition.action.clone());
+                } else {
+                    advanced.insert(step.sequence, step.index + 1);
+                }
+            } else if step.index == 0 && !step.awaiting {
+                if is_last {
+                    started_actions.push(definition.action.clone());
+                } else {
+                    started.insert(step.sequence, 1);
+                }
+            }
+        }
+        if continued {
+            let finished = continued_actions.len();
+            let pending = |matched: HashMap<_, _>| PendingSequences { matched, time };
+            self.pending = (finished == 0 && !advanced.is_empty()).then(|| pending(advanced));
+            continued_actions
+        } else {
+            if !started.is_empty() {
+                self.pending = Some(PendingSequences { matched: started, time });
+            } else if pressed.map_or(false, |key| !is_side_key(key)) {
+                self.pending = None;
+            }
+            started_actions
+        }
+    }
+
+    fn cancel_expired(&mut self, time: f32) {
+        let expired = |pending: &PendingSequences| time - pending.time > SEQUENCE_TIMEOUT_MS;
+        if self.pending.as_ref().map_or(false, expired) {
+            self.pending = None;
+        }
+    }
+
+    /// The chords of the sequence which is currently being entered, like "ctrl k", or `None` if
+    /// there is no pending sequence or it has timed out. If several sequences are pending, the
+    /// longest matched prefix is returned.
+    pub fn pending_prefix(&self) -> Option<String> {
+        let time = web::time_from_start() as f32;
+        let pending = self.pending.as_ref()?;
+        let expired = time - pending.time > SEQUENCE_TIMEOUT_MS;
+        let longest = pending.matched.iter().map(|(seq, matched)| (*matched, *seq)).max();
+        let (matched, sequence) = longest.filter(|_| !expired)?;
+        let chords = &self.sequences[sequence].chords[..matched];
+        Some(chords.join(", "))
+    }
+
+    /// The time in milliseconds left until the pending sequence times out, or `None` if there is
+    /// no pending sequence.
+    pub fn pending_time_left(&self) -> Option<f32> {
+        let time = web::time_from_start() as f32;
+        let pending = self.pending.as_ref()?;
+        Some((pending.time + SEQUENCE_TIMEOUT_MS - time).max(0.0))
+    }
+
+    /// Cancel the pending sequence. Used when the sequence times out, so the pending chords are not
+    /// shown to the user any longer.
+    pub fn cancel_pending(&mut self) {
+        self.pending = None;
+    }
+
+    /// All pairs of bindings where one binding is a prefix of the other one. Chords are compared
+    /// regardless of their key order, so "ctrl k" and "k ctrl" are the same chord.
+    pub fn prefix_conflicts(&self) -> Vec<PrefixConflict> {
+        let normalize = |chords: &[String]| chords.iter().map(|c| normalize_chord(c)).collect_vec();
+        let normalized = self.sequences.iter().map(|s| normalize(&s.chords)).collect_vec();
+        let mut conflicts = Vec::new();
+        for (prefix_ix, prefix) in normalized.iter().enumerate() {
+            for (sequence_ix, sequence) in normalized.iter().enumerate() {
+                if prefix.len() < sequence.len() && sequence.starts_with(prefix) {
+                    let prefix = self.sequences[prefix_ix].chords.join(", ");
+                    let sequence = self.sequences[sequence_ix].chords.join(", ");
+                    conflicts.push(PrefixConflict { prefix, sequence });
+                }
+            }
+        }
+        conflicts.into_iter().unique().collect()
+    }
+}
+
+/// Split the expression to chords. Chords are separated by commas, like "ctrl k, ctrl s".
+fn split_chords(expr: &str) -> Vec<String> {
+    let chords = expr.split(',').map(|chord| chord.split_whitespace().join(" "));
+    chords.filter(|chord| !chord.is_empty()).collect()
+}
+
+fn normalize_chord(chord: &str) -> String {
+    chord.to_lowercase().split_whitespace().sorted().join(" ")
+}
+
+/// Checks whether the input is a side key, like "ctrl-left". Pressing side keys does not cancel
+/// the pending sequence, as they are needed to enter the next chord.
+fn is_side_key(input: &str) -> bool {
+    let input = input.to_lowercase();
+    let key = input.strip_suffix("-left").or_else(|| input.strip_suffix("-right"));
+    SIDE_KEYS_SET.contains(key.unwrap_or(&input))
+}
+
+
+// === SequenceRegistry ===
+
+/// Shortcut registry supporting multi-stroke sequences, like "ctrl k, ctrl s", known from emacs or
+/// VS Code. Chords of a sequence are separated by commas and each chord is resolved by the
+/// underlying `HashSetRegistry`, so it uses the same syntax as shortcuts of other registries. The
+/// action type applies to the last chord only, all other chords need to be pressed. An expression
+/// without commas is a sequence of a single chord, so this registry is a drop-in replacement of the
+/// `HashSetRegistry`. As the comma separates chords, the comma key is called "comma".
+///
+/// When the first chords of a sequence are pressed, the sequence becomes pending until the next
+/// chord is pressed. Pressing any other non-modifier key or waiting longer than
+/// `SEQUENCE_TIMEOUT_MS` cancels it. Use `pending_prefix` to show the pending chords to the user.
+/// Bindings which are prefixes of other bindings are allowed, but they are almost always a mistake,
+/// as the shorter binding triggers before the longer one could be finished. Use `prefix_conflicts`
+/// to detect them.
+#[derive(CloneRef, Debug, Derivative)]
+#[derivative(Clone(bound = ""))]
+#[derivative(Default(bound = ""))]
+pub struct SequenceRegistry<T> {
+    rc: Rc<RefCell<SequenceRegistryModel<T>>>,
+}
+
+impl<T> SequenceRegistry<T> {
+    /// Constructor.
+    pub fn new() -> Self {
+        default()
+    }
+}
+
+impl<T: Clone> SequenceRegistry<T> {
+    /// Remove all shortcut definitions and cancel the pending sequence.
+    pub fn clear(&self) {
+        self.rc.borrow_mut().clear()
+    }
+
+    /// The chords of the sequence which is currently being entered. See
+    /// `SequenceRegistryModel::pending_prefix` to learn more.
+    pub fn pending_prefix(&self) -> Option<String> {
+        self.rc.borrow().pending_prefix()
+    }
+
+    /// The time left until the pending sequence times out. See
+    /// `SequenceRegistryModel::pending_time_left` to learn more.
+    pub fn pending_time_left(&self) -> Option<f32> {
+        self.rc.borrow().pending_time_left()
+    }
+
+    /// Cancel the pending sequence.
+    pub fn cancel_pending(&self) {
+        self.rc.borrow_mut().cancel_pending()
+    }
+
+    /// All pairs of bindings where one binding is a prefix of the other one.
+    pub fn prefix_conflicts(&self) -> Vec<PrefixConflict> {
+        self.rc.borrow().prefix_conflicts()
+    }
+}
+
+impl<T: Clone> Registry<T> for SequenceRegistry<T> {
+    fn add(&self, action_type: ActionType, expr: impl AsRef<str>, action: impl Into<T>) {
+        self.rc.borrow_mut().add(action_type, expr, action)
+    }
+
+    fn on_press(&self, input: impl AsRef<str>) -> Vec<T> {
+        self.rc.borrow_mut().on_press(input)
+    }
+
+    fn on_release(&self, input: impl AsRef<str>) -> Vec<T> {
+        self.rc.borrow_mut().on_release(input)
+    }
+}
+
+
+
 // =============
 // === Tests ===
 // =============
@@ -729,6 +1024,10 @@ mod tests {
     fn hash_set_registry_press() {
         press::<HashSetRegistry<i32>>();
     }
+    #[test]
+    fn sequence_registry_press() {
+        press::<SequenceRegistry<i32>>();
+    }
     fn press<T: Registry<i32>>() -> T {
         let nothing = Vec::<i32>::new();
         let registry = <T>::default();
@@ -752,6 +1051,10 @@ mod tests {
     fn hash_set_registry_release() {
         release::<HashSetRegistry<i32>>();
     }
+    #[test]
+    fn sequence_registry_release() {
+        release::<SequenceRegistry<i32>>();
+    }
     fn release<T: Registry<i32>>() -> T {
         let nothing = Vec::<i32>::new();
         let registry = <T>::default();
@@ -772,6 +1075,10 @@ mod tests {
     fn hash_set_registry_double_press() {
         double_press::<HashSetRegistry<i32>>();
     }
+    #[test]
+    fn sequence_registry_double_press() {
+        double_press::<SequenceRegistry<i32>>();
+    }
     fn double_press<T: Registry<i32>>() -> T {
         let nothing = Vec::<i32>::new();
         let registry = <T>::default();
@@ -799,6 +1106,10 @@ mod tests {
     fn hash_set_registry_overlapping() {
         overlapping::<HashSetRegistry<i32>>();
     }
+    #[test]
+    fn sequence_registry_overlapping() {
+        overlapping::<SequenceRegistry<i32>>();
+    }
     fn overlapping<T: Registry<i32>>() -> T {
         let nothing = Vec::<i32>::new();
         let registry = <T>::default();
@@ -825,6 +1136,10 @@ mod tests {
     fn hash_set_registry_side_keys() {
         side_keys::<HashSetRegistry<i32>>();
     }
+    #[test]
+    fn sequence_registry_side_keys() {
+        side_keys::<SequenceRegistry<i32>>();
+    }
     fn side_keys<T: Registry<i32>>() -> T {
         let nothing = Vec::<i32>::new();
         let registry: T = default();
@@ -857,6 +1172,10 @@ mod tests {
     fn hash_set_registry_sequence() {
         sequence::<HashSetRegistry<&'static str>>();
     }
+    #[test]
+    fn sequence_registry_sequence() {
+        sequence::<SequenceRegistry<&'static str>>();
+    }
     fn sequence<T: Registry<&'static str>>() -> T {
         let nothing = Vec::<&'static str>::new();
         let registry: T = default();
@@ -879,6 +1198,10 @@ mod tests {
     fn hash_set_registry_repeat() {
         repeat::<HashSetRegistry<&'static str>>();
     }
+    #[test]
+    fn sequence_registry_repeat() {
+        repeat::<SequenceRegistry<&'static str>>();
+    }
     fn repeat<T: Registry<&'static str>>() -> T {
         let nothing = Vec::<&'static str>::new();
         let registry: T = default();
@@ -940,6 +1263,92 @@ mod tests {
         assert_eq!(registry.on_press("a"), vec![0]);
         registry
     }
+
+    // === Multi-Stroke Sequences ===
+
+    #[test]
+    fn sequence_registry_chords() {
+        let nothing = Vec::<&'static str>::new();
+        let registry = SequenceRegistry::<&'static str>::new();
+        registry.add(Press, "ctrl k, ctrl s", "save all");
+        registry.add(Press, "ctrl s", "save");
+        registry.add(Release, "ctrl k, comma", "settings");
+        assert_eq!(registry.on_press("ctrl-left"), nothing);
+        assert_eq!(registry.on_press("k"), nothing);
+        assert_eq!(registry.pending_prefix(), Some("ctrl k".into()));
+        assert_eq!(registry.on_release("k"), nothing);
+        assert_eq!(registry.on_press("s"), vec!["save all"]);
+        assert_eq!(registry.pending_prefix(), None);
+        assert_eq!(registry.on_release("s"), nothing);
+        assert_eq!(registry.on_press("s"), vec!["save"]);
+        assert_eq!(registry.on_release("s"), nothing);
+        assert_eq!(registry.on_press("k"), nothing);
+        assert_eq!(registry.on_release("k"), nothing);
+        assert_eq!(registry.on_release("ctrl-left"), nothing);
+        assert_eq!(registry.on_press(","), nothing);
+        assert_eq!(registry.on_release(","), vec!["settings"]);
+        assert_eq!(registry.pending_prefix(), None);
+    }
+
+    #[test]
+    fn sequence_registry_cancel() {
+        let nothing = Vec::<i32>::new();
+        let registry = SequenceRegistry::<i32>::new();
+        registry.add(Press, "ctrl k, ctrl s", 0);
+        registry.add(Press, "ctrl s", 1);
+        // Pressing another key cancels the pending sequence.
+        assert_eq!(registry.on_press("ctrl-left"), nothing);
+        assert_eq!(registry.on_press("k"), nothing);
+        assert_eq!(registry.on_release("k"), nothing);
+        assert_eq!(registry.on_press("a"), nothing);
+        assert_eq!(registry.pending_prefix(), None);
+        assert_eq!(registry.on_release("a"), nothing);
+        assert_eq!(registry.on_press("s"), vec![1]);
+        assert_eq!(registry.on_release("s"), nothing);
+        // Waiting too long cancels the pending sequence.
+        assert_eq!(registry.on_press("k"), nothing);
+        assert_eq!(registry.on_release("k"), nothing);
+        web::simulate_sleep((SEQUENCE_TIMEOUT_MS + 100.0) as f64);
+        assert_eq!(registry.pending_prefix(), None);
+        assert_eq!(registry.on_press("s"), vec![1]);
+        assert_eq!(registry.on_release("s"), nothing);
+        // The pending sequence can be cancelled when its timeout expires.
+        assert_eq!(registry.pending_time_left(), None);
+        assert_eq!(registry.on_press("k"), nothing);
+        assert_eq!(registry.on_release("k"), nothing);
+        web::simulate_sleep(500.0);
+        let time_left = registry.pending_time_left().unwrap();
+        assert!(time_left > 0.0 && time_left <= SEQUENCE_TIMEOUT_MS - 500.0);
+        registry.cancel_pending();
+        assert_eq!(registry.pending_prefix(), None);
+        assert_eq!(registry.pending_time_left(), None);
+        assert_eq!(registry.on_press("s"), vec![1]);
+        assert_eq!(registry.on_release("s"), nothing);
+        assert_eq!(registry.on_release("ctrl-left"), nothing);
+    }
+
+    #[test]
+    fn sequence_registry_prefix_conflicts() {
+        let registry = SequenceRegistry::<i32>::new();
+        registry.add(Press, "ctrl k", 0);
+        registry.add(Press, "ctrl k, ctrl s", 1);
+        registry.add(Press, "k ctrl, s", 2);
+        registry.add(Press, "ctrl s", 3);
+        let conflicts = registry.prefix_conflicts();
+        let conflict = |prefix: &str, sequence: &str| {
+            let (prefix, sequence) = (prefix.into(), sequence.into());
+            PrefixConflict { prefix, sequence }
+        };
+        assert_eq!(conflicts, vec![
+            conflict("ctrl k", "ctrl k, ctrl s"),
+            conflict("ctrl k", "k ctrl, s")
+        ]);
+        // The shorter binding is triggered immediately and the longer one is still pending.
+        assert_eq!(registry.on_press("ctrl-left"), Vec::<i32>::new());
+        assert_eq!(registry.on_press("k"), vec![0]);
+        assert_eq!(registry.on_release("k"), Vec::<i32>::new());
+        assert_eq!(registry.on_press("s"), vec![1]);
+    }
 }
 
 